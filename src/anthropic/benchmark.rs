@@ -0,0 +1,237 @@
+//! 账号基准测试
+//!
+//! 直接使用账号的 Provider 顺序发送固定请求（不经过账号选择，也不更新账号统计），
+//! 统计首 token 延迟、总耗时分位数与 token 用量，便于比较不同账号的性能
+
+use std::time::Instant;
+
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::kiro::model::events::Event;
+use crate::kiro::model::requests::kiro::KiroRequest;
+use crate::kiro::parser::decoder::EventStreamDecoder;
+use crate::kiro::provider::KiroProvider;
+use crate::token;
+
+use super::converter::convert_request;
+use super::types::{Message, MessagesRequest};
+
+/// 未指定时使用的测试提示词
+pub const DEFAULT_BENCHMARK_PROMPT: &str = "Hello";
+/// 单次基准测试的最大请求数
+pub const MAX_BENCHMARK_SAMPLES: usize = 20;
+/// 基准测试使用的模型
+const BENCHMARK_MODEL: &str = "claude-sonnet-4";
+/// 基准测试请求的最大输出 tokens
+const BENCHMARK_MAX_TOKENS: i32 = 256;
+
+/// 单次请求结果
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkSample {
+    pub success: bool,
+    pub first_token_ms: Option<u64>,
+    pub total_duration_ms: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 基准测试报告（延迟分位数按成功请求的总耗时计算）
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkReport {
+    pub account_id: String,
+    pub samples: usize,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+    pub success_rate: f64,
+    pub avg_input_tokens: f64,
+    pub avg_output_tokens: f64,
+    pub results: Vec<BenchmarkSample>,
+}
+
+impl BenchmarkReport {
+    /// 由各次请求结果汇总报告
+    pub fn from_samples(account_id: impl Into<String>, results: Vec<BenchmarkSample>) -> Self {
+        let succeeded: Vec<&BenchmarkSample> = results.iter().filter(|r| r.success).collect();
+        let mut durations: Vec<u64> = succeeded.iter().map(|r| r.total_duration_ms).collect();
+        durations.sort_unstable();
+
+        let average = |f: fn(&BenchmarkSample) -> u64| {
+            if succeeded.is_empty() {
+                0.0
+            } else {
+                succeeded.iter().map(|r| f(r)).sum::<u64>() as f64 / succeeded.len() as f64
+            }
+        };
+        let success_rate = if results.is_empty() {
+            0.0
+        } else {
+            succeeded.len() as f64 / results.len() as f64
+        };
+
+        Self {
+            account_id: account_id.into(),
+            samples: results.len(),
+            p50_ms: percentile(&durations, 50.0),
+            p95_ms: percentile(&durations, 95.0),
+            p99_ms: percentile(&durations, 99.0),
+            success_rate,
+            avg_input_tokens: average(|r| r.input_tokens),
+            avg_output_tokens: average(|r| r.output_tokens),
+            results,
+        }
+    }
+}
+
+/// 最近秩法计算分位数（输入需已排序）
+fn percentile(sorted: &[u64], p: f64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// 使用指定 Provider 顺序发送 `samples` 次测试请求
+pub async fn run_benchmark(
+    provider: &KiroProvider,
+    account_id: &str,
+    samples: usize,
+    prompt: &str,
+) -> anyhow::Result<BenchmarkReport> {
+    let messages = vec![Message {
+        role: "user".to_string(),
+        content: serde_json::Value::String(prompt.to_string()),
+    }];
+    let request = MessagesRequest {
+        model: BENCHMARK_MODEL.to_string(),
+        max_tokens: BENCHMARK_MAX_TOKENS,
+        messages: messages.clone(),
+        stream: true,
+        system: None,
+        tools: None,
+        tool_choice: None,
+        thinking: None,
+        streaming_format: None,
+    };
+    let conversion = convert_request(&request)?;
+    let kiro_request = KiroRequest::builder()
+        .conversation_state(conversion.conversation_state)
+        .build()?;
+    let body = serde_json::to_string(&kiro_request)?;
+    let input_tokens = token::count_all_tokens(BENCHMARK_MODEL.to_string(), None, messages, None);
+
+    let mut results = Vec::with_capacity(samples);
+    for _ in 0..samples {
+        results.push(run_sample(provider, &body, input_tokens).await);
+    }
+
+    let report = BenchmarkReport::from_samples(account_id, results);
+    tracing::info!(
+        "账号 {} 基准测试完成：{} 次，成功率 {:.0}%，p50 {:?}ms",
+        account_id,
+        report.samples,
+        report.success_rate * 100.0,
+        report.p50_ms
+    );
+    Ok(report)
+}
+
+/// 发送一次流式请求，记录首个文本事件的到达时间与总耗时
+async fn run_sample(provider: &KiroProvider, body: &str, input_tokens: u64) -> BenchmarkSample {
+    let started = Instant::now();
+    let failed = |error: String, first_token_ms: Option<u64>| BenchmarkSample {
+        success: false,
+        first_token_ms,
+        total_duration_ms: started.elapsed().as_millis() as u64,
+        input_tokens,
+        output_tokens: 0,
+        error: Some(error),
+    };
+
+    let response = match provider.call_api_stream(body).await {
+        Ok(resp) => resp.response,
+        Err(e) => return failed(e.to_string(), None),
+    };
+
+    let mut stream = response.bytes_stream();
+    let mut decoder = EventStreamDecoder::new();
+    let mut first_token_ms = None;
+    let mut text = String::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => return failed(format!("读取响应失败: {}", e), first_token_ms),
+        };
+        let fed = decoder.feed_incremental(&chunk, |frame| {
+            if let Ok(Event::AssistantResponse(resp)) = Event::from_frame(frame) {
+                first_token_ms.get_or_insert(started.elapsed().as_millis() as u64);
+                text.push_str(&resp.content);
+            }
+        });
+        if let Err(e) = fed {
+            return failed(e.to_string(), first_token_ms);
+        }
+    }
+
+    BenchmarkSample {
+        success: true,
+        first_token_ms,
+        total_duration_ms: started.elapsed().as_millis() as u64,
+        input_tokens,
+        output_tokens: token::count_tokens(&text),
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(success: bool, total_duration_ms: u64, output_tokens: u64) -> BenchmarkSample {
+        BenchmarkSample {
+            success,
+            first_token_ms: success.then_some(total_duration_ms / 2),
+            total_duration_ms,
+            input_tokens: 10,
+            output_tokens,
+            error: (!success).then(|| "boom".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let sorted: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&sorted, 50.0), Some(50));
+        assert_eq!(percentile(&sorted, 95.0), Some(95));
+        assert_eq!(percentile(&sorted, 99.0), Some(99));
+        assert_eq!(percentile(&[7], 99.0), Some(7));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_report_ignores_failed_samples_for_latency() {
+        let report = BenchmarkReport::from_samples(
+            "a",
+            vec![
+                sample(true, 300, 4),
+                sample(true, 100, 2),
+                sample(false, 5000, 0),
+                sample(true, 200, 6),
+            ],
+        );
+        assert_eq!(report.samples, 4);
+        assert_eq!(report.p50_ms, Some(200));
+        assert_eq!(report.p99_ms, Some(300));
+        assert_eq!(report.success_rate, 0.75);
+        assert_eq!(report.avg_input_tokens, 10.0);
+        assert_eq!(report.avg_output_tokens, 4.0);
+
+        let empty = BenchmarkReport::from_samples("a", Vec::new());
+        assert_eq!(empty.p50_ms, None);
+        assert_eq!(empty.success_rate, 0.0);
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! 负责将 Anthropic API 请求格式转换为 Kiro API 请求格式
 
+use std::collections::HashMap;
+
 use uuid::Uuid;
 
 use crate::kiro::model::requests::conversation::{
@@ -12,7 +14,7 @@ use crate::kiro::model::requests::tool::{
     InputSchema, Tool, ToolResult, ToolSpecification, ToolUseEntry,
 };
 
-use super::types::{ContentBlock, MessagesRequest, Thinking};
+use super::types::{ContentBlock, MessagesRequest, SystemMessage, Thinking};
 
 /// 模型映射：将 Anthropic 模型名映射到 Kiro 模型 ID
 ///
@@ -97,6 +99,54 @@ impl std::fmt::Display for ConversionError {
 
 impl std::error::Error for ConversionError {}
 
+/// 在请求的系统提示词前后注入运营方配置的内容
+///
+/// 请求没有系统提示词时会新建，空字符串视为未配置；返回是否发生了注入
+pub fn inject_system_prompt(
+    req: &mut MessagesRequest,
+    prefix: Option<&str>,
+    suffix: Option<&str>,
+) -> bool {
+    let prefix = prefix.filter(|p| !p.is_empty());
+    let suffix = suffix.filter(|s| !s.is_empty());
+    if prefix.is_none() && suffix.is_none() {
+        return false;
+    }
+
+    let system = req.system.get_or_insert_with(Vec::new);
+    if let Some(prefix) = prefix {
+        system.insert(
+            0,
+            SystemMessage {
+                text: prefix.to_string(),
+            },
+        );
+    }
+    if let Some(suffix) = suffix {
+        system.push(SystemMessage {
+            text: suffix.to_string(),
+        });
+    }
+    true
+}
+
+/// 注入命名系统提示词模板：请求已有系统提示词时追加在末尾，否则作为系统提示词
+///
+/// 模板不存在时返回 false
+pub fn apply_system_template(
+    req: &mut MessagesRequest,
+    name: &str,
+    templates: &HashMap<String, String>,
+) -> bool {
+    let Some(template) = templates.get(name) else {
+        return false;
+    };
+    req.system.get_or_insert_with(Vec::new).push(SystemMessage {
+        text: template.clone(),
+    });
+    true
+}
+
 /// 将 Anthropic 请求转换为 Kiro 请求
 pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, ConversionError> {
     // 1. 检查消息列表
@@ -206,6 +256,7 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
         tools: req.tools.clone(),
         tool_choice: req.tool_choice.clone(),
         thinking: req.thinking.clone(),
+        streaming_format: None,
     };
     let history = build_history(&history_req, &model_id, strip_tools)?;
 
@@ -674,10 +725,83 @@ mod tests {
             tools: None,
             tool_choice: None,
             thinking: None,
+            streaming_format: None,
         };
         assert_eq!(determine_chat_trigger_type(&req), "MANUAL");
     }
 
+    /// 取出转换结果中作为系统消息的首条历史内容
+    fn system_history_content(req: &MessagesRequest) -> String {
+        let res = convert_request(req).unwrap();
+        match &res.conversation_state.history[0] {
+            crate::kiro::model::requests::conversation::Message::User(u) => {
+                u.user_input_message.content.clone()
+            }
+            _ => panic!("expected user message"),
+        }
+    }
+
+    #[test]
+    fn test_inject_system_prompt() {
+        let mut req = MessagesRequest {
+            model: "claude-sonnet-4".to_string(),
+            max_tokens: 1024,
+            messages: vec![types::Message {
+                role: "user".to_string(),
+                content: json!("hi"),
+            }],
+            stream: false,
+            system: Some(vec![SystemMessage {
+                text: "client".to_string(),
+            }]),
+            tools: None,
+            tool_choice: None,
+            thinking: None,
+            streaming_format: None,
+        };
+
+        assert!(!inject_system_prompt(&mut req, None, Some("")));
+        assert!(inject_system_prompt(&mut req, Some("pre"), Some("post")));
+        assert_eq!(system_history_content(&req), "pre\nclient\npost");
+
+        // 没有系统提示词时新建
+        req.system = None;
+        assert!(inject_system_prompt(&mut req, Some("pre"), None));
+        assert_eq!(system_history_content(&req), "pre");
+    }
+
+    #[test]
+    fn test_apply_system_template() {
+        let mut req = MessagesRequest {
+            model: "claude-sonnet-4".to_string(),
+            max_tokens: 1024,
+            messages: vec![types::Message {
+                role: "user".to_string(),
+                content: json!("hi"),
+            }],
+            stream: false,
+            system: None,
+            tools: None,
+            tool_choice: None,
+            thinking: None,
+            streaming_format: None,
+        };
+        let templates = HashMap::from([("support".to_string(), "be kind".to_string())]);
+
+        assert!(!apply_system_template(&mut req, "missing", &templates));
+        assert!(req.system.is_none());
+
+        assert!(apply_system_template(&mut req, "support", &templates));
+        assert_eq!(system_history_content(&req), "be kind");
+
+        // 已有系统提示词时追加在末尾
+        req.system = Some(vec![SystemMessage {
+            text: "client".to_string(),
+        }]);
+        assert!(apply_system_template(&mut req, "support", &templates));
+        assert_eq!(system_history_content(&req), "client\nbe kind");
+    }
+
     #[test]
     fn test_is_unsupported_tool() {
         assert!(is_unsupported_tool("web_search"));
@@ -696,6 +820,7 @@ mod tests {
             tools: None,
             tool_choice: None,
             thinking: None,
+            streaming_format: None,
             messages: vec![
                 types::Message {
                     role: "user".to_string(),
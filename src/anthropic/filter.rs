@@ -0,0 +1,93 @@
+//! 输出内容过滤模块
+//!
+//! 按配置的正则规则替换响应文本中的敏感内容（邮箱、手机号、证件号等）
+
+use regex::Regex;
+
+use crate::model::config::ContentFilterRule;
+
+/// 编译后的过滤规则
+struct CompiledRule {
+    name: String,
+    regex: Regex,
+    replacement: String,
+}
+
+/// 输出内容过滤器
+#[derive(Default)]
+pub struct ContentFilter {
+    rules: Vec<CompiledRule>,
+}
+
+impl ContentFilter {
+    /// 从配置规则编译过滤器（跳过未启用的规则）
+    pub fn from_rules(rules: &[ContentFilterRule]) -> anyhow::Result<Self> {
+        let mut compiled = Vec::new();
+        for rule in rules.iter().filter(|r| r.enabled) {
+            let regex = Regex::new(&rule.pattern)
+                .map_err(|e| anyhow::anyhow!("过滤规则 {} 的正则无效: {}", rule.name, e))?;
+            compiled.push(CompiledRule {
+                name: rule.name.clone(),
+                regex,
+                replacement: rule.replacement.clone(),
+            });
+        }
+        Ok(Self { rules: compiled })
+    }
+
+    /// 是否没有任何启用的规则
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 对文本应用所有规则
+    ///
+    /// 返回 `Some(替换后的文本)` 表示有内容被过滤，`None` 表示文本未变化。
+    /// 注意：流式响应按事件逐段过滤，跨事件边界的匹配无法识别。
+    pub fn apply(&self, text: &str) -> Option<String> {
+        let mut current: Option<String> = None;
+        for rule in &self.rules {
+            let input = current.as_deref().unwrap_or(text);
+            if rule.regex.is_match(input) {
+                tracing::debug!("内容过滤规则 {} 命中", rule.name);
+                let replaced = rule
+                    .regex
+                    .replace_all(input, rule.replacement.as_str())
+                    .into_owned();
+                current = Some(replaced);
+            }
+        }
+        current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, pattern: &str, enabled: bool) -> ContentFilterRule {
+        ContentFilterRule {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            replacement: "[REDACTED]".to_string(),
+            enabled,
+        }
+    }
+
+    #[test]
+    fn test_content_filter_skips_disabled_and_rejects_invalid() {
+        let filter = ContentFilter::from_rules(&[
+            rule("email", r"[\w.+-]+@[\w-]+\.[\w.]+", true),
+            rule("ssn", r"\d{3}-\d{2}-\d{4}", false),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            filter.apply("mail a.b@example.com, ssn 123-45-6789"),
+            Some("mail [REDACTED], ssn 123-45-6789".to_string())
+        );
+        assert_eq!(filter.apply("nothing here"), None);
+
+        assert!(ContentFilter::from_rules(&[rule("bad", "(", true)]).is_err());
+    }
+}
@@ -5,11 +5,16 @@ use std::convert::Infallible;
 use crate::kiro::model::events::Event;
 use crate::kiro::model::requests::kiro::KiroRequest;
 use crate::kiro::parser::decoder::EventStreamDecoder;
+use crate::kiro::parser::validator::validate_event;
+use crate::kiro::telemetry::{TelemetryRecord, TelemetryStore};
+use crate::model::config::RetryPolicy;
+use crate::pool::manager::SelectedAccount;
+use crate::pool::{AccountPool, SelectionFilter};
 use crate::token;
 use axum::{
     body::Body,
     extract::State,
-    http::{header, StatusCode},
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Json, Response},
     Json as JsonExtractor,
 };
@@ -20,8 +25,9 @@ use std::time::Duration;
 use tokio::time::interval;
 use uuid::Uuid;
 
-use super::converter::{convert_request, ConversionError};
-use super::middleware::AppState;
+use super::converter::{convert_request, inject_system_prompt, ConversionError};
+use super::filter::ContentFilter;
+use super::middleware::{api_key_from_headers, body_hash, AppState, IdempotentLookup};
 use super::stream::{SseEvent, StreamContext};
 use super::types::{
     CountTokensRequest, CountTokensResponse, ErrorResponse, MessagesRequest, Model, ModelsResponse,
@@ -84,14 +90,61 @@ pub async fn get_models() -> impl IntoResponse {
     })
 }
 
+/// 按可用账号数判定健康状态：无可用账号为 unavailable，可用比例低于阈值为 degraded
+fn health_status(active: usize, total: usize, degraded_ratio: f64) -> &'static str {
+    if active == 0 {
+        "unavailable"
+    } else if (active as f64) < total as f64 * degraded_ratio {
+        "degraded"
+    } else {
+        "ok"
+    }
+}
+
+/// GET /health
+///
+/// 免认证的存活 / 就绪探针：账号池模式按可用账号比例判定；单账号模式检查缓存的 token 状态。
+/// unavailable 时返回 503
+pub async fn health(State(state): State<AppState>) -> impl IntoResponse {
+    let (active, total) = if let Some(pool) = &state.account_pool {
+        let stats = pool.get_stats().await;
+        (stats.active, stats.total)
+    } else if let Some(provider) = &state.kiro_provider {
+        (usize::from(provider.can_provide_token()), 1)
+    } else {
+        (0, 0)
+    };
+    let status = health_status(active, total, state.health_degraded_ratio().await);
+    let code = if status == "unavailable" {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+
+    (
+        code,
+        Json(json!({
+            "status": status,
+            "active_accounts": active,
+            "total_accounts": total,
+            "uptime_secs": state.start_time.elapsed().as_secs(),
+        })),
+    )
+}
+
 /// POST /v1/messages
 ///
 /// 创建消息（对话）
 pub async fn post_messages(
     State(state): State<AppState>,
-    JsonExtractor(payload): JsonExtractor<MessagesRequest>,
+    headers: HeaderMap,
+    body: Bytes,
 ) -> Response {
     let start_time = std::time::Instant::now();
+    let mut payload = match JsonExtractor::<MessagesRequest>::from_bytes(&body) {
+        Ok(JsonExtractor(payload)) => payload,
+        Err(rejection) => return rejection.into_response(),
+    };
 
     tracing::info!(
         model = %payload.model,
@@ -101,31 +154,169 @@ pub async fn post_messages(
         "Received POST /v1/messages request"
     );
 
+    // 会话 ID 用于在请求记录中关联同一多轮对话
+    let session_id = session_id_from_headers(&headers);
+
+    // 非流式请求支持 Idempotency-Key，命中缓存直接返回，避免重试重复消耗配额。
+    // 缓存按调用方 API Key 隔离，并校验请求体与首次请求一致
+    let idempotency = if payload.stream {
+        None
+    } else {
+        headers
+            .get("idempotency-key")
+            .and_then(|v| v.to_str().ok())
+            .map(|key| IdempotencyScope {
+                api_key: api_key_from_headers(&headers).unwrap_or_default(),
+                key: key.to_string(),
+                body_hash: body_hash(&body),
+            })
+    };
+    if let Some(scope) = &idempotency {
+        match state
+            .get_idempotent_response(&scope.api_key, &scope.key, scope.body_hash)
+            .await
+        {
+            IdempotentLookup::Hit(cached) => {
+                tracing::info!("命中幂等缓存: {}", scope.key);
+                return (StatusCode::OK, Json(cached)).into_response();
+            }
+            IdempotentLookup::BodyMismatch => {
+                return (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Json(ErrorResponse::new(
+                        "invalid_request_error",
+                        "Idempotency-Key was already used with a different request body.",
+                    )),
+                )
+                    .into_response();
+            }
+            IdempotentLookup::Miss => {}
+        }
+    }
+
+    // 原样转发上游事件流需显式开启
+    let raw_stream = payload.stream && payload.streaming_format.as_deref() == Some("raw");
+    if raw_stream && !state.allow_raw_stream().await {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "Raw streaming is disabled"})),
+        )
+            .into_response();
+    }
+
+    // 消息结构校验：警告仅记录日志，错误直接拒绝（在选择账号之前，避免无效请求占用账号）
+    let validation_errors: Vec<String> = token::validate_messages(&payload.messages)
+        .into_iter()
+        .filter_map(|w| {
+            tracing::warn!(
+                severity = ?w.severity,
+                "消息 #{} 校验未通过: {}",
+                w.message_index,
+                w.message
+            );
+            (w.severity == token::ValidationSeverity::Error)
+                .then(|| format!("messages[{}]: {}", w.message_index, w.message))
+        })
+        .collect();
+    if !validation_errors.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(
+                "invalid_request_error",
+                validation_errors.join("; "),
+            )),
+        )
+            .into_response();
+    }
+
+    // 注入请求头指定的系统提示词模板（在选择账号之前校验，避免无效请求占用账号）
+    if let Some(name) = headers
+        .get("x-system-template")
+        .and_then(|v| v.to_str().ok())
+    {
+        if !state.apply_system_template(&mut payload, name).await {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": format!("Unknown system template: {}", name)})),
+            )
+                .into_response();
+        }
+        tracing::info!("已注入系统提示词模板: {}", name);
+    }
+
+    // 账号选择条件：地域感知策略优先使用请求头指定的区域，标签路由只在带有全部指定标签的账号中选择
+    let failover = AccountFailover {
+        policy: state.retry_policy().await,
+        provider_retries: state.upstream_retries_enabled().await,
+        preferred_region: headers
+            .get("x-preferred-region")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+        tags: tags_from_headers(&headers),
+    };
+
     // 获取 provider：优先从账号池获取，否则使用单账号模式
-    let (provider, account_id, account_name, pool_ref) = if let Some(pool) = &state.account_pool {
-        match pool.select_account().await {
+    let (provider, account_id, account_name, pool_ref, request_timeout) = if let Some(pool) =
+        &state.account_pool
+    {
+        // 全局限速
+        if !pool.try_acquire_rate_limit().await {
+            tracing::warn!("账号池请求速率超过全局限速");
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(ErrorResponse::new(
+                    "rate_limit_error",
+                    "Request rate limit exceeded, please retry later",
+                )),
+            )
+                .into_response();
+        }
+
+        match pool
+            .select_account_for_region(failover.preferred_region.as_deref(), &failover.tag_refs())
+            .await
+        {
             Some(selected) => (
-                selected.provider,
-                Some(selected.id),
-                selected.name,
+                selected.provider.clone(),
+                Some(selected.id.clone()),
+                // 设置了显示名覆盖时，请求记录使用覆盖后的名称
+                selected.log_name().to_string(),
                 Some(pool.clone()),
+                selected.request_timeout_secs.map(Duration::from_secs),
             ),
-            None => {
-                tracing::error!("账号池中没有可用账号");
+            None if !failover.tags.is_empty() => {
+                let tags = &failover.tags;
+                tracing::warn!("没有带有标签 {:?} 的可用账号", tags);
                 return (
                     StatusCode::SERVICE_UNAVAILABLE,
                     Json(ErrorResponse::new(
                         "service_unavailable",
-                        "No available accounts in pool",
+                        format!("No available accounts matching tags: {}", tags.join(",")),
                     )),
                 )
                     .into_response();
             }
+            None => {
+                tracing::error!("账号池中没有可用账号");
+                let next_reset_at = pool.estimate_next_reset().await;
+                return (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    Json(json!({
+                        "error": ErrorResponse::new(
+                            "service_unavailable",
+                            "No available accounts in pool",
+                        )
+                        .error,
+                        "next_reset_at": next_reset_at.map(|t| t.to_rfc3339()),
+                    })),
+                )
+                    .into_response();
+            }
         }
     } else {
         // 单账号模式
         match &state.kiro_provider {
-            Some(p) => (p.clone(), None, "单账号模式".to_string(), None),
+            Some(p) => (p.clone(), None, "单账号模式".to_string(), None, None),
             None => {
                 tracing::error!("KiroProvider 未配置");
                 return (
@@ -143,6 +334,16 @@ pub async fn post_messages(
     // 获取 profile_arn
     let profile_arn = state.profile_arn.clone();
 
+    // 注入运营方配置的系统提示词前缀/后缀
+    let (prompt_prefix, prompt_suffix) = state.system_prompt_affixes().await;
+    if inject_system_prompt(
+        &mut payload,
+        prompt_prefix.as_deref(),
+        prompt_suffix.as_deref(),
+    ) {
+        tracing::debug!("已注入系统提示词前缀/后缀");
+    }
+
     // 转换请求
     let conversion_result = match convert_request(&payload) {
         Ok(result) => result,
@@ -165,9 +366,20 @@ pub async fn post_messages(
     };
 
     // 构建 Kiro 请求
-    let kiro_request = KiroRequest {
-        conversation_state: conversion_result.conversation_state,
-        profile_arn: profile_arn.clone(),
+    let kiro_request = match KiroRequest::builder()
+        .conversation_state(conversion_result.conversation_state)
+        .profile_arn(profile_arn.clone())
+        .build()
+    {
+        Ok(request) => request,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse::new("internal_error", e.to_string())),
+            )
+                .into_response();
+        }
     };
 
     let request_body = match serde_json::to_string(&kiro_request) {
@@ -185,7 +397,14 @@ pub async fn post_messages(
         }
     };
 
-    tracing::debug!("Kiro request body: {}", request_body);
+    let (request_body_log, response_body_log) = state.body_log_flags().await;
+    if request_body_log {
+        let access_token = provider.access_token().await;
+        tracing::debug!(
+            request_body = %body_log_excerpt(&request_body, access_token.as_deref()),
+            "Kiro 请求体"
+        );
+    }
 
     // 估算输入 tokens
     let input_tokens = token::count_all_tokens(
@@ -223,34 +442,179 @@ pub async fn post_messages(
         .map(|t| t.thinking_type == "enabled")
         .unwrap_or(false);
 
-    if payload.stream {
-        // 流式响应
-        handle_stream_request(
+    if raw_stream {
+        // 调试用：跳过事件转换，原样转发
+        handle_raw_stream_request(
             provider,
             &request_body,
-            &payload.model,
-            input_tokens,
-            thinking_enabled,
+            request_timeout,
             account_id,
-            account_name,
             pool_ref,
-            start_time,
         )
         .await
     } else {
-        // 非流式响应
-        handle_non_stream_request(
+        let call = UpstreamCall {
             provider,
-            &request_body,
-            &payload.model,
+            request_body: &request_body,
+            request_timeout,
+            model: &payload.model,
             input_tokens,
+            request_id: String::new(),
             account_id,
             account_name,
-            pool_ref,
+            session_id,
+            pool: pool_ref,
+            failover: &failover,
             start_time,
+        };
+        if payload.stream {
+            // 流式响应
+            return handle_stream_request(
+                call,
+                thinking_enabled,
+                state.content_filter.clone(),
+                state.telemetry.clone(),
+            )
+            .await;
+        }
+
+        // 非流式响应
+        let response = handle_non_stream_request(
+            call,
+            response_body_log,
+            state.content_filter.clone(),
+            state.telemetry.clone(),
         )
-        .await
+        .await;
+
+        match idempotency {
+            Some(scope) if response.status() == StatusCode::OK => {
+                cache_idempotent_response(&state, scope, response).await
+            }
+            _ => response,
+        }
+    }
+}
+
+/// 会话 ID 最大长度
+const MAX_SESSION_ID_LEN: usize = 128;
+
+/// 从 `X-Session-ID` 请求头提取会话 ID（仅接受不超过 128 个字符的字母、数字与连字符）
+fn session_id_from_headers(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get("x-session-id")?.to_str().ok()?.trim();
+    let valid = !value.is_empty()
+        && value.len() <= MAX_SESSION_ID_LEN
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !valid {
+        tracing::debug!("忽略无效的 X-Session-ID 请求头");
+        return None;
+    }
+    Some(value.to_string())
+}
+
+/// 从 `X-Kiro-Tags` 请求头提取账号标签过滤条件（逗号分隔，忽略空项）
+fn tags_from_headers(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get("x-kiro-tags")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| {
+            v.split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 换号重试：账号选择条件与重试策略
+struct AccountFailover {
+    policy: RetryPolicy,
+    /// Provider 已对 5xx 做过退避重试时为 true，此时 5xx 不再换号，避免请求次数成倍叠加
+    provider_retries: bool,
+    preferred_region: Option<String>,
+    tags: Vec<String>,
+}
+
+impl AccountFailover {
+    fn tag_refs(&self) -> Vec<&str> {
+        self.tags.iter().map(String::as_str).collect()
+    }
+
+    /// 上游错误可重试且未超过重试次数时，选出一个尚未尝试过的账号并记入 `tried`
+    async fn next_account(
+        &self,
+        pool: &AccountPool,
+        error_msg: &str,
+        tried: &mut Vec<String>,
+    ) -> Option<SelectedAccount> {
+        if tried.len().saturating_sub(1) >= self.policy.max_retries as usize {
+            return None;
+        }
+        let status = upstream_status_from_error(error_msg)?;
+        if !self.policy.retryable_status_codes.contains(&status) {
+            return None;
+        }
+        if self.provider_retries
+            && StatusCode::from_u16(status).is_ok_and(crate::kiro::provider::is_retryable_status)
+        {
+            return None;
+        }
+        let tags = self.tag_refs();
+        let filter = SelectionFilter {
+            tags: &tags,
+            exclude: tried,
+        };
+        let selected = pool
+            .select_account_filtered(self.preferred_region.as_deref(), &filter)
+            .await?;
+        tried.push(selected.id.clone());
+        Some(selected)
+    }
+}
+
+/// 从上游错误信息（`<类型> API 请求失败: 429 Too Many Requests ...`）中提取 HTTP 状态码
+fn upstream_status_from_error(error_msg: &str) -> Option<u16> {
+    let (_, rest) = error_msg.split_once("API 请求失败: ")?;
+    rest.get(..3)?.parse().ok()
+}
+
+/// 幂等缓存的作用域：调用方 API Key + Idempotency-Key + 请求体哈希
+struct IdempotencyScope {
+    api_key: String,
+    key: String,
+    body_hash: u64,
+}
+
+/// 缓存成功的非流式响应并原样返回
+async fn cache_idempotent_response(
+    state: &AppState,
+    scope: IdempotencyScope,
+    response: Response,
+) -> Response {
+    let (parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("读取响应体失败，跳过幂等缓存: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse::new(
+                    "internal_error",
+                    format!("读取响应失败: {}", e),
+                )),
+            )
+                .into_response();
+        }
+    };
+
+    if let Ok(value) = serde_json::from_slice::<serde_json::Value>(&bytes) {
+        state
+            .store_idempotent_response(scope.api_key, scope.key, scope.body_hash, value)
+            .await;
     }
+
+    Response::from_parts(parts, Body::from(bytes))
 }
 
 /// 流结束时的统计信息
@@ -258,120 +622,239 @@ pub async fn post_messages(
 struct StreamStats {
     output_tokens: i32,
     input_tokens: i32,
+    was_filtered: bool,
+    ttfb_ms: Option<u64>,
+    decode_ms: u64,
+    validation_errors: usize,
+    context_window_used_pct: Option<f64>,
+    credits_used: Option<f64>,
+    heartbeat_count: u32,
+    thinking_tokens: i32,
 }
 
-/// 处理流式请求
-async fn handle_stream_request(
+/// 一次上游调用的上下文：当前账号及换号重试所需的信息，流式与非流式请求共用
+struct UpstreamCall<'a> {
     provider: std::sync::Arc<crate::kiro::provider::KiroProvider>,
-    request_body: &str,
-    model: &str,
+    request_body: &'a str,
+    request_timeout: Option<Duration>,
+    model: &'a str,
     input_tokens: i32,
-    thinking_enabled: bool,
+    /// 当前这次上游调用的请求 ID（每次换号重新生成），错误记录与完成记录共用，便于合并重复记录
+    request_id: String,
     account_id: Option<String>,
     account_name: String,
+    session_id: Option<String>,
     pool: Option<std::sync::Arc<crate::pool::AccountPool>>,
+    failover: &'a AccountFailover,
     start_time: std::time::Instant,
-) -> Response {
-    // 调用 Kiro API
-    let response = match provider.call_api_stream(request_body).await {
-        Ok(resp) => resp,
-        Err(e) => {
-            let error_msg = e.to_string();
-            tracing::error!("Kiro API 调用失败: {}", error_msg);
+}
 
-            // 记录错误到账号池
-            if let (Some(id), Some(pool)) = (&account_id, &pool) {
-                let is_rate_limit = error_msg.contains("429") || error_msg.contains("rate");
-                let is_suspended = error_msg.contains("suspended") || error_msg.contains("403");
-                // 402 Payment Required 表示月度请求限制已达上限
-                let is_quota_exceeded = error_msg.contains("402")
-                    || error_msg.contains("Payment Required")
-                    || error_msg.contains("MONTHLY_REQUEST_COUNT")
-                    || error_msg.contains("reached the limit");
-
-                if is_suspended {
-                    pool.mark_invalid(id).await;
-                    tracing::warn!("账号 {} 已自动禁用（403/suspended）", id);
-                } else if is_quota_exceeded {
-                    let next_reset = pool.get_account_usage(id).await.and_then(|u| u.next_reset);
-                    pool.mark_exhausted(id, next_reset).await;
-                    tracing::warn!("账号 {} 已被标记为配额耗尽", id);
-                } else {
-                    pool.record_error(id, is_rate_limit).await;
-                    tracing::warn!("账号 {} 记录错误，限流: {}", id, is_rate_limit);
+impl UpstreamCall<'_> {
+    /// 调用 Kiro API，可重试错误时换用其他账号重试
+    ///
+    /// 成功时返回上游响应、Token 刷新耗时与本次调用的开始时间；失败时返回应直接回给客户端的错误响应，
+    /// 换号后 `provider`、`account_id` 等字段指向最终使用的账号
+    async fn send(
+        &mut self,
+        streaming: bool,
+    ) -> Result<(reqwest::Response, Option<u64>, std::time::Instant), Response> {
+        let mut tried: Vec<String> = self.account_id.iter().cloned().collect();
+        loop {
+            self.request_id = uuid::Uuid::new_v4().to_string();
+            let call_start = std::time::Instant::now();
+            let result = match (self.request_timeout, streaming) {
+                (Some(timeout), true) => {
+                    self.provider
+                        .call_api_stream_with_timeout(self.request_body, timeout)
+                        .await
                 }
-
-                // 记录失败的请求
-                let log = crate::pool::RequestLog {
-                    id: uuid::Uuid::new_v4().to_string(),
-                    account_id: id.clone(),
-                    account_name: account_name.clone(),
-                    model: model.to_string(),
-                    input_tokens,
-                    output_tokens: 0,
-                    success: false,
-                    error: Some(error_msg.clone()),
-                    timestamp: chrono::Utc::now(),
-                    duration_ms: start_time.elapsed().as_millis() as u64,
-                };
-                pool.add_request_log(log).await;
-
-                // 对于配额耗尽，返回 402 错误
-                if is_quota_exceeded {
-                    return (
-                        StatusCode::PAYMENT_REQUIRED,
-                        Json(ErrorResponse::new(
-                            "billing_error",
-                            "Your account has reached its monthly request limit. Please check your plan and billing details.",
-                        )),
-                    )
-                        .into_response();
+                (None, true) => self.provider.call_api_stream(self.request_body).await,
+                (Some(timeout), false) => {
+                    self.provider
+                        .call_api_with_timeout(self.request_body, timeout)
+                        .await
                 }
+                (None, false) => self.provider.call_api(self.request_body).await,
+            };
+            match result {
+                Ok(resp) => return Ok((resp.response, resp.token_refresh_ms, call_start)),
+                Err(e) => {
+                    let error_msg = e.to_string();
+                    tracing::error!("Kiro API 调用失败: {}", error_msg);
+
+                    // 记录错误到账号池
+                    if let (Some(id), Some(pool)) = (&self.account_id, &self.pool) {
+                        let is_rate_limit = error_msg.contains("429") || error_msg.contains("rate");
+                        let is_suspended =
+                            error_msg.contains("suspended") || error_msg.contains("403");
+                        // 402 Payment Required 表示月度请求限制已达上限
+                        let is_quota_exceeded = error_msg.contains("402")
+                            || error_msg.contains("Payment Required")
+                            || error_msg.contains("MONTHLY_REQUEST_COUNT")
+                            || error_msg.contains("reached the limit");
+
+                        if is_suspended {
+                            pool.mark_invalid(id).await;
+                            tracing::warn!("账号 {} 已自动禁用（403/suspended）", id);
+                        } else if is_quota_exceeded {
+                            let next_reset =
+                                pool.get_account_usage(id).await.and_then(|u| u.next_reset);
+                            pool.mark_exhausted(id, next_reset).await;
+                            tracing::warn!("账号 {} 已被标记为配额耗尽", id);
+                        } else {
+                            pool.record_error(id, is_rate_limit).await;
+                            tracing::warn!("账号 {} 记录错误，限流: {}", id, is_rate_limit);
+                        }
+
+                        // 记录失败的请求
+                        let log = crate::pool::RequestLog {
+                            id: self.request_id.clone(),
+                            account_id: id.clone(),
+                            account_name: self.account_name.clone(),
+                            model: self.model.to_string(),
+                            input_tokens: self.input_tokens,
+                            output_tokens: 0,
+                            success: false,
+                            error: Some(error_msg.clone()),
+                            timestamp: chrono::Utc::now(),
+                            duration_ms: self.start_time.elapsed().as_millis() as u64,
+                            was_filtered: false,
+                            context_window_used_pct: None,
+                            credits_used: None,
+                            heartbeat_count: None,
+                            session_id: self.session_id.clone(),
+                        };
+                        pool.add_request_log(log).await;
+
+                        // 可重试错误：换用尚未尝试过的账号重新请求
+                        if let Some(next) = self
+                            .failover
+                            .next_account(pool, &error_msg, &mut tried)
+                            .await
+                        {
+                            tracing::warn!(
+                                "账号 {} 请求失败，换用账号 {} 重试（{}/{}）",
+                                self.account_name,
+                                next.log_name(),
+                                tried.len() - 1,
+                                self.failover.policy.max_retries
+                            );
+                            self.provider = next.provider.clone();
+                            self.request_timeout =
+                                next.request_timeout_secs.map(Duration::from_secs);
+                            self.account_name = next.log_name().to_string();
+                            self.account_id = Some(next.id);
+                            continue;
+                        }
+
+                        // 对于配额耗尽，返回 402 错误
+                        if is_quota_exceeded {
+                            return Err((
+                                StatusCode::PAYMENT_REQUIRED,
+                                Json(ErrorResponse::new(
+                                    "billing_error",
+                                    "Your account has reached its monthly request limit. Please check your plan and billing details.",
+                                )),
+                            )
+                                .into_response());
+                        }
 
-                // 对于账号暂停，返回 403 错误
-                if is_suspended {
-                    return (
-                        StatusCode::FORBIDDEN,
+                        // 对于账号暂停，返回 403 错误
+                        if is_suspended {
+                            return Err((
+                                StatusCode::FORBIDDEN,
+                                Json(ErrorResponse::new(
+                                    "permission_error",
+                                    "Your API key does not have permission to access this resource.",
+                                )),
+                            )
+                                .into_response());
+                        }
+                    }
+
+                    return Err((
+                        StatusCode::BAD_GATEWAY,
                         Json(ErrorResponse::new(
-                            "permission_error",
-                            "Your API key does not have permission to access this resource.",
+                            "api_error",
+                            format!("上游 API 调用失败: {}", e),
                         )),
                     )
-                        .into_response();
+                        .into_response());
                 }
             }
-
-            return (
-                StatusCode::BAD_GATEWAY,
-                Json(ErrorResponse::new(
-                    "api_error",
-                    format!("上游 API 调用失败: {}", e),
-                )),
-            )
-                .into_response();
         }
+    }
+}
+
+/// 流处理过程中的耗时统计
+struct StreamTiming {
+    call_start: std::time::Instant,
+    ttfb_ms: Option<u64>,
+    decode: std::time::Duration,
+}
+
+/// 处理流式请求
+async fn handle_stream_request(
+    mut call: UpstreamCall<'_>,
+    thinking_enabled: bool,
+    content_filter: Option<std::sync::Arc<ContentFilter>>,
+    telemetry: std::sync::Arc<TelemetryStore>,
+) -> Response {
+    let (response, token_refresh_ms, call_start) = match call.send(true).await {
+        Ok(sent) => sent,
+        Err(response) => return response,
     };
+    let UpstreamCall {
+        model,
+        input_tokens,
+        request_id,
+        account_id,
+        account_name,
+        session_id,
+        pool,
+        start_time,
+        ..
+    } = call;
 
     // 创建 channel 用于在流结束时传递统计信息
     let (stats_tx, stats_rx) = tokio::sync::oneshot::channel::<StreamStats>();
 
     // 创建流处理上下文
-    let mut ctx = StreamContext::new_with_thinking(model, input_tokens, thinking_enabled);
+    let mut ctx = StreamContext::new_with_thinking(model, input_tokens, thinking_enabled)
+        .with_content_filter(content_filter);
 
     // 生成初始事件
     let initial_events = ctx.generate_initial_events();
 
     // 创建 SSE 流（传入 stats_tx）
-    let stream = create_sse_stream(response, ctx, initial_events, Some(stats_tx));
+    let stream = create_sse_stream(response, ctx, initial_events, Some(stats_tx), call_start);
+
+    // 异步等待流结束并记录遥测和日志
+    let model = model.to_string();
+    tokio::spawn(async move {
+        let stats = stats_rx.await;
+        if let Ok(stats) = &stats {
+            if stats.validation_errors > 0 {
+                tracing::warn!("流式响应中有 {} 个事件未通过校验", stats.validation_errors);
+            }
+            telemetry
+                .record(TelemetryRecord {
+                    request_id: request_id.clone(),
+                    token_refresh_ms,
+                    ttfb_ms: stats.ttfb_ms,
+                    decode_ms: stats.decode_ms,
+                })
+                .await;
+        }
 
-    // 异步等待流结束并记录日志
-    if let (Some(id), Some(pool)) = (account_id, pool) {
-        let model = model.to_string();
-        tokio::spawn(async move {
-            match stats_rx.await {
+        if let (Some(id), Some(pool)) = (account_id, pool) {
+            match stats {
                 Ok(stats) => {
+                    if let Some(ttfb_ms) = stats.ttfb_ms {
+                        pool.record_first_token_latency(&id, ttfb_ms).await;
+                    }
                     let log = crate::pool::RequestLog {
-                        id: uuid::Uuid::new_v4().to_string(),
+                        id: request_id,
                         account_id: id,
                         account_name,
                         model,
@@ -381,14 +864,23 @@ async fn handle_stream_request(
                         error: None,
                         timestamp: chrono::Utc::now(),
                         duration_ms: start_time.elapsed().as_millis() as u64,
+                        was_filtered: stats.was_filtered,
+                        context_window_used_pct: stats.context_window_used_pct,
+                        credits_used: stats.credits_used,
+                        heartbeat_count: Some(stats.heartbeat_count),
+                        session_id: session_id.clone(),
                     };
                     pool.add_request_log(log).await;
-                    tracing::debug!("流式请求完成，output_tokens: {}", stats.output_tokens);
+                    tracing::debug!(
+                        "流式请求完成，output_tokens: {}，thinking_tokens: {}",
+                        stats.output_tokens,
+                        stats.thinking_tokens
+                    );
                 }
                 Err(_) => {
                     // channel 被关闭，可能是客户端断开连接
                     let log = crate::pool::RequestLog {
-                        id: uuid::Uuid::new_v4().to_string(),
+                        id: request_id,
                         account_id: id,
                         account_name,
                         model,
@@ -398,13 +890,18 @@ async fn handle_stream_request(
                         error: Some("客户端可能提前断开".to_string()),
                         timestamp: chrono::Utc::now(),
                         duration_ms: start_time.elapsed().as_millis() as u64,
+                        was_filtered: false,
+                        context_window_used_pct: None,
+                        credits_used: None,
+                        heartbeat_count: None,
+                        session_id: session_id.clone(),
                     };
                     pool.add_request_log(log).await;
                     tracing::warn!("流式请求统计 channel 关闭，可能客户端断开");
                 }
             }
-        });
-    }
+        }
+    });
 
     // 返回 SSE 响应
     Response::builder()
@@ -416,6 +913,55 @@ async fn handle_stream_request(
         .unwrap()
 }
 
+/// 处理原样转发的流式请求（调试用，不经过 `StreamContext` 转换）
+async fn handle_raw_stream_request(
+    provider: std::sync::Arc<crate::kiro::provider::KiroProvider>,
+    request_body: &str,
+    request_timeout: Option<Duration>,
+    account_id: Option<String>,
+    pool: Option<std::sync::Arc<crate::pool::AccountPool>>,
+) -> Response {
+    let result = match request_timeout {
+        Some(timeout) => {
+            provider
+                .call_api_stream_with_timeout(request_body, timeout)
+                .await
+        }
+        None => provider.call_api_stream(request_body).await,
+    };
+    match result {
+        Ok(resp) => {
+            tracing::info!("原样转发 Kiro 上游事件流");
+            raw_stream_response(resp.response)
+        }
+        Err(e) => {
+            tracing::error!("Kiro API 调用失败: {}", e);
+            if let (Some(id), Some(pool)) = (&account_id, &pool) {
+                pool.record_error(id, false).await;
+            }
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(ErrorResponse::new(
+                    "api_error",
+                    format!("上游 API 调用失败: {}", e),
+                )),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// 将上游响应体不做任何转换地转发给客户端
+fn raw_stream_response(response: reqwest::Response) -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header(header::CONNECTION, "keep-alive")
+        .body(Body::from_stream(response.bytes_stream()))
+        .unwrap()
+}
+
 /// Ping 事件间隔（25秒）
 const PING_INTERVAL_SECS: u64 = 25;
 
@@ -430,6 +976,7 @@ fn create_sse_stream(
     ctx: StreamContext,
     initial_events: Vec<SseEvent>,
     stats_tx: Option<tokio::sync::oneshot::Sender<StreamStats>>,
+    call_start: std::time::Instant,
 ) -> impl Stream<Item = Result<Bytes, Infallible>> {
     // 先发送初始事件
     let initial_stream = stream::iter(
@@ -440,10 +987,15 @@ fn create_sse_stream(
 
     // 然后处理 Kiro 响应流，同时每25秒发送 ping 保活
     let body_stream = response.bytes_stream();
+    let timing = StreamTiming {
+        call_start,
+        ttfb_ms: None,
+        decode: std::time::Duration::ZERO,
+    };
 
     let processing_stream = stream::unfold(
-        (body_stream, ctx, EventStreamDecoder::new(), false, interval(Duration::from_secs(PING_INTERVAL_SECS)), stats_tx),
-        |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval, stats_tx)| async move {
+        (body_stream, ctx, EventStreamDecoder::new(), false, interval(Duration::from_secs(PING_INTERVAL_SECS)), stats_tx, timing),
+        |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval, stats_tx, mut timing)| async move {
             if finished {
                 return None;
             }
@@ -454,33 +1006,28 @@ fn create_sse_stream(
                 chunk_result = body_stream.next() => {
                     match chunk_result {
                         Some(Ok(chunk)) => {
-                            // 解码事件
-                            if let Err(e) = decoder.feed(&chunk) {
-                                tracing::warn!("缓冲区溢出: {}", e);
+                            if timing.ttfb_ms.is_none() {
+                                timing.ttfb_ms = Some(timing.call_start.elapsed().as_millis() as u64);
                             }
 
-                            let mut events = Vec::new();
-                            for result in decoder.decode_iter() {
-                                match result {
-                                    Ok(frame) => {
-                                        if let Ok(event) = Event::from_frame(frame) {
-                                            let sse_events = ctx.process_kiro_event(&event);
-                                            events.extend(sse_events);
-                                        }
-                                    }
-                                    Err(e) => {
-                                        tracing::warn!("解码事件失败: {}", e);
-                                    }
+                            // 逐帧解码，每帧完成后立即转换为 SSE 字节
+                            let decode_start = std::time::Instant::now();
+                            let mut bytes: Vec<Result<Bytes, Infallible>> = Vec::new();
+                            let fed = decoder.feed_incremental(&chunk, |frame| {
+                                if let Ok(event) = Event::from_frame(frame) {
+                                    bytes.extend(
+                                        ctx.process_kiro_event(&event)
+                                            .into_iter()
+                                            .map(|e| Ok(Bytes::from(e.to_sse_string()))),
+                                    );
                                 }
+                            });
+                            if let Err(e) = fed {
+                                tracing::warn!("缓冲区溢出: {}", e);
                             }
+                            timing.decode += decode_start.elapsed();
 
-                            // 转换为 SSE 字节流
-                            let bytes: Vec<Result<Bytes, Infallible>> = events
-                                .into_iter()
-                                .map(|e| Ok(Bytes::from(e.to_sse_string())))
-                                .collect();
-
-                            Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval, stats_tx)))
+                            Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval, stats_tx, timing)))
                         }
                         Some(Err(e)) => {
                             tracing::error!("读取响应流失败: {}", e);
@@ -493,6 +1040,14 @@ fn create_sse_stream(
                                 let _ = tx.send(StreamStats {
                                     output_tokens: ctx.output_tokens,
                                     input_tokens: final_input_tokens,
+                                    was_filtered: ctx.was_filtered,
+                                    ttfb_ms: timing.ttfb_ms,
+                                    decode_ms: timing.decode.as_millis() as u64,
+                                    validation_errors: ctx.validation_errors,
+                                    context_window_used_pct: ctx.context_usage_pct,
+                                    credits_used: ctx.credits_used,
+                                    heartbeat_count: ctx.heartbeat_count,
+                                    thinking_tokens: ctx.thinking_tokens,
                                 });
                             }
 
@@ -500,7 +1055,7 @@ fn create_sse_stream(
                                 .into_iter()
                                 .map(|e| Ok(Bytes::from(e.to_sse_string())))
                                 .collect();
-                            Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval, None)))
+                            Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval, None, timing)))
                         }
                         None => {
                             // 流结束，发送最终事件
@@ -512,6 +1067,14 @@ fn create_sse_stream(
                                 let _ = tx.send(StreamStats {
                                     output_tokens: ctx.output_tokens,
                                     input_tokens: final_input_tokens,
+                                    was_filtered: ctx.was_filtered,
+                                    ttfb_ms: timing.ttfb_ms,
+                                    decode_ms: timing.decode.as_millis() as u64,
+                                    validation_errors: ctx.validation_errors,
+                                    context_window_used_pct: ctx.context_usage_pct,
+                                    credits_used: ctx.credits_used,
+                                    heartbeat_count: ctx.heartbeat_count,
+                                    thinking_tokens: ctx.thinking_tokens,
                                 });
                             }
 
@@ -519,7 +1082,7 @@ fn create_sse_stream(
                                 .into_iter()
                                 .map(|e| Ok(Bytes::from(e.to_sse_string())))
                                 .collect();
-                            Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval, None)))
+                            Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval, None, timing)))
                         }
                     }
                 }
@@ -527,7 +1090,7 @@ fn create_sse_stream(
                 _ = ping_interval.tick() => {
                     tracing::trace!("发送 ping 保活事件");
                     let bytes: Vec<Result<Bytes, Infallible>> = vec![Ok(create_ping_sse())];
-                    Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval, stats_tx)))
+                    Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval, stats_tx, timing)))
                 }
             }
         },
@@ -537,99 +1100,49 @@ fn create_sse_stream(
     initial_stream.chain(processing_stream)
 }
 
+/// 调试日志中请求体 / 响应体的最大字符数
+const BODY_LOG_MAX_CHARS: usize = 500;
+
+/// 截取用于调试日志的请求体 / 响应体片段，并隐去 access token
+fn body_log_excerpt(body: &str, access_token: Option<&str>) -> String {
+    let body = match access_token.filter(|t| !t.is_empty()) {
+        Some(token) => body.replace(token, "[REDACTED]"),
+        None => body.to_string(),
+    };
+    body.chars().take(BODY_LOG_MAX_CHARS).collect()
+}
+
 /// 上下文窗口大小（200k tokens）
 const CONTEXT_WINDOW_SIZE: i32 = 200_000;
 
 /// 处理非流式请求
 async fn handle_non_stream_request(
-    provider: std::sync::Arc<crate::kiro::provider::KiroProvider>,
-    request_body: &str,
-    model: &str,
-    input_tokens: i32,
-    account_id: Option<String>,
-    account_name: String,
-    pool: Option<std::sync::Arc<crate::pool::AccountPool>>,
-    start_time: std::time::Instant,
+    mut call: UpstreamCall<'_>,
+    response_body_log: bool,
+    content_filter: Option<std::sync::Arc<ContentFilter>>,
+    telemetry: std::sync::Arc<TelemetryStore>,
 ) -> Response {
-    // 调用 Kiro API
-    let response = match provider.call_api(request_body).await {
-        Ok(resp) => resp,
-        Err(e) => {
-            let error_msg = e.to_string();
-            tracing::error!("Kiro API 调用失败: {}", error_msg);
-
-            // 记录错误到账号池
-            if let (Some(id), Some(pool)) = (&account_id, &pool) {
-                let is_rate_limit = error_msg.contains("429") || error_msg.contains("rate");
-                let is_suspended = error_msg.contains("suspended") || error_msg.contains("403");
-                // 402 Payment Required 表示月度请求限制已达上限
-                let is_quota_exceeded = error_msg.contains("402")
-                    || error_msg.contains("Payment Required")
-                    || error_msg.contains("MONTHLY_REQUEST_COUNT")
-                    || error_msg.contains("reached the limit");
-
-                if is_suspended {
-                    pool.mark_invalid(id).await;
-                    tracing::warn!("账号 {} 已自动禁用（403/suspended）", id);
-                } else if is_quota_exceeded {
-                    let next_reset = pool.get_account_usage(id).await.and_then(|u| u.next_reset);
-                    pool.mark_exhausted(id, next_reset).await;
-                    tracing::warn!("账号 {} 已被标记为配额耗尽", id);
-                } else {
-                    pool.record_error(id, is_rate_limit).await;
-                    tracing::warn!("账号 {} 记录错误，限流: {}", id, is_rate_limit);
-                }
-
-                // 记录失败的请求
-                let log = crate::pool::RequestLog {
-                    id: uuid::Uuid::new_v4().to_string(),
-                    account_id: id.clone(),
-                    account_name: account_name.clone(),
-                    model: model.to_string(),
-                    input_tokens,
-                    output_tokens: 0,
-                    success: false,
-                    error: Some(error_msg.clone()),
-                    timestamp: chrono::Utc::now(),
-                    duration_ms: start_time.elapsed().as_millis() as u64,
-                };
-                pool.add_request_log(log).await;
-
-                // 对于配额耗尽，返回 402 错误
-                if is_quota_exceeded {
-                    return (
-                        StatusCode::PAYMENT_REQUIRED,
-                        Json(ErrorResponse::new(
-                            "billing_error",
-                            "Your account has reached its monthly request limit. Please check your plan and billing details.",
-                        )),
-                    )
-                        .into_response();
-                }
-
-                // 对于账号暂停，返回 403 错误
-                if is_suspended {
-                    return (
-                        StatusCode::FORBIDDEN,
-                        Json(ErrorResponse::new(
-                            "permission_error",
-                            "Your API key does not have permission to access this resource.",
-                        )),
-                    )
-                        .into_response();
-                }
-            }
-
-            return (
-                StatusCode::BAD_GATEWAY,
-                Json(ErrorResponse::new(
-                    "api_error",
-                    format!("上游 API 调用失败: {}", e),
-                )),
-            )
-                .into_response();
-        }
+    let (response, token_refresh_ms, call_start) = match call.send(false).await {
+        Ok(sent) => sent,
+        Err(response) => return response,
     };
+    // 首字节耗时取响应头到达的时间，避免把整段生成耗时计入延迟排序与遥测的 TTFB 分位数
+    let header_ms = call_start.elapsed().as_millis() as u64;
+    let UpstreamCall {
+        provider,
+        model,
+        input_tokens,
+        request_id,
+        account_id,
+        account_name,
+        session_id,
+        pool,
+        start_time,
+        ..
+    } = call;
+    if let (Some(id), Some(pool)) = (&account_id, &pool) {
+        pool.record_first_token_latency(id, header_ms).await;
+    }
 
     // 读取响应体
     let body_bytes = match response.bytes().await {
@@ -647,7 +1160,19 @@ async fn handle_non_stream_request(
         }
     };
 
+    if response_body_log {
+        let access_token = provider.access_token().await;
+        tracing::debug!(
+            response_body = %body_log_excerpt(
+                &String::from_utf8_lossy(&body_bytes),
+                access_token.as_deref(),
+            ),
+            "Kiro 响应体"
+        );
+    }
+
     // 解析事件流
+    let decode_start = std::time::Instant::now();
     let mut decoder = EventStreamDecoder::new();
     if let Err(e) = decoder.feed(&body_bytes) {
         tracing::warn!("缓冲区溢出: {}", e);
@@ -659,6 +1184,8 @@ async fn handle_non_stream_request(
     let mut stop_reason = "end_turn".to_string();
     // 从 contextUsageEvent 计算的实际输入 tokens
     let mut context_input_tokens: Option<i32> = None;
+    let mut context_window_used_pct: Option<f64> = None;
+    let mut credits_used: Option<f64> = None;
 
     // 收集工具调用的增量 JSON
     let mut tool_json_buffers: std::collections::HashMap<String, String> =
@@ -668,6 +1195,7 @@ async fn handle_non_stream_request(
         match result {
             Ok(frame) => {
                 if let Ok(event) = Event::from_frame(frame) {
+                    validate_event(&event);
                     match event {
                         Event::AssistantResponse(resp) => {
                             text_content.push_str(&resp.content);
@@ -700,6 +1228,9 @@ async fn handle_non_stream_request(
                                 }));
                             }
                         }
+                        Event::Metering(metering) => {
+                            *credits_used.get_or_insert(0.0) += metering.usage;
+                        }
                         Event::ContextUsage(context_usage) => {
                             // 从上下文使用百分比计算实际的 input_tokens
                             // 公式: percentage * 200000 / 100 = percentage * 2000
@@ -708,6 +1239,7 @@ async fn handle_non_stream_request(
                                 / 100.0)
                                 as i32;
                             context_input_tokens = Some(actual_input_tokens);
+                            context_window_used_pct = Some(context_usage.context_usage_percentage);
                             tracing::debug!(
                                 "收到 contextUsageEvent: {}%, 计算 input_tokens: {}",
                                 context_usage.context_usage_percentage,
@@ -729,6 +1261,24 @@ async fn handle_non_stream_request(
         }
     }
 
+    telemetry
+        .record(TelemetryRecord {
+            request_id: request_id.clone(),
+            token_refresh_ms,
+            ttfb_ms: Some(header_ms),
+            decode_ms: decode_start.elapsed().as_millis() as u64,
+        })
+        .await;
+
+    // 过滤敏感内容
+    let was_filtered = match content_filter.as_ref().and_then(|f| f.apply(&text_content)) {
+        Some(filtered) => {
+            text_content = filtered;
+            true
+        }
+        None => false,
+    };
+
     // 确定 stop_reason
     if has_tool_use && stop_reason == "end_turn" {
         stop_reason = "tool_use".to_string();
@@ -770,7 +1320,7 @@ async fn handle_non_stream_request(
     // 记录成功的请求
     if let (Some(id), Some(pool)) = (&account_id, &pool) {
         let log = crate::pool::RequestLog {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: request_id,
             account_id: id.clone(),
             account_name,
             model: model.to_string(),
@@ -780,6 +1330,11 @@ async fn handle_non_stream_request(
             error: None,
             timestamp: chrono::Utc::now(),
             duration_ms: start_time.elapsed().as_millis() as u64,
+            was_filtered,
+            context_window_used_pct,
+            credits_used,
+            heartbeat_count: None,
+            session_id: session_id.clone(),
         };
         pool.add_request_log(log).await;
     }
@@ -787,10 +1342,18 @@ async fn handle_non_stream_request(
     (StatusCode::OK, Json(response_body)).into_response()
 }
 
+/// GET /api/keys
+///
+/// 获取附加 API Key 的使用统计（仅主 API Key 可访问）
+pub async fn get_api_keys(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.allowed_keys.usage())
+}
+
 /// POST /v1/messages/count_tokens
 ///
 /// 计算消息的 token 数量
 pub async fn count_tokens(
+    State(state): State<AppState>,
     JsonExtractor(payload): JsonExtractor<CountTokensRequest>,
 ) -> impl IntoResponse {
     tracing::info!(
@@ -799,6 +1362,30 @@ pub async fn count_tokens(
         "Received POST /v1/messages/count_tokens request"
     );
 
+    let warnings = token::validate_messages(&payload.messages);
+
+    // 代理模式：通过账号调用 Kiro 服务端计数，失败时回退本地估算
+    let proxy_endpoint = match &state.config {
+        Some(config) => {
+            let config = config.read().await;
+            config
+                .count_tokens_proxy_mode
+                .then(|| config.count_tokens_api_endpoint.clone())
+        }
+        None => None,
+    };
+    if let Some(endpoint) = proxy_endpoint {
+        match proxy_count_tokens(&state, &payload, endpoint.as_deref()).await {
+            Ok(input_tokens) => {
+                return Json(CountTokensResponse {
+                    input_tokens: input_tokens.max(1),
+                    warnings,
+                })
+            }
+            Err(e) => tracing::warn!("count_tokens 代理失败，回退到本地估算: {}", e),
+        }
+    }
+
     let total_tokens = token::count_all_tokens(
         payload.model,
         payload.system,
@@ -808,5 +1395,182 @@ pub async fn count_tokens(
 
     Json(CountTokensResponse {
         input_tokens: total_tokens.max(1) as i32,
+        warnings,
     })
 }
+
+/// 通过账号池（或单账号）调用 Kiro 服务端 count_tokens 接口
+async fn proxy_count_tokens(
+    state: &AppState,
+    payload: &CountTokensRequest,
+    endpoint: Option<&str>,
+) -> anyhow::Result<i32> {
+    let provider = match &state.account_pool {
+        // 只读取账号的 Provider，不经过账号选择，避免计入账号统计或占用熔断探测名额
+        Some(pool) => {
+            let id = pool
+                .peek_available_account()
+                .await
+                .ok_or_else(|| anyhow::anyhow!("账号池中没有可用账号"))?;
+            pool.get_provider(&id)
+                .await
+                .ok_or_else(|| anyhow::anyhow!("账号 {} 的 Provider 不存在", id))?
+        }
+        None => state
+            .kiro_provider
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("KiroProvider 未配置"))?,
+    };
+
+    let request = MessagesRequest {
+        model: payload.model.clone(),
+        max_tokens: 1,
+        messages: payload.messages.clone(),
+        stream: false,
+        system: payload.system.clone(),
+        tools: payload.tools.clone(),
+        tool_choice: None,
+        thinking: None,
+        streaming_format: None,
+    };
+    let conversion_result = convert_request(&request)?;
+    let kiro_request = KiroRequest::builder()
+        .conversation_state(conversion_result.conversation_state)
+        .profile_arn(state.profile_arn.clone())
+        .build()?;
+    let request_body = serde_json::to_string(&kiro_request)?;
+
+    provider.count_tokens(&request_body, endpoint).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_status() {
+        assert_eq!(health_status(0, 0, 0.5), "unavailable");
+        assert_eq!(health_status(0, 4, 0.5), "unavailable");
+        assert_eq!(health_status(1, 4, 0.5), "degraded");
+        assert_eq!(health_status(2, 4, 0.5), "ok");
+        assert_eq!(health_status(1, 1, 0.5), "ok");
+    }
+
+    #[test]
+    fn test_upstream_status_from_error() {
+        assert_eq!(
+            upstream_status_from_error("流式 API 请求失败: 429 Too Many Requests {}"),
+            Some(429)
+        );
+        assert_eq!(
+            upstream_status_from_error("非流式 API 请求失败: 503 Service Unavailable"),
+            Some(503)
+        );
+        assert_eq!(upstream_status_from_error("error sending request"), None);
+    }
+
+    #[tokio::test]
+    async fn test_failover_skips_tried_accounts() {
+        use crate::kiro::model::credentials::KiroCredentials;
+        use crate::model::config::Config;
+        use crate::pool::AccountBuilder;
+
+        let pool = AccountPool::new(Config::default(), None);
+        for id in ["a", "b", "c"] {
+            pool.add_account(AccountBuilder::new(id, id, KiroCredentials::default()).build())
+                .await
+                .unwrap();
+        }
+        let failover = AccountFailover {
+            policy: RetryPolicy::default(),
+            provider_retries: false,
+            preferred_region: None,
+            tags: Vec::new(),
+        };
+        let rate_limited = "流式 API 请求失败: 429 Too Many Requests";
+
+        let mut tried = vec!["a".to_string()];
+        let first = failover
+            .next_account(&pool, rate_limited, &mut tried)
+            .await
+            .unwrap();
+        let second = failover
+            .next_account(&pool, rate_limited, &mut tried)
+            .await
+            .unwrap();
+        let mut ids = vec![first.id, second.id];
+        ids.sort();
+        assert_eq!(ids, ["b", "c"]);
+
+        // 达到 maxRetries 后不再换号
+        assert!(failover
+            .next_account(&pool, rate_limited, &mut tried)
+            .await
+            .is_none());
+
+        // 不在可重试列表中的状态码不换号
+        let mut tried = vec!["a".to_string()];
+        assert!(failover
+            .next_account(&pool, "流式 API 请求失败: 400 Bad Request", &mut tried)
+            .await
+            .is_none());
+
+        // Provider 已重试过的 5xx 不再换号，429 仍换号
+        let failover = AccountFailover {
+            provider_retries: true,
+            ..failover
+        };
+        let mut tried = vec!["a".to_string()];
+        assert!(failover
+            .next_account(
+                &pool,
+                "流式 API 请求失败: 503 Service Unavailable",
+                &mut tried
+            )
+            .await
+            .is_none());
+        assert!(failover
+            .next_account(&pool, rate_limited, &mut tried)
+            .await
+            .is_some());
+    }
+
+    #[test]
+    fn test_session_id_from_headers_validates_format() {
+        let with = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-session-id", value.parse().unwrap());
+            session_id_from_headers(&headers)
+        };
+        assert_eq!(with("chat-42-abc"), Some("chat-42-abc".to_string()));
+        assert_eq!(with("bad id"), None);
+        assert_eq!(with("a_b"), None);
+        assert_eq!(with(&"a".repeat(MAX_SESSION_ID_LEN + 1)), None);
+        assert_eq!(session_id_from_headers(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_tags_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-kiro-tags", "tier=high, opus,,".parse().unwrap());
+        assert_eq!(tags_from_headers(&headers), vec!["tier=high", "opus"]);
+        assert!(tags_from_headers(&HeaderMap::new()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_raw_stream_forwards_upstream_bytes_unchanged() {
+        let upstream: Vec<u8> = vec![0, 0, 0, 42, 0xff, b'{', b'}', 0x00, 0x7f];
+        let response = reqwest::Response::from(http::Response::new(upstream.clone()));
+
+        let forwarded = raw_stream_response(response);
+        assert_eq!(forwarded.status(), StatusCode::OK);
+        assert_eq!(
+            forwarded.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        let body = axum::body::to_bytes(forwarded.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.as_ref(), upstream.as_slice());
+    }
+}
@@ -0,0 +1,251 @@
+//! 多 API Key 管理
+//!
+//! 除主 API Key 外，允许配置多个附加 Key，每个 Key 可设置每日请求配额
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::middleware::constant_time_eq;
+
+/// 单个 Key 的配额与使用量
+pub struct KeyQuota {
+    /// 每日请求上限（None 表示不限）
+    pub requests_per_day: Option<u32>,
+    /// 当日用量：高 32 位为日期（自 UNIX 纪元起的天数），低 32 位为当日已用请求数，
+    /// 合并存放以便跨日清零与计数在同一次 CAS 中完成
+    daily: AtomicU64,
+    /// 累计请求数
+    total_requests: AtomicU64,
+}
+
+impl KeyQuota {
+    pub fn new(requests_per_day: Option<u32>) -> Self {
+        Self {
+            requests_per_day,
+            daily: AtomicU64::new(pack_daily(current_day(), 0)),
+            total_requests: AtomicU64::new(0),
+        }
+    }
+
+    /// 尝试占用一次请求配额，超出每日上限时返回 false
+    fn try_acquire(&self) -> bool {
+        self.try_acquire_on(current_day())
+    }
+
+    fn try_acquire_on(&self, today: u32) -> bool {
+        let acquired = self
+            .daily
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |packed| {
+                let (day, used) = unpack_daily(packed);
+                let used = if day == today { used } else { 0 };
+                match self.requests_per_day {
+                    Some(limit) if used >= limit => None,
+                    _ => Some(pack_daily(today, used.saturating_add(1))),
+                }
+            })
+            .is_ok();
+        if acquired {
+            self.total_requests.fetch_add(1, Ordering::SeqCst);
+        }
+        acquired
+    }
+
+    /// 指定日期的已用请求数（记录的日期不是该日时为 0）
+    fn used_on(&self, today: u32) -> u32 {
+        match unpack_daily(self.daily.load(Ordering::SeqCst)) {
+            (day, used) if day == today => used,
+            _ => 0,
+        }
+    }
+}
+
+fn current_day() -> u32 {
+    Utc::now().timestamp().div_euclid(86_400) as u32
+}
+
+fn pack_daily(day: u32, used: u32) -> u64 {
+    (u64::from(day) << 32) | u64::from(used)
+}
+
+fn unpack_daily(packed: u64) -> (u32, u32) {
+    ((packed >> 32) as u32, packed as u32)
+}
+
+/// Key 校验结果
+#[derive(Debug, PartialEq, Eq)]
+pub enum KeyCheck {
+    /// 允许访问
+    Allowed,
+    /// 当日配额已用尽
+    QuotaExceeded,
+    /// 未知 Key
+    Unknown,
+}
+
+/// 附加 Key 的配额配置（ALLOWED_API_KEYS_JSON 中的值）
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyQuotaConfig {
+    #[serde(default)]
+    requests_per_day: Option<u32>,
+}
+
+/// 单个 Key 的使用统计
+#[derive(Debug, Serialize)]
+pub struct KeyUsage {
+    /// 脱敏后的 Key
+    pub key: String,
+    pub requests_per_day: Option<u32>,
+    pub used_today: u32,
+    pub total_requests: u64,
+}
+
+/// 附加 API Key 集合
+#[derive(Default)]
+pub struct AllowedKeys {
+    keys: HashMap<String, KeyQuota>,
+}
+
+impl AllowedKeys {
+    /// 由配置中的 Key 列表和 ALLOWED_API_KEYS_JSON 构建
+    ///
+    /// ALLOWED_API_KEYS_JSON 支持字符串数组，或 `{"<key>": {"requestsPerDay": 100}}` 形式的对象
+    pub fn load(config_keys: &[String]) -> anyhow::Result<Self> {
+        let mut allowed = Self::default();
+        for key in config_keys {
+            allowed.insert(key.clone(), None);
+        }
+
+        if let Ok(json) = std::env::var("ALLOWED_API_KEYS_JSON") {
+            allowed.extend_from_json(&json)?;
+        }
+        Ok(allowed)
+    }
+
+    fn extend_from_json(&mut self, json: &str) -> anyhow::Result<()> {
+        let value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| anyhow::anyhow!("ALLOWED_API_KEYS_JSON 解析失败: {}", e))?;
+        match value {
+            serde_json::Value::Array(_) => {
+                let keys: Vec<String> = serde_json::from_value(value)
+                    .map_err(|e| anyhow::anyhow!("ALLOWED_API_KEYS_JSON 解析失败: {}", e))?;
+                for key in keys {
+                    self.insert(key, None);
+                }
+            }
+            serde_json::Value::Object(_) => {
+                let keys: HashMap<String, Option<KeyQuotaConfig>> =
+                    serde_json::from_value(value)
+                        .map_err(|e| anyhow::anyhow!("ALLOWED_API_KEYS_JSON 解析失败: {}", e))?;
+                for (key, quota) in keys {
+                    self.insert(key, quota.unwrap_or_default().requests_per_day);
+                }
+            }
+            _ => anyhow::bail!("ALLOWED_API_KEYS_JSON 必须是数组或对象"),
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, key: String, requests_per_day: Option<u32>) {
+        if !key.is_empty() {
+            self.keys.insert(key, KeyQuota::new(requests_per_day));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// 校验 Key 并记录使用量
+    pub fn check(&self, key: &str) -> KeyCheck {
+        // 逐个常量时间比较，避免通过时序泄露 Key
+        let mut matched = None;
+        for (candidate, quota) in &self.keys {
+            if constant_time_eq(key, candidate) {
+                matched = Some(quota);
+            }
+        }
+
+        match matched {
+            Some(quota) if quota.try_acquire() => KeyCheck::Allowed,
+            Some(_) => KeyCheck::QuotaExceeded,
+            None => KeyCheck::Unknown,
+        }
+    }
+
+    /// 获取每个 Key 的使用统计
+    pub fn usage(&self) -> Vec<KeyUsage> {
+        let today = current_day();
+        let mut usage: Vec<KeyUsage> = self
+            .keys
+            .iter()
+            .map(|(key, quota)| KeyUsage {
+                key: mask_key(key),
+                requests_per_day: quota.requests_per_day,
+                used_today: quota.used_on(today),
+                total_requests: quota.total_requests.load(Ordering::SeqCst),
+            })
+            .collect();
+        usage.sort_by(|a, b| a.key.cmp(&b.key));
+        usage
+    }
+}
+
+/// Key 脱敏：仅保留前 6 位
+fn mask_key(key: &str) -> String {
+    let prefix: String = key.chars().take(6).collect();
+    format!("{}***", prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_keys_quota() {
+        let mut keys = AllowedKeys::default();
+        keys.extend_from_json(r#"{"sk-team-a": {"requestsPerDay": 2}, "sk-team-b": null}"#)
+            .unwrap();
+        assert_eq!(keys.len(), 2);
+
+        assert_eq!(keys.check("sk-team-a"), KeyCheck::Allowed);
+        assert_eq!(keys.check("sk-team-a"), KeyCheck::Allowed);
+        assert_eq!(keys.check("sk-team-a"), KeyCheck::QuotaExceeded);
+        assert_eq!(keys.check("sk-team-b"), KeyCheck::Allowed);
+        assert_eq!(keys.check("sk-unknown"), KeyCheck::Unknown);
+
+        let usage = keys.usage();
+        assert_eq!(usage[0].key, "sk-tea***");
+        assert_eq!(usage.iter().map(|u| u.total_requests).sum::<u64>(), 3);
+    }
+
+    #[test]
+    fn test_quota_resets_on_new_day() {
+        let quota = KeyQuota::new(Some(1));
+        let today = current_day();
+        assert!(quota.try_acquire_on(today));
+        assert!(!quota.try_acquire_on(today));
+        assert_eq!(quota.used_on(today), 1);
+
+        // 跨日后计数在同一次更新中清零并计入新的请求
+        assert!(quota.try_acquire_on(today + 1));
+        assert_eq!(quota.used_on(today + 1), 1);
+        assert_eq!(quota.used_on(today), 0);
+        assert!(!quota.try_acquire_on(today + 1));
+    }
+
+    #[test]
+    fn test_allowed_keys_from_array() {
+        let mut keys = AllowedKeys::default();
+        keys.extend_from_json(r#"["sk-1", "sk-2"]"#).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert!(keys.extend_from_json("42").is_err());
+    }
+}
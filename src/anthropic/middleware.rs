@@ -1,19 +1,56 @@
 //! Anthropic API 中间件
 
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 
 use axum::{
     body::Body,
     extract::State,
-    http::{header, Request, StatusCode},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Json, Response},
 };
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
 
 use crate::kiro::provider::KiroProvider;
+use crate::kiro::telemetry::TelemetryStore;
+use crate::model::config::{Config, RetryPolicy, UpstreamRetryPolicy};
 use crate::pool::AccountPool;
 
-use super::types::ErrorResponse;
+use super::converter::apply_system_template;
+use super::filter::ContentFilter;
+use super::keys::{AllowedKeys, KeyCheck};
+use super::types::{ErrorResponse, MessagesRequest};
+
+/// 幂等缓存有效期（5 分钟）
+const IDEMPOTENCY_TTL_SECS: i64 = 5 * 60;
+/// 幂等缓存清理间隔（1 分钟）
+const IDEMPOTENCY_EVICT_INTERVAL_SECS: u64 = 60;
+
+/// 幂等响应缓存（(调用方 API Key, Idempotency-Key) -> (缓存时间, 请求体哈希, 响应 JSON)）
+type IdempotencyCache = RwLock<HashMap<(String, String), (DateTime<Utc>, u64, serde_json::Value)>>;
+
+/// 幂等缓存查询结果
+#[derive(Debug, PartialEq)]
+pub enum IdempotentLookup {
+    /// 无缓存或已过期
+    Miss,
+    /// 命中，返回缓存的响应
+    Hit(serde_json::Value),
+    /// 同一 Idempotency-Key 对应的请求体不同
+    BodyMismatch,
+}
+
+/// 请求体哈希（用于识别复用 Idempotency-Key 的不同请求）
+pub fn body_hash(body: &[u8]) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    body.hash(&mut hasher);
+    hasher.finish()
+}
 
 /// 应用共享状态
 #[derive(Clone)]
@@ -27,6 +64,18 @@ pub struct AppState {
     pub profile_arn: Option<String>,
     /// 账号池（可选，用于多账号模式）
     pub account_pool: Option<Arc<AccountPool>>,
+    /// 输出内容过滤器（可选）
+    pub content_filter: Option<Arc<ContentFilter>>,
+    /// 非流式请求的幂等响应缓存
+    pub idempotency_cache: Arc<IdempotencyCache>,
+    /// 调用耗时遥测
+    pub telemetry: Arc<TelemetryStore>,
+    /// 可热更新的共享配置（可选，设置后 API Key 以其为准）
+    pub config: Option<Arc<RwLock<Config>>>,
+    /// 附加 API Key（可设置每日配额）
+    pub allowed_keys: Arc<AllowedKeys>,
+    /// 服务启动时间
+    pub start_time: Instant,
 }
 
 impl AppState {
@@ -37,6 +86,12 @@ impl AppState {
             kiro_provider: None,
             profile_arn: None,
             account_pool: None,
+            content_filter: None,
+            idempotency_cache: Arc::new(RwLock::new(HashMap::new())),
+            telemetry: Arc::new(TelemetryStore::default()),
+            config: None,
+            allowed_keys: Arc::new(AllowedKeys::default()),
+            start_time: Instant::now(),
         }
     }
 
@@ -57,6 +112,174 @@ impl AppState {
         self.account_pool = Some(pool);
         self
     }
+
+    /// 设置附加 API Key
+    pub fn with_allowed_keys(mut self, allowed_keys: Arc<AllowedKeys>) -> Self {
+        self.allowed_keys = allowed_keys;
+        self
+    }
+
+    /// 设置可热更新的共享配置
+    pub fn with_shared_config(mut self, config: Arc<RwLock<Config>>) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// 当前生效的 API Key
+    pub async fn current_api_key(&self) -> String {
+        match &self.config {
+            Some(config) => config
+                .read()
+                .await
+                .api_key
+                .clone()
+                .unwrap_or_else(|| self.api_key.clone()),
+            None => self.api_key.clone(),
+        }
+    }
+
+    /// 是否允许原样转发上游事件流
+    pub async fn allow_raw_stream(&self) -> bool {
+        match &self.config {
+            Some(config) => config.read().await.allow_raw_stream,
+            None => false,
+        }
+    }
+
+    /// 换号重试策略（未关联配置时使用默认值）
+    pub async fn retry_policy(&self) -> RetryPolicy {
+        match &self.config {
+            Some(config) => config.read().await.retry_policy.clone(),
+            None => RetryPolicy::default(),
+        }
+    }
+
+    /// Provider 是否会对上游 5xx 做退避重试（未关联配置时使用默认值）
+    pub async fn upstream_retries_enabled(&self) -> bool {
+        let policy = match &self.config {
+            Some(config) => config.read().await.upstream_retry_policy.clone(),
+            None => UpstreamRetryPolicy::default(),
+        };
+        policy.max_retries > 0
+    }
+
+    /// `/health` 的 degraded 比例阈值（未关联配置时使用默认值）
+    pub async fn health_degraded_ratio(&self) -> f64 {
+        match &self.config {
+            Some(config) => config.read().await.health_degraded_ratio,
+            None => Config::default().health_degraded_ratio,
+        }
+    }
+
+    /// 请求体 / 响应体调试日志开关
+    pub async fn body_log_flags(&self) -> (bool, bool) {
+        match &self.config {
+            Some(config) => {
+                let config = config.read().await;
+                (config.request_body_log, config.response_body_log)
+            }
+            None => (false, false),
+        }
+    }
+
+    /// 注入指定名称的系统提示词模板，模板不存在时返回 false
+    pub async fn apply_system_template(&self, req: &mut MessagesRequest, name: &str) -> bool {
+        match &self.config {
+            Some(config) => {
+                apply_system_template(req, name, &config.read().await.system_prompt_templates)
+            }
+            None => false,
+        }
+    }
+
+    /// 系统提示词注入内容（前缀, 后缀）
+    pub async fn system_prompt_affixes(&self) -> (Option<String>, Option<String>) {
+        match &self.config {
+            Some(config) => {
+                let config = config.read().await;
+                (
+                    config.system_prompt_prefix.clone(),
+                    config.system_prompt_suffix.clone(),
+                )
+            }
+            None => (None, None),
+        }
+    }
+
+    /// 设置遥测存储（与管理 API 共享）
+    pub fn with_telemetry(mut self, telemetry: Arc<TelemetryStore>) -> Self {
+        self.telemetry = telemetry;
+        self
+    }
+
+    /// 查询未过期的幂等缓存（按调用方 API Key 隔离，请求体不一致时返回 `BodyMismatch`）
+    pub async fn get_idempotent_response(
+        &self,
+        api_key: &str,
+        key: &str,
+        body_hash: u64,
+    ) -> IdempotentLookup {
+        let cache = self.idempotency_cache.read().await;
+        match cache.get(&(api_key.to_string(), key.to_string())) {
+            Some((cached_at, _, _))
+                if (Utc::now() - *cached_at).num_seconds() >= IDEMPOTENCY_TTL_SECS =>
+            {
+                IdempotentLookup::Miss
+            }
+            Some((_, cached_hash, _)) if *cached_hash != body_hash => {
+                IdempotentLookup::BodyMismatch
+            }
+            Some((_, _, value)) => IdempotentLookup::Hit(value.clone()),
+            None => IdempotentLookup::Miss,
+        }
+    }
+
+    /// 缓存幂等响应
+    pub async fn store_idempotent_response(
+        &self,
+        api_key: String,
+        key: String,
+        body_hash: u64,
+        value: serde_json::Value,
+    ) {
+        let mut cache = self.idempotency_cache.write().await;
+        cache.insert((api_key, key), (Utc::now(), body_hash, value));
+    }
+
+    /// 清理过期的幂等缓存，返回清理数量
+    pub async fn evict_expired_idempotency(&self) -> usize {
+        let mut cache = self.idempotency_cache.write().await;
+        let before = cache.len();
+        let now = Utc::now();
+        cache
+            .retain(|_, (cached_at, _, _)| (now - *cached_at).num_seconds() < IDEMPOTENCY_TTL_SECS);
+        before - cache.len()
+    }
+
+    /// 启动后台任务，每分钟清理过期的幂等缓存
+    pub fn spawn_idempotency_eviction(&self) {
+        let state = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(std::time::Duration::from_secs(
+                IDEMPOTENCY_EVICT_INTERVAL_SECS,
+            ));
+            loop {
+                ticker.tick().await;
+                let evicted = state.evict_expired_idempotency().await;
+                if evicted > 0 {
+                    tracing::debug!("已清理 {} 条过期幂等缓存", evicted);
+                }
+            }
+        });
+    }
+
+    /// 设置输出内容过滤器（无启用规则时忽略）
+    pub fn with_content_filter(mut self, filter: ContentFilter) -> Self {
+        if !filter.is_empty() {
+            self.content_filter = Some(Arc::new(filter));
+        }
+        self
+    }
 }
 
 /// 从请求中提取 API Key
@@ -65,18 +288,18 @@ impl AppState {
 /// - `x-api-key` header
 /// - `Authorization: Bearer <token>` header
 fn extract_api_key(request: &Request<Body>) -> Option<String> {
+    api_key_from_headers(request.headers())
+}
+
+/// 从请求头提取调用方 API Key（与认证中间件的取值规则一致）
+pub fn api_key_from_headers(headers: &HeaderMap) -> Option<String> {
     // 优先检查 x-api-key
-    if let Some(key) = request
-        .headers()
-        .get("x-api-key")
-        .and_then(|v| v.to_str().ok())
-    {
+    if let Some(key) = headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
         return Some(key.to_string());
     }
 
     // 其次检查 Authorization: Bearer
-    request
-        .headers()
+    headers
         .get(header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
         .and_then(|v| v.strip_prefix("Bearer "))
@@ -87,7 +310,7 @@ fn extract_api_key(request: &Request<Body>) -> Option<String> {
 ///
 /// 无论字符串内容如何，比较所需的时间都是恒定的，
 /// 这可以防止攻击者通过测量响应时间来猜测 API Key。
-fn constant_time_eq(a: &str, b: &str) -> bool {
+pub(crate) fn constant_time_eq(a: &str, b: &str) -> bool {
     let a_bytes = a.as_bytes();
     let b_bytes = b.as_bytes();
 
@@ -112,13 +335,47 @@ fn constant_time_eq(a: &str, b: &str) -> bool {
 }
 
 /// API Key 认证中间件
+///
+/// 接受主 API Key 或任一附加 Key，附加 Key 超出每日配额时返回 429
 pub async fn auth_middleware(
     State(state): State<AppState>,
     request: Request<Body>,
     next: Next,
 ) -> Response {
+    let api_key = state.current_api_key().await;
+    let Some(key) = extract_api_key(&request) else {
+        let error = ErrorResponse::authentication_error();
+        return (StatusCode::UNAUTHORIZED, Json(error)).into_response();
+    };
+
+    if constant_time_eq(&key, &api_key) {
+        return next.run(request).await;
+    }
+    match state.allowed_keys.check(&key) {
+        KeyCheck::Allowed => next.run(request).await,
+        KeyCheck::QuotaExceeded => {
+            let error = ErrorResponse::new(
+                "rate_limit_error",
+                "This API key has exceeded its daily request quota.",
+            );
+            (StatusCode::TOO_MANY_REQUESTS, Json(error)).into_response()
+        }
+        KeyCheck::Unknown => {
+            let error = ErrorResponse::authentication_error();
+            (StatusCode::UNAUTHORIZED, Json(error)).into_response()
+        }
+    }
+}
+
+/// 管理接口认证中间件（仅接受主 API Key）
+pub async fn admin_auth_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let api_key = state.current_api_key().await;
     match extract_api_key(&request) {
-        Some(key) if constant_time_eq(&key, &state.api_key) => next.run(request).await,
+        Some(key) if constant_time_eq(&key, &api_key) => next.run(request).await,
         _ => {
             let error = ErrorResponse::authentication_error();
             (StatusCode::UNAUTHORIZED, Json(error)).into_response()
@@ -126,20 +383,241 @@ pub async fn auth_middleware(
     }
 }
 
+/// 判断请求 Content-Type 是否为 JSON（允许 charset 等参数）
+fn is_json_content_type(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+        .unwrap_or(false)
+}
+
+/// Content-Type 校验中间件
+///
+/// POST 请求的 Content-Type 必须为 application/json，否则返回 415
+pub async fn content_negotiation_middleware(request: Request<Body>, next: Next) -> Response {
+    if request.method() == Method::POST && !is_json_content_type(request.headers()) {
+        let body = serde_json::json!({
+            "type": "error",
+            "error": {
+                "type": "invalid_request_error",
+                "message": "Content-Type must be application/json"
+            }
+        });
+        return (StatusCode::UNSUPPORTED_MEDIA_TYPE, Json(body)).into_response();
+    }
+    next.run(request).await
+}
+
 /// CORS 中间件层
 ///
-/// **安全说明**：当前配置允许所有来源（Any），这是为了支持公开 API 服务。
-/// 如果需要更严格的安全控制，请根据实际需求配置具体的允许来源、方法和头信息。
+/// **安全说明**：默认允许所有来源（`"*"`），以支持公开 API 服务。
+/// 如需更严格的控制，请通过 `corsAllowedOrigins` / `CORS_ORIGINS` 指定允许的来源。
 ///
 /// # 配置说明
-/// - `allow_origin(Any)`: 允许任何来源的请求
+/// - `allow_origin`: 来源列表为空或包含 `"*"` 时允许任意来源，无法解析的来源会被忽略
 /// - `allow_methods(Any)`: 允许任何 HTTP 方法
-/// - `allow_headers(Any)`: 允许任何请求头
-pub fn cors_layer() -> tower_http::cors::CorsLayer {
-    use tower_http::cors::{Any, CorsLayer};
+/// - `allow_headers`: `Content-Type`、`Authorization`、`x-api-key`、`anthropic-version`
+/// - `expose_headers`: `X-Request-ID`
+pub fn cors_layer(allowed_origins: &[String]) -> tower_http::cors::CorsLayer {
+    use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+    let allow_origin = if allowed_origins.is_empty() || allowed_origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(allowed_origins.iter().filter_map(|origin| {
+            HeaderValue::from_str(origin)
+                .inspect_err(|_| tracing::warn!("忽略无效的 CORS 来源: {}", origin))
+                .ok()
+        }))
+    };
 
     CorsLayer::new()
-        .allow_origin(Any)
+        .allow_origin(allow_origin)
         .allow_methods(Any)
-        .allow_headers(Any)
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            HeaderName::from_static("x-api-key"),
+            HeaderName::from_static("anthropic-version"),
+        ])
+        .expose_headers([HeaderName::from_static("x-request-id")])
+}
+
+/// 响应压缩中间件层
+///
+/// 仅压缩非流式响应：`text/event-stream` 被显式排除，
+/// 因为 SSE 在 Content-Encoding 压缩下无法逐事件推送
+pub fn compression_layer() -> tower_http::compression::CompressionLayer<
+    tower_http::compression::predicate::And<
+        tower_http::compression::DefaultPredicate,
+        tower_http::compression::predicate::NotForContentType,
+    >,
+> {
+    use tower_http::compression::predicate::{NotForContentType, Predicate};
+    use tower_http::compression::{CompressionLayer, DefaultPredicate};
+
+    CompressionLayer::new().compress_when(
+        DefaultPredicate::new().and(NotForContentType::const_new("text/event-stream")),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_json_content_type() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_json_content_type(&headers));
+
+        headers.insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+        assert!(is_json_content_type(&headers));
+
+        headers.insert(
+            header::CONTENT_TYPE,
+            "Application/JSON; charset=utf-8".parse().unwrap(),
+        );
+        assert!(is_json_content_type(&headers));
+
+        headers.insert(
+            header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded".parse().unwrap(),
+        );
+        assert!(!is_json_content_type(&headers));
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight_allows_configured_origin() {
+        use tower::ServiceExt;
+
+        let origins = vec!["https://app.example.com".to_string()];
+        let app = axum::Router::new()
+            .route("/v1/messages", axum::routing::post(|| async { "ok" }))
+            .layer(cors_layer(&origins));
+
+        let preflight = |origin: &'static str| {
+            Request::builder()
+                .method(Method::OPTIONS)
+                .uri("/v1/messages")
+                .header(header::ORIGIN, origin)
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(preflight("https://app.example.com"))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+
+        let response = app
+            .oneshot(preflight("https://evil.example.com"))
+            .await
+            .unwrap();
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn test_compression_layer_gzips_json_but_not_sse() {
+        use tower::ServiceExt;
+
+        let large = serde_json::json!({ "text": "x".repeat(64 * 1024) });
+        let app = axum::Router::new()
+            .route(
+                "/json",
+                axum::routing::post(move || async move { Json(large) }),
+            )
+            .route(
+                "/sse",
+                axum::routing::post(|| async {
+                    (
+                        [(header::CONTENT_TYPE, "text/event-stream")],
+                        "x".repeat(64 * 1024),
+                    )
+                }),
+            )
+            .layer(compression_layer());
+
+        let request = |uri: &str| {
+            Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header(header::ACCEPT_ENCODING, "gzip")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("/json")).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+
+        let response = app.oneshot(request("/sse")).await.unwrap();
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_cache_expiry() {
+        let state = AppState::new("key");
+        state
+            .store_idempotent_response(
+                "caller".to_string(),
+                "k1".to_string(),
+                7,
+                serde_json::json!({"id": 1}),
+            )
+            .await;
+        assert_eq!(
+            state.get_idempotent_response("caller", "k1", 7).await,
+            IdempotentLookup::Hit(serde_json::json!({"id": 1}))
+        );
+
+        {
+            let mut cache = state.idempotency_cache.write().await;
+            cache
+                .get_mut(&("caller".to_string(), "k1".to_string()))
+                .unwrap()
+                .0 = Utc::now() - chrono::Duration::seconds(IDEMPOTENCY_TTL_SECS + 1);
+        }
+        assert_eq!(
+            state.get_idempotent_response("caller", "k1", 7).await,
+            IdempotentLookup::Miss
+        );
+        assert_eq!(state.evict_expired_idempotency().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_cache_scoped_by_caller_and_body() {
+        let state = AppState::new("key");
+        let body = body_hash(br#"{"model":"m"}"#);
+        state
+            .store_idempotent_response(
+                "alice".to_string(),
+                "k1".to_string(),
+                body,
+                serde_json::json!({"id": 1}),
+            )
+            .await;
+
+        // 其他调用方复用同一 Idempotency-Key 不会命中
+        assert_eq!(
+            state.get_idempotent_response("bob", "k1", body).await,
+            IdempotentLookup::Miss
+        );
+        // 同一调用方换了请求体
+        assert_eq!(
+            state
+                .get_idempotent_response("alice", "k1", body_hash(br#"{"model":"x"}"#))
+                .await,
+            IdempotentLookup::BodyMismatch
+        );
+    }
 }
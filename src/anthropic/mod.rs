@@ -16,11 +16,15 @@
 //! axum::serve(listener, app).await?;
 //! ```
 
+pub mod benchmark;
 mod converter;
+pub mod filter;
 mod handlers;
+pub mod keys;
 mod middleware;
 mod router;
 mod stream;
 pub mod types;
 
+pub(crate) use middleware::constant_time_eq;
 pub use router::{create_router_with_pool, create_router_with_provider};
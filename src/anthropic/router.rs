@@ -6,13 +6,23 @@ use axum::{
     Router,
 };
 use std::sync::Arc;
+use tokio::sync::RwLock;
 
 use crate::kiro::provider::KiroProvider;
+use crate::kiro::telemetry::TelemetryStore;
+use crate::model::config::Config;
 use crate::pool::AccountPool;
 
 use super::{
-    handlers::{count_tokens, get_models, openai_chat_completions, post_messages},
-    middleware::{auth_middleware, cors_layer, AppState},
+    filter::ContentFilter,
+    handlers::{
+        count_tokens, get_api_keys, get_models, health, openai_chat_completions, post_messages,
+    },
+    keys::AllowedKeys,
+    middleware::{
+        admin_auth_middleware, auth_middleware, compression_layer, content_negotiation_middleware,
+        cors_layer, AppState,
+    },
 };
 /// 创建 Anthropic API 路由
 ///
@@ -20,6 +30,7 @@ use super::{
 /// - `GET /v1/models` - 获取可用模型列表
 /// - `POST /v1/messages` - 创建消息（对话）
 /// - `POST /v1/messages/count_tokens` - 计算 token 数量
+/// - `GET /health` - 健康检查（免认证）
 ///
 /// # 认证
 /// 所有 `/v1` 路径需要 API Key 认证，支持：
@@ -35,14 +46,22 @@ pub fn create_router_with_provider(
     api_key: impl Into<String>,
     kiro_provider: Option<KiroProvider>,
     profile_arn: Option<String>,
+    content_filter: ContentFilter,
+    shared_config: Arc<RwLock<Config>>,
+    allowed_keys: Arc<AllowedKeys>,
+    config: &Config,
 ) -> Router {
-    let mut state = AppState::new(api_key);
+    let mut state = AppState::new(api_key)
+        .with_content_filter(content_filter)
+        .with_shared_config(shared_config)
+        .with_allowed_keys(allowed_keys);
     if let Some(provider) = kiro_provider {
         state = state.with_kiro_provider(provider);
     }
     if let Some(arn) = profile_arn {
         state = state.with_profile_arn(arn);
     }
+    state.spawn_idempotency_eviction();
 
     // 需要认证的 /v1 路由
     let v1_routes = Router::new()
@@ -51,20 +70,46 @@ pub fn create_router_with_provider(
         .route("/messages/count_tokens", post(count_tokens))
         // OpenAI 格式请求拦截
         .route("/chat/completions", post(openai_chat_completions))
+        .layer(middleware::from_fn(content_negotiation_middleware))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
         ));
 
-    Router::new()
+    // 仅主 API Key 可访问的管理路由
+    let admin_routes =
+        Router::new()
+            .route("/api/keys", get(get_api_keys))
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                admin_auth_middleware,
+            ));
+
+    let router = Router::new()
+        .route("/health", get(health))
         .nest("/v1", v1_routes)
-        .layer(cors_layer())
-        .with_state(state)
+        .merge(admin_routes)
+        .layer(cors_layer(&config.cors_allowed_origins));
+    with_compression(router, config).with_state(state)
 }
 
 /// 创建带有账号池的 Anthropic API 路由
-pub fn create_router_with_pool(api_key: impl Into<String>, pool: Arc<AccountPool>) -> Router {
-    let state = AppState::new(api_key).with_account_pool(pool);
+pub fn create_router_with_pool(
+    api_key: impl Into<String>,
+    pool: Arc<AccountPool>,
+    content_filter: ContentFilter,
+    telemetry: Arc<TelemetryStore>,
+    shared_config: Arc<RwLock<Config>>,
+    allowed_keys: Arc<AllowedKeys>,
+    config: &Config,
+) -> Router {
+    let state = AppState::new(api_key)
+        .with_account_pool(pool)
+        .with_content_filter(content_filter)
+        .with_telemetry(telemetry)
+        .with_shared_config(shared_config)
+        .with_allowed_keys(allowed_keys);
+    state.spawn_idempotency_eviction();
 
     // 需要认证的 /v1 路由
     let v1_routes = Router::new()
@@ -73,13 +118,34 @@ pub fn create_router_with_pool(api_key: impl Into<String>, pool: Arc<AccountPool
         .route("/messages/count_tokens", post(count_tokens))
         // OpenAI 格式请求拦截
         .route("/chat/completions", post(openai_chat_completions))
+        .layer(middleware::from_fn(content_negotiation_middleware))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
         ));
 
-    Router::new()
+    // 仅主 API Key 可访问的管理路由
+    let admin_routes =
+        Router::new()
+            .route("/api/keys", get(get_api_keys))
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                admin_auth_middleware,
+            ));
+
+    let router = Router::new()
+        .route("/health", get(health))
         .nest("/v1", v1_routes)
-        .layer(cors_layer())
-        .with_state(state)
+        .merge(admin_routes)
+        .layer(cors_layer(&config.cors_allowed_origins));
+    with_compression(router, config).with_state(state)
+}
+
+/// 按配置为路由添加响应压缩层（SSE 流式响应不压缩）
+fn with_compression(router: Router<AppState>, config: &Config) -> Router<AppState> {
+    if config.compress_responses {
+        router.layer(compression_layer())
+    } else {
+        router
+    }
 }
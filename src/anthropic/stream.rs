@@ -3,11 +3,16 @@
 //! 实现 Kiro → Anthropic 流式响应转换和 SSE 状态管理
 
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use serde_json::json;
 use uuid::Uuid;
 
 use crate::kiro::model::events::Event;
+use crate::kiro::parser::validator::validate_event;
+
+use super::filter::ContentFilter;
 
 /// 找到小于等于目标位置的最近有效UTF-8字符边界
 ///
@@ -411,6 +416,8 @@ impl SseStateManager {
 
 /// 上下文窗口大小（200k tokens）
 const CONTEXT_WINDOW_SIZE: i32 = 200_000;
+/// 上游心跳间隔超过该秒数时记录警告
+const HEARTBEAT_STALL_SECS: u64 = 60;
 
 /// 流处理上下文
 pub struct StreamContext {
@@ -424,6 +431,10 @@ pub struct StreamContext {
     pub input_tokens: i32,
     /// 从 contextUsageEvent 计算的实际输入 tokens
     pub context_input_tokens: Option<i32>,
+    /// contextUsageEvent 报告的上下文窗口使用百分比
+    pub context_usage_pct: Option<f64>,
+    /// meteringEvent 报告的额度消耗累计
+    pub credits_used: Option<f64>,
     /// 输出 tokens 累计
     pub output_tokens: i32,
     /// 工具块索引映射 (tool_id -> block_index)
@@ -440,6 +451,20 @@ pub struct StreamContext {
     pub thinking_block_index: Option<i32>,
     /// 文本块索引（thinking 启用时动态分配）
     pub text_block_index: Option<i32>,
+    /// thinkingBlockEvent 的文本缓冲区（收到 stop 时整体作为 thinking 块发出）
+    pub thinking_event_buffer: String,
+    /// thinking 内容的 tokens（同时计入 output_tokens）
+    pub thinking_tokens: i32,
+    /// 输出内容过滤器
+    pub content_filter: Option<Arc<ContentFilter>>,
+    /// 是否有内容被过滤
+    pub was_filtered: bool,
+    /// 事件校验失败次数
+    pub validation_errors: usize,
+    /// 最近一次收到上游心跳的时间
+    pub last_heartbeat_at: Option<Instant>,
+    /// 收到的上游心跳次数
+    pub heartbeat_count: u32,
 }
 
 impl StreamContext {
@@ -455,6 +480,8 @@ impl StreamContext {
             message_id: format!("msg_{}", Uuid::new_v4().to_string().replace('-', "")),
             input_tokens,
             context_input_tokens: None,
+            context_usage_pct: None,
+            credits_used: None,
             output_tokens: 0,
             tool_block_indices: HashMap::new(),
             thinking_enabled,
@@ -463,9 +490,22 @@ impl StreamContext {
             thinking_extracted: false,
             thinking_block_index: None,
             text_block_index: None,
+            thinking_event_buffer: String::new(),
+            thinking_tokens: 0,
+            content_filter: None,
+            was_filtered: false,
+            validation_errors: 0,
+            last_heartbeat_at: None,
+            heartbeat_count: 0,
         }
     }
 
+    /// 设置输出内容过滤器
+    pub fn with_content_filter(mut self, filter: Option<Arc<ContentFilter>>) -> Self {
+        self.content_filter = filter;
+        self
+    }
+
     /// 生成 message_start 事件
     pub fn create_message_start_event(&self) -> serde_json::Value {
         json!({
@@ -527,9 +567,43 @@ impl StreamContext {
 
     /// 处理 Kiro 事件并转换为 Anthropic SSE 事件
     pub fn process_kiro_event(&mut self, event: &Event) -> Vec<SseEvent> {
+        self.validation_errors += validate_event(event).len();
+
         match event {
-            Event::AssistantResponse(resp) => self.process_assistant_response(&resp.content),
+            Event::AssistantResponse(resp) => {
+                // 先过滤敏感内容，再进入 thinking/text 处理
+                match self
+                    .content_filter
+                    .as_ref()
+                    .and_then(|f| f.apply(&resp.content))
+                {
+                    Some(filtered) => {
+                        self.was_filtered = true;
+                        self.process_assistant_response(&filtered)
+                    }
+                    None => self.process_assistant_response(&resp.content),
+                }
+            }
             Event::ToolUse(tool_use) => self.process_tool_use(tool_use),
+            Event::ThinkingBlock {
+                thinking_id,
+                text,
+                stop,
+            } => {
+                if *stop {
+                    tracing::debug!("thinking 块 {} 结束", thinking_id);
+                }
+                self.process_thinking_block(text, *stop)
+            }
+            Event::Metering(metering) => {
+                *self.credits_used.get_or_insert(0.0) += metering.usage;
+                tracing::debug!("收到 meteringEvent: {}", metering);
+                Vec::new()
+            }
+            Event::Heartbeat => {
+                self.record_heartbeat(Instant::now());
+                Vec::new()
+            }
             Event::ContextUsage(context_usage) => {
                 // 从上下文使用百分比计算实际的 input_tokens
                 // 公式: percentage * 200000 / 100 = percentage * 2000
@@ -537,6 +611,7 @@ impl StreamContext {
                     * (CONTEXT_WINDOW_SIZE as f64)
                     / 100.0) as i32;
                 self.context_input_tokens = Some(actual_input_tokens);
+                self.context_usage_pct = Some(context_usage.context_usage_percentage);
                 tracing::debug!(
                     "收到 contextUsageEvent: {}%, 计算 input_tokens: {}",
                     context_usage.context_usage_percentage,
@@ -566,6 +641,68 @@ impl StreamContext {
         }
     }
 
+    /// 记录上游心跳，间隔过长时提示连接可能停滞
+    fn record_heartbeat(&mut self, now: Instant) {
+        if let Some(last) = self.last_heartbeat_at {
+            let gap = now.duration_since(last);
+            if gap > Duration::from_secs(HEARTBEAT_STALL_SECS) {
+                tracing::warn!(
+                    "上游心跳间隔 {} 秒，超过 {} 秒，上游连接可能停滞",
+                    gap.as_secs(),
+                    HEARTBEAT_STALL_SECS
+                );
+            }
+        }
+        self.last_heartbeat_at = Some(now);
+        self.heartbeat_count += 1;
+    }
+
+    /// 处理 thinkingBlockEvent：累积文本，收到 stop 后发出完整的 thinking 块
+    fn process_thinking_block(&mut self, text: &str, stop: bool) -> Vec<SseEvent> {
+        if !text.is_empty() {
+            let tokens = estimate_tokens(text);
+            self.thinking_tokens += tokens;
+            self.output_tokens += tokens;
+            self.thinking_event_buffer.push_str(text);
+        }
+        if stop {
+            self.flush_thinking_event_buffer()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// 将缓冲的 thinking 文本作为独立的 thinking 块发出
+    fn flush_thinking_event_buffer(&mut self) -> Vec<SseEvent> {
+        if self.thinking_event_buffer.is_empty() {
+            return Vec::new();
+        }
+        let thinking = std::mem::take(&mut self.thinking_event_buffer);
+        let mut events = Vec::new();
+
+        // 先关闭当前文本块，后续文本会自动创建新的文本块
+        if let Some(idx) = self.text_block_index.take() {
+            events.extend(self.state_manager.handle_content_block_stop(idx));
+        }
+
+        let index = self.state_manager.next_block_index();
+        events.extend(self.state_manager.handle_content_block_start(
+            index,
+            "thinking",
+            json!({
+                "type": "content_block_start",
+                "index": index,
+                "content_block": {
+                    "type": "thinking",
+                    "thinking": ""
+                }
+            }),
+        ));
+        events.push(self.create_thinking_delta_event(index, &thinking));
+        events.extend(self.state_manager.handle_content_block_stop(index));
+        events
+    }
+
     /// 处理助手响应事件
     fn process_assistant_response(&mut self, content: &str) -> Vec<SseEvent> {
         if content.is_empty() {
@@ -890,6 +1027,9 @@ impl StreamContext {
             self.thinking_buffer.clear();
         }
 
+        // 上游未发送 stop 的 thinkingBlockEvent 文本同样输出
+        events.extend(self.flush_thinking_event_buffer());
+
         // 使用从 contextUsageEvent 计算的 input_tokens，如果没有则使用估算值
         let final_input_tokens = self.context_input_tokens.unwrap_or(self.input_tokens);
 
@@ -1109,6 +1249,168 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_content_filter_redacts_credit_card_in_stream() {
+        let filter = ContentFilter::from_rules(&[crate::model::config::ContentFilterRule {
+            name: "credit-card".to_string(),
+            pattern: r"\b(?:\d{4}[ -]?){3}\d{4}\b".to_string(),
+            replacement: "[REDACTED]".to_string(),
+            enabled: true,
+        }])
+        .unwrap();
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false)
+            .with_content_filter(Some(Arc::new(filter)));
+        let _initial_events = ctx.generate_initial_events();
+
+        let event = Event::AssistantResponse(
+            serde_json::from_value(json!({"content": "card: 4111 1111 1111 1111."})).unwrap(),
+        );
+        let events = ctx.process_kiro_event(&event);
+
+        let sse: String = events.iter().map(|e| e.to_sse_string()).collect();
+        assert!(sse.contains("card: [REDACTED]."));
+        assert!(!sse.contains("4111"));
+        assert!(ctx.was_filtered);
+    }
+
+    /// 按 AWS event-stream 格式编码一个带 `:message-type` / `:event-type` 头部的事件帧
+    fn encode_event_frame(event_type: &str, payload: &str) -> Vec<u8> {
+        use crate::kiro::parser::crc::crc32;
+
+        let mut headers = Vec::new();
+        for (name, value) in [(":message-type", "event"), (":event-type", event_type)] {
+            headers.push(name.len() as u8);
+            headers.extend_from_slice(name.as_bytes());
+            headers.push(7);
+            headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            headers.extend_from_slice(value.as_bytes());
+        }
+
+        let total_length = (12 + headers.len() + payload.len() + 4) as u32;
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&total_length.to_be_bytes());
+        frame.extend_from_slice(&(headers.len() as u32).to_be_bytes());
+        let prelude_crc = crc32(&frame);
+        frame.extend_from_slice(&prelude_crc.to_be_bytes());
+        frame.extend_from_slice(&headers);
+        frame.extend_from_slice(payload.as_bytes());
+        let message_crc = crc32(&frame);
+        frame.extend_from_slice(&message_crc.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_thinking_block_events_emit_thinking_content_block() {
+        use crate::kiro::parser::decoder::EventStreamDecoder;
+
+        let fixture = [
+            (
+                "thinkingBlockEvent",
+                r#"{"thinkingId":"t1","text":"Let me think","stop":false}"#,
+            ),
+            (
+                "thinkingBlockEvent",
+                r#"{"thinkingId":"t1","text":" about it.","stop":true}"#,
+            ),
+            ("assistantResponseEvent", r#"{"content":"Answer"}"#),
+        ];
+        let mut bytes = Vec::new();
+        for (event_type, payload) in fixture {
+            bytes.extend(encode_event_frame(event_type, payload));
+        }
+
+        let mut decoder = EventStreamDecoder::new();
+        let mut parsed = Vec::new();
+        decoder
+            .feed_incremental(&bytes, |frame| {
+                parsed.push(Event::from_frame(frame).unwrap())
+            })
+            .unwrap();
+        assert!(matches!(
+            &parsed[0],
+            Event::ThinkingBlock { thinking_id, stop: false, .. } if thinking_id == "t1"
+        ));
+
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, true);
+        let _ = ctx.generate_initial_events();
+        let first = ctx.process_kiro_event(&parsed[0]);
+        assert!(
+            first.is_empty(),
+            "thinking text should be buffered until stop"
+        );
+
+        let mut events: Vec<SseEvent> = parsed[1..]
+            .iter()
+            .flat_map(|event| ctx.process_kiro_event(event))
+            .collect();
+        events.extend(ctx.generate_final_events());
+        let kinds: Vec<(&str, String)> = events
+            .iter()
+            .map(|e| {
+                let kind = e.data["content_block"]["type"]
+                    .as_str()
+                    .or(e.data["delta"]["type"].as_str())
+                    .unwrap_or_default();
+                (e.event.as_str(), kind.to_string())
+            })
+            .collect();
+        assert_eq!(
+            kinds[..4],
+            [
+                ("content_block_start", "thinking".to_string()),
+                ("content_block_delta", "thinking_delta".to_string()),
+                ("content_block_stop", String::new()),
+                ("content_block_start", "text".to_string()),
+            ]
+        );
+        assert_eq!(
+            events[1].data["delta"]["thinking"],
+            "Let me think about it."
+        );
+        assert_eq!(events[0].data["index"], 0);
+        assert_eq!(events[3].data["index"], 1);
+        assert!(ctx.thinking_tokens > 0);
+        assert!(ctx.output_tokens > ctx.thinking_tokens);
+        assert!(ctx.thinking_event_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_heartbeat_events_are_counted() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        let events = ctx.process_kiro_event(&Event::Heartbeat);
+        assert!(events.is_empty());
+
+        let stalled = Instant::now() + Duration::from_secs(HEARTBEAT_STALL_SECS + 1);
+        ctx.record_heartbeat(stalled);
+        assert_eq!(ctx.heartbeat_count, 2);
+        assert_eq!(ctx.last_heartbeat_at, Some(stalled));
+    }
+
+    #[test]
+    fn test_metering_events_accumulate_credits() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        for usage in [0.25, 0.5] {
+            let event = Event::Metering(
+                serde_json::from_value(json!({"unit": "credit", "usage": usage})).unwrap(),
+            );
+            ctx.process_kiro_event(&event);
+        }
+
+        assert_eq!(ctx.credits_used, Some(0.75));
+    }
+
+    #[test]
+    fn test_context_usage_event_records_percentage() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        let event = Event::ContextUsage(
+            serde_json::from_value(json!({"contextUsagePercentage": 85.5})).unwrap(),
+        );
+        ctx.process_kiro_event(&event);
+
+        assert_eq!(ctx.context_usage_pct, Some(85.5));
+        assert_eq!(ctx.context_input_tokens, Some(171_000));
+    }
+
     #[test]
     fn test_estimate_tokens() {
         assert!(estimate_tokens("Hello") > 0);
@@ -98,6 +98,9 @@ pub struct MessagesRequest {
     pub tools: Option<Vec<Tool>>,
     pub tool_choice: Option<serde_json::Value>,
     pub thinking: Option<Thinking>,
+    /// 流式输出格式（扩展字段）：`"raw"` 时原样转发 Kiro 上游事件流，用于调试
+    #[serde(default)]
+    pub streaming_format: Option<String>,
 }
 
 /// 消息
@@ -173,4 +176,7 @@ pub struct CountTokensRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CountTokensResponse {
     pub input_tokens: i32,
+    /// 消息列表的结构性问题（见 [`crate::token::validate_messages`]）
+    #[serde(default)]
+    pub warnings: Vec<crate::token::ValidationWarning>,
 }
@@ -3,16 +3,19 @@
 //! 提供统一的 HTTP Client 构建功能，支持代理配置
 
 use reqwest::{Client, Proxy};
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 /// 代理配置
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ProxyConfig {
     /// 代理地址，支持 http/https/socks5
     pub url: String,
     /// 代理认证用户名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub username: Option<String>,
     /// 代理认证密码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub password: Option<String>,
 }
 
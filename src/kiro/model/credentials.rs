@@ -5,7 +5,7 @@
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Kiro OAuth 凭证
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -41,9 +41,41 @@ pub struct KiroCredentials {
 }
 
 impl KiroCredentials {
+    /// 旧版默认凭证文件路径（当前目录）
+    pub const LEGACY_CREDENTIALS_PATH: &'static str = "credentials.json";
+
     /// 获取默认凭证文件路径
-    pub fn default_credentials_path() -> &'static str {
-        "credentials.json"
+    ///
+    /// 按 [`Self::credentials_search_paths`] 的顺序返回第一个存在的文件，
+    /// 均不存在时回退到旧版路径以保持兼容
+    pub fn default_credentials_path() -> PathBuf {
+        Self::first_existing(Self::credentials_search_paths())
+    }
+
+    /// 第一个存在的候选路径，都不存在时返回旧版路径
+    fn first_existing(paths: Vec<PathBuf>) -> PathBuf {
+        paths
+            .into_iter()
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| PathBuf::from(Self::LEGACY_CREDENTIALS_PATH))
+    }
+
+    /// 所有候选凭证文件路径（按优先级排序，用于诊断输出）
+    ///
+    /// Linux 为 `$XDG_CONFIG_HOME/kiro2api/credentials.json`，
+    /// macOS 为 `~/Library/Application Support/kiro2api/credentials.json`，最后为旧版路径
+    pub fn credentials_search_paths() -> Vec<PathBuf> {
+        Self::search_paths_in(dirs::config_dir().as_deref())
+    }
+
+    /// 以 `config_dir` 为平台配置目录构建候选路径
+    fn search_paths_in(config_dir: Option<&Path>) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = config_dir
+            .map(|dir| dir.join("kiro2api").join("credentials.json"))
+            .into_iter()
+            .collect();
+        paths.push(PathBuf::from(Self::LEGACY_CREDENTIALS_PATH));
+        paths
     }
 
     /// 从环境变量加载凭证
@@ -96,6 +128,54 @@ impl KiroCredentials {
         Self::load(path)
     }
 
+    /// 是否为 IdC 认证
+    ///
+    /// 显式指定 auth_method 时以其为准（idc / builder-id），否则根据是否同时具备
+    /// client_id 与 client_secret 判断
+    pub fn is_idc(&self) -> bool {
+        match self.auth_method.as_deref() {
+            Some(method) => matches!(method.to_lowercase().as_str(), "idc" | "builder-id"),
+            None => self.client_id.is_some() && self.client_secret.is_some(),
+        }
+    }
+
+    /// 是否为 Social 认证
+    pub fn is_social(&self) -> bool {
+        !self.is_idc()
+    }
+
+    /// 认证方式名称（idc / social）
+    pub fn auth_method_str(&self) -> &'static str {
+        if self.is_idc() {
+            "idc"
+        } else {
+            "social"
+        }
+    }
+
+    /// 生成用于日志输出的脱敏 JSON
+    ///
+    /// refreshToken 仅保留末 4 位（`rt_***xxxx`），accessToken / clientSecret 替换为 `[REDACTED]`
+    pub fn to_redacted_json(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(obj) = value.as_object_mut() {
+            if let Some(refresh_token) = &self.refresh_token {
+                let chars: Vec<char> = refresh_token.chars().collect();
+                let tail: String = chars[chars.len().saturating_sub(4)..].iter().collect();
+                obj.insert(
+                    "refreshToken".to_string(),
+                    serde_json::Value::String(format!("rt_***{}", tail)),
+                );
+            }
+            for key in ["accessToken", "clientSecret"] {
+                if obj.contains_key(key) {
+                    obj.insert(key.to_string(), serde_json::json!("[REDACTED]"));
+                }
+            }
+        }
+        value
+    }
+
     /// 序列化为格式化的 JSON 字符串
     pub fn to_pretty_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string_pretty(self)
@@ -124,6 +204,25 @@ mod tests {
         assert_eq!(creds.auth_method, Some("social".to_string()));
     }
 
+    #[test]
+    fn test_to_redacted_json() {
+        let creds = KiroCredentials {
+            access_token: Some("secret_access".to_string()),
+            refresh_token: Some("aorAAAAAverylongrefreshtokenABCD".to_string()),
+            client_id: Some("client".to_string()),
+            client_secret: Some("secret_client".to_string()),
+            auth_method: Some("idc".to_string()),
+            ..Default::default()
+        };
+
+        let json = creds.to_redacted_json();
+        assert_eq!(json["refreshToken"], "rt_***ABCD");
+        assert_eq!(json["accessToken"], "[REDACTED]");
+        assert_eq!(json["clientSecret"], "[REDACTED]");
+        assert_eq!(json["clientId"], "client");
+        assert!(!json.to_string().contains("secret_"));
+    }
+
     #[test]
     fn test_from_json_with_unknown_keys() {
         let json = r#"{
@@ -155,9 +254,72 @@ mod tests {
 
     #[test]
     fn test_default_credentials_path() {
+        let paths = KiroCredentials::credentials_search_paths();
         assert_eq!(
-            KiroCredentials::default_credentials_path(),
-            "credentials.json"
+            paths.last().unwrap(),
+            Path::new(KiroCredentials::LEGACY_CREDENTIALS_PATH)
         );
+        let default = KiroCredentials::default_credentials_path();
+        assert!(default.is_file() || default == Path::new("credentials.json"));
+    }
+
+    #[test]
+    fn test_search_paths_prefer_config_dir() {
+        let dir = std::env::temp_dir().join(format!("kiro-config-{}", uuid::Uuid::new_v4()));
+        let paths = KiroCredentials::search_paths_in(Some(&dir));
+        assert_eq!(paths[0], dir.join("kiro2api").join("credentials.json"));
+        assert_eq!(
+            paths[1],
+            Path::new(KiroCredentials::LEGACY_CREDENTIALS_PATH)
+        );
+        assert_eq!(KiroCredentials::search_paths_in(None).len(), 1);
+
+        fs::create_dir_all(dir.join("kiro2api")).unwrap();
+        fs::write(&paths[0], "{}").unwrap();
+        assert_eq!(KiroCredentials::first_existing(paths.clone()), paths[0]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_credentials_search_paths_use_application_support() {
+        let paths = KiroCredentials::credentials_search_paths();
+        assert!(paths[0].ends_with("Library/Application Support/kiro2api/credentials.json"));
+    }
+
+    #[test]
+    fn test_is_idc() {
+        let creds = KiroCredentials {
+            client_id: Some("c".to_string()),
+            client_secret: Some("s".to_string()),
+            ..Default::default()
+        };
+        assert!(creds.is_idc());
+        assert_eq!(creds.auth_method_str(), "idc");
+
+        let builder_id = KiroCredentials {
+            auth_method: Some("Builder-ID".to_string()),
+            ..Default::default()
+        };
+        assert!(builder_id.is_idc());
+    }
+
+    #[test]
+    fn test_is_social() {
+        let creds = KiroCredentials {
+            client_id: Some("c".to_string()),
+            ..Default::default()
+        };
+        assert!(creds.is_social());
+        assert_eq!(creds.auth_method_str(), "social");
+
+        // 显式指定的 auth_method 优先于 client 信息
+        let explicit = KiroCredentials {
+            auth_method: Some("social".to_string()),
+            client_id: Some("c".to_string()),
+            client_secret: Some("s".to_string()),
+            ..Default::default()
+        };
+        assert!(explicit.is_social());
     }
 }
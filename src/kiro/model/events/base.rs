@@ -16,6 +16,10 @@ pub enum EventType {
     Metering,
     /// 上下文使用率事件
     ContextUsage,
+    /// 思考块事件
+    ThinkingBlock,
+    /// 上游心跳
+    Ping,
     /// 未知事件类型
     Unknown,
 }
@@ -28,6 +32,8 @@ impl EventType {
             "toolUseEvent" => Self::ToolUse,
             "meteringEvent" => Self::Metering,
             "contextUsageEvent" => Self::ContextUsage,
+            "thinkingBlockEvent" => Self::ThinkingBlock,
+            "ping" => Self::Ping,
             _ => Self::Unknown,
         }
     }
@@ -39,6 +45,8 @@ impl EventType {
             Self::ToolUse => "toolUseEvent",
             Self::Metering => "meteringEvent",
             Self::ContextUsage => "contextUsageEvent",
+            Self::ThinkingBlock => "thinkingBlockEvent",
+            Self::Ping => "ping",
             Self::Unknown => "unknown",
         }
     }
@@ -68,9 +76,20 @@ pub enum Event {
     /// 工具使用
     ToolUse(super::ToolUseEvent),
     /// 计费
-    Metering(()),
+    Metering(super::MeteringEvent),
     /// 上下文使用率
     ContextUsage(super::ContextUsageEvent),
+    /// 思考块片段
+    ThinkingBlock {
+        /// 思考块 ID
+        thinking_id: String,
+        /// 思考文本片段
+        text: String,
+        /// 是否为该思考块的最后一个片段
+        stop: bool,
+    },
+    /// 上游心跳（Kiro 的 ping 事件）
+    Heartbeat,
     /// 未知事件 (保留原始帧数据)
     Unknown {},
     /// 服务端错误
@@ -116,11 +135,23 @@ impl Event {
                 let payload = super::ToolUseEvent::from_frame(&frame)?;
                 Ok(Self::ToolUse(payload))
             }
-            EventType::Metering => Ok(Self::Metering(())),
+            EventType::Metering => {
+                let payload = super::MeteringEvent::from_frame(&frame)?;
+                Ok(Self::Metering(payload))
+            }
             EventType::ContextUsage => {
                 let payload = super::ContextUsageEvent::from_frame(&frame)?;
                 Ok(Self::ContextUsage(payload))
             }
+            EventType::ThinkingBlock => {
+                let payload = super::ThinkingBlockEvent::from_frame(&frame)?;
+                Ok(Self::ThinkingBlock {
+                    thinking_id: payload.thinking_id,
+                    text: payload.text,
+                    stop: payload.stop,
+                })
+            }
+            EventType::Ping => Ok(Self::Heartbeat),
             EventType::Unknown => Ok(Self::Unknown {}),
         }
     }
@@ -172,6 +203,11 @@ mod tests {
             EventType::from_str("contextUsageEvent"),
             EventType::ContextUsage
         );
+        assert_eq!(
+            EventType::from_str("thinkingBlockEvent"),
+            EventType::ThinkingBlock
+        );
+        assert_eq!(EventType::from_str("ping"), EventType::Ping);
         assert_eq!(EventType::from_str("unknown_type"), EventType::Unknown);
     }
 
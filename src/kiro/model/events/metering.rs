@@ -0,0 +1,44 @@
+//! 计费事件
+//!
+//! 处理 meteringEvent 类型的事件
+
+use serde::Deserialize;
+
+use crate::kiro::parser::error::ParseResult;
+use crate::kiro::parser::frame::Frame;
+
+use super::base::EventPayload;
+
+/// 计费事件
+///
+/// 包含本次请求消耗的额度
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeteringEvent {
+    /// 计费单位（如 "credit"）
+    #[serde(default)]
+    pub unit: Option<String>,
+    /// 计费单位复数形式
+    #[serde(default)]
+    pub unit_plural: Option<String>,
+    /// 消耗量
+    #[serde(default)]
+    pub usage: f64,
+}
+
+impl EventPayload for MeteringEvent {
+    fn from_frame(frame: &Frame) -> ParseResult<Self> {
+        frame.payload_as_json()
+    }
+}
+
+impl std::fmt::Display for MeteringEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unit = self
+            .unit_plural
+            .as_deref()
+            .or(self.unit.as_deref())
+            .unwrap_or("");
+        write!(f, "{} {}", self.usage, unit)
+    }
+}
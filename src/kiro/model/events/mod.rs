@@ -5,9 +5,13 @@
 mod assistant;
 mod base;
 mod context_usage;
+mod metering;
+mod thinking;
 mod tool_use;
 
 pub use assistant::AssistantResponseEvent;
 pub use base::Event;
 pub use context_usage::ContextUsageEvent;
+pub use metering::MeteringEvent;
+pub use thinking::ThinkingBlockEvent;
 pub use tool_use::ToolUseEvent;
@@ -0,0 +1,33 @@
+//! 思考块事件
+//!
+//! 处理 thinkingBlockEvent 类型的事件
+
+use serde::Deserialize;
+
+use crate::kiro::parser::error::ParseResult;
+use crate::kiro::parser::frame::Frame;
+
+use super::base::EventPayload;
+
+/// 思考块事件
+///
+/// 同一 `thinking_id` 的文本分多次下发，`stop` 为 true 时表示该思考块结束
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThinkingBlockEvent {
+    /// 思考块 ID
+    #[serde(default)]
+    pub thinking_id: String,
+    /// 思考文本片段
+    #[serde(default)]
+    pub text: String,
+    /// 是否为该思考块的最后一个片段
+    #[serde(default)]
+    pub stop: bool,
+}
+
+impl EventPayload for ThinkingBlockEvent {
+    fn from_frame(frame: &Frame) -> ParseResult<Self> {
+        frame.payload_as_json()
+    }
+}
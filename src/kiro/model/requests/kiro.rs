@@ -36,9 +36,92 @@ pub struct KiroRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub profile_arn: Option<String>,
 }
+
+impl KiroRequest {
+    /// 创建请求构建器
+    pub fn builder() -> KiroRequestBuilder {
+        KiroRequestBuilder::default()
+    }
+}
+
+/// KiroRequest 构建错误
+#[derive(Debug)]
+pub enum KiroRequestBuilderError {
+    /// 未设置对话状态
+    MissingConversationState,
+}
+
+impl std::fmt::Display for KiroRequestBuilderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KiroRequestBuilderError::MissingConversationState => {
+                write!(f, "构建 Kiro 请求失败: 未设置 conversation_state")
+            }
+        }
+    }
+}
+
+impl std::error::Error for KiroRequestBuilderError {}
+
+/// KiroRequest 构建器
+#[derive(Debug, Default)]
+pub struct KiroRequestBuilder {
+    conversation_state: Option<ConversationState>,
+    profile_arn: Option<String>,
+}
+
+impl KiroRequestBuilder {
+    /// 设置对话状态（必填）
+    pub fn conversation_state(mut self, state: ConversationState) -> Self {
+        self.conversation_state = Some(state);
+        self
+    }
+
+    /// 设置 Profile ARN
+    pub fn profile_arn(mut self, arn: Option<String>) -> Self {
+        self.profile_arn = arn;
+        self
+    }
+
+    /// 构建请求，校验必填字段
+    pub fn build(self) -> Result<KiroRequest, KiroRequestBuilderError> {
+        let conversation_state = self
+            .conversation_state
+            .ok_or(KiroRequestBuilderError::MissingConversationState)?;
+        Ok(KiroRequest {
+            conversation_state,
+            profile_arn: self.profile_arn,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_builder_requires_conversation_state() {
+        let err = KiroRequest::builder()
+            .profile_arn(Some("arn:aws:test".to_string()))
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            KiroRequestBuilderError::MissingConversationState
+        ));
+    }
+
+    #[test]
+    fn test_builder_sets_fields() {
+        let request = KiroRequest::builder()
+            .conversation_state(ConversationState::new("conv-1"))
+            .profile_arn(Some("arn:aws:test".to_string()))
+            .build()
+            .unwrap();
+        assert_eq!(request.conversation_state.conversation_id, "conv-1");
+        assert_eq!(request.profile_arn.as_deref(), Some("arn:aws:test"));
+    }
+
     #[test]
     fn test_kiro_request_deserialize() {
         let json = r#"{
@@ -233,6 +233,30 @@ impl EventStreamDecoder {
         DecodeIter { decoder: self }
     }
 
+    /// 提供数据并立即对每个解析完成的帧调用 `on_frame`，不在中间收集
+    ///
+    /// 解码错误按 `decode_iter` 的方式处理：记录警告并等待下一批数据。
+    /// 返回本次交付的帧数量
+    pub fn feed_incremental<F: FnMut(Frame)>(
+        &mut self,
+        chunk: &[u8],
+        mut on_frame: F,
+    ) -> ParseResult<usize> {
+        self.feed(chunk)?;
+
+        let mut delivered = 0;
+        for result in self.decode_iter() {
+            match result {
+                Ok(frame) => {
+                    on_frame(frame);
+                    delivered += 1;
+                }
+                Err(e) => tracing::warn!("解码事件失败: {}", e),
+            }
+        }
+        Ok(delivered)
+    }
+
     /// 尝试容错恢复
     ///
     /// 根据错误类型采用不同的恢复策略（参考 kiro-kt 的设计）：
@@ -412,6 +436,48 @@ mod tests {
         assert_eq!(decoder.buffer_len(), 4);
     }
 
+    /// 构造一个无头部的事件帧
+    fn build_frame(payload: &[u8]) -> Vec<u8> {
+        use crate::kiro::parser::crc::crc32;
+
+        let total_length = (PRELUDE_SIZE + payload.len() + 4) as u32;
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&total_length.to_be_bytes());
+        frame.extend_from_slice(&0u32.to_be_bytes());
+        let prelude_crc = crc32(&frame);
+        frame.extend_from_slice(&prelude_crc.to_be_bytes());
+        frame.extend_from_slice(payload);
+        let message_crc = crc32(&frame);
+        frame.extend_from_slice(&message_crc.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_feed_incremental_emits_completed_frames() {
+        let mut stream = build_frame(b"{\"a\":1}");
+        stream.extend(build_frame(b"{\"b\":2}"));
+        let split = stream.len() - 5;
+
+        let mut decoder = EventStreamDecoder::new();
+        let mut payloads = Vec::new();
+        let delivered = decoder
+            .feed_incremental(&stream[..split], |frame| {
+                payloads.push(frame.payload_as_str())
+            })
+            .unwrap();
+        assert_eq!(delivered, 1);
+        assert_eq!(payloads, vec![r#"{"a":1}"#]);
+
+        let delivered = decoder
+            .feed_incremental(&stream[split..], |frame| {
+                payloads.push(frame.payload_as_str())
+            })
+            .unwrap();
+        assert_eq!(delivered, 1);
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(decoder.buffer_len(), 0);
+    }
+
     #[test]
     fn test_decoder_buffer_overflow() {
         let mut decoder = EventStreamDecoder::with_config(1024, 5, 100);
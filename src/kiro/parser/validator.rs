@@ -0,0 +1,162 @@
+//! 事件校验
+//!
+//! 按 Kiro API 约定校验已解码事件的必填字段、取值范围和长度限制，
+//! 仅记录违规情况，不影响事件流的正常处理
+
+use std::fmt;
+
+use crate::kiro::model::events::Event;
+
+/// 工具名称最大长度
+const MAX_TOOL_NAME_LEN: usize = 64;
+/// 工具调用 ID 最大长度
+const MAX_TOOL_USE_ID_LEN: usize = 256;
+/// 单个事件文本内容最大长度（字节）
+const MAX_CONTENT_LEN: usize = 1024 * 1024;
+
+/// 事件校验错误
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// 事件类型
+    pub event_type: &'static str,
+    /// 字段名
+    pub field: &'static str,
+    /// 错误描述
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}: {}", self.event_type, self.field, self.message)
+    }
+}
+
+/// 校验事件，返回所有违规项（同时以 warn 级别记录）
+pub fn validate_event(event: &Event) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let mut check = |ok: bool, event_type: &'static str, field: &'static str, message: String| {
+        if !ok {
+            errors.push(ValidationError {
+                event_type,
+                field,
+                message,
+            });
+        }
+    };
+
+    match event {
+        Event::AssistantResponse(resp) => {
+            check(
+                resp.content.len() <= MAX_CONTENT_LEN,
+                "assistantResponseEvent",
+                "content",
+                format!("长度 {} 超过上限 {}", resp.content.len(), MAX_CONTENT_LEN),
+            );
+        }
+        Event::ToolUse(tool_use) => {
+            check(
+                !tool_use.name.is_empty(),
+                "toolUseEvent",
+                "name",
+                "不能为空".to_string(),
+            );
+            check(
+                tool_use.name.len() <= MAX_TOOL_NAME_LEN,
+                "toolUseEvent",
+                "name",
+                format!(
+                    "长度 {} 超过上限 {}",
+                    tool_use.name.len(),
+                    MAX_TOOL_NAME_LEN
+                ),
+            );
+            check(
+                !tool_use.tool_use_id.is_empty(),
+                "toolUseEvent",
+                "toolUseId",
+                "不能为空".to_string(),
+            );
+            check(
+                tool_use.tool_use_id.len() <= MAX_TOOL_USE_ID_LEN,
+                "toolUseEvent",
+                "toolUseId",
+                format!(
+                    "长度 {} 超过上限 {}",
+                    tool_use.tool_use_id.len(),
+                    MAX_TOOL_USE_ID_LEN
+                ),
+            );
+            check(
+                tool_use.input.len() <= MAX_CONTENT_LEN,
+                "toolUseEvent",
+                "input",
+                format!("长度 {} 超过上限 {}", tool_use.input.len(), MAX_CONTENT_LEN),
+            );
+        }
+        Event::ContextUsage(usage) => {
+            let pct = usage.context_usage_percentage;
+            check(
+                pct.is_finite() && (0.0..=100.0).contains(&pct),
+                "contextUsageEvent",
+                "contextUsagePercentage",
+                format!("取值 {} 不在 [0, 100] 范围内", pct),
+            );
+        }
+        Event::Error { error_code, .. } => {
+            check(
+                !error_code.is_empty(),
+                "error",
+                "errorCode",
+                "不能为空".to_string(),
+            );
+        }
+        Event::Exception { exception_type, .. } => {
+            check(
+                !exception_type.is_empty(),
+                "exception",
+                "exceptionType",
+                "不能为空".to_string(),
+            );
+        }
+        Event::Metering(_) | Event::ThinkingBlock { .. } | Event::Heartbeat | Event::Unknown {} => {
+        }
+    }
+
+    for error in &errors {
+        tracing::warn!("事件校验失败: {}", error);
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiro::model::events::{ContextUsageEvent, ToolUseEvent};
+
+    #[test]
+    fn test_validate_context_usage_range() {
+        let valid = Event::ContextUsage(ContextUsageEvent {
+            context_usage_percentage: 42.0,
+        });
+        assert!(validate_event(&valid).is_empty());
+
+        let invalid = Event::ContextUsage(ContextUsageEvent {
+            context_usage_percentage: 120.0,
+        });
+        let errors = validate_event(&invalid);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "contextUsagePercentage");
+    }
+
+    #[test]
+    fn test_validate_tool_use_required_fields() {
+        let event = Event::ToolUse(ToolUseEvent {
+            name: String::new(),
+            tool_use_id: String::new(),
+            input: "{}".to_string(),
+            stop: true,
+        });
+        let fields: Vec<&str> = validate_event(&event).iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec!["name", "toolUseId"]);
+    }
+}
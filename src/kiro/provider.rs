@@ -5,6 +5,7 @@
 
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONNECTION, CONTENT_TYPE, HOST};
 use reqwest::{Client, StatusCode};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
@@ -14,6 +15,7 @@ use crate::http_client::{build_client, ProxyConfig};
 use crate::kiro::machine_id;
 use crate::kiro::model::credentials::KiroCredentials;
 use crate::kiro::token_manager::TokenManager;
+use crate::model::config::UpstreamRetryPolicy;
 
 /// Kiro API Provider
 ///
@@ -22,23 +24,50 @@ use crate::kiro::token_manager::TokenManager;
 pub struct KiroProvider {
     token_manager: Arc<Mutex<TokenManager>>,
     client: Client,
+    /// 最近一次观察到的 token 可用状态（Token 刷新占用锁时供健康检查使用）
+    token_available: AtomicBool,
 }
 
-const KIRO_MAX_ATTEMPTS: usize = 3;
-const KIRO_RETRY_BASE_DELAY_MS: u64 = 200;
-const KIRO_RETRY_MAX_DELAY_MS: u64 = 2_000;
+/// 上游 API 调用结果
+pub struct ApiResponse {
+    /// 原始 HTTP 响应
+    pub response: reqwest::Response,
+    /// 本次调用触发 Token 刷新的耗时（毫秒）
+    pub token_refresh_ms: Option<u64>,
+}
+
+/// 默认请求超时（12 分钟）
+const KIRO_REQUEST_TIMEOUT_SECS: u64 = 720;
+/// count_tokens 请求超时
+const KIRO_COUNT_TOKENS_TIMEOUT_SECS: u64 = 30;
 
-fn retry_backoff(attempt: usize) -> Duration {
-    let exp = 1u64 << (attempt.saturating_sub(1).min(10) as u32);
-    let base = KIRO_RETRY_BASE_DELAY_MS
+/// 第 `attempt` 次请求失败后的等待时间（指数退避，按 `jitter_factor` 随机增加）
+fn retry_backoff(policy: &UpstreamRetryPolicy, attempt: u32) -> Duration {
+    let exp = 1u64 << attempt.saturating_sub(1).min(10);
+    let base = policy
+        .base_delay_ms
         .saturating_mul(exp)
-        .min(KIRO_RETRY_MAX_DELAY_MS);
-    let jitter = fastrand::u64(0..=(base / 4).max(1));
+        .min(policy.max_delay_ms);
+    let jitter = (base as f64 * policy.jitter_factor.max(0.0) * fastrand::f64()) as u64;
     Duration::from_millis(base + jitter)
 }
 
-fn is_retryable_status(status: StatusCode) -> bool {
-    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+/// 可重试的上游状态码：500 / 502 / 503 / 504
+///
+/// 429 等其余错误直接返回，交由账号池处理（限流需立即反馈给账号池）
+pub(crate) fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// 缓存的 access token 未过期，或持有可用于刷新的 refresh token
+fn token_available(tm: &TokenManager) -> bool {
+    tm.is_token_valid() || tm.credentials().refresh_token.is_some()
 }
 
 fn is_auth_status(status: StatusCode) -> bool {
@@ -58,12 +87,14 @@ impl KiroProvider {
 
     /// 创建带代理配置的 KiroProvider 实例
     pub fn with_proxy(token_manager: TokenManager, proxy: Option<ProxyConfig>) -> Self {
-        let client = build_client(proxy.as_ref(), 720) // 12 分钟超时
-            .expect("创建 HTTP 客户端失败");
+        let client =
+            build_client(proxy.as_ref(), KIRO_REQUEST_TIMEOUT_SECS).expect("创建 HTTP 客户端失败");
 
+        let token_available = AtomicBool::new(token_available(&token_manager));
         Self {
             token_manager: Arc::new(Mutex::new(token_manager)),
             client,
+            token_available,
         }
     }
 
@@ -72,36 +103,58 @@ impl KiroProvider {
         token_manager: Arc<Mutex<TokenManager>>,
         proxy: Option<ProxyConfig>,
     ) -> Self {
-        let client = build_client(proxy.as_ref(), 720) // 12 分钟超时
-            .expect("创建 HTTP 客户端失败");
-
+        let client =
+            build_client(proxy.as_ref(), KIRO_REQUEST_TIMEOUT_SECS).expect("创建 HTTP 客户端失败");
+
+        // 创建时锁被占用（例如正在刷新）说明持有凭证，视为可用
+        let token_available = AtomicBool::new(
+            token_manager
+                .try_lock()
+                .map(|tm| token_available(&tm))
+                .unwrap_or(true),
+        );
         Self {
             token_manager,
             client,
+            token_available,
         }
     }
 
     /// 获取 API 基础 URL
     #[allow(dead_code)]
     pub async fn base_url(&self) -> String {
-        let region = {
-            let tm = self.token_manager.lock().await;
-            tm.config().region.clone()
-        };
+        let tm = self.token_manager.lock().await;
         format!(
-            "https://q.{}.amazonaws.com/generateAssistantResponse",
-            region
+            "{}/generateAssistantResponse",
+            tm.config().kiro_stream_api_url()
         )
     }
 
     /// 获取 API 基础域名
     #[allow(dead_code)]
     pub async fn base_domain(&self) -> String {
-        let region = {
-            let tm = self.token_manager.lock().await;
-            tm.config().region.clone()
-        };
-        format!("q.{}.amazonaws.com", region)
+        let tm = self.token_manager.lock().await;
+        tm.config().kiro_stream_api_host()
+    }
+
+    /// 能否提供有效 token：缓存的 access token 未过期，或持有可用于刷新的 refresh token
+    ///
+    /// 不发起网络请求，也不等待 TokenManager 的锁：锁被占用（如正在刷新 Token）时返回最近一次观察到的状态
+    pub fn can_provide_token(&self) -> bool {
+        match self.token_manager.try_lock() {
+            Ok(tm) => {
+                let available = token_available(&tm);
+                self.token_available.store(available, Ordering::Relaxed);
+                available
+            }
+            Err(_) => self.token_available.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 当前 access token（用于日志脱敏）
+    pub async fn access_token(&self) -> Option<String> {
+        let tm = self.token_manager.lock().await;
+        tm.credentials().access_token.clone()
     }
 
     /// 构建请求头
@@ -116,7 +169,7 @@ impl KiroProvider {
         let kiro_version = config.kiro_version.clone();
         let os_name = config.system_version.clone();
         let node_version = config.node_version.clone();
-        let base_domain = format!("q.{}.amazonaws.com", config.region);
+        let base_domain = config.kiro_stream_api_host();
 
         let x_amz_user_agent = format!("aws-sdk-js/1.0.27 KiroIDE-{}-{}", kiro_version, machine_id);
 
@@ -161,12 +214,40 @@ impl KiroProvider {
 
     async fn acquire_token_snapshot(
         &self,
-    ) -> anyhow::Result<(String, crate::model::config::Config, KiroCredentials)> {
+    ) -> anyhow::Result<(
+        String,
+        crate::model::config::Config,
+        KiroCredentials,
+        Option<u64>,
+    )> {
         let mut tm = self.token_manager.lock().await;
         let token = tm.ensure_valid_token().await?;
+        let refresh_ms = tm.take_last_refresh_ms();
         let config = tm.config().clone();
         let credentials = tm.credentials().clone();
-        Ok((token, config, credentials))
+        if tm.try_begin_prefetch() {
+            self.spawn_prefetch(tm.clone());
+        }
+        Ok((token, config, credentials, refresh_ms))
+    }
+
+    /// 在后台刷新即将过期的 Token，避免后续请求阻塞在同步刷新上
+    ///
+    /// 刷新在管理器的克隆上进行，不占用共享锁，完成后再写回
+    fn spawn_prefetch(&self, mut detached: TokenManager) {
+        let shared = self.token_manager.clone();
+        tokio::spawn(async move {
+            let original = detached.credentials().clone();
+            match detached.force_refresh().await {
+                Ok(()) => {
+                    let refreshed = detached.credentials().clone();
+                    shared.lock().await.apply_prefetched(&original, refreshed);
+                    tracing::debug!("Token 后台预刷新完成");
+                }
+                Err(e) => tracing::warn!("Token 后台预刷新失败: {}", e),
+            }
+            detached.finish_prefetch();
+        });
     }
 
     /// 发送非流式 API 请求
@@ -175,9 +256,19 @@ impl KiroProvider {
     /// * `request_body` - JSON 格式的请求体字符串
     ///
     /// # Returns
-    /// 返回原始的 HTTP Response，不做解析
-    pub async fn call_api(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
-        self.call_api_with_retry(request_body, false).await
+    /// 返回原始的 HTTP Response（不做解析）及 Token 刷新耗时
+    pub async fn call_api(&self, request_body: &str) -> anyhow::Result<ApiResponse> {
+        self.call_api_with_timeout(request_body, Self::default_timeout())
+            .await
+    }
+
+    /// 发送非流式 API 请求，使用指定的单次请求超时（不影响共享客户端配置）
+    pub async fn call_api_with_timeout(
+        &self,
+        request_body: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<ApiResponse> {
+        self.call_api_with_retry(request_body, false, timeout).await
     }
 
     /// 发送流式 API 请求
@@ -186,26 +277,80 @@ impl KiroProvider {
     /// * `request_body` - JSON 格式的请求体字符串
     ///
     /// # Returns
-    /// 返回原始的 HTTP Response，调用方负责处理流式数据
-    pub async fn call_api_stream(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
-        self.call_api_with_retry(request_body, true).await
+    /// 返回原始的 HTTP Response（调用方负责处理流式数据）及 Token 刷新耗时
+    pub async fn call_api_stream(&self, request_body: &str) -> anyhow::Result<ApiResponse> {
+        self.call_api_stream_with_timeout(request_body, Self::default_timeout())
+            .await
+    }
+
+    /// 发送流式 API 请求，使用指定的单次请求超时（不影响共享客户端配置）
+    pub async fn call_api_stream_with_timeout(
+        &self,
+        request_body: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<ApiResponse> {
+        self.call_api_with_retry(request_body, true, timeout).await
+    }
+
+    /// 调用 Kiro 服务端 count_tokens 接口，返回输入 token 数
+    ///
+    /// `endpoint` 为 None 时使用 `{对话 API 基础地址}/countTokens`
+    pub async fn count_tokens(
+        &self,
+        request_body: &str,
+        endpoint: Option<&str>,
+    ) -> anyhow::Result<i32> {
+        let (token, config, credentials, _) = self.acquire_token_snapshot().await?;
+        let url = endpoint
+            .map(|e| e.to_string())
+            .unwrap_or_else(|| format!("{}/countTokens", config.kiro_stream_api_url()));
+        let headers = Self::build_headers(&token, &credentials, &config)?;
+
+        let response = self
+            .client
+            .post(&url)
+            .headers(headers)
+            .body(request_body.to_string())
+            .timeout(Duration::from_secs(KIRO_COUNT_TOKENS_TIMEOUT_SECS))
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("count_tokens API 请求失败: {} {}", status, body_text);
+        }
+
+        let value: serde_json::Value = response.json().await?;
+        parse_token_count(&value)
+            .ok_or_else(|| anyhow::anyhow!("count_tokens API 响应缺少 token 数: {}", value))
+    }
+
+    /// 全局默认请求超时
+    fn default_timeout() -> Duration {
+        Duration::from_secs(KIRO_REQUEST_TIMEOUT_SECS)
     }
 
     async fn call_api_with_retry(
         &self,
         request_body: &str,
         streaming: bool,
-    ) -> anyhow::Result<reqwest::Response> {
+        timeout: Duration,
+    ) -> anyhow::Result<ApiResponse> {
         let body = request_body.to_string();
         let kind = if streaming { "流式" } else { "非流式" };
         let mut forced_refresh = false;
-
-        for attempt in 1..=KIRO_MAX_ATTEMPTS {
-            let (token, config, credentials) = self.acquire_token_snapshot().await?;
-            let url = format!(
-                "https://q.{}.amazonaws.com/generateAssistantResponse",
-                config.region
-            );
+        let mut token_refresh_ms: Option<u64> = None;
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let (token, config, credentials, refresh_ms) = self.acquire_token_snapshot().await?;
+            if let Some(ms) = refresh_ms {
+                *token_refresh_ms.get_or_insert(0) += ms;
+            }
+            let policy = &config.upstream_retry_policy;
+            let max_attempts = policy.max_retries.saturating_add(1);
+            let url = format!("{}/generateAssistantResponse", config.kiro_stream_api_url());
             let headers = Self::build_headers(&token, &credentials, &config)?;
 
             let response = match self
@@ -213,20 +358,21 @@ impl KiroProvider {
                 .post(&url)
                 .headers(headers)
                 .body(body.clone())
+                .timeout(timeout)
                 .send()
                 .await
             {
                 Ok(r) => r,
                 Err(e) => {
-                    if attempt < KIRO_MAX_ATTEMPTS && is_retryable_reqwest_error(&e) {
-                        let delay = retry_backoff(attempt);
+                    if attempt < max_attempts && is_retryable_reqwest_error(&e) {
+                        let delay = retry_backoff(policy, attempt);
                         tracing::warn!(
                             "Kiro {} API 请求发送失败: {}，{:?} 后重试（{}/{}）",
                             kind,
                             e,
                             delay,
                             attempt,
-                            KIRO_MAX_ATTEMPTS
+                            max_attempts
                         );
                         tokio::time::sleep(delay).await;
                         continue;
@@ -237,34 +383,37 @@ impl KiroProvider {
 
             let status = response.status();
             if status.is_success() {
-                return Ok(response);
+                return Ok(ApiResponse {
+                    response,
+                    token_refresh_ms,
+                });
             }
 
             let body_text = response.text().await.unwrap_or_default();
 
-            if attempt < KIRO_MAX_ATTEMPTS && is_auth_status(status) && !forced_refresh {
+            if attempt < max_attempts && is_auth_status(status) && !forced_refresh {
                 forced_refresh = true;
                 tracing::warn!(
                     "{} API 返回 {}，将强制刷新 Token 后重试（{}/{}）",
                     kind,
                     status,
                     attempt,
-                    KIRO_MAX_ATTEMPTS
+                    max_attempts
                 );
                 let mut tm = self.token_manager.lock().await;
                 tm.force_refresh().await?;
                 continue;
             }
 
-            if attempt < KIRO_MAX_ATTEMPTS && is_retryable_status(status) {
-                let delay = retry_backoff(attempt);
+            if attempt < max_attempts && is_retryable_status(status) {
+                let delay = retry_backoff(policy, attempt);
                 tracing::warn!(
                     "{} API 返回 {}，{:?} 后重试（{}/{}）",
                     kind,
                     status,
                     delay,
                     attempt,
-                    KIRO_MAX_ATTEMPTS
+                    max_attempts
                 );
                 tokio::time::sleep(delay).await;
                 continue;
@@ -272,11 +421,17 @@ impl KiroProvider {
 
             anyhow::bail!("{} API 请求失败: {} {}", kind, status, body_text);
         }
-
-        unreachable!("attempt loop should return")
     }
 }
 
+/// 从 count_tokens 响应中读取 token 数（兼容 camelCase / snake_case 字段）
+fn parse_token_count(value: &serde_json::Value) -> Option<i32> {
+    ["inputTokens", "input_tokens", "tokenCount"]
+        .iter()
+        .find_map(|key| value.get(key).and_then(|v| v.as_i64()))
+        .map(|n| n as i32)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -304,6 +459,111 @@ mod tests {
         assert_eq!(provider.base_domain().await, "q.us-east-1.amazonaws.com");
     }
 
+    #[tokio::test]
+    async fn test_can_provide_token_does_not_wait_for_lock() {
+        let credentials = KiroCredentials {
+            refresh_token: Some("rt".to_string()),
+            ..Default::default()
+        };
+        let provider = KiroProvider::new(TokenManager::new(Config::default(), credentials, None));
+        let empty = KiroProvider::new(TokenManager::new(
+            Config::default(),
+            KiroCredentials::default(),
+            None,
+        ));
+        assert!(provider.can_provide_token());
+        assert!(!empty.can_provide_token());
+
+        // 锁被占用（如正在刷新 Token）时直接返回缓存的状态
+        let _tm = provider.token_manager.lock().await;
+        let _empty_tm = empty.token_manager.lock().await;
+        assert!(provider.can_provide_token());
+        assert!(!empty.can_provide_token());
+    }
+
+    #[test]
+    fn test_parse_token_count() {
+        assert_eq!(
+            parse_token_count(&serde_json::json!({"inputTokens": 42})),
+            Some(42)
+        );
+        assert_eq!(
+            parse_token_count(&serde_json::json!({"input_tokens": 7})),
+            Some(7)
+        );
+        assert_eq!(parse_token_count(&serde_json::json!({"other": 1})), None);
+    }
+
+    #[test]
+    fn test_retryable_status() {
+        for code in [500, 502, 503, 504] {
+            assert!(is_retryable_status(StatusCode::from_u16(code).unwrap()));
+        }
+        for code in [400, 402, 403, 429] {
+            assert!(!is_retryable_status(StatusCode::from_u16(code).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_retry_backoff_is_capped() {
+        let policy = UpstreamRetryPolicy {
+            max_retries: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 300,
+            jitter_factor: 0.0,
+        };
+        assert_eq!(retry_backoff(&policy, 1), Duration::from_millis(100));
+        assert_eq!(retry_backoff(&policy, 2), Duration::from_millis(200));
+        assert_eq!(retry_backoff(&policy, 5), Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn test_call_api_retries_server_errors_then_succeeds() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // 模拟上游：前两次返回 503，之后返回 200
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = axum::Router::new().route(
+            "/generateAssistantResponse",
+            axum::routing::post(move || {
+                let counter = counter.clone();
+                async move {
+                    if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                        axum::http::StatusCode::SERVICE_UNAVAILABLE
+                    } else {
+                        axum::http::StatusCode::OK
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config = Config {
+            kiro_stream_api_base_url: Some(format!("http://{}", addr)),
+            upstream_retry_policy: UpstreamRetryPolicy {
+                max_retries: 2,
+                base_delay_ms: 1,
+                max_delay_ms: 5,
+                jitter_factor: 0.0,
+            },
+            ..Config::default()
+        };
+        let credentials = KiroCredentials {
+            access_token: Some("token".to_string()),
+            refresh_token: Some("a".repeat(150)),
+            expires_at: Some((chrono::Utc::now() + chrono::Duration::hours(2)).to_rfc3339()),
+            ..Default::default()
+        };
+        let provider = KiroProvider::new(TokenManager::new(config, credentials, None));
+
+        let result = provider.call_api("{}").await.unwrap();
+        assert!(result.response.status().is_success());
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
     #[tokio::test]
     async fn test_build_headers() {
         let mut config = Config::default();
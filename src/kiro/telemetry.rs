@@ -0,0 +1,134 @@
+//! 调用耗时遥测模块
+//!
+//! 按阶段记录每次上游调用的耗时（Token 刷新、首字节、解码），并提供分位数统计
+
+use std::collections::VecDeque;
+
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+/// 默认保留的遥测记录数
+const DEFAULT_MAX_RECORDS: usize = 1000;
+
+/// 单次调用的耗时记录
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetryRecord {
+    /// 请求 ID
+    pub request_id: String,
+    /// Token 刷新耗时（毫秒，未触发刷新时为 None）
+    pub token_refresh_ms: Option<u64>,
+    /// 首字节耗时（毫秒）
+    pub ttfb_ms: Option<u64>,
+    /// 事件解码累计耗时（毫秒）
+    pub decode_ms: u64,
+}
+
+/// 分位数统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Percentiles {
+    pub count: usize,
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+}
+
+impl Percentiles {
+    /// 按 nearest-rank 计算分位数
+    fn from_values(mut values: Vec<u64>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_unstable();
+        let rank = |p: f64| {
+            let idx = ((p * values.len() as f64).ceil() as usize).saturating_sub(1);
+            values[idx.min(values.len() - 1)]
+        };
+        Self {
+            count: values.len(),
+            p50: rank(0.50),
+            p95: rank(0.95),
+            p99: rank(0.99),
+        }
+    }
+}
+
+/// 各阶段耗时分位数
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyBreakdown {
+    pub token_refresh: Percentiles,
+    pub ttfb: Percentiles,
+    pub decode: Percentiles,
+}
+
+/// 遥测存储（环形缓冲区）
+pub struct TelemetryStore {
+    records: RwLock<VecDeque<TelemetryRecord>>,
+    max_records: usize,
+}
+
+impl Default for TelemetryStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_RECORDS)
+    }
+}
+
+impl TelemetryStore {
+    pub fn new(max_records: usize) -> Self {
+        Self {
+            records: RwLock::new(VecDeque::with_capacity(max_records)),
+            max_records,
+        }
+    }
+
+    /// 记录一次调用耗时
+    pub async fn record(&self, record: TelemetryRecord) {
+        let mut records = self.records.write().await;
+        if records.len() >= self.max_records {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// 计算各阶段耗时分位数
+    pub async fn latency_breakdown(&self) -> LatencyBreakdown {
+        let records = self.records.read().await;
+        LatencyBreakdown {
+            token_refresh: Percentiles::from_values(
+                records.iter().filter_map(|r| r.token_refresh_ms).collect(),
+            ),
+            ttfb: Percentiles::from_values(records.iter().filter_map(|r| r.ttfb_ms).collect()),
+            decode: Percentiles::from_values(records.iter().map(|r| r.decode_ms).collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(ttfb_ms: u64) -> TelemetryRecord {
+        TelemetryRecord {
+            request_id: ttfb_ms.to_string(),
+            token_refresh_ms: None,
+            ttfb_ms: Some(ttfb_ms),
+            decode_ms: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_latency_percentiles_and_ring_buffer() {
+        let store = TelemetryStore::new(100);
+        for ms in 1..=200 {
+            store.record(record(ms)).await;
+        }
+
+        let breakdown = store.latency_breakdown().await;
+        // 只保留最近 100 条（101..=200）
+        assert_eq!(breakdown.ttfb.count, 100);
+        assert_eq!(breakdown.ttfb.p50, 150);
+        assert_eq!(breakdown.ttfb.p95, 195);
+        assert_eq!(breakdown.ttfb.p99, 199);
+        assert_eq!(breakdown.token_refresh.count, 0);
+        assert_eq!(breakdown.decode.p99, 1);
+    }
+}
@@ -2,6 +2,9 @@
 //!
 //! 负责 Token 过期检测和刷新，支持 Social 和 IdC 认证方式
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
 use anyhow::bail;
 use chrono::{DateTime, Duration, Utc};
 
@@ -16,10 +19,17 @@ use crate::model::config::Config;
 /// Token 管理器
 ///
 /// 负责管理凭据和 Token 的自动刷新
+#[derive(Clone)]
 pub struct TokenManager {
     config: Config,
     credentials: KiroCredentials,
     proxy: Option<ProxyConfig>,
+    /// 最近一次 Token 刷新耗时（毫秒），被读取后清空
+    last_refresh_ms: Option<u64>,
+    /// 待生效凭证，当前 Token 过期后启用
+    pending_credentials: Option<KiroCredentials>,
+    /// 后台预刷新是否进行中（克隆间共享）
+    refreshing: Arc<AtomicBool>,
 }
 
 impl TokenManager {
@@ -29,6 +39,9 @@ impl TokenManager {
             config,
             credentials,
             proxy,
+            last_refresh_ms: None,
+            pending_credentials: None,
+            refreshing: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -37,6 +50,27 @@ impl TokenManager {
         &self.credentials
     }
 
+    /// 当前 Token 是否仍有效（距过期超过 5 分钟）
+    pub fn is_token_valid(&self) -> bool {
+        !is_token_expired(&self.credentials)
+    }
+
+    /// 暂存新凭证，待当前 Token 过期后启用
+    pub fn stage_credentials(&mut self, credentials: KiroCredentials) {
+        self.pending_credentials = Some(credentials);
+    }
+
+    /// 立即替换凭证并丢弃暂存凭证
+    pub fn replace_credentials(&mut self, credentials: KiroCredentials) {
+        self.credentials = credentials;
+        self.pending_credentials = None;
+    }
+
+    /// 是否存在待生效凭证
+    pub fn has_pending_credentials(&self) -> bool {
+        self.pending_credentials.is_some()
+    }
+
     /// 获取配置的引用
     pub fn config(&self) -> &Config {
         &self.config
@@ -46,9 +80,19 @@ impl TokenManager {
     ///
     /// 如果 Token 过期或即将过期，会自动刷新
     pub async fn ensure_valid_token(&mut self) -> anyhow::Result<String> {
-        if is_token_expired(&self.credentials) || is_token_expiring_soon(&self.credentials) {
+        // 当前 Token 需要刷新时启用暂存凭证，避免继续续期旧凭证
+        if needs_refresh(&self.credentials) {
+            if let Some(pending) = self.pending_credentials.take() {
+                tracing::info!("当前 Token 即将过期，启用暂存的新凭证");
+                self.credentials = pending;
+            }
+        }
+
+        if needs_refresh(&self.credentials) {
+            let started = std::time::Instant::now();
             self.credentials =
                 refresh_token(&self.credentials, &self.config, self.proxy.as_ref()).await?;
+            self.last_refresh_ms = Some(started.elapsed().as_millis() as u64);
 
             // 刷新后再次检查 token 时间有效性
             if is_token_expired(&self.credentials) {
@@ -62,6 +106,40 @@ impl TokenManager {
             .ok_or_else(|| anyhow::anyhow!("没有可用的 accessToken"))
     }
 
+    /// 距同步刷新阈值不足 `refresh_buffer_secs` 时占用预刷新标记并返回 true
+    ///
+    /// 已有预刷新进行中、存在暂存凭证或 Token 已需同步刷新时返回 false
+    pub fn try_begin_prefetch(&self) -> bool {
+        if self.pending_credentials.is_some() || is_token_expiring_soon(&self.credentials) {
+            return false;
+        }
+        let buffer = Duration::minutes(TOKEN_REFRESH_AHEAD_MINUTES)
+            + Duration::seconds(self.config.refresh_buffer_secs as i64);
+        if !is_token_expiring_before(&self.credentials, buffer).unwrap_or(false) {
+            return false;
+        }
+        self.refreshing
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    /// 预刷新结束，释放标记
+    pub fn finish_prefetch(&self) {
+        self.refreshing.store(false, Ordering::Release);
+    }
+
+    /// 应用后台预刷新得到的凭证（凭证在刷新期间被替换时丢弃）
+    pub fn apply_prefetched(&mut self, original: &KiroCredentials, refreshed: KiroCredentials) {
+        if self.credentials.refresh_token == original.refresh_token {
+            self.credentials = refreshed;
+        }
+    }
+
+    /// 取出最近一次 Token 刷新耗时（读取后清空）
+    pub fn take_last_refresh_ms(&mut self) -> Option<u64> {
+        self.last_refresh_ms.take()
+    }
+
     /// 强制刷新访问 Token（不依赖 expiresAt 判断）
     pub async fn force_refresh(&mut self) -> anyhow::Result<()> {
         self.credentials =
@@ -76,13 +154,26 @@ impl TokenManager {
     }
 }
 
-/// 检查 Token 是否在指定时间内过期
-fn is_token_expiring_within(credentials: &KiroCredentials, minutes: i64) -> Option<bool> {
+/// Token 已过期或即将过期，请求前需要同步刷新
+fn needs_refresh(credentials: &KiroCredentials) -> bool {
+    is_token_expired(credentials) || is_token_expiring_soon(credentials)
+}
+
+/// 同步刷新的提前量（分钟），Token 在此时间内过期时请求前会阻塞刷新
+const TOKEN_REFRESH_AHEAD_MINUTES: i64 = 10;
+
+/// 检查 Token 是否在指定时长内过期
+fn is_token_expiring_before(credentials: &KiroCredentials, within: Duration) -> Option<bool> {
     credentials
         .expires_at
         .as_ref()
         .and_then(|expires_at| DateTime::parse_from_rfc3339(expires_at).ok())
-        .map(|expires| expires <= Utc::now() + Duration::minutes(minutes))
+        .map(|expires| expires <= Utc::now() + within)
+}
+
+/// 检查 Token 是否在指定时间内过期
+fn is_token_expiring_within(credentials: &KiroCredentials, minutes: i64) -> Option<bool> {
+    is_token_expiring_before(credentials, Duration::minutes(minutes))
 }
 
 /// 检查 Token 是否已过期（提前 5 分钟判断）
@@ -92,7 +183,7 @@ fn is_token_expired(credentials: &KiroCredentials) -> bool {
 
 /// 检查 Token 是否即将过期（10分钟内）
 fn is_token_expiring_soon(credentials: &KiroCredentials) -> bool {
-    is_token_expiring_within(credentials, 10).unwrap_or(false)
+    is_token_expiring_within(credentials, TOKEN_REFRESH_AHEAD_MINUTES).unwrap_or(false)
 }
 
 /// 验证 refreshToken 的基本有效性
@@ -126,12 +217,11 @@ async fn refresh_token(
 ) -> anyhow::Result<KiroCredentials> {
     validate_refresh_token(credentials)?;
 
-    // 根据 auth_method 选择刷新方式
-    let auth_method = credentials.auth_method.as_deref().unwrap_or("social");
-
-    match auth_method.to_lowercase().as_str() {
-        "idc" | "builder-id" => refresh_idc_token(credentials, config, proxy).await,
-        _ => refresh_social_token(credentials, config, proxy).await,
+    // 根据认证方式选择刷新方式
+    if credentials.is_social() {
+        refresh_social_token(credentials, config, proxy).await
+    } else {
+        refresh_idc_token(credentials, config, proxy).await
     }
 }
 
@@ -296,6 +386,76 @@ mod tests {
         assert!(tm.credentials().access_token.is_none());
     }
 
+    #[test]
+    fn test_try_begin_prefetch_near_expiry() {
+        let config = Config {
+            refresh_buffer_secs: 60,
+            ..Config::default()
+        };
+        let near = KiroCredentials {
+            access_token: Some("token".to_string()),
+            expires_at: Some((Utc::now() + Duration::seconds(10 * 60 + 30)).to_rfc3339()),
+            ..Default::default()
+        };
+        let tm = TokenManager::new(config.clone(), near, None);
+        assert!(tm.try_begin_prefetch());
+        assert!(tm.refreshing.load(Ordering::Acquire));
+        // 克隆共享标记，不会重复触发
+        assert!(!tm.clone().try_begin_prefetch());
+        tm.finish_prefetch();
+        assert!(!tm.refreshing.load(Ordering::Acquire));
+
+        let far = KiroCredentials {
+            access_token: Some("token".to_string()),
+            expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+            ..Default::default()
+        };
+        assert!(!TokenManager::new(config, far, None).try_begin_prefetch());
+    }
+
+    #[tokio::test]
+    async fn test_pending_credentials_promoted_after_expiry() {
+        let current = KiroCredentials {
+            access_token: Some("old".to_string()),
+            expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+            ..Default::default()
+        };
+        let staged = KiroCredentials {
+            access_token: Some("new".to_string()),
+            expires_at: Some((Utc::now() + Duration::hours(2)).to_rfc3339()),
+            ..Default::default()
+        };
+
+        let mut tm = TokenManager::new(Config::default(), current, None);
+        tm.stage_credentials(staged);
+        assert_eq!(tm.ensure_valid_token().await.unwrap(), "old");
+        assert!(tm.has_pending_credentials());
+
+        tm.credentials.expires_at = Some("2020-01-01T00:00:00Z".to_string());
+        assert_eq!(tm.ensure_valid_token().await.unwrap(), "new");
+        assert!(!tm.has_pending_credentials());
+    }
+
+    #[tokio::test]
+    async fn test_pending_credentials_promoted_before_refresh_window() {
+        // 距过期约 7 分钟：尚未过期，但已进入同步刷新窗口，应启用暂存凭证而不是续期旧凭证
+        let current = KiroCredentials {
+            access_token: Some("old".to_string()),
+            expires_at: Some((Utc::now() + Duration::minutes(7)).to_rfc3339()),
+            ..Default::default()
+        };
+        let staged = KiroCredentials {
+            access_token: Some("new".to_string()),
+            expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+            ..Default::default()
+        };
+
+        let mut tm = TokenManager::new(Config::default(), current, None);
+        tm.stage_credentials(staged);
+        assert_eq!(tm.ensure_valid_token().await.unwrap(), "new");
+        assert!(!tm.has_pending_credentials());
+    }
+
     #[test]
     fn test_is_token_expired_with_expired_token() {
         let mut credentials = KiroCredentials::default();
@@ -1,6 +1,7 @@
 mod anthropic;
 mod http_client;
 mod kiro;
+mod metrics;
 mod model;
 mod pool;
 pub mod token;
@@ -15,9 +16,9 @@ use kiro::model::credentials::KiroCredentials;
 use kiro::provider::KiroProvider;
 use kiro::token_manager::TokenManager;
 use model::arg::Args;
-use model::config::Config;
-use pool::{Account, AccountPool};
-use tokio::time::{interval, Duration};
+use model::config::{Config, ConfigWatcher};
+use pool::{AccountBuilder, AccountPool};
+use tokio::sync::RwLock;
 
 #[tokio::main]
 async fn main() {
@@ -44,6 +45,10 @@ async fn main() {
 
     // 从环境变量覆盖配置
     config.override_from_env();
+    if let Err(e) = config.validate() {
+        tracing::error!("配置校验失败: {}", e);
+        std::process::exit(1);
+    }
 
     // 获取 API Key
     let api_key = config.api_key.clone().unwrap_or_else(|| {
@@ -60,10 +65,34 @@ async fn main() {
         proxy
     });
 
+    // 编译输出内容过滤规则
+    let content_filter = anthropic::filter::ContentFilter::from_rules(&config.content_filter)
+        .unwrap_or_else(|e| {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        });
+    if !content_filter.is_empty() {
+        tracing::info!("已启用 {} 条输出内容过滤规则", config.content_filter.len());
+    }
+
+    // 加载附加 API Key
+    let allowed_keys =
+        anthropic::keys::AllowedKeys::load(&config.allowed_api_keys).unwrap_or_else(|e| {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        });
+    if !allowed_keys.is_empty() {
+        tracing::info!("已加载 {} 个附加 API Key", allowed_keys.len());
+    }
+    let allowed_keys = Arc::new(allowed_keys);
+
     if proxy_config.is_some() {
         tracing::info!("已配置 HTTP 代理: {}", config.proxy_url.as_ref().unwrap());
     }
 
+    // 监听配置文件变更（热更新 apiKey 等字段），由各模式在创建应用时启动
+    let config_watcher = ConfigWatcher::new(&config_path, Arc::new(RwLock::new(config.clone())));
+
     // 检查是否启用账号池模式（通过环境变量 POOL_MODE=true）
     let pool_mode = std::env::var("POOL_MODE")
         .map(|v| v == "true" || v == "1")
@@ -71,10 +100,28 @@ async fn main() {
 
     let app = if pool_mode {
         tracing::info!("启用账号池模式");
-        create_pool_mode_app(&config, &api_key, proxy_config).await
+        create_pool_mode_app(
+            &args,
+            &config,
+            &api_key,
+            proxy_config,
+            content_filter,
+            config_watcher,
+            allowed_keys,
+        )
+        .await
     } else {
         tracing::info!("启用单账号模式");
-        create_single_mode_app(&args, &config, &api_key, proxy_config).await
+        create_single_mode_app(
+            &args,
+            &config,
+            &api_key,
+            proxy_config,
+            content_filter,
+            config_watcher,
+            allowed_keys,
+        )
+        .await
     };
 
     // 启动服务器
@@ -93,18 +140,32 @@ async fn main() {
     axum::serve(listener, app).await.unwrap();
 }
 
+/// 启动配置文件监听（失败时仅记录警告，服务继续运行）
+fn spawn_config_watcher(watcher: ConfigWatcher) {
+    if let Err(e) = watcher.spawn() {
+        tracing::warn!("启动配置文件监听失败: {}", e);
+    }
+}
+
 /// 创建单账号模式应用
 async fn create_single_mode_app(
     args: &Args,
     config: &Config,
     api_key: &str,
     proxy_config: Option<http_client::ProxyConfig>,
+    content_filter: anthropic::filter::ContentFilter,
+    config_watcher: ConfigWatcher,
+    allowed_keys: Arc<anthropic::keys::AllowedKeys>,
 ) -> Router {
+    let shared_config = config_watcher.shared();
+    spawn_config_watcher(config_watcher);
+
     // 加载凭证（优先环境变量）
-    let credentials_path = args
-        .credentials
-        .clone()
-        .unwrap_or_else(|| KiroCredentials::default_credentials_path().to_string());
+    let credentials_path = args.credentials.clone().unwrap_or_else(|| {
+        KiroCredentials::default_credentials_path()
+            .to_string_lossy()
+            .into_owned()
+    });
     let credentials =
         KiroCredentials::load_with_env_fallback(&credentials_path).unwrap_or_else(|e| {
             tracing::error!("加载凭证失败: {}", e);
@@ -114,7 +175,7 @@ async fn create_single_mode_app(
             std::process::exit(1);
         });
 
-    tracing::debug!("凭证已加载: {:?}", credentials);
+    tracing::debug!("凭证已加载: {}", credentials.to_redacted_json());
 
     // 创建 KiroProvider
     let token_manager =
@@ -130,18 +191,28 @@ async fn create_single_mode_app(
     });
 
     // 构建路由
-    anthropic::create_router_with_provider(api_key, Some(kiro_provider), credentials.profile_arn)
+    anthropic::create_router_with_provider(
+        api_key,
+        Some(kiro_provider),
+        credentials.profile_arn,
+        content_filter,
+        shared_config,
+        allowed_keys,
+        config,
+    )
 }
 
 /// 创建账号池模式应用
 async fn create_pool_mode_app(
+    args: &Args,
     config: &Config,
     api_key: &str,
     proxy_config: Option<http_client::ProxyConfig>,
+    content_filter: anthropic::filter::ContentFilter,
+    config_watcher: ConfigWatcher,
+    allowed_keys: Arc<anthropic::keys::AllowedKeys>,
 ) -> Router {
-    const COOLDOWN_SCAN_SECS: u64 = 15 * 60;
-    const EXHAUSTED_SCAN_SECS: u64 = 60 * 60;
-
+    let shared_config = config_watcher.shared();
     // 获取数据目录（默认 ./data）
     let data_dir = std::env::var("DATA_DIR")
         .map(std::path::PathBuf::from)
@@ -156,6 +227,9 @@ async fn create_pool_mode_app(
         data_dir,
     ));
 
+    // 配置热加载时同步账号池的限速参数
+    spawn_config_watcher(config_watcher.with_pool(pool.clone()));
+
     // 从文件加载已保存的账号
     if let Err(e) = pool.load_from_file().await {
         tracing::warn!("加载账号文件失败: {}", e);
@@ -171,48 +245,42 @@ async fn create_pool_mode_app(
         tracing::warn!("加载配额缓存失败: {}", e);
     }
 
-    // 后台任务 A：每 15 分钟扫描冷却账号
-    {
-        let pool = pool.clone();
-        tokio::spawn(async move {
-            let mut ticker = interval(Duration::from_secs(COOLDOWN_SCAN_SECS));
-            loop {
-                ticker.tick().await;
-                let recovered = pool.recover_cooldown_accounts().await;
-                if recovered > 0 {
-                    tracing::info!("冷却扫描完成，恢复 {} 个账号", recovered);
-                }
-            }
-        });
+    // 从文件加载账号分组
+    if let Err(e) = pool.load_groups().await {
+        tracing::warn!("加载账号分组失败: {}", e);
     }
 
-    // 后台任务 B：每 1 小时扫描配额耗尽账号
-    {
-        let pool = pool.clone();
-        tokio::spawn(async move {
-            let mut ticker = interval(Duration::from_secs(EXHAUSTED_SCAN_SECS));
-            loop {
-                ticker.tick().await;
-                let (recovered, scanned) = pool.refresh_exhausted_accounts().await;
-                if scanned > 0 {
-                    tracing::info!(
-                        "配额耗尽扫描完成，检查 {} 个账号，恢复 {} 个",
-                        scanned,
-                        recovered
-                    );
-                }
-            }
-        });
+    // 从文件恢复上次使用的选择策略
+    if let Err(e) = pool.load_strategy().await {
+        tracing::warn!("加载选择策略失败: {}", e);
+    }
+
+    // 从文件加载策略定时切换计划
+    if let Err(e) = pool.load_strategy_schedule().await {
+        tracing::warn!("加载策略定时切换计划失败: {}", e);
+    }
+
+    // 后台监督循环：冷却恢复、配额耗尽扫描、健康检查与账号池事件
+    pool::monitor::PoolMonitor::run(pool.clone());
+
+    // 按 cron 规则定时切换选择策略
+    pool::schedule::spawn_strategy_scheduler(pool.clone());
+
+    // 从远程种子地址拉取账号（POOL_SEED_URL）
+    if let Some(seed) = pool::seed::PoolSeed::from_env(proxy_config.clone()) {
+        seed.load_initial(&pool).await;
+        seed.spawn_refresh(pool.clone());
     }
 
     // 尝试从环境变量加载初始账号（如果池中没有账号）
     if pool.get_stats().await.total == 0 {
         if let Some(creds) = KiroCredentials::from_env() {
-            let account = Account::new(
+            let account = AccountBuilder::new(
                 uuid::Uuid::new_v4().to_string(),
                 "默认账号 (环境变量)",
                 creds,
-            );
+            )
+            .build();
             if let Err(e) = pool.add_account(account).await {
                 tracing::warn!("添加默认账号失败: {}", e);
             } else {
@@ -221,6 +289,17 @@ async fn create_pool_mode_app(
         }
     }
 
+    // 启动时预热账号池（WARM_UP_ON_START=true）
+    let warm_up_on_start = std::env::var("WARM_UP_ON_START")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    if warm_up_on_start {
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            pool.warm_up().await;
+        });
+    }
+
     // 初始化 count_tokens 配置
     token::init_config(token::CountTokensConfig {
         api_url: config.count_tokens_api_url.clone(),
@@ -229,16 +308,31 @@ async fn create_pool_mode_app(
         proxy: proxy_config,
     });
 
+    // 调用耗时遥测（Anthropic API 与管理 API 共享）
+    let telemetry = Arc::new(kiro::telemetry::TelemetryStore::default());
+
     // 创建 UI 状态
     let ui_state = ui::UiState {
         pool: pool.clone(),
+        telemetry: telemetry.clone(),
         start_time: Instant::now(),
         version: env!("CARGO_PKG_VERSION").to_string(),
         api_key: api_key.to_string(),
+        config: Some(shared_config.clone()),
+        admin_key: config.admin_key.clone(),
+        import_concurrency: args.import_concurrency,
     };
 
     // 构建路由：API + UI
-    let api_router = anthropic::create_router_with_pool(api_key, pool);
+    let api_router = anthropic::create_router_with_pool(
+        api_key,
+        pool,
+        content_filter,
+        telemetry,
+        shared_config,
+        allowed_keys,
+        config,
+    );
     let ui_router = ui::create_ui_router(ui_state);
 
     // 合并路由
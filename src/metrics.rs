@@ -0,0 +1,148 @@
+//! Prometheus 指标
+//!
+//! 请求与错误计数在写入请求记录时累加；账号数量与剩余配额为瞬时值，在每次抓取时按账号池当前状态刷新
+
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::pool::usage::RequestLog;
+
+/// 请求耗时直方图分桶（秒）
+const DURATION_BUCKETS: &[f64] = &[0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0];
+
+/// 账号池指标集合（每个账号池独立注册表）
+pub struct PoolMetrics {
+    registry: Registry,
+    requests_total: IntCounterVec,
+    request_duration_seconds: HistogramVec,
+    tokens_total: IntCounterVec,
+    account_errors_total: IntCounterVec,
+    accounts_total: IntGaugeVec,
+    quota_available: GaugeVec,
+}
+
+impl PoolMetrics {
+    pub fn new() -> Self {
+        let requests_total = IntCounterVec::new(
+            Opts::new("kiro_requests_total", "请求总数"),
+            &["account_id", "model", "success"],
+        )
+        .expect("指标定义有效");
+        let request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("kiro_request_duration_seconds", "请求耗时（秒）")
+                .buckets(DURATION_BUCKETS.to_vec()),
+            &["account_id", "model"],
+        )
+        .expect("指标定义有效");
+        let tokens_total = IntCounterVec::new(
+            Opts::new(
+                "kiro_tokens_total",
+                "累计 tokens（direction 为 input 或 output）",
+            ),
+            &["account_id", "direction"],
+        )
+        .expect("指标定义有效");
+        let account_errors_total = IntCounterVec::new(
+            Opts::new("kiro_account_errors_total", "账号记录的错误次数"),
+            &["account_id", "rate_limited"],
+        )
+        .expect("指标定义有效");
+        let accounts_total = IntGaugeVec::new(
+            Opts::new("kiro_accounts_total", "各状态的账号数"),
+            &["status"],
+        )
+        .expect("指标定义有效");
+        let quota_available = GaugeVec::new(
+            Opts::new("kiro_quota_available", "配额缓存中的剩余额度"),
+            &["account_id"],
+        )
+        .expect("指标定义有效");
+
+        let registry = Registry::new();
+        registry
+            .register(Box::new(requests_total.clone()))
+            .expect("指标名称不重复");
+        registry
+            .register(Box::new(request_duration_seconds.clone()))
+            .expect("指标名称不重复");
+        registry
+            .register(Box::new(tokens_total.clone()))
+            .expect("指标名称不重复");
+        registry
+            .register(Box::new(account_errors_total.clone()))
+            .expect("指标名称不重复");
+        registry
+            .register(Box::new(accounts_total.clone()))
+            .expect("指标名称不重复");
+        registry
+            .register(Box::new(quota_available.clone()))
+            .expect("指标名称不重复");
+
+        Self {
+            registry,
+            requests_total,
+            request_duration_seconds,
+            tokens_total,
+            account_errors_total,
+            accounts_total,
+            quota_available,
+        }
+    }
+
+    /// 按一条请求记录累加请求数、耗时与 tokens
+    pub fn observe_request(&self, log: &RequestLog) {
+        let success = if log.success { "true" } else { "false" };
+        self.requests_total
+            .with_label_values(&[log.account_id.as_str(), log.model.as_str(), success])
+            .inc();
+        self.request_duration_seconds
+            .with_label_values(&[log.account_id.as_str(), log.model.as_str()])
+            .observe(log.duration_ms as f64 / 1000.0);
+        for (direction, tokens) in [("input", log.input_tokens), ("output", log.output_tokens)] {
+            self.tokens_total
+                .with_label_values(&[log.account_id.as_str(), direction])
+                .inc_by(tokens.max(0) as u64);
+        }
+    }
+
+    /// 记录账号错误
+    pub fn observe_error(&self, account_id: &str, is_rate_limit: bool) {
+        let rate_limited = if is_rate_limit { "true" } else { "false" };
+        self.account_errors_total
+            .with_label_values(&[account_id, rate_limited])
+            .inc();
+    }
+
+    /// 以当前账号状态与配额刷新瞬时指标（先清空，已删除的账号不会残留）
+    pub fn set_pool_state<'a>(
+        &self,
+        status_counts: impl IntoIterator<Item = (&'a str, i64)>,
+        quota: impl IntoIterator<Item = (&'a str, f64)>,
+    ) {
+        self.accounts_total.reset();
+        for (status, count) in status_counts {
+            self.accounts_total.with_label_values(&[status]).set(count);
+        }
+        self.quota_available.reset();
+        for (account_id, available) in quota {
+            self.quota_available
+                .with_label_values(&[account_id])
+                .set(available);
+        }
+    }
+
+    /// 以 Prometheus 文本格式输出全部指标
+    pub fn render(&self) -> anyhow::Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+impl Default for PoolMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
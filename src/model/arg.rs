@@ -11,4 +11,8 @@ pub struct Args {
     /// 凭证文件路径
     #[arg(long)]
     pub credentials: Option<String>,
+
+    /// 批量导入账号时并发验证凭证的数量
+    #[arg(long, default_value_t = 5)]
+    pub import_concurrency: usize,
 }
@@ -1,10 +1,23 @@
+use notify::{RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::pool::throttle::RateLimitSettings;
+use crate::pool::AccountPool;
+
+/// 默认 Kiro API 基础地址
+const DEFAULT_KIRO_API_BASE_URL: &str = "https://codewhisperer.us-east-1.amazonaws.com";
 
 /// KNA 应用配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// `Debug` 输出会隐去 [`Config::sensitive_fields`] 中的字段值
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
     #[serde(default = "default_host")]
@@ -25,6 +38,10 @@ pub struct Config {
     #[serde(default)]
     pub api_key: Option<String>,
 
+    /// 管理员密钥（可选，导出未脱敏凭证等高危接口需要，未设置时这些接口禁用）
+    #[serde(default)]
+    pub admin_key: Option<String>,
+
     #[serde(default = "default_system_version")]
     pub system_version: String,
 
@@ -43,6 +60,14 @@ pub struct Config {
     #[serde(default = "default_count_tokens_auth_type")]
     pub count_tokens_auth_type: String,
 
+    /// count_tokens 是否通过账号调用 Kiro 服务端计数（失败时回退本地估算）
+    #[serde(default)]
+    pub count_tokens_proxy_mode: bool,
+
+    /// Kiro 服务端 count_tokens 接口地址（可选，默认 {Kiro 对话 API 基础地址}/countTokens）
+    #[serde(default)]
+    pub count_tokens_api_endpoint: Option<String>,
+
     /// HTTP 代理地址（可选）
     /// 支持格式: http://host:port, https://host:port, socks5://host:port
     #[serde(default)]
@@ -55,6 +80,236 @@ pub struct Config {
     /// 代理认证密码（可选）
     #[serde(default)]
     pub proxy_password: Option<String>,
+
+    /// Kiro API 基础地址（可选，用于配额查询，默认 https://codewhisperer.us-east-1.amazonaws.com）
+    #[serde(default)]
+    pub kiro_api_base_url: Option<String>,
+
+    /// Kiro 对话 API 基础地址（可选，默认 https://q.{region}.amazonaws.com）
+    #[serde(default)]
+    pub kiro_stream_api_base_url: Option<String>,
+
+    /// request_logs.json 的大小上限（MB，可选，默认不限制），
+    /// 超出时循环移除内存中最早的 20% 记录直到能够容纳
+    #[serde(default)]
+    pub max_log_disk_mb: Option<u64>,
+
+    /// 内存中保留的请求记录条数上限（默认 1000）
+    #[serde(default = "default_max_request_logs")]
+    pub max_request_logs: usize,
+
+    /// 允许导入的账号池快照最大时长（小时，默认 24），防止恢复过期状态
+    #[serde(default = "default_snapshot_max_age_hours")]
+    pub snapshot_max_age_hours: u64,
+
+    /// Token 后台预刷新提前量（秒，默认 60），距同步刷新不足该时长时在后台刷新
+    #[serde(default = "default_refresh_buffer_secs")]
+    pub refresh_buffer_secs: u64,
+
+    /// 账号池全局每秒请求数上限（可选，未设置时不限速）
+    #[serde(default)]
+    pub rps_limit: Option<f64>,
+
+    /// 是否根据近 10 分钟错误率自动调整限速（需设置 rpsLimit）
+    #[serde(default)]
+    pub auto_throttle_enabled: bool,
+
+    /// 自动限速的最低每秒请求数（默认 1）
+    #[serde(default = "default_min_rps")]
+    pub min_rps: f64,
+
+    /// 自动限速的最高每秒请求数（可选，默认取 rpsLimit）
+    #[serde(default)]
+    pub max_rps: Option<f64>,
+
+    /// 是否以 DEBUG 级别输出上游请求体前 500 个字符（排查问题用，默认关闭）
+    #[serde(default)]
+    pub request_body_log: bool,
+
+    /// 是否以 DEBUG 级别输出非流式响应体前 500 个字符（排查问题用，默认关闭）
+    #[serde(default)]
+    pub response_body_log: bool,
+
+    /// 上游 500/502/503/504 错误的重试策略
+    #[serde(default)]
+    pub upstream_retry_policy: UpstreamRetryPolicy,
+
+    /// 账号池模式下可重试错误的换号重试策略
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+
+    /// 账号熔断器
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+
+    /// 连续限流时冷却时长的上限（分钟，默认 120）
+    #[serde(default = "default_max_cooldown_minutes")]
+    pub max_cooldown_minutes: u32,
+
+    /// `/health` 判定为 degraded 的可用账号比例阈值（可用 / 总数低于该值，默认 0.5）
+    #[serde(default = "default_health_degraded_ratio")]
+    pub health_degraded_ratio: f64,
+
+    /// 请求记录 Webhook（可选），每条请求记录实时推送到该地址
+    #[serde(default)]
+    pub request_log_webhook: Option<RequestLogWebhook>,
+
+    /// 注入到每个请求系统提示词开头的内容（可选）
+    #[serde(default)]
+    pub system_prompt_prefix: Option<String>,
+
+    /// 追加到每个请求系统提示词末尾的内容（可选）
+    #[serde(default)]
+    pub system_prompt_suffix: Option<String>,
+
+    /// 命名系统提示词模板（请求头 `X-System-Template` 指定名称后注入）
+    #[serde(default)]
+    pub system_prompt_templates: HashMap<String, String>,
+
+    /// 允许跨域访问的来源列表（默认 ["*"]，即允许任意来源）
+    #[serde(default = "default_cors_allowed_origins")]
+    pub cors_allowed_origins: Vec<String>,
+
+    /// 是否对非流式响应启用 gzip 压缩（默认关闭，SSE 流式响应始终不压缩）
+    #[serde(default)]
+    pub compress_responses: bool,
+
+    /// 是否允许请求以 `streaming_format: "raw"` 获取未经转换的上游事件流（调试用，默认关闭）
+    #[serde(default)]
+    pub allow_raw_stream: bool,
+
+    /// 按 cron 表达式定时切换选择策略（账号池模式，可被 strategy_schedule.json 覆盖）
+    #[serde(default)]
+    pub strategy_schedule: StrategySchedule,
+
+    /// 附加 API Key 列表（可选，每日配额通过 ALLOWED_API_KEYS_JSON 配置）
+    #[serde(default)]
+    pub allowed_api_keys: Vec<String>,
+
+    /// 输出内容过滤规则（可选）
+    /// 对上游返回的文本按正则替换，用于屏蔽邮箱、手机号等敏感信息
+    #[serde(default)]
+    pub content_filter: Vec<ContentFilterRule>,
+}
+
+/// 选择策略定时切换计划
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StrategySchedule {
+    #[serde(default)]
+    pub rules: Vec<StrategyRule>,
+}
+
+/// 定时切换规则：`cron` 触发后切换到 `strategy`（kebab-case 策略名）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyRule {
+    /// cron 表达式（5 段为 分 时 日 月 周，也支持带秒的 6/7 段），按服务器本地时间计算
+    pub cron: String,
+    pub strategy: String,
+}
+
+/// 上游瞬时错误重试策略（指数退避 + 随机抖动）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamRetryPolicy {
+    /// 最大重试次数（默认 2，即最多请求 3 次）
+    #[serde(default = "default_upstream_max_retries")]
+    pub max_retries: u32,
+    /// 首次重试前的等待时间（毫秒，默认 200），之后每次翻倍
+    #[serde(default = "default_upstream_base_delay_ms")]
+    pub base_delay_ms: u64,
+    /// 单次等待时间上限（毫秒，默认 2000）
+    #[serde(default = "default_upstream_max_delay_ms")]
+    pub max_delay_ms: u64,
+    /// 抖动比例（默认 0.25，即在退避时间上随机增加至多 25%）
+    #[serde(default = "default_upstream_jitter_factor")]
+    pub jitter_factor: f64,
+}
+
+impl Default for UpstreamRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: default_upstream_max_retries(),
+            base_delay_ms: default_upstream_base_delay_ms(),
+            max_delay_ms: default_upstream_max_delay_ms(),
+            jitter_factor: default_upstream_jitter_factor(),
+        }
+    }
+}
+
+/// 换号重试策略：上游返回可重试状态码时，改用尚未尝试过的账号重新发起请求
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// 单个请求最多换号重试次数（默认 2，0 表示不换号）
+    #[serde(default = "default_failover_max_retries")]
+    pub max_retries: u8,
+    /// 触发换号的上游状态码（默认 429、500、502、503、504）
+    #[serde(default = "default_failover_status_codes")]
+    pub retryable_status_codes: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: default_failover_max_retries(),
+            retryable_status_codes: default_failover_status_codes(),
+        }
+    }
+}
+
+/// 账号熔断器配置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitBreakerConfig {
+    /// 60 秒内失败次数超过该值时打开熔断（默认 5，0 表示不启用）
+    #[serde(default = "default_circuit_failure_threshold")]
+    pub failure_threshold: u32,
+    /// 半开状态下关闭熔断所需的探测成功次数（默认 1）
+    #[serde(default = "default_circuit_success_threshold")]
+    pub success_threshold: u32,
+    /// 打开熔断后放行探测请求前的等待时间（秒，默认 30）
+    #[serde(default = "default_circuit_half_open_timeout_secs")]
+    pub half_open_timeout_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_circuit_failure_threshold(),
+            success_threshold: default_circuit_success_threshold(),
+            half_open_timeout_secs: default_circuit_half_open_timeout_secs(),
+        }
+    }
+}
+
+/// 请求记录 Webhook 配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestLogWebhook {
+    /// 推送地址
+    pub url: String,
+    /// HMAC-SHA256 签名密钥（可选）
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// 失败重试次数（默认 3）
+    #[serde(default = "default_webhook_max_retries")]
+    pub max_retries: u32,
+}
+
+/// 输出内容过滤规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentFilterRule {
+    /// 规则名称
+    pub name: String,
+    /// 正则表达式
+    pub pattern: String,
+    /// 替换文本（默认 "[REDACTED]"）
+    #[serde(default = "default_filter_replacement")]
+    pub replacement: String,
+    /// 是否启用（默认 true）
+    #[serde(default = "default_true")]
+    pub enabled: bool,
 }
 
 impl Config {
@@ -74,6 +329,9 @@ impl Config {
         if let Ok(api_key) = env::var("API_KEY") {
             self.api_key = Some(api_key);
         }
+        if let Ok(admin_key) = env::var("ADMIN_KEY") {
+            self.admin_key = Some(admin_key);
+        }
         if let Ok(kiro_version) = env::var("KIRO_VERSION") {
             self.kiro_version = kiro_version;
         }
@@ -95,6 +353,12 @@ impl Config {
         if let Ok(auth_type) = env::var("COUNT_TOKENS_AUTH_TYPE") {
             self.count_tokens_auth_type = auth_type;
         }
+        if let Ok(enabled) = env::var("COUNT_TOKENS_PROXY_MODE") {
+            self.count_tokens_proxy_mode = enabled == "true" || enabled == "1";
+        }
+        if let Ok(endpoint) = env::var("COUNT_TOKENS_API_ENDPOINT") {
+            self.count_tokens_api_endpoint = Some(endpoint);
+        }
         if let Ok(proxy) = env::var("PROXY_URL") {
             self.proxy_url = Some(proxy);
         }
@@ -104,6 +368,92 @@ impl Config {
         if let Ok(password) = env::var("PROXY_PASSWORD") {
             self.proxy_password = Some(password);
         }
+        if let Ok(url) = env::var("KIRO_API_BASE_URL") {
+            self.kiro_api_base_url = Some(url);
+        }
+        if let Ok(url) = env::var("KIRO_STREAM_API_BASE_URL") {
+            self.kiro_stream_api_base_url = Some(url);
+        }
+        if let Ok(mb) = env::var("MAX_LOG_DISK_MB") {
+            if let Ok(mb) = mb.parse() {
+                self.max_log_disk_mb = Some(mb);
+            }
+        }
+        if let Ok(hours) = env::var("SNAPSHOT_MAX_AGE_HOURS") {
+            if let Ok(hours) = hours.parse() {
+                self.snapshot_max_age_hours = hours;
+            }
+        }
+        if let Ok(secs) = env::var("REFRESH_BUFFER_SECS") {
+            if let Ok(secs) = secs.parse() {
+                self.refresh_buffer_secs = secs;
+            }
+        }
+        if let Ok(rps) = env::var("RPS_LIMIT") {
+            if let Ok(rps) = rps.parse() {
+                self.rps_limit = Some(rps);
+            }
+        }
+        if let Ok(enabled) = env::var("AUTO_THROTTLE_ENABLED") {
+            self.auto_throttle_enabled = enabled == "true" || enabled == "1";
+        }
+        if let Ok(rps) = env::var("MIN_RPS") {
+            if let Ok(rps) = rps.parse() {
+                self.min_rps = rps;
+            }
+        }
+        if let Ok(rps) = env::var("MAX_RPS") {
+            if let Ok(rps) = rps.parse() {
+                self.max_rps = Some(rps);
+            }
+        }
+        if let Ok(enabled) = env::var("REQUEST_BODY_LOG") {
+            self.request_body_log = enabled == "true" || enabled == "1";
+        }
+        if let Ok(enabled) = env::var("RESPONSE_BODY_LOG") {
+            self.response_body_log = enabled == "true" || enabled == "1";
+        }
+        if let Ok(prefix) = env::var("SYSTEM_PROMPT_PREFIX") {
+            self.system_prompt_prefix = Some(prefix);
+        }
+        if let Ok(suffix) = env::var("SYSTEM_PROMPT_SUFFIX") {
+            self.system_prompt_suffix = Some(suffix);
+        }
+        if let Ok(origins) = env::var("CORS_ORIGINS") {
+            self.cors_allowed_origins = origins
+                .split(',')
+                .map(|o| o.trim().to_string())
+                .filter(|o| !o.is_empty())
+                .collect();
+        }
+        if let Ok(enabled) = env::var("GZIP_RESPONSES") {
+            self.compress_responses = enabled == "true" || enabled == "1";
+        }
+        if let Ok(enabled) = env::var("ALLOW_RAW_STREAM") {
+            self.allow_raw_stream = enabled == "true" || enabled == "1";
+        }
+        if let Ok(url) = env::var("REQUESTS_LOG_WEBHOOK") {
+            match &mut self.request_log_webhook {
+                Some(webhook) => webhook.url = url,
+                None => {
+                    self.request_log_webhook = Some(RequestLogWebhook {
+                        url,
+                        secret: None,
+                        max_retries: default_webhook_max_retries(),
+                    })
+                }
+            }
+        }
+        if let Some(webhook) = &mut self.request_log_webhook {
+            if let Ok(secret) = env::var("REQUESTS_LOG_WEBHOOK_SECRET") {
+                webhook.secret = Some(secret);
+            }
+            if let Ok(retries) = env::var("REQUESTS_LOG_WEBHOOK_MAX_RETRIES") {
+                if let Ok(retries) = retries.parse() {
+                    webhook.max_retries = retries;
+                }
+            }
+        }
     }
 }
 
@@ -139,6 +489,82 @@ fn default_count_tokens_auth_type() -> String {
     "x-api-key".to_string()
 }
 
+fn default_snapshot_max_age_hours() -> u64 {
+    24
+}
+
+fn default_refresh_buffer_secs() -> u64 {
+    60
+}
+
+fn default_min_rps() -> f64 {
+    1.0
+}
+
+fn default_max_request_logs() -> usize {
+    1000
+}
+
+fn default_max_cooldown_minutes() -> u32 {
+    120
+}
+
+fn default_health_degraded_ratio() -> f64 {
+    0.5
+}
+
+fn default_cors_allowed_origins() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+fn default_upstream_max_retries() -> u32 {
+    2
+}
+
+fn default_upstream_base_delay_ms() -> u64 {
+    200
+}
+
+fn default_upstream_max_delay_ms() -> u64 {
+    2_000
+}
+
+fn default_upstream_jitter_factor() -> f64 {
+    0.25
+}
+
+fn default_failover_max_retries() -> u8 {
+    2
+}
+
+fn default_failover_status_codes() -> Vec<u16> {
+    vec![429, 500, 502, 503, 504]
+}
+
+fn default_circuit_failure_threshold() -> u32 {
+    5
+}
+
+fn default_circuit_success_threshold() -> u32 {
+    1
+}
+
+fn default_circuit_half_open_timeout_secs() -> u64 {
+    30
+}
+
+fn default_webhook_max_retries() -> u32 {
+    3
+}
+
+fn default_filter_replacement() -> String {
+    "[REDACTED]".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -148,19 +574,94 @@ impl Default for Config {
             kiro_version: default_kiro_version(),
             machine_id: None,
             api_key: None,
+            admin_key: None,
             system_version: default_system_version(),
             node_version: default_node_version(),
             count_tokens_api_url: None,
             count_tokens_api_key: None,
             count_tokens_auth_type: default_count_tokens_auth_type(),
+            count_tokens_proxy_mode: false,
+            count_tokens_api_endpoint: None,
             proxy_url: None,
             proxy_username: None,
             proxy_password: None,
+            kiro_api_base_url: None,
+            kiro_stream_api_base_url: None,
+            max_log_disk_mb: None,
+            max_request_logs: default_max_request_logs(),
+            snapshot_max_age_hours: default_snapshot_max_age_hours(),
+            refresh_buffer_secs: default_refresh_buffer_secs(),
+            rps_limit: None,
+            auto_throttle_enabled: false,
+            min_rps: default_min_rps(),
+            max_rps: None,
+            request_body_log: false,
+            response_body_log: false,
+            upstream_retry_policy: UpstreamRetryPolicy::default(),
+            retry_policy: RetryPolicy::default(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+            max_cooldown_minutes: default_max_cooldown_minutes(),
+            health_degraded_ratio: default_health_degraded_ratio(),
+            request_log_webhook: None,
+            system_prompt_prefix: None,
+            system_prompt_suffix: None,
+            system_prompt_templates: HashMap::new(),
+            cors_allowed_origins: default_cors_allowed_origins(),
+            compress_responses: false,
+            allow_raw_stream: false,
+            strategy_schedule: StrategySchedule::default(),
+            allowed_api_keys: Vec::new(),
+            content_filter: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let serde_json::Value::Object(fields) =
+            serde_json::to_value(self).map_err(|_| fmt::Error)?
+        else {
+            return Err(fmt::Error);
+        };
+
+        let mut debug = f.debug_struct("Config");
+        for (key, value) in &fields {
+            let name = camel_to_snake(key);
+            if !value.is_null() && Self::sensitive_fields().contains(&name.as_str()) {
+                debug.field(&name, &"[REDACTED]");
+            } else {
+                debug.field(&name, &format_args!("{}", value));
+            }
         }
+        debug.finish()
     }
 }
 
+/// camelCase 字段名转为 snake_case
+fn camel_to_snake(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('_');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
 impl Config {
+    /// `Debug` 输出时需要隐去的字段
+    pub fn sensitive_fields() -> &'static [&'static str] {
+        &[
+            "api_key",
+            "admin_key",
+            "proxy_password",
+            "count_tokens_api_key",
+        ]
+    }
+
     /// 获取默认配置文件路径
     pub fn default_config_path() -> &'static str {
         "config.json"
@@ -178,4 +679,315 @@ impl Config {
         let config: Config = serde_json::from_str(&content)?;
         Ok(config)
     }
+
+    /// Kiro API 基础地址（配额查询等接口）
+    pub fn kiro_api_url(&self) -> String {
+        self.kiro_api_base_url
+            .as_deref()
+            .unwrap_or(DEFAULT_KIRO_API_BASE_URL)
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    /// Kiro 对话 API 基础地址（generateAssistantResponse）
+    pub fn kiro_stream_api_url(&self) -> String {
+        match &self.kiro_stream_api_base_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => format!("https://q.{}.amazonaws.com", self.region),
+        }
+    }
+
+    /// Kiro 对话 API 的 Host（含非默认端口）
+    pub fn kiro_stream_api_host(&self) -> String {
+        let url = self.kiro_stream_api_url();
+        let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(&url);
+        without_scheme
+            .split('/')
+            .next()
+            .unwrap_or(without_scheme)
+            .to_string()
+    }
+
+    /// 校验配置的基本合法性
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.host.trim().is_empty() {
+            anyhow::bail!("host 不能为空");
+        }
+        if self.port == 0 {
+            anyhow::bail!("port 不能为 0");
+        }
+        if self.region.trim().is_empty() {
+            anyhow::bail!("region 不能为空");
+        }
+        if matches!(self.api_key.as_deref(), Some(key) if key.trim().is_empty()) {
+            anyhow::bail!("apiKey 不能为空字符串");
+        }
+        if matches!(self.admin_key.as_deref(), Some(key) if key.trim().is_empty()) {
+            anyhow::bail!("adminKey 不能为空字符串");
+        }
+        if !matches!(self.count_tokens_auth_type.as_str(), "x-api-key" | "bearer") {
+            anyhow::bail!(
+                "countTokensAuthType 无效: {}（可选 x-api-key / bearer）",
+                self.count_tokens_auth_type
+            );
+        }
+        Ok(())
+    }
+
+    /// 将新配置中可热更新的字段（见 [`LIVE_UPDATE_FIELDS`]）应用到当前配置
+    ///
+    /// 返回发生变化但需要重启才能生效的其余字段名（camelCase，按字母排序）
+    pub fn apply_live_update(&mut self, new: &Config) -> Vec<String> {
+        let restart_required = match (serde_json::to_value(&*self), serde_json::to_value(new)) {
+            (Ok(serde_json::Value::Object(current)), Ok(serde_json::Value::Object(updated))) => {
+                updated
+                    .into_iter()
+                    .filter(|(key, value)| {
+                        current.get(key) != Some(value)
+                            && !LIVE_UPDATE_FIELDS.contains(&key.as_str())
+                    })
+                    .map(|(key, _)| key)
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+
+        self.api_key = new.api_key.clone();
+        self.request_body_log = new.request_body_log;
+        self.response_body_log = new.response_body_log;
+        self.retry_policy = new.retry_policy.clone();
+        self.health_degraded_ratio = new.health_degraded_ratio;
+        self.system_prompt_prefix = new.system_prompt_prefix.clone();
+        self.system_prompt_suffix = new.system_prompt_suffix.clone();
+        self.system_prompt_templates = new.system_prompt_templates.clone();
+        self.allow_raw_stream = new.allow_raw_stream;
+        self.count_tokens_proxy_mode = new.count_tokens_proxy_mode;
+        self.count_tokens_api_endpoint = new.count_tokens_api_endpoint.clone();
+        self.rps_limit = new.rps_limit;
+        self.min_rps = new.min_rps;
+        self.max_rps = new.max_rps;
+
+        restart_required
+    }
+}
+
+/// 可热更新的配置字段：请求处理时从共享配置读取，或在重新加载时同步到账号池（限速参数）
+///
+/// 其余字段（如 `circuitBreaker`、`port`）变更后需要重启
+pub const LIVE_UPDATE_FIELDS: &[&str] = &[
+    "apiKey",
+    "requestBodyLog",
+    "responseBodyLog",
+    "retryPolicy",
+    "healthDegradedRatio",
+    "systemPromptPrefix",
+    "systemPromptSuffix",
+    "systemPromptTemplates",
+    "allowRawStream",
+    "countTokensProxyMode",
+    "countTokensApiEndpoint",
+    "rpsLimit",
+    "minRps",
+    "maxRps",
+];
+
+/// 配置文件监听器
+///
+/// 监听 config.json 变更并热更新共享配置（可热更新的字段见 [`LIVE_UPDATE_FIELDS`]）
+pub struct ConfigWatcher {
+    path: PathBuf,
+    shared: Arc<RwLock<Config>>,
+    /// 账号池模式下，重新加载后同步限速参数
+    pool: Option<Arc<AccountPool>>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: impl Into<PathBuf>, shared: Arc<RwLock<Config>>) -> Self {
+        Self {
+            path: path.into(),
+            shared,
+            pool: None,
+        }
+    }
+
+    /// 重新加载后将限速参数同步到账号池
+    pub fn with_pool(mut self, pool: Arc<AccountPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// 被监听的共享配置
+    pub fn shared(&self) -> Arc<RwLock<Config>> {
+        self.shared.clone()
+    }
+
+    /// 启动后台监听任务
+    pub fn spawn(self) -> anyhow::Result<()> {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |res| {
+            let _ = tx.send(res);
+        })?;
+
+        // 监听所在目录，兼容编辑器以重命名方式保存文件
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        let file_name = self.path.file_name().map(|n| n.to_os_string());
+        tracing::info!("已启用配置文件热加载: {:?}", self.path);
+
+        tokio::spawn(async move {
+            // watcher 需要与任务同生命周期
+            let _watcher = watcher;
+            while let Some(res) = rx.recv().await {
+                let event: notify::Event = match res {
+                    Ok(event) => event,
+                    Err(e) => {
+                        tracing::warn!("配置文件监听出错: {}", e);
+                        continue;
+                    }
+                };
+                let touches_config = event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name().map(|n| n.to_os_string()) == file_name);
+                if !touches_config || !(event.kind.is_modify() || event.kind.is_create()) {
+                    continue;
+                }
+
+                // 合并短时间内的连续写入事件
+                tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                while rx.try_recv().is_ok() {}
+
+                self.reload().await;
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn reload(&self) {
+        let mut new_config = match Config::load(&self.path) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("重新加载配置文件失败，保留当前配置: {}", e);
+                return;
+            }
+        };
+        new_config.override_from_env();
+        if let Err(e) = new_config.validate() {
+            tracing::warn!("新配置校验失败，保留当前配置: {}", e);
+            return;
+        }
+
+        let mut config = self.shared.write().await;
+        let restart_required = config.apply_live_update(&new_config);
+        let rate_limit = RateLimitSettings::from_config(&config);
+        drop(config);
+        if let Some(pool) = &self.pool {
+            pool.apply_rate_limit(rate_limit).await;
+        }
+        tracing::info!("配置文件已重新加载");
+        if !restart_required.is_empty() {
+            tracing::warn!(
+                "以下配置项已变更，需要重启服务才能生效: {}",
+                restart_required.join(", ")
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_redacts_sensitive_fields() {
+        let config = Config {
+            api_key: Some("sk-super-secret".to_string()),
+            proxy_password: Some("hunter2".to_string()),
+            ..Config::default()
+        };
+        let output = format!("{:?}", config);
+        assert!(!output.contains("sk-super-secret"));
+        assert!(!output.contains("hunter2"));
+        assert!(output.contains(r#"api_key: "[REDACTED]""#));
+        assert!(output.contains("count_tokens_api_key: null"));
+        assert!(output.contains(r#"region: "us-east-1""#));
+    }
+
+    #[test]
+    fn test_kiro_api_urls_from_config() {
+        let mut config = Config {
+            region: "eu-west-1".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.kiro_api_url(),
+            "https://codewhisperer.us-east-1.amazonaws.com"
+        );
+        assert_eq!(
+            config.kiro_stream_api_url(),
+            "https://q.eu-west-1.amazonaws.com"
+        );
+        assert_eq!(config.kiro_stream_api_host(), "q.eu-west-1.amazonaws.com");
+
+        config.kiro_api_base_url = Some("http://localhost:9000/".to_string());
+        config.kiro_stream_api_base_url = Some("https://kiro.staging.example.com/api".to_string());
+        assert_eq!(config.kiro_api_url(), "http://localhost:9000");
+        assert_eq!(
+            config.kiro_stream_api_url(),
+            "https://kiro.staging.example.com/api"
+        );
+        assert_eq!(config.kiro_stream_api_host(), "kiro.staging.example.com");
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_values() {
+        let mut config = Config::default();
+        assert!(config.validate().is_ok());
+
+        config.count_tokens_auth_type = "basic".to_string();
+        assert!(config.validate().is_err());
+
+        config.count_tokens_auth_type = "bearer".to_string();
+        config.api_key = Some(" ".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_apply_live_update() {
+        let mut current = Config {
+            api_key: Some("old".to_string()),
+            ..Config::default()
+        };
+
+        let mut new = current.clone();
+        new.api_key = Some("new".to_string());
+        new.retry_policy.max_retries = 0;
+        new.system_prompt_prefix = Some("prefix".to_string());
+        new.port = current.port.wrapping_add(1);
+        new.rps_limit = Some(5.0);
+        new.max_rps = Some(8.0);
+        new.circuit_breaker.failure_threshold += 1;
+
+        let restart_required = current.apply_live_update(&new);
+        assert_eq!(current.api_key.as_deref(), Some("new"));
+        assert_eq!(current.retry_policy.max_retries, 0);
+        assert_eq!(current.system_prompt_prefix.as_deref(), Some("prefix"));
+        assert_eq!(current.rps_limit, Some(5.0));
+        assert_eq!(current.max_rps, Some(8.0));
+        // 其余变更的字段全部提示需要重启，且不会被应用
+        assert_eq!(restart_required, vec!["circuitBreaker", "port"]);
+        assert_ne!(current.port, new.port);
+
+        // 列出的每个可热更新字段都存在且已被应用
+        let current = serde_json::to_value(&current).unwrap();
+        let new = serde_json::to_value(&new).unwrap();
+        for field in LIVE_UPDATE_FIELDS {
+            assert!(new.get(field).is_some(), "未知字段 {}", field);
+            assert_eq!(current[field], new[field], "{} 未被应用", field);
+        }
+    }
 }
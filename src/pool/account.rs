@@ -1,8 +1,39 @@
 //! 账号状态管理
 
+use crate::http_client::ProxyConfig;
 use crate::kiro::model::credentials::KiroCredentials;
+use crate::pool::circuit::CircuitState;
+use crate::pool::strategy::StrategyConfig;
+use crate::pool::usage::UsageLimits;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// 账号备注最大字符数
+pub const MAX_NOTES_CHARS: usize = 2000;
+/// 保留的状态变更历史条数
+pub const MAX_STATUS_HISTORY: usize = 10;
+/// LowestLatency 策略统计的最近首 token 延迟条数
+pub const LATENCY_WINDOW_SIZE: usize = 10;
+/// 账号默认加权轮询权重
+pub const DEFAULT_WEIGHT: u32 = 1;
+
+pub(crate) fn default_weight() -> u32 {
+    DEFAULT_WEIGHT
+}
+
+/// 首次限流的冷却时长（分钟）
+const BASE_COOLDOWN_MINUTES: u64 = 5;
+
+/// 第 `consecutive` 次连续限流的冷却时长：`5 * 2^(consecutive - 1)` 分钟，不超过 `max_minutes`
+pub fn rate_limit_cooldown_minutes(consecutive: u32, max_minutes: u32) -> u64 {
+    let factor = 1u64
+        .checked_shl(consecutive.saturating_sub(1))
+        .unwrap_or(u64::MAX);
+    BASE_COOLDOWN_MINUTES
+        .saturating_mul(factor)
+        .min(max_minutes as u64)
+}
 
 /// 账号状态
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -20,6 +51,156 @@ pub enum AccountStatus {
     Disabled,
 }
 
+impl AccountStatus {
+    /// 面向用户展示的状态字符串
+    pub fn to_status_string(self) -> &'static str {
+        match self {
+            AccountStatus::Active => "active",
+            AccountStatus::Cooldown => "cooldown",
+            AccountStatus::Exhausted => "exhausted",
+            AccountStatus::Invalid => "invalid",
+            AccountStatus::Disabled => "disabled",
+        }
+    }
+}
+
+/// 一次账号状态变更
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusTransition {
+    pub from: AccountStatus,
+    pub to: AccountStatus,
+    pub at: DateTime<Utc>,
+    /// 变更原因
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Kiro 原始凭证格式（导入 / 迁移导出用）
+///
+/// 兼容 Kiro IDE 导出的 camelCase JSON 与管理面板表单的 snake_case 字段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KiroRawCredentials {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default, alias = "access_token")]
+    pub access_token: Option<String>,
+    #[serde(alias = "refresh_token")]
+    pub refresh_token: String,
+    #[serde(default, alias = "auth_method")]
+    pub auth_method: Option<String>,
+    #[serde(default, alias = "client_id")]
+    pub client_id: Option<String>,
+    #[serde(default, alias = "client_secret")]
+    pub client_secret: Option<String>,
+    #[serde(default, alias = "profile_arn")]
+    pub profile_arn: Option<String>,
+    #[serde(default)]
+    pub region: Option<String>,
+    /// 单次请求超时（秒，可选）
+    #[serde(default, alias = "request_timeout_secs")]
+    pub request_timeout_secs: Option<u64>,
+    /// 是否固定（不参与账号池自动选择）
+    #[serde(default)]
+    pub pinned: bool,
+    /// 请求记录中替代账号名称的显示名（可选）
+    #[serde(default, alias = "display_name_override")]
+    pub display_name_override: Option<String>,
+    /// 账号专用代理地址（可选，未设置时使用全局代理）
+    #[serde(default, alias = "proxy_url")]
+    pub proxy_url: Option<String>,
+    /// 账号专用代理认证用户名
+    #[serde(default, alias = "proxy_username")]
+    pub proxy_username: Option<String>,
+    /// 账号专用代理认证密码
+    #[serde(default, alias = "proxy_password")]
+    pub proxy_password: Option<String>,
+    /// 运维备注（可选）
+    #[serde(default, alias = "description")]
+    pub notes: Option<String>,
+}
+
+impl KiroRawCredentials {
+    /// 由账号生成可重新导入的凭证（不含 accessToken），缺少 refreshToken 时返回 None
+    pub fn from_account(account: &Account) -> Option<Self> {
+        let credentials = &account.credentials;
+        Some(Self {
+            name: Some(account.name.clone()),
+            email: None,
+            label: None,
+            provider: None,
+            access_token: None,
+            refresh_token: credentials.refresh_token.clone()?,
+            auth_method: Some(credentials.auth_method_str().to_string()),
+            client_id: credentials.client_id.clone(),
+            client_secret: credentials.client_secret.clone(),
+            profile_arn: credentials.profile_arn.clone(),
+            region: account.region.clone(),
+            request_timeout_secs: account.request_timeout_secs,
+            pinned: account.pinned,
+            display_name_override: account.display_name_override.clone(),
+            proxy_url: account.proxy_override.as_ref().map(|p| p.url.clone()),
+            proxy_username: account
+                .proxy_override
+                .as_ref()
+                .and_then(|p| p.username.clone()),
+            proxy_password: account
+                .proxy_override
+                .as_ref()
+                .and_then(|p| p.password.clone()),
+            notes: account.notes.clone(),
+        })
+    }
+
+    /// 转换为新账号（未指定认证方式时自动检测）
+    pub fn into_account(self) -> Account {
+        // 名称：优先自定义名称，其次 label，再次 email
+        let name = self
+            .name
+            .or(self.label)
+            .or(self.email)
+            .unwrap_or_else(|| "导入的账号".to_string());
+
+        let mut credentials = KiroCredentials {
+            access_token: self.access_token,
+            refresh_token: Some(self.refresh_token),
+            profile_arn: self.profile_arn,
+            expires_at: Some("2000-01-01T00:00:00Z".to_string()), // 强制刷新
+            auth_method: self.auth_method,
+            client_id: self.client_id,
+            client_secret: self.client_secret,
+        };
+        if credentials.auth_method.is_none() {
+            credentials.auth_method = Some(credentials.auth_method_str().to_string());
+        }
+
+        // 代理地址为空时不设置覆盖；用户名与密码需同时提供
+        let proxy_override = self.proxy_url.filter(|url| !url.is_empty()).map(|url| {
+            match (self.proxy_username, self.proxy_password) {
+                (Some(username), Some(password)) => {
+                    ProxyConfig::new(url).with_auth(username, password)
+                }
+                _ => ProxyConfig::new(url),
+            }
+        });
+
+        AccountBuilder::new(uuid::Uuid::new_v4().to_string(), name, credentials)
+            .region(self.region)
+            .proxy_override(proxy_override)
+            .request_timeout_secs(self.request_timeout_secs)
+            .pinned(self.pinned)
+            .display_name_override(self.display_name_override.filter(|n| !n.is_empty()))
+            .notes(self.notes.filter(|n| !n.is_empty()))
+            .build()
+    }
+}
+
 /// 账号信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Account {
@@ -27,8 +208,8 @@ pub struct Account {
     pub id: String,
     /// 显示名称
     pub name: String,
-    /// 凭证信息
-    #[serde(skip_serializing)]
+    /// 凭证信息（不序列化；反序列化缺失时为空）
+    #[serde(skip_serializing, default)]
     pub credentials: KiroCredentials,
     /// 状态
     pub status: AccountStatus,
@@ -44,6 +225,48 @@ pub struct Account {
     pub exhausted_until: Option<DateTime<Utc>>,
     /// 创建时间
     pub created_at: DateTime<Utc>,
+    /// 所在区域（可选，用于地域感知策略）
+    #[serde(default)]
+    pub region: Option<String>,
+    /// 预热时测得的基线延迟（毫秒）
+    #[serde(default)]
+    pub warmup_latency_ms: Option<u64>,
+    /// 运维备注
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// 单次上游请求超时（秒，可选，未设置时使用全局超时）
+    #[serde(default)]
+    pub request_timeout_secs: Option<u64>,
+    /// 是否固定：固定的账号不参与账号池自动选择，仅供手动指定使用
+    #[serde(default)]
+    pub pinned: bool,
+    /// 显示名覆盖：设置后请求记录中以此替代账号名称，便于对外分享时匿名化
+    #[serde(default)]
+    pub display_name_override: Option<String>,
+    /// 键值标签（如 `team=infra`），用于按标签查找账号
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+    /// 账号专用代理（可选，未设置时使用账号池的全局代理）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_override: Option<ProxyConfig>,
+    /// 加权轮询权重（默认 1），为 0 时视同禁用，不参与自动选择
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// 待生效凭证（当前 Token 过期后启用）
+    #[serde(skip_serializing, default)]
+    pub pending_credentials: Option<KiroCredentials>,
+    /// 最近的状态变更（最多 10 条，由旧到新）
+    #[serde(default)]
+    pub history: VecDeque<StatusTransition>,
+    /// 最近的首 token 延迟（毫秒，最多 10 条，由旧到新）
+    #[serde(default)]
+    pub latency_window: VecDeque<u64>,
+    /// 熔断器状态
+    #[serde(default)]
+    pub circuit_state: CircuitState,
+    /// 连续限流次数（决定冷却时长，请求成功后清零）
+    #[serde(default)]
+    pub consecutive_rate_limit_errors: u32,
 }
 
 impl Account {
@@ -64,9 +287,57 @@ impl Account {
             cooldown_until: None,
             exhausted_until: None,
             created_at: Utc::now(),
+            region: None,
+            warmup_latency_ms: None,
+            notes: None,
+            request_timeout_secs: None,
+            pinned: false,
+            display_name_override: None,
+            tags: BTreeMap::new(),
+            proxy_override: None,
+            weight: DEFAULT_WEIGHT,
+            pending_credentials: None,
+            history: VecDeque::new(),
+            latency_window: VecDeque::new(),
+            circuit_state: CircuitState::default(),
+            consecutive_rate_limit_errors: 0,
         }
     }
 
+    /// 变更状态并记录历史（状态未变化时不记录）
+    pub fn set_status(&mut self, to: AccountStatus, reason: &str) {
+        let from = self.status;
+        if from == to {
+            return;
+        }
+        self.status = to;
+        if self.history.len() >= MAX_STATUS_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(StatusTransition {
+            from,
+            to,
+            at: Utc::now(),
+            reason: Some(reason.to_string()),
+        });
+    }
+
+    /// 是否带有全部要求的标签：`key=value` 要求值相等，仅 `key` 时只要求存在该键
+    pub fn has_tags(&self, required: &[&str]) -> bool {
+        required.iter().all(|tag| match tag.split_once('=') {
+            Some((key, value)) => self.tags.get(key).is_some_and(|v| v == value),
+            None => self.tags.contains_key(*tag),
+        })
+    }
+
+    /// 检查是否可被账号池自动选择（可用、未固定、权重不为 0 且熔断器放行）
+    pub fn is_selectable(&self) -> bool {
+        !self.pinned
+            && self.weight > 0
+            && self.is_available()
+            && self.circuit_state.allows_request(Utc::now())
+    }
+
     /// 检查是否可用
     pub fn is_available(&self) -> bool {
         match self.status {
@@ -85,41 +356,75 @@ impl Account {
         }
     }
 
-    /// 记录使用
-    pub fn record_use(&mut self) {
+    /// 记录使用，已知首 token 延迟时一并记入延迟窗口
+    pub fn record_use(&mut self, first_token_ms: Option<u64>) {
         self.request_count += 1;
         self.last_used_at = Some(Utc::now());
+        self.circuit_state.on_selected(Utc::now());
+        if let Some(ms) = first_token_ms {
+            self.record_first_token_latency(ms);
+        }
         // 如果冷却结束，恢复为活跃状态
         if self.status == AccountStatus::Cooldown && self.is_available() {
-            self.status = AccountStatus::Active;
+            self.set_status(AccountStatus::Active, "冷却结束");
             self.cooldown_until = None;
         }
         if self.status == AccountStatus::Exhausted && self.is_available() {
-            self.status = AccountStatus::Active;
+            self.set_status(AccountStatus::Active, "配额已恢复");
             self.exhausted_until = None;
         }
     }
 
-    /// 记录错误
-    pub fn record_error(&mut self, is_rate_limit: bool) {
+    /// 记录一次首 token 延迟，超出窗口时丢弃最早的记录
+    pub fn record_first_token_latency(&mut self, ms: u64) {
+        if self.latency_window.len() >= LATENCY_WINDOW_SIZE {
+            self.latency_window.pop_front();
+        }
+        self.latency_window.push_back(ms);
+    }
+
+    /// 延迟窗口的中位数（偶数个时取两者均值），无数据时返回 None
+    pub fn median_latency_ms(&self) -> Option<u64> {
+        let mut sorted: Vec<u64> = self.latency_window.iter().copied().collect();
+        sorted.sort_unstable();
+        let mid = sorted.len() / 2;
+        match sorted.len() {
+            0 => None,
+            n if n % 2 == 0 => Some((sorted[mid - 1] + sorted[mid]) / 2),
+            _ => Some(sorted[mid]),
+        }
+    }
+
+    /// 记录错误，限流时按连续限流次数指数退避进入冷却（不超过 `max_cooldown_minutes`）
+    pub fn record_error(&mut self, is_rate_limit: bool, max_cooldown_minutes: u32) {
         self.error_count += 1;
         if is_rate_limit {
-            // 限流，进入冷却
-            self.status = AccountStatus::Cooldown;
-            self.cooldown_until = Some(Utc::now() + chrono::Duration::minutes(5));
+            self.consecutive_rate_limit_errors =
+                self.consecutive_rate_limit_errors.saturating_add(1);
+            let minutes = rate_limit_cooldown_minutes(
+                self.consecutive_rate_limit_errors,
+                max_cooldown_minutes,
+            );
+            self.set_status(AccountStatus::Cooldown, "限流");
+            self.cooldown_until = Some(Utc::now() + chrono::Duration::minutes(minutes as i64));
         }
     }
 
+    /// 记录请求成功，清零连续限流次数
+    pub fn record_success(&mut self) {
+        self.consecutive_rate_limit_errors = 0;
+    }
+
     /// 标记为失效（自动转为禁用）
     pub fn mark_invalid(&mut self) {
-        self.status = AccountStatus::Disabled;
+        self.set_status(AccountStatus::Disabled, "凭证失效");
         self.cooldown_until = None;
         self.exhausted_until = None;
     }
 
     /// 标记为配额耗尽
     pub fn mark_exhausted(&mut self, next_reset: Option<DateTime<Utc>>) {
-        self.status = AccountStatus::Exhausted;
+        self.set_status(AccountStatus::Exhausted, "配额耗尽");
         self.exhausted_until = next_reset;
         self.cooldown_until = None;
     }
@@ -129,12 +434,12 @@ impl Account {
         let now = Utc::now();
         match self.status {
             AccountStatus::Cooldown if self.cooldown_until.map(|t| now >= t).unwrap_or(true) => {
-                self.status = AccountStatus::Active;
+                self.set_status(AccountStatus::Active, "冷却结束");
                 self.cooldown_until = None;
                 true
             }
             AccountStatus::Exhausted if self.exhausted_until.map(|t| now >= t).unwrap_or(false) => {
-                self.status = AccountStatus::Active;
+                self.set_status(AccountStatus::Active, "配额已恢复");
                 self.exhausted_until = None;
                 true
             }
@@ -145,16 +450,368 @@ impl Account {
     /// 启用账号
     pub fn enable(&mut self) {
         if self.status == AccountStatus::Disabled {
-            self.status = AccountStatus::Active;
+            self.set_status(AccountStatus::Active, "手动启用");
             self.cooldown_until = None;
             self.exhausted_until = None;
+            self.circuit_state = CircuitState::default();
+        }
+    }
+
+    /// 清零请求与错误计数及最近使用时间，冷却中的账号恢复为活跃
+    pub fn reset_stats(&mut self) {
+        self.request_count = 0;
+        self.error_count = 0;
+        self.last_used_at = None;
+        self.consecutive_rate_limit_errors = 0;
+        if self.status == AccountStatus::Cooldown {
+            self.set_status(AccountStatus::Active, "重置统计");
+            self.cooldown_until = None;
         }
     }
 
     /// 禁用账号
     pub fn disable(&mut self) {
-        self.status = AccountStatus::Disabled;
+        self.set_status(AccountStatus::Disabled, "手动禁用");
         self.cooldown_until = None;
         self.exhausted_until = None;
     }
+
+    /// 生成用于日志输出的脱敏 JSON（凭证字段经过脱敏处理）
+    pub fn to_redacted_json(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert(
+                "credentials".to_string(),
+                self.credentials.to_redacted_json(),
+            );
+            if let Some(password) = obj
+                .get_mut("proxy_override")
+                .and_then(|p| p.get_mut("password"))
+                .filter(|p| !p.is_null())
+            {
+                *password = serde_json::Value::String("***".to_string());
+            }
+        }
+        value
+    }
+
+    /// 配额缓存中该账号的免费试用是否仍有效（状态为 ACTIVE 且未过期）
+    pub fn is_in_free_trial(&self, usage_cache: &HashMap<String, UsageLimits>) -> bool {
+        usage_cache
+            .get(&self.id)
+            .and_then(|u| u.free_trial.as_ref())
+            .is_some_and(|ft| {
+                ft.status == "ACTIVE" && ft.expiry.is_none_or(|expiry| expiry > Utc::now())
+            })
+    }
+
+    /// 计算账号健康分（0.0 ~ 1.0）
+    ///
+    /// 基础分：活跃 1.0，冷却 0.5，其他 0.0；
+    /// 按错误率与已用配额比例（需有配额缓存）分别以策略参数中的权重扣减
+    pub fn credit_score(&self, usage: Option<&UsageLimits>, config: &StrategyConfig) -> f64 {
+        let mut score = match self.status {
+            AccountStatus::Active => 1.0,
+            AccountStatus::Cooldown => 0.5,
+            _ => 0.0,
+        };
+
+        let error_rate = if self.request_count > 0 {
+            self.error_count as f64 / self.request_count as f64
+        } else {
+            0.0
+        };
+        score -= config.error_rate_weight * error_rate.min(1.0);
+
+        if let Some(usage) = usage {
+            let quota_fraction = if usage.usage_limit > 0.0 {
+                (usage.available / usage.usage_limit).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            score -= config.quota_weight * (1.0 - quota_fraction);
+        }
+
+        score.clamp(0.0, 1.0)
+    }
+}
+
+/// 账号构建器
+///
+/// 以链式调用设置初始字段，避免各处直接修改 `Account::new` 的结果；未设置的字段与 `Account::new` 一致
+#[derive(Debug, Clone)]
+pub struct AccountBuilder {
+    account: Account,
+}
+
+impl AccountBuilder {
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        credentials: KiroCredentials,
+    ) -> Self {
+        Self {
+            account: Account::new(id, name, credentials),
+        }
+    }
+
+    pub fn region(mut self, region: Option<String>) -> Self {
+        self.account.region = region;
+        self
+    }
+
+    pub fn request_timeout_secs(mut self, request_timeout_secs: Option<u64>) -> Self {
+        self.account.request_timeout_secs = request_timeout_secs;
+        self
+    }
+
+    pub fn pinned(mut self, pinned: bool) -> Self {
+        self.account.pinned = pinned;
+        self
+    }
+
+    pub fn display_name_override(mut self, display_name_override: Option<String>) -> Self {
+        self.account.display_name_override = display_name_override;
+        self
+    }
+
+    pub fn proxy_override(mut self, proxy_override: Option<ProxyConfig>) -> Self {
+        self.account.proxy_override = proxy_override;
+        self
+    }
+
+    pub fn notes(mut self, notes: Option<String>) -> Self {
+        self.account.notes = notes;
+        self
+    }
+
+    pub fn build(self) -> Account {
+        self.account
+    }
+}
+
+/// 仅测试中用到的初始字段
+#[cfg(test)]
+impl AccountBuilder {
+    /// 初始状态（不记录状态变更历史）
+    pub fn status(mut self, status: AccountStatus) -> Self {
+        self.account.status = status;
+        self
+    }
+
+    pub fn request_count(mut self, request_count: u64) -> Self {
+        self.account.request_count = request_count;
+        self
+    }
+
+    pub fn error_count(mut self, error_count: u64) -> Self {
+        self.account.error_count = error_count;
+        self
+    }
+
+    pub fn created_at(mut self, created_at: DateTime<Utc>) -> Self {
+        self.account.created_at = created_at;
+        self
+    }
+
+    pub fn cooldown_until(mut self, cooldown_until: Option<DateTime<Utc>>) -> Self {
+        self.account.cooldown_until = cooldown_until;
+        self
+    }
+
+    pub fn tags(mut self, tags: BTreeMap<String, String>) -> Self {
+        self.account.tags = tags;
+        self
+    }
+
+    pub fn weight(mut self, weight: u32) -> Self {
+        self.account.weight = weight;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool::usage::FreeTrialInfo;
+
+    #[test]
+    fn test_latency_window_median() {
+        let mut account = Account::new("a", "A", KiroCredentials::default());
+        assert_eq!(account.median_latency_ms(), None);
+
+        account.record_use(Some(300));
+        account.record_use(None);
+        account.record_first_token_latency(100);
+        assert_eq!(account.median_latency_ms(), Some(200));
+        account.record_first_token_latency(500);
+        assert_eq!(account.median_latency_ms(), Some(300));
+
+        for _ in 0..LATENCY_WINDOW_SIZE {
+            account.record_first_token_latency(50);
+        }
+        assert_eq!(account.latency_window.len(), LATENCY_WINDOW_SIZE);
+        assert_eq!(account.median_latency_ms(), Some(50));
+    }
+
+    #[test]
+    fn test_status_string() {
+        assert_eq!(AccountStatus::Active.to_status_string(), "active");
+        assert_eq!(AccountStatus::Cooldown.to_status_string(), "cooldown");
+        assert_eq!(AccountStatus::Exhausted.to_status_string(), "exhausted");
+        assert_eq!(AccountStatus::Invalid.to_status_string(), "invalid");
+        assert_eq!(AccountStatus::Disabled.to_status_string(), "disabled");
+    }
+
+    fn usage(available: f64) -> UsageLimits {
+        UsageLimits {
+            resource_type: "CREDIT".to_string(),
+            usage_limit: 100.0,
+            current_usage: 100.0 - available,
+            available,
+            next_reset: None,
+            free_trial: None,
+            user_email: None,
+            subscription_type: None,
+            request_limits: None,
+            fetched_at: None,
+        }
+    }
+
+    #[test]
+    fn test_status_history_keeps_last_transitions() {
+        let mut account = Account::new("a", "A", KiroCredentials::default());
+        account.disable();
+        account.disable();
+        assert_eq!(account.history.len(), 1);
+        assert_eq!(account.history[0].from, AccountStatus::Active);
+        assert_eq!(account.history[0].to, AccountStatus::Disabled);
+
+        for _ in 0..10 {
+            account.enable();
+            account.record_error(true, 120);
+            account.mark_invalid();
+        }
+        assert_eq!(account.history.len(), MAX_STATUS_HISTORY);
+        let last = account.history.back().unwrap();
+        assert_eq!(last.to, AccountStatus::Disabled);
+        assert_eq!(last.reason.as_deref(), Some("凭证失效"));
+    }
+
+    #[test]
+    fn test_rate_limit_cooldown_backs_off() {
+        assert_eq!(rate_limit_cooldown_minutes(1, 120), 5);
+        assert_eq!(rate_limit_cooldown_minutes(2, 120), 10);
+        assert_eq!(rate_limit_cooldown_minutes(5, 120), 80);
+        assert_eq!(rate_limit_cooldown_minutes(6, 120), 120);
+        assert_eq!(rate_limit_cooldown_minutes(100, 120), 120);
+
+        let mut account = Account::new("a", "A", KiroCredentials::default());
+        account.record_error(true, 120);
+        account.record_error(true, 120);
+        assert_eq!(account.consecutive_rate_limit_errors, 2);
+        let remaining = account.cooldown_until.unwrap() - Utc::now();
+        assert!(remaining > chrono::Duration::minutes(9));
+
+        // 非限流错误不影响计数，成功后清零
+        account.record_error(false, 120);
+        assert_eq!(account.consecutive_rate_limit_errors, 2);
+        account.record_success();
+        assert_eq!(account.consecutive_rate_limit_errors, 0);
+    }
+
+    #[test]
+    fn test_reset_stats() {
+        let mut account = Account::new("a", "A", KiroCredentials::default());
+        account.record_use(None);
+        account.record_error(true, 120);
+        assert_eq!(account.status, AccountStatus::Cooldown);
+
+        account.reset_stats();
+        assert_eq!(account.request_count, 0);
+        assert_eq!(account.error_count, 0);
+        assert!(account.last_used_at.is_none());
+        assert_eq!(account.status, AccountStatus::Active);
+        assert!(account.cooldown_until.is_none());
+
+        // 非冷却状态保持不变
+        account.disable();
+        account.reset_stats();
+        assert_eq!(account.status, AccountStatus::Disabled);
+    }
+
+    #[test]
+    fn test_is_in_free_trial() {
+        let account = Account::new("a", "A", KiroCredentials::default());
+        let mut cache = HashMap::new();
+        assert!(!account.is_in_free_trial(&cache));
+
+        let mut trial = usage(50.0);
+        trial.free_trial = Some(FreeTrialInfo {
+            status: "ACTIVE".to_string(),
+            usage_limit: 50.0,
+            current_usage: 10.0,
+            expiry: Some(Utc::now() + chrono::Duration::days(3)),
+        });
+        cache.insert("a".to_string(), trial.clone());
+        assert!(account.is_in_free_trial(&cache));
+
+        trial.free_trial.as_mut().unwrap().expiry = Some(Utc::now() - chrono::Duration::days(1));
+        cache.insert("a".to_string(), trial);
+        assert!(!account.is_in_free_trial(&cache));
+    }
+
+    #[test]
+    fn test_credit_score_by_status() {
+        let mut account = Account::new("a", "A", KiroCredentials::default());
+        assert_eq!(account.credit_score(None, &StrategyConfig::default()), 1.0);
+
+        account.record_error(true, 120);
+        assert_eq!(account.status, AccountStatus::Cooldown);
+        assert_eq!(account.credit_score(None, &StrategyConfig::default()), 0.5);
+
+        account.disable();
+        assert_eq!(account.credit_score(None, &StrategyConfig::default()), 0.0);
+    }
+
+    #[test]
+    fn test_credit_score_error_rate_and_quota() {
+        let mut account = AccountBuilder::new("a", "A", KiroCredentials::default())
+            .request_count(10)
+            .error_count(5)
+            .build();
+
+        // 1.0 - 0.1 * 0.5 - 0.2 * (1.0 - 0.25)
+        let score = account.credit_score(Some(&usage(25.0)), &StrategyConfig::default());
+        assert!((score - 0.8).abs() < 1e-9);
+
+        // 错误率封顶为 1.0
+        account.error_count = 50;
+        let score = account.credit_score(Some(&usage(100.0)), &StrategyConfig::default());
+        assert!((score - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_account_builder() {
+        let created_at = Utc::now() - chrono::Duration::days(1);
+        let account = AccountBuilder::new("a", "A", KiroCredentials::default())
+            .status(AccountStatus::Disabled)
+            .request_count(3)
+            .created_at(created_at)
+            .region(Some("us-east-1".to_string()))
+            .pinned(true)
+            .build();
+        assert_eq!(account.id, "a");
+        assert_eq!(account.status, AccountStatus::Disabled);
+        assert!(account.history.is_empty());
+        assert_eq!(account.request_count, 3);
+        assert_eq!(account.created_at, created_at);
+        assert_eq!(account.region.as_deref(), Some("us-east-1"));
+        assert!(account.pinned);
+
+        let default = AccountBuilder::new("b", "B", KiroCredentials::default()).build();
+        assert_eq!(default.status, AccountStatus::Active);
+        assert_eq!(default.request_count, 0);
+        assert!(!default.pinned);
+    }
 }
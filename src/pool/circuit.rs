@@ -0,0 +1,192 @@
+//! 账号熔断器
+//!
+//! 60 秒内失败次数超过阈值时打开熔断，期间不再选择该账号；超时后放行一个探测请求（半开），
+//! 探测成功达到阈值后关闭，失败则重新打开
+
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::model::config::CircuitBreakerConfig;
+
+/// 统计失败次数的滚动窗口（秒）
+const FAILURE_WINDOW_SECS: i64 = 60;
+
+/// 熔断器状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CircuitStatus {
+    /// 正常放行
+    #[default]
+    Closed,
+    /// 熔断中，拒绝所有请求
+    Open,
+    /// 半开，一次只放行一个探测请求
+    HalfOpen,
+}
+
+/// 账号的熔断器状态
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CircuitState {
+    #[serde(default)]
+    pub status: CircuitStatus,
+    /// 滚动窗口内的失败时间（由旧到新）
+    #[serde(default, skip_serializing_if = "VecDeque::is_empty")]
+    pub recent_failures: VecDeque<DateTime<Utc>>,
+    /// 打开或半开时，下一次允许探测的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe_at: Option<DateTime<Utc>>,
+    /// 打开熔断时的半开等待时间（秒），探测请求无结果时按此间隔重新放行
+    #[serde(default)]
+    pub half_open_timeout_secs: u64,
+    /// 半开状态下已成功的探测次数
+    #[serde(default)]
+    pub half_open_successes: u32,
+}
+
+impl CircuitState {
+    /// 是否处于初始状态（持久化时省略）
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 当前是否允许请求：关闭时放行；打开或半开时仅在到达探测时间后放行
+    pub fn allows_request(&self, now: DateTime<Utc>) -> bool {
+        match self.status {
+            CircuitStatus::Closed => true,
+            CircuitStatus::Open | CircuitStatus::HalfOpen => {
+                self.probe_at.is_none_or(|at| now >= at)
+            }
+        }
+    }
+
+    /// 账号被选中：打开或半开时本次请求作为探测，在结果返回前不再放行其他请求
+    pub fn on_selected(&mut self, now: DateTime<Utc>) {
+        if self.status != CircuitStatus::Closed {
+            self.status = CircuitStatus::HalfOpen;
+            self.probe_at = Some(now + Duration::seconds(self.half_open_timeout_secs as i64));
+        }
+    }
+
+    /// 记录一次失败，返回是否因此打开熔断
+    pub fn on_failure(&mut self, now: DateTime<Utc>, config: &CircuitBreakerConfig) -> bool {
+        if config.failure_threshold == 0 {
+            return false;
+        }
+        match self.status {
+            CircuitStatus::HalfOpen => {
+                self.open(now, config);
+                true
+            }
+            CircuitStatus::Open => false,
+            CircuitStatus::Closed => {
+                let cutoff = now - Duration::seconds(FAILURE_WINDOW_SECS);
+                while self.recent_failures.front().is_some_and(|t| *t < cutoff) {
+                    self.recent_failures.pop_front();
+                }
+                self.recent_failures.push_back(now);
+                if self.recent_failures.len() as u32 > config.failure_threshold {
+                    self.open(now, config);
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    /// 记录一次成功，返回是否因此关闭熔断
+    pub fn on_success(&mut self, now: DateTime<Utc>, config: &CircuitBreakerConfig) -> bool {
+        if self.status != CircuitStatus::HalfOpen {
+            return false;
+        }
+        self.half_open_successes += 1;
+        if self.half_open_successes >= config.success_threshold.max(1) {
+            *self = Self::default();
+            true
+        } else {
+            // 立即放行下一个探测请求；被选中后 on_selected 会再次推迟探测时间，保证一次只有一个探测
+            self.probe_at = Some(now);
+            false
+        }
+    }
+
+    fn open(&mut self, now: DateTime<Utc>, config: &CircuitBreakerConfig) {
+        self.status = CircuitStatus::Open;
+        self.recent_failures.clear();
+        self.half_open_successes = 0;
+        self.half_open_timeout_secs = config.half_open_timeout_secs;
+        self.probe_at = Some(now + Duration::seconds(config.half_open_timeout_secs as i64));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            failure_threshold: 2,
+            success_threshold: 2,
+            half_open_timeout_secs: 30,
+        }
+    }
+
+    #[test]
+    fn test_circuit_opens_probes_and_closes() {
+        let config = config();
+        let now = Utc::now();
+        let mut circuit = CircuitState::default();
+
+        assert!(!circuit.on_failure(now, &config));
+        assert!(!circuit.on_failure(now, &config));
+        assert!(circuit.on_failure(now, &config));
+        assert_eq!(circuit.status, CircuitStatus::Open);
+        assert!(!circuit.allows_request(now));
+
+        // 超时后放行一个探测请求，探测期间拒绝其他请求
+        let later = now + Duration::seconds(31);
+        assert!(circuit.allows_request(later));
+        circuit.on_selected(later);
+        assert_eq!(circuit.status, CircuitStatus::HalfOpen);
+        assert!(!circuit.allows_request(later));
+
+        // 探测失败重新打开
+        assert!(circuit.on_failure(later, &config));
+        assert_eq!(circuit.status, CircuitStatus::Open);
+
+        // 连续两次探测成功后关闭
+        let probe = later + Duration::seconds(31);
+        circuit.on_selected(probe);
+        assert!(!circuit.on_success(probe, &config));
+        assert_eq!(circuit.status, CircuitStatus::HalfOpen);
+        assert!(circuit.allows_request(probe));
+        // 第二个探测进行中时仍然只放行这一个请求
+        circuit.on_selected(probe);
+        assert!(!circuit.allows_request(probe));
+        assert!(circuit.on_success(probe, &config));
+        assert_eq!(circuit.status, CircuitStatus::Closed);
+        assert!(circuit.is_default());
+    }
+
+    #[test]
+    fn test_failures_outside_window_are_dropped() {
+        let config = config();
+        let now = Utc::now();
+        let mut circuit = CircuitState::default();
+        circuit.on_failure(now - Duration::seconds(120), &config);
+        circuit.on_failure(now - Duration::seconds(90), &config);
+        assert!(!circuit.on_failure(now, &config));
+        assert_eq!(circuit.recent_failures.len(), 1);
+
+        // 阈值为 0 时不启用熔断
+        let disabled = CircuitBreakerConfig {
+            failure_threshold: 0,
+            ..config
+        };
+        for _ in 0..10 {
+            assert!(!circuit.on_failure(now, &disabled));
+        }
+    }
+}
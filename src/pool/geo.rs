@@ -0,0 +1,82 @@
+//! 地域感知选择策略
+//!
+//! 按账号所在区域与期望区域的接近程度分层，优先选择同区域账号
+
+/// 地域感知策略
+#[derive(Debug, Clone)]
+pub struct GeoAwareStrategy {
+    /// 期望区域（如 us-east-1）
+    preferred_region: String,
+}
+
+impl GeoAwareStrategy {
+    pub fn new(preferred_region: impl Into<String>) -> Self {
+        Self {
+            preferred_region: preferred_region.into(),
+        }
+    }
+
+    /// 计算区域所属地理大区（如 us-east-1 -> us-east）
+    fn area(region: &str) -> &str {
+        match region.rsplit_once('-') {
+            Some((area, suffix)) if suffix.chars().all(|c| c.is_ascii_digit()) => area,
+            _ => region,
+        }
+    }
+
+    /// 计算账号区域的优先级层级
+    ///
+    /// - 0：区域完全一致
+    /// - 1：同一地理大区
+    /// - 2：其他（含未设置区域）
+    pub fn tier(&self, region: Option<&str>) -> u8 {
+        match region {
+            Some(r) if r.eq_ignore_ascii_case(&self.preferred_region) => 0,
+            Some(r) if Self::area(r).eq_ignore_ascii_case(Self::area(&self.preferred_region)) => 1,
+            _ => 2,
+        }
+    }
+
+    /// 从候选账号中筛选出最优层级的账号 id（保持原有顺序）
+    pub fn best_tier(&self, candidates: &[(String, Option<String>)]) -> Vec<String> {
+        let Some(best) = candidates
+            .iter()
+            .map(|(_, region)| self.tier(region.as_deref()))
+            .min()
+        else {
+            return Vec::new();
+        };
+
+        candidates
+            .iter()
+            .filter(|(_, region)| self.tier(region.as_deref()) == best)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GeoAwareStrategy;
+
+    #[test]
+    fn test_geo_tiers() {
+        let geo = GeoAwareStrategy::new("us-east-1");
+        assert_eq!(geo.tier(Some("us-east-1")), 0);
+        assert_eq!(geo.tier(Some("us-east-2")), 1);
+        assert_eq!(geo.tier(Some("eu-west-1")), 2);
+        assert_eq!(geo.tier(None), 2);
+    }
+
+    #[test]
+    fn test_geo_best_tier() {
+        let geo = GeoAwareStrategy::new("us-east-1");
+        let candidates = vec![
+            ("a".to_string(), Some("eu-west-1".to_string())),
+            ("b".to_string(), Some("us-east-2".to_string())),
+            ("c".to_string(), None),
+            ("d".to_string(), Some("us-east-2".to_string())),
+        ];
+        assert_eq!(geo.best_tier(&candidates), vec!["b", "d"]);
+    }
+}
@@ -0,0 +1,58 @@
+//! 账号分组
+//!
+//! 以命名分组管理一批账号，便于按名称而非逐个 id 操作
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+/// 账号分组
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountGroup {
+    pub id: String,
+    pub name: String,
+    pub account_ids: HashSet<String>,
+}
+
+impl AccountGroup {
+    /// 创建空分组，名称为空时使用 id
+    pub fn new(id: impl Into<String>, name: Option<String>) -> Self {
+        let id = id.into();
+        Self {
+            name: name.filter(|n| !n.is_empty()).unwrap_or_else(|| id.clone()),
+            id,
+            account_ids: HashSet::new(),
+        }
+    }
+
+    /// 加入账号，返回新加入的数量
+    pub fn assign<'a>(&mut self, account_ids: impl IntoIterator<Item = &'a String>) -> usize {
+        account_ids
+            .into_iter()
+            .filter(|id| self.account_ids.insert((*id).clone()))
+            .count()
+    }
+
+    /// 移除账号，返回是否存在
+    pub fn unassign(&mut self, account_id: &str) -> bool {
+        self.account_ids.remove(account_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assign_is_idempotent() {
+        let mut group = AccountGroup::new("prod", None);
+        assert_eq!(group.name, "prod");
+
+        let ids = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(group.assign(&ids), 2);
+        assert_eq!(group.assign(&ids), 0);
+        assert!(group.unassign("a"));
+        assert!(!group.unassign("a"));
+        assert_eq!(group.account_ids.len(), 1);
+    }
+}
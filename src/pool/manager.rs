@@ -2,24 +2,70 @@
 
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, Mutex, RwLock};
 
 use crate::http_client::ProxyConfig;
 use crate::kiro::provider::KiroProvider;
 use crate::kiro::token_manager::TokenManager;
-use crate::model::config::Config;
-
-use super::account::{Account, AccountStatus};
-use super::strategy::SelectionStrategy;
-use super::usage::{RequestLog, RequestLogger, RequestStats, UsageLimits};
+use crate::metrics::PoolMetrics;
+use crate::model::config::{Config, StrategySchedule};
+
+use super::account::{Account, AccountStatus, KiroRawCredentials, MAX_NOTES_CHARS};
+use super::circuit::{CircuitState, CircuitStatus};
+use super::geo::GeoAwareStrategy;
+use super::groups::AccountGroup;
+use super::strategy::{
+    capacity_weight, least_quota_pick, lowest_latency_pick, weighted_pick,
+    weighted_round_robin_pick, SelectionStrategy, StrategyConfig, StrategyConfigPatch,
+};
+use super::tags::TagIndex;
+use super::throttle::{throttle_adjustment, RateLimitSettings, RateLimiter};
+use super::usage::{
+    aggregate_usage_timeline, logs_to_json_within, DailyCount, RequestLog, RequestLogger,
+    RequestStats, UsageIntervalBucket, UsageLimits,
+};
+use super::webhook;
 
 /// 账号存储文件名
 const ACCOUNTS_FILE: &str = "accounts.json";
+/// 账号存储结构的当前版本（存储格式发生不兼容变更时递增，并在 `StoredAccount::migrate` 中补充迁移）
+const CURRENT_SCHEMA_VERSION: u32 = 1;
 /// 请求记录存储文件名
 const LOGS_FILE: &str = "request_logs.json";
 /// 配额缓存存储文件名
 const USAGE_CACHE_FILE: &str = "usage_cache.json";
+/// 账号分组存储文件名
+const GROUPS_FILE: &str = "groups.json";
+/// 策略定时切换计划文件名
+const STRATEGY_SCHEDULE_FILE: &str = "strategy_schedule.json";
+/// 当前选择策略存储文件名
+const STRATEGY_FILE: &str = "strategy.json";
+
+/// 自动限速统计错误率的时间窗口（10 分钟）
+const AUTO_THROTTLE_WINDOW_MINUTES: i64 = 10;
+/// 自动限速所需的最少请求样本数
+const AUTO_THROTTLE_MIN_SAMPLES: usize = 10;
+/// 两次自动限速调整的最小间隔（秒）
+const AUTO_THROTTLE_INTERVAL_SECS: u64 = 10;
+
+/// 容量估算使用的最近成功请求样本数
+const CAPACITY_SAMPLE_SIZE: usize = 100;
+
+/// 账号池事件广播缓冲区大小
+const POOL_EVENT_CAPACITY: usize = 256;
+
+/// 账号池事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum PoolEvent {
+    /// 账号状态变更
+    StatusChanged {
+        account_id: String,
+        from: AccountStatus,
+        to: AccountStatus,
+    },
+}
 
 /// 账号池管理器
 pub struct AccountPool {
@@ -29,8 +75,12 @@ pub struct AccountPool {
     token_managers: RwLock<HashMap<String, Arc<tokio::sync::Mutex<TokenManager>>>>,
     /// Provider 缓存（每账号一个，避免每请求创建 Client）
     providers: RwLock<HashMap<String, Arc<KiroProvider>>>,
+    /// refreshToken 哈希 -> 账号 id（用于去重，避免比较原始 token）
+    refresh_token_hashes: RwLock<HashMap<u64, String>>,
     /// 选择策略
     strategy: RwLock<SelectionStrategy>,
+    /// 策略参数
+    strategy_config: RwLock<StrategyConfig>,
     /// 轮询索引
     round_robin_index: RwLock<usize>,
     /// 顺序耗尽策略当前账号
@@ -42,51 +92,125 @@ pub struct AccountPool {
     /// 数据存储目录
     data_dir: Option<PathBuf>,
     /// 请求记录器
-    request_logger: RwLock<RequestLogger>,
+    request_logger: Arc<RwLock<RequestLogger>>,
+    /// 请求记录文件的写入锁（同一时间只有一个写入者）
+    log_writer: Arc<Mutex<()>>,
+    /// 是否已有尚未开始的请求记录保存任务
+    log_save_pending: Arc<AtomicBool>,
     /// 账号配额缓存
     usage_cache: RwLock<HashMap<String, UsageLimits>>,
+    /// 账号池事件广播
+    events: broadcast::Sender<PoolEvent>,
+    /// 全局限速（配置 rps_limit 时启用，配置热加载时更新）
+    rate_limiter: Mutex<RateLimiter>,
+    /// 上次自动限速调整时间
+    last_throttle_at: Mutex<Option<std::time::Instant>>,
+    /// 标签索引（`key=value` -> 账号 id）
+    tag_index: RwLock<TagIndex>,
+    /// 账号分组（分组 id -> 分组）
+    groups: RwLock<HashMap<String, AccountGroup>>,
+    /// 选择策略定时切换计划
+    strategy_schedule: RwLock<StrategySchedule>,
+    /// Prometheus 指标
+    metrics: PoolMetrics,
+}
+
+/// 自动选择账号时的过滤条件
+pub struct SelectionFilter<'a> {
+    /// 要求带有的全部标签（格式见 [`Account::has_tags`]）
+    pub tags: &'a [&'a str],
+    /// 排除的账号 id
+    pub exclude: &'a [String],
+}
+
+impl SelectionFilter<'_> {
+    /// 账号是否可被自动选择且满足过滤条件
+    fn accepts(&self, account: &Account) -> bool {
+        account.is_selectable()
+            && account.has_tags(self.tags)
+            && !self.exclude.contains(&account.id)
+    }
 }
 
 /// 账号池选择结果
 pub struct SelectedAccount {
     pub id: String,
     pub name: String,
+    /// 显示名覆盖（用于请求记录）
+    pub display_name_override: Option<String>,
     pub provider: Arc<KiroProvider>,
+    /// 账号级请求超时（秒）
+    pub request_timeout_secs: Option<u64>,
+}
+
+impl SelectedAccount {
+    /// 请求记录中使用的名称（优先显示名覆盖）
+    pub fn log_name(&self) -> &str {
+        self.display_name_override.as_deref().unwrap_or(&self.name)
+    }
 }
 
 impl AccountPool {
     /// 创建新的账号池
     #[allow(dead_code)]
     pub fn new(config: Config, proxy: Option<ProxyConfig>) -> Self {
+        let rate_limiter = Mutex::new(RateLimiter::new(RateLimitSettings::from_config(&config)));
+        let strategy_schedule = RwLock::new(config.strategy_schedule.clone());
+        let max_request_logs = config.max_request_logs.max(1);
         Self {
             accounts: RwLock::new(HashMap::new()),
             token_managers: RwLock::new(HashMap::new()),
             providers: RwLock::new(HashMap::new()),
+            refresh_token_hashes: RwLock::new(HashMap::new()),
             strategy: RwLock::new(SelectionStrategy::default()),
+            strategy_config: RwLock::new(StrategyConfig::default()),
             round_robin_index: RwLock::new(0),
             sequential_current_id: RwLock::new(None),
             config,
             proxy,
             data_dir: None,
-            request_logger: RwLock::new(RequestLogger::default()),
+            request_logger: Arc::new(RwLock::new(RequestLogger::new(max_request_logs))),
+            log_writer: Arc::new(Mutex::new(())),
+            log_save_pending: Arc::new(AtomicBool::new(false)),
             usage_cache: RwLock::new(HashMap::new()),
+            events: broadcast::channel(POOL_EVENT_CAPACITY).0,
+            rate_limiter,
+            last_throttle_at: Mutex::new(None),
+            tag_index: RwLock::new(TagIndex::default()),
+            groups: RwLock::new(HashMap::new()),
+            strategy_schedule,
+            metrics: PoolMetrics::new(),
         }
     }
 
     /// 创建带持久化存储的账号池
     pub fn with_data_dir(config: Config, proxy: Option<ProxyConfig>, data_dir: PathBuf) -> Self {
+        let rate_limiter = Mutex::new(RateLimiter::new(RateLimitSettings::from_config(&config)));
+        let strategy_schedule = RwLock::new(config.strategy_schedule.clone());
+        let max_request_logs = config.max_request_logs.max(1);
         Self {
             accounts: RwLock::new(HashMap::new()),
             token_managers: RwLock::new(HashMap::new()),
             providers: RwLock::new(HashMap::new()),
+            refresh_token_hashes: RwLock::new(HashMap::new()),
             strategy: RwLock::new(SelectionStrategy::default()),
+            strategy_config: RwLock::new(StrategyConfig::default()),
             round_robin_index: RwLock::new(0),
             sequential_current_id: RwLock::new(None),
             config,
             proxy,
             data_dir: Some(data_dir),
-            request_logger: RwLock::new(RequestLogger::default()),
+            request_logger: Arc::new(RwLock::new(RequestLogger::new(max_request_logs))),
+            log_writer: Arc::new(Mutex::new(())),
+            log_save_pending: Arc::new(AtomicBool::new(false)),
             usage_cache: RwLock::new(HashMap::new()),
+            events: broadcast::channel(POOL_EVENT_CAPACITY).0,
+            rate_limiter,
+            last_throttle_at: Mutex::new(None),
+            tag_index: RwLock::new(TagIndex::default()),
+            groups: RwLock::new(HashMap::new()),
+            strategy_schedule,
+            metrics: PoolMetrics::new(),
         }
     }
 
@@ -105,10 +229,11 @@ impl AccountPool {
         let stored: Vec<StoredAccount> = serde_json::from_str(&content)?;
 
         let mut count = 0;
-        let mut migrated_invalid = 0;
+        let mut migrated = 0;
         for stored_account in stored {
-            if stored_account.status == AccountStatus::Invalid {
-                migrated_invalid += 1;
+            let (stored_account, was_migrated) = stored_account.migrate();
+            if was_migrated {
+                migrated += 1;
             }
             let account = stored_account.into_account();
             if let Err(e) = self.add_account_internal(account).await {
@@ -118,10 +243,11 @@ impl AccountPool {
             }
         }
 
-        if migrated_invalid > 0 {
+        if migrated > 0 {
             tracing::warn!(
-                "检测到 {} 个历史 invalid 账号，已自动迁移为 disabled",
-                migrated_invalid
+                "检测到 {} 个旧版本账号记录，已迁移至 schema v{}",
+                migrated,
+                CURRENT_SCHEMA_VERSION
             );
             // 写回持久化，避免重启后重复迁移
             self.save_to_file().await?;
@@ -131,6 +257,91 @@ impl AccountPool {
         Ok(count)
     }
 
+    /// 比较内存中的账号与 accounts.json 的差异
+    ///
+    /// `apply` 为 false 时仅报告；为 true 时加入仅存在于磁盘的账号并移除磁盘上已不存在的账号，
+    /// 双方都存在但字段不一致的账号只报告、不修改
+    pub async fn reconcile(&self, apply: bool) -> anyhow::Result<ReconcileReport> {
+        let Some(data_dir) = &self.data_dir else {
+            anyhow::bail!("未配置数据目录，无法对账");
+        };
+        let file_path = data_dir.join(ACCOUNTS_FILE);
+        if !file_path.exists() {
+            anyhow::bail!("账号文件不存在: {}", file_path.display());
+        }
+
+        let content = tokio::fs::read_to_string(&file_path).await?;
+        let disk: HashMap<String, StoredAccount> =
+            serde_json::from_str::<Vec<StoredAccount>>(&content)?
+                .into_iter()
+                .map(|stored| {
+                    let (stored, _) = stored.migrate();
+                    (stored.id.clone(), stored)
+                })
+                .collect();
+
+        let (disk_only, memory_only, conflicts) = {
+            let accounts = self.accounts.read().await;
+            let mut disk_only: Vec<&String> = disk
+                .keys()
+                .filter(|id| !accounts.contains_key(*id))
+                .collect();
+            disk_only.sort();
+            let mut memory_only: Vec<String> = accounts
+                .keys()
+                .filter(|id| !disk.contains_key(*id))
+                .cloned()
+                .collect();
+            memory_only.sort();
+
+            let mut conflicts = Vec::new();
+            let mut shared: Vec<&Account> = accounts
+                .values()
+                .filter(|a| disk.contains_key(&a.id))
+                .collect();
+            shared.sort_by(|a, b| a.id.cmp(&b.id));
+            for account in shared {
+                conflicts.extend(stored_account_conflicts(
+                    &disk[&account.id],
+                    &StoredAccount::from_account(account),
+                )?);
+            }
+            (
+                disk_only.into_iter().cloned().collect::<Vec<_>>(),
+                memory_only,
+                conflicts,
+            )
+        };
+
+        let report = ReconcileReport {
+            added_from_disk: disk_only.len(),
+            removed_not_on_disk: memory_only.len(),
+            conflicts,
+        };
+        if !apply {
+            return Ok(report);
+        }
+
+        let mut disk = disk;
+        for id in &disk_only {
+            if let Some(stored) = disk.remove(id) {
+                if let Err(e) = self.add_account_internal(stored.into_account()).await {
+                    tracing::warn!("对账加入账号 {} 失败: {}", id, e);
+                }
+            }
+        }
+        for id in &memory_only {
+            self.remove_account(id).await;
+        }
+        tracing::info!(
+            "账号对账完成：从磁盘加入 {} 个，移除 {} 个，字段冲突 {} 处",
+            report.added_from_disk,
+            report.removed_not_on_disk,
+            report.conflicts.len()
+        );
+        Ok(report)
+    }
+
     /// 保存账号到文件
     pub async fn save_to_file(&self) -> anyhow::Result<()> {
         let Some(data_dir) = &self.data_dir else {
@@ -140,6 +351,7 @@ impl AccountPool {
         // 确保目录存在
         tokio::fs::create_dir_all(data_dir).await?;
 
+        self.sync_promoted_credentials().await;
         let accounts = self.accounts.read().await;
         let stored: Vec<StoredAccount> =
             accounts.values().map(StoredAccount::from_account).collect();
@@ -152,24 +364,87 @@ impl AccountPool {
         Ok(())
     }
 
+    /// 导出全部账号凭证（未脱敏），用于迁移到其他工具
+    ///
+    /// 支持 `kiro-json`（与导入接口相同的 JSON 数组）和 `csv`（`name,refresh_token,auth_method,client_id`）
+    pub async fn export_for_migration(&self, target_format: &str) -> anyhow::Result<String> {
+        self.sync_promoted_credentials().await;
+        let mut accounts: Vec<Account> = self.accounts.read().await.values().cloned().collect();
+        accounts.sort_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let exported: Vec<KiroRawCredentials> = accounts
+            .iter()
+            .filter_map(|account| {
+                let raw = KiroRawCredentials::from_account(account);
+                if raw.is_none() {
+                    tracing::warn!("账号 {} 缺少 refreshToken，跳过导出", account.id);
+                }
+                raw
+            })
+            .collect();
+
+        match target_format {
+            "kiro-json" => Ok(serde_json::to_string_pretty(&exported)?),
+            "csv" => {
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                writer.write_record(["name", "refresh_token", "auth_method", "client_id"])?;
+                for raw in &exported {
+                    writer.write_record([
+                        raw.name.as_deref().unwrap_or_default(),
+                        raw.refresh_token.as_str(),
+                        raw.auth_method.as_deref().unwrap_or_default(),
+                        raw.client_id.as_deref().unwrap_or_default(),
+                    ])?;
+                }
+                Ok(String::from_utf8(writer.into_inner()?)?)
+            }
+            other => anyhow::bail!("不支持的导出格式: {}（可选 kiro-json、csv）", other),
+        }
+    }
+
+    /// 账号实际使用的代理：优先账号专用代理，否则使用账号池的全局代理
+    fn proxy_for(&self, account: &Account) -> Option<ProxyConfig> {
+        account
+            .proxy_override
+            .clone()
+            .or_else(|| self.proxy.clone())
+    }
+
     /// 内部添加账号（不保存文件）
     async fn add_account_internal(&self, account: Account) -> anyhow::Result<()> {
+        tracing::debug!("添加账号: {}", account.to_redacted_json());
         let id = account.id.clone();
         let credentials = account.credentials.clone();
+        let proxy = self.proxy_for(&account);
 
         // 创建 TokenManager
-        let token_manager = TokenManager::new(self.config.clone(), credentials, self.proxy.clone());
+        let mut token_manager = TokenManager::new(self.config.clone(), credentials, proxy.clone());
+        if let Some(pending) = account.pending_credentials.clone() {
+            token_manager.stage_credentials(pending);
+        }
 
         let tm = Arc::new(tokio::sync::Mutex::new(token_manager));
-        let provider = Arc::new(KiroProvider::with_shared_token_manager(
-            tm.clone(),
-            self.proxy.clone(),
-        ));
+        let provider = Arc::new(KiroProvider::with_shared_token_manager(tm.clone(), proxy));
 
         let mut accounts = self.accounts.write().await;
         let mut managers = self.token_managers.write().await;
         let mut providers = self.providers.write().await;
+        let mut token_hashes = self.refresh_token_hashes.write().await;
+        let mut tag_index = self.tag_index.write().await;
 
+        // 先移除旧账号的索引再写入新的，新旧 refreshToken 相同时哈希才不会被误删
+        if let Some(old) = accounts.get(&id) {
+            forget_refresh_token(&mut token_hashes, old);
+            tag_index.remove(&id, &old.tags);
+        }
+        if let Some(token) = account.credentials.refresh_token.as_deref() {
+            token_hashes.insert(refresh_token_hash(token), id.clone());
+        }
+        tag_index.insert(&id, &account.tags);
         accounts.insert(id.clone(), account);
         managers.insert(id.clone(), tm);
         providers.insert(id, provider);
@@ -184,16 +459,18 @@ impl AccountPool {
         Ok(())
     }
 
-    /// 验证凭证是否有效（尝试刷新 token）
+    /// 验证凭证是否有效（尝试刷新 token），通过指定代理发起请求
     ///
     /// 返回 Ok(()) 表示凭证有效，Err 表示凭证无效
+    ///
+    /// 不借用账号池，便于在批量导入的并发任务中调用
     pub async fn validate_credentials(
-        &self,
-        credentials: &crate::kiro::model::credentials::KiroCredentials,
+        config: Config,
+        credentials: crate::kiro::model::credentials::KiroCredentials,
+        proxy: Option<ProxyConfig>,
     ) -> anyhow::Result<()> {
         // 创建临时 TokenManager 进行验证
-        let mut token_manager =
-            TokenManager::new(self.config.clone(), credentials.clone(), self.proxy.clone());
+        let mut token_manager = TokenManager::new(config, credentials, proxy);
 
         // 尝试获取有效 token（会触发刷新）
         token_manager.ensure_valid_token().await?;
@@ -201,17 +478,244 @@ impl AccountPool {
         Ok(())
     }
 
-    /// 添加账号（带验证）
+    /// 从 JSON 字符串导入账号（单个对象或数组）
     ///
-    /// 先验证凭证是否有效，有效才添加
-    pub async fn add_account_with_validation(&self, account: Account) -> anyhow::Result<()> {
-        // 先验证凭证
-        self.validate_credentials(&account.credentials).await?;
+    /// `validate` 为 true 时逐个验证凭证，失败的跳过；refreshToken 已存在的凭证同样跳过。
+    /// 返回成功导入的数量；全部失败（不含重复跳过）时返回第一条错误
+    pub async fn import_from_json_string(
+        &self,
+        json: &str,
+        validate: bool,
+    ) -> anyhow::Result<usize> {
+        let outcome = self.import_json(json, validate, 1).await?;
+        let imported = outcome.imported();
+        if let (0, Some(e)) = (imported, outcome.first_error()) {
+            anyhow::bail!(e.to_string());
+        }
+        Ok(imported)
+    }
 
-        // 验证通过，添加账号
-        self.add_account_internal(account).await?;
-        self.save_to_file().await?;
-        Ok(())
+    /// 从 JSON 字符串导入账号（单个对象或数组），返回逐条结果
+    ///
+    /// 解析后交由 [`AccountPool::import_accounts_bulk`] 处理，同时记录输入是否为单个对象，
+    /// 便于调用方为单个对象保留 `{"id": ...}` 形式的响应
+    pub async fn import_json(
+        &self,
+        json: &str,
+        validate: bool,
+        concurrency: usize,
+    ) -> anyhow::Result<JsonImportOutcome> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| anyhow::anyhow!("JSON 解析失败: {}", e))?;
+        let single = !value.is_array();
+        let raws: Vec<KiroRawCredentials> = if single {
+            serde_json::from_value(value).map(|raw| vec![raw])
+        } else {
+            serde_json::from_value(value)
+        }
+        .map_err(|e| anyhow::anyhow!("JSON 解析失败: {}", e))?;
+
+        let results = self
+            .import_accounts_bulk(raws, validate, concurrency)
+            .await?;
+        Ok(JsonImportOutcome { single, results })
+    }
+
+    /// 批量导入账号
+    ///
+    /// `validate` 为 true 时以 `concurrency` 个并发验证全部凭证；通过验证的账号统一写入并只保存一次文件，
+    /// 写入失败时整体回滚。返回与输入顺序一致的逐条结果
+    pub async fn import_accounts_bulk(
+        &self,
+        raws: Vec<KiroRawCredentials>,
+        validate: bool,
+        concurrency: usize,
+    ) -> anyhow::Result<Vec<BulkImportResult>> {
+        let mut results: Vec<BulkImportResult> = Vec::with_capacity(raws.len());
+        let mut candidates: Vec<Option<Account>> = Vec::with_capacity(raws.len());
+        let mut seen_hashes: HashSet<u64> = HashSet::new();
+        for (index, raw) in raws.into_iter().enumerate() {
+            let hash = refresh_token_hash(&raw.refresh_token);
+            let account = raw.into_account();
+            let mut result = BulkImportResult {
+                index,
+                name: account.name.clone(),
+                success: false,
+                duplicate: false,
+                account_id: None,
+                error: None,
+            };
+            if self.get_account_by_refresh_token_hash(hash).await.is_some()
+                || !seen_hashes.insert(hash)
+            {
+                tracing::debug!("refreshToken 已存在，跳过导入");
+                result.duplicate = true;
+                result.error = Some("refreshToken 已存在".to_string());
+                candidates.push(None);
+            } else if let Err(e) = check_notes_length(account.notes.as_deref()) {
+                result.error = Some(e.to_string());
+                candidates.push(None);
+            } else {
+                candidates.push(Some(account));
+            }
+            results.push(result);
+        }
+
+        if validate {
+            let semaphore = Arc::new(tokio::sync::Semaphore::new(concurrency.max(1)));
+            let mut join_set = tokio::task::JoinSet::new();
+            for (index, account) in candidates.iter().enumerate() {
+                let Some(account) = account else {
+                    continue;
+                };
+                let semaphore = semaphore.clone();
+                let config = self.config.clone();
+                let credentials = account.credentials.clone();
+                let proxy = self.proxy_for(account);
+                join_set.spawn(async move {
+                    let _permit = semaphore.acquire_owned().await.ok();
+                    (
+                        index,
+                        Self::validate_credentials(config, credentials, proxy).await,
+                    )
+                });
+            }
+            while let Some(joined) = join_set.join_next().await {
+                let (index, outcome) = joined?;
+                if let Err(e) = outcome {
+                    tracing::warn!("账号 {} 凭证验证失败，跳过导入: {}", results[index].name, e);
+                    results[index].error = Some(format!("凭证验证失败: {}", e));
+                    candidates[index] = None;
+                }
+            }
+        }
+
+        let mut staged_ids = Vec::new();
+        let mut outcome = Ok(());
+        for (index, account) in candidates.into_iter().enumerate() {
+            let Some(account) = account else {
+                continue;
+            };
+            let id = account.id.clone();
+            outcome = self.add_account_internal(account).await;
+            if outcome.is_err() {
+                break;
+            }
+            staged_ids.push(id.clone());
+            results[index].account_id = Some(id);
+        }
+        if outcome.is_ok() && !staged_ids.is_empty() {
+            outcome = self.save_to_file().await;
+        }
+        if let Err(e) = outcome {
+            self.discard_accounts(&staged_ids).await;
+            anyhow::bail!("批量导入失败，已回滚: {}", e);
+        }
+        for result in results.iter_mut() {
+            result.success = result.account_id.is_some();
+        }
+
+        tracing::info!("批量导入账号完成：{}/{}", staged_ids.len(), results.len());
+        Ok(results)
+    }
+
+    /// 从 accounts.json 内容构建一个独立的账号池（不持久化，沿用当前配置与代理）
+    pub async fn detached_from_json(&self, json: &str) -> anyhow::Result<AccountPool> {
+        let stored: Vec<StoredAccount> = serde_json::from_str(json)
+            .map_err(|e| anyhow::anyhow!("accounts.json 解析失败: {}", e))?;
+        let pool = AccountPool::new(self.config.clone(), self.proxy.clone());
+        for stored_account in stored {
+            pool.add_account_internal(stored_account.migrate().0.into_account())
+                .await?;
+        }
+        Ok(pool)
+    }
+
+    /// 按 refreshToken 哈希查找账号 id
+    pub async fn get_account_by_refresh_token_hash(&self, hash: u64) -> Option<String> {
+        self.refresh_token_hashes.read().await.get(&hash).cloned()
+    }
+
+    /// 合并另一个账号池
+    ///
+    /// 按 refreshToken 哈希（缺失时用 id）去重，已存在的跳过；id 冲突时分配新 id。
+    /// 先暂存全部待添加账号再统一写入，任一步失败则整体回滚。
+    /// 返回 `(added, skipped)`
+    pub async fn merge_pools(&self, other: AccountPool) -> anyhow::Result<(usize, usize)> {
+        let existing_ids: HashSet<String> = self.accounts.read().await.keys().cloned().collect();
+        let mut seen_hashes: HashSet<u64> = HashSet::new();
+
+        let mut incoming: Vec<Account> = other.accounts.into_inner().into_values().collect();
+        incoming.sort_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let mut staged = Vec::new();
+        let mut skipped = 0;
+        for mut account in incoming {
+            let duplicate = match account.credentials.refresh_token.as_deref() {
+                Some(token) => {
+                    let hash = refresh_token_hash(token);
+                    self.get_account_by_refresh_token_hash(hash).await.is_some()
+                        || !seen_hashes.insert(hash)
+                }
+                // 无 refreshToken 时按 id 去重
+                None => {
+                    existing_ids.contains(&account.id)
+                        || !seen_hashes.insert(refresh_token_hash(&account.id))
+                }
+            };
+            if duplicate {
+                skipped += 1;
+                continue;
+            }
+            if existing_ids.contains(&account.id) {
+                account.id = uuid::Uuid::new_v4().to_string();
+            }
+            staged.push(account);
+        }
+
+        let staged_ids: Vec<String> = staged.iter().map(|a| a.id.clone()).collect();
+        let mut result = Ok(());
+        for account in staged {
+            result = self.add_account_internal(account).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        if result.is_ok() && !staged_ids.is_empty() {
+            result = self.save_to_file().await;
+        }
+        if let Err(e) = result {
+            self.discard_accounts(&staged_ids).await;
+            anyhow::bail!("合并账号池失败，已回滚: {}", e);
+        }
+
+        tracing::info!(
+            "合并账号池完成：新增 {}，跳过 {}",
+            staged_ids.len(),
+            skipped
+        );
+        Ok((staged_ids.len(), skipped))
+    }
+
+    /// 从内存中丢弃账号（不保存文件，用于回滚）
+    async fn discard_accounts(&self, ids: &[String]) {
+        let mut accounts = self.accounts.write().await;
+        let mut managers = self.token_managers.write().await;
+        let mut providers = self.providers.write().await;
+        let mut token_hashes = self.refresh_token_hashes.write().await;
+        let mut tag_index = self.tag_index.write().await;
+        for id in ids {
+            if let Some(account) = accounts.remove(id) {
+                forget_refresh_token(&mut token_hashes, &account);
+                tag_index.remove(id, &account.tags);
+            }
+            managers.remove(id);
+            providers.remove(id);
+        }
     }
 
     /// 移除账号
@@ -226,6 +730,10 @@ impl AccountPool {
         providers.remove(id);
         usage_cache.remove(id);
         let removed = accounts.remove(id);
+        if let Some(account) = &removed {
+            forget_refresh_token(&mut *self.refresh_token_hashes.write().await, account);
+            self.tag_index.write().await.remove(id, &account.tags);
+        }
         if sequential_current_id.as_deref() == Some(id) {
             *sequential_current_id = None;
         }
@@ -240,143 +748,691 @@ impl AccountPool {
             tracing::warn!("保存账号文件失败: {}", e);
         }
         self.save_usage_cache().await;
+        self.forget_group_member(id).await;
 
         removed
     }
 
-    /// 获取所有账号（不含凭证）
-    pub async fn list_accounts(&self) -> Vec<Account> {
-        let accounts = self.accounts.read().await;
-        accounts.values().cloned().collect()
+    /// 将账号加入分组（分组不存在时创建），返回更新后的分组
+    ///
+    /// 任一账号不存在时不做修改并返回错误
+    pub async fn assign_to_group(
+        &self,
+        group_id: &str,
+        name: Option<String>,
+        account_ids: &[String],
+    ) -> anyhow::Result<AccountGroup> {
+        if group_id.trim().is_empty() {
+            anyhow::bail!("group_id 不能为空");
+        }
+        {
+            let accounts = self.accounts.read().await;
+            let missing: Vec<&str> = account_ids
+                .iter()
+                .filter(|id| !accounts.contains_key(*id))
+                .map(|id| id.as_str())
+                .collect();
+            if !missing.is_empty() {
+                anyhow::bail!("账号不存在: {}", missing.join(", "));
+            }
+        }
+
+        let group = {
+            let mut groups = self.groups.write().await;
+            let group = groups
+                .entry(group_id.to_string())
+                .or_insert_with(|| AccountGroup::new(group_id, name.clone()));
+            if let Some(name) = name.filter(|n| !n.is_empty()) {
+                group.name = name;
+            }
+            group.assign(account_ids);
+            group.clone()
+        };
+        self.save_groups().await?;
+        Ok(group)
     }
 
-    /// 设置选择策略
-    pub async fn set_strategy(&self, strategy: SelectionStrategy) {
-        *self.strategy.write().await = strategy;
-        *self.sequential_current_id.write().await = None;
+    /// 列出所有分组（按名称排序）
+    pub async fn list_groups(&self) -> Vec<AccountGroup> {
+        let mut groups: Vec<AccountGroup> = self.groups.read().await.values().cloned().collect();
+        groups.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        groups
     }
 
-    /// 获取当前策略
-    pub async fn get_strategy(&self) -> SelectionStrategy {
-        *self.strategy.read().await
+    /// 账号删除后将其移出所有分组
+    async fn forget_group_member(&self, account_id: &str) {
+        self.forget_group_members(&[account_id.to_string()]).await;
     }
 
-    /// 选择一个可用账号并获取其 TokenManager
-    pub async fn select_account(&self) -> Option<SelectedAccount> {
-        let strategy = *self.strategy.read().await;
-        if strategy == SelectionStrategy::SequentialExhaust {
-            return self.select_account_sequential_exhaust().await;
+    /// 批量删除账号后将其移出所有分组（只保存一次分组文件）
+    async fn forget_group_members(&self, account_ids: &[String]) {
+        let mut changed = false;
+        for group in self.groups.write().await.values_mut() {
+            for account_id in account_ids {
+                changed |= group.unassign(account_id);
+            }
+        }
+        if changed {
+            if let Err(e) = self.save_groups().await {
+                tracing::warn!("保存分组文件失败: {}", e);
+            }
         }
+    }
 
-        // 先用读锁快速收集可用账号（避免长时间持有写锁）
-        let available: Vec<(String, u64)> = {
-            let accounts = self.accounts.read().await;
-            accounts
-                .iter()
-                .filter(|(_, a)| a.is_available())
-                .map(|(id, a)| (id.clone(), a.request_count))
-                .collect()
+    /// 保存分组到文件
+    async fn save_groups(&self) -> anyhow::Result<()> {
+        let Some(data_dir) = &self.data_dir else {
+            return Ok(());
+        };
+        let groups = self.groups.read().await;
+        let mut sorted: Vec<&AccountGroup> = groups.values().collect();
+        sorted.sort_by(|a, b| a.id.cmp(&b.id));
+        tokio::fs::create_dir_all(data_dir).await?;
+        tokio::fs::write(
+            data_dir.join(GROUPS_FILE),
+            serde_json::to_string_pretty(&sorted)?,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// 从文件加载分组
+    pub async fn load_groups(&self) -> anyhow::Result<usize> {
+        let Some(data_dir) = &self.data_dir else {
+            return Ok(0);
         };
 
-        if available.is_empty() {
-            return None;
+        let file_path = data_dir.join(GROUPS_FILE);
+        if !file_path.exists() {
+            return Ok(0);
         }
 
-        // 根据策略选出候选 id（不持有 accounts 锁）
-        let candidate_id = match strategy {
-            SelectionStrategy::RoundRobin => {
-                let mut index = self.round_robin_index.write().await;
-                let id = available[*index % available.len()].0.clone();
-                *index = (*index + 1) % available.len();
-                id
-            }
-            SelectionStrategy::Random => {
-                let idx = fastrand::usize(..available.len());
-                available[idx].0.clone()
-            }
-            SelectionStrategy::LeastUsed => available
-                .iter()
-                .min_by_key(|(_, count)| *count)
-                .map(|(id, _)| id.clone())
-                .unwrap_or_else(|| available[0].0.clone()),
-            SelectionStrategy::SequentialExhaust => unreachable!(),
-        };
+        let content = tokio::fs::read_to_string(&file_path).await?;
+        let loaded: Vec<AccountGroup> = serde_json::from_str(&content)?;
+        let count = loaded.len();
+        *self.groups.write().await = loaded.into_iter().map(|g| (g.id.clone(), g)).collect();
 
-        // 用写锁记录使用，并最终确认选中的账号
-        let (selected_id, selected_name) = {
-            let mut accounts = self.accounts.write().await;
+        tracing::info!("从文件加载了 {} 个账号分组", count);
+        Ok(count)
+    }
 
-            if let Some(account) = accounts.get_mut(&candidate_id) {
-                if account.is_available() {
-                    account.record_use();
-                    (candidate_id.clone(), account.name.clone())
-                } else {
-                    // 候选账号在并发下变为不可用，退化为找一个可用账号
-                    let mut picked: Option<(String, String)> = None;
-                    for (id, a) in accounts.iter_mut() {
-                        if a.is_available() {
-                            a.record_use();
-                            picked = Some((id.clone(), a.name.clone()));
-                            break;
-                        }
-                    }
-                    picked?
-                }
-            } else {
-                // 候选账号已被删除，退化为找一个可用账号
-                let mut picked: Option<(String, String)> = None;
-                for (id, a) in accounts.iter_mut() {
-                    if a.is_available() {
-                        a.record_use();
-                        picked = Some((id.clone(), a.name.clone()));
-                        break;
-                    }
-                }
-                picked?
-            }
-        };
+    /// 获取策略定时切换计划
+    pub async fn get_strategy_schedule(&self) -> StrategySchedule {
+        self.strategy_schedule.read().await.clone()
+    }
 
-        let provider = {
-            let providers = self.providers.read().await;
-            providers.get(&selected_id).cloned()?
-        };
+    /// 替换策略定时切换计划（校验全部规则后持久化）
+    pub async fn set_strategy_schedule(&self, schedule: StrategySchedule) -> anyhow::Result<()> {
+        super::schedule::validate_schedule(&schedule)?;
+        *self.strategy_schedule.write().await = schedule;
+        self.save_strategy_schedule().await
+    }
 
-        Some(SelectedAccount {
-            id: selected_id,
-            name: selected_name,
-            provider,
-        })
+    /// 保存策略定时切换计划到文件
+    async fn save_strategy_schedule(&self) -> anyhow::Result<()> {
+        let Some(data_dir) = &self.data_dir else {
+            return Ok(());
+        };
+        let schedule = self.strategy_schedule.read().await;
+        tokio::fs::create_dir_all(data_dir).await?;
+        tokio::fs::write(
+            data_dir.join(STRATEGY_SCHEDULE_FILE),
+            serde_json::to_string_pretty(&*schedule)?,
+        )
+        .await?;
+        Ok(())
     }
 
-    /// 顺序耗尽策略选账号：当前可用则持续使用，不可用才切下一个
-    async fn select_account_sequential_exhaust(&self) -> Option<SelectedAccount> {
-        let current_id = self.sequential_current_id.read().await.clone();
+    /// 从文件加载策略定时切换计划（存在时覆盖配置文件中的计划），返回规则数
+    pub async fn load_strategy_schedule(&self) -> anyhow::Result<usize> {
+        let Some(data_dir) = &self.data_dir else {
+            return Ok(0);
+        };
 
-        // 快照：稳定顺序 + 是否可选（包含 cached quota 可用性）
-        let (ordered_ids, selectable_map, cached_exhausted_ids) = {
-            let accounts = self.accounts.read().await;
-            let usage_cache = self.usage_cache.read().await;
+        let file_path = data_dir.join(STRATEGY_SCHEDULE_FILE);
+        if !file_path.exists() {
+            return Ok(0);
+        }
 
-            let mut ordered_accounts: Vec<&Account> = accounts.values().collect();
-            ordered_accounts.sort_by(|a, b| {
-                a.created_at
-                    .cmp(&b.created_at)
-                    .then_with(|| a.id.cmp(&b.id))
-            });
+        let content = tokio::fs::read_to_string(&file_path).await?;
+        let loaded: StrategySchedule = serde_json::from_str(&content)?;
+        super::schedule::validate_schedule(&loaded)?;
+        let count = loaded.rules.len();
+        *self.strategy_schedule.write().await = loaded;
 
-            let cached_exhausted_ids: HashSet<String> = usage_cache
-                .iter()
-                .filter(|(_, usage)| usage.available <= 0.0)
-                .map(|(id, _)| id.clone())
-                .collect();
+        tracing::info!("从文件加载了 {} 条策略定时切换规则", count);
+        Ok(count)
+    }
 
-            let ordered_ids: Vec<String> = ordered_accounts.iter().map(|a| a.id.clone()).collect();
+    /// 保存当前选择策略（以及顺序耗尽策略的当前账号）到文件
+    async fn save_strategy(&self) -> anyhow::Result<()> {
+        let Some(data_dir) = &self.data_dir else {
+            return Ok(());
+        };
+        let stored = StoredStrategy {
+            strategy: *self.strategy.read().await,
+            sequential_current_id: self.sequential_current_id.read().await.clone(),
+        };
+        tokio::fs::create_dir_all(data_dir).await?;
+        tokio::fs::write(
+            data_dir.join(STRATEGY_FILE),
+            serde_json::to_string_pretty(&stored)?,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// 保存选择策略，失败时仅记录日志
+    async fn persist_strategy(&self) {
+        if let Err(e) = self.save_strategy().await {
+            tracing::warn!("保存选择策略失败: {}", e);
+        }
+    }
+
+    /// 从文件恢复选择策略，文件不存在时返回 `None`
+    pub async fn load_strategy(&self) -> anyhow::Result<Option<SelectionStrategy>> {
+        let Some(data_dir) = &self.data_dir else {
+            return Ok(None);
+        };
+
+        let file_path = data_dir.join(STRATEGY_FILE);
+        if !file_path.exists() {
+            return Ok(None);
+        }
+
+        let content = tokio::fs::read_to_string(&file_path).await?;
+        let loaded: StoredStrategy = serde_json::from_str(&content)?;
+        *self.strategy.write().await = loaded.strategy;
+        *self.sequential_current_id.write().await = loaded
+            .sequential_current_id
+            .filter(|_| loaded.strategy == SelectionStrategy::SequentialExhaust);
+
+        tracing::info!("从文件恢复选择策略: {}", loaded.strategy.as_str());
+        Ok(Some(loaded.strategy))
+    }
+
+    /// 清理长期配额耗尽的账号
+    ///
+    /// 移除状态为 Exhausted 且 `exhausted_until` 为空或早于 `older_than_days` 天前的账号，
+    /// 返回移除数量
+    pub async fn prune_exhausted_accounts(&self, older_than_days: u32) -> usize {
+        let threshold = chrono::Utc::now() - chrono::Duration::days(older_than_days as i64);
+
+        let mut accounts = self.accounts.write().await;
+        let mut managers = self.token_managers.write().await;
+        let mut providers = self.providers.write().await;
+        let mut sequential_current_id = self.sequential_current_id.write().await;
+        let mut usage_cache = self.usage_cache.write().await;
+
+        let pruned_ids: Vec<String> = accounts
+            .values()
+            .filter(|a| {
+                a.status == AccountStatus::Exhausted
+                    && a.exhausted_until.map(|t| t < threshold).unwrap_or(true)
+            })
+            .map(|a| a.id.clone())
+            .collect();
+
+        let mut token_hashes = self.refresh_token_hashes.write().await;
+        let mut tag_index = self.tag_index.write().await;
+        for id in &pruned_ids {
+            if let Some(account) = accounts.remove(id) {
+                forget_refresh_token(&mut token_hashes, &account);
+                tag_index.remove(id, &account.tags);
+            }
+            managers.remove(id);
+            providers.remove(id);
+            usage_cache.remove(id);
+            if sequential_current_id.as_deref() == Some(id.as_str()) {
+                *sequential_current_id = None;
+            }
+        }
+
+        drop(accounts);
+        drop(managers);
+        drop(providers);
+        drop(sequential_current_id);
+        drop(usage_cache);
+        drop(token_hashes);
+        drop(tag_index);
+
+        if !pruned_ids.is_empty() {
+            tracing::info!(
+                "已清理 {} 个长期配额耗尽账号: {:?}",
+                pruned_ids.len(),
+                pruned_ids
+            );
+            if let Err(e) = self.save_to_file().await {
+                tracing::warn!("保存账号文件失败: {}", e);
+            }
+            self.save_usage_cache().await;
+            self.forget_group_members(&pruned_ids).await;
+        }
+
+        pruned_ids.len()
+    }
+
+    /// 移除全部账号并清空配额缓存与请求记录，返回移除数量
+    ///
+    /// 分组保留，但成员会被清空
+    pub async fn remove_all_accounts(&self) -> usize {
+        let mut accounts = self.accounts.write().await;
+        let mut managers = self.token_managers.write().await;
+        let mut providers = self.providers.write().await;
+        let mut round_robin_index = self.round_robin_index.write().await;
+        let mut sequential_current_id = self.sequential_current_id.write().await;
+        let mut usage_cache = self.usage_cache.write().await;
+        let mut token_hashes = self.refresh_token_hashes.write().await;
+
+        let removed = accounts.len();
+        accounts.clear();
+        managers.clear();
+        providers.clear();
+        usage_cache.clear();
+        token_hashes.clear();
+        self.tag_index.write().await.clear();
+        *round_robin_index = 0;
+        *sequential_current_id = None;
+
+        drop(accounts);
+        drop(managers);
+        drop(providers);
+        drop(round_robin_index);
+        drop(sequential_current_id);
+        drop(usage_cache);
+        drop(token_hashes);
+
+        if let Err(e) = self.save_to_file().await {
+            tracing::warn!("保存账号文件失败: {}", e);
+        }
+        self.save_usage_cache().await;
+
+        {
+            let _writer = self.log_writer.lock().await;
+            let mut logger = self.request_logger.write().await;
+            logger.clear();
+            if let Some(data_dir) = &self.data_dir {
+                if let Err(e) = tokio::fs::write(data_dir.join(LOGS_FILE), "[]").await {
+                    tracing::warn!("清空请求记录文件失败: {}", e);
+                }
+            }
+        }
+
+        let had_members = {
+            let mut groups = self.groups.write().await;
+            let had_members = groups.values().any(|g| !g.account_ids.is_empty());
+            groups.values_mut().for_each(|g| g.account_ids.clear());
+            had_members
+        };
+        if had_members {
+            if let Err(e) = self.save_groups().await {
+                tracing::warn!("保存分组文件失败: {}", e);
+            }
+        }
+
+        tracing::warn!("已移除全部 {} 个账号", removed);
+        removed
+    }
+
+    /// 获取所有账号（不含凭证）
+    pub async fn list_accounts(&self) -> Vec<Account> {
+        let accounts = self.accounts.read().await;
+        accounts.values().cloned().collect()
+    }
+
+    /// 设置选择策略
+    pub async fn set_strategy(&self, strategy: SelectionStrategy) {
+        *self.strategy.write().await = strategy;
+        *self.sequential_current_id.write().await = None;
+        self.persist_strategy().await;
+    }
+
+    /// 获取当前策略
+    pub async fn get_strategy(&self) -> SelectionStrategy {
+        *self.strategy.read().await
+    }
+
+    /// 获取当前策略参数
+    pub async fn get_strategy_config(&self) -> StrategyConfig {
+        self.strategy_config.read().await.clone()
+    }
+
+    /// 同时设置选择策略与（可选的）部分策略参数
+    ///
+    /// 参数校验失败时策略与参数均保持不变，返回新的生效策略与参数
+    pub async fn set_strategy_with_config(
+        &self,
+        strategy: SelectionStrategy,
+        patch: Option<StrategyConfigPatch>,
+    ) -> anyhow::Result<(SelectionStrategy, StrategyConfig)> {
+        let mut current_strategy = self.strategy.write().await;
+        let mut current_config = self.strategy_config.write().await;
+
+        if let Some(patch) = patch {
+            *current_config = current_config.apply(patch)?;
+        }
+        *current_strategy = strategy;
+        *self.sequential_current_id.write().await = None;
+        let config = current_config.clone();
+        drop(current_config);
+        drop(current_strategy);
+        self.persist_strategy().await;
+
+        Ok((strategy, config))
+    }
+
+    /// 模拟指定策略下 n 次选择的分配结果（不修改真实账号的计数与使用时间）
+    ///
+    /// 在账号、配额缓存与策略参数的副本上运行全新的选择状态，返回 account_id -> 分配次数
+    pub async fn simulate_selection(
+        &self,
+        strategy: SelectionStrategy,
+        n: usize,
+    ) -> HashMap<String, usize> {
+        let simulated = AccountPool::new(self.config.clone(), self.proxy.clone());
+        let accounts = self.list_accounts().await;
+        for account in accounts {
+            let _ = simulated.add_account_internal(account).await;
+        }
+        *simulated.usage_cache.write().await = self.get_all_usage().await;
+        *simulated.strategy_config.write().await = self.get_strategy_config().await;
+        *simulated.strategy.write().await = strategy;
+
+        let mut counts = HashMap::new();
+        for _ in 0..n {
+            let Some(selected) = simulated.select_account().await else {
+                break;
+            };
+            *counts.entry(selected.id).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// 获取账号的 Provider（不经过账号选择，不影响账号统计）
+    pub async fn get_provider(&self, id: &str) -> Option<Arc<KiroProvider>> {
+        self.providers.read().await.get(id).cloned()
+    }
+
+    /// 随机取一个可用账号 id（只读：不记录使用、不推进轮询计数，也不占用熔断器的探测名额）
+    ///
+    /// 用于 count_tokens 等不计入账号统计的辅助请求；熔断未关闭的账号不参与
+    pub async fn peek_available_account(&self) -> Option<String> {
+        let accounts = self.accounts.read().await;
+        let available: Vec<&String> = accounts
+            .values()
+            .filter(|a| a.is_selectable() && a.circuit_state.status == CircuitStatus::Closed)
+            .map(|a| &a.id)
+            .collect();
+        if available.is_empty() {
+            return None;
+        }
+        Some(available[fastrand::usize(..available.len())].clone())
+    }
+
+    /// 组装选择结果
+    async fn build_selected(&self, id: String, name: String) -> Option<SelectedAccount> {
+        let (request_timeout_secs, display_name_override) = self
+            .accounts
+            .read()
+            .await
+            .get(&id)
+            .map(|a| (a.request_timeout_secs, a.display_name_override.clone()))
+            .unwrap_or_default();
+        let provider = self.providers.read().await.get(&id).cloned()?;
+        Some(SelectedAccount {
+            id,
+            name,
+            display_name_override,
+            provider,
+            request_timeout_secs,
+        })
+    }
+
+    /// 订阅账号池事件
+    pub fn subscribe(&self) -> broadcast::Receiver<PoolEvent> {
+        self.events.subscribe()
+    }
+
+    /// 状态发生变化时广播事件（无订阅者时忽略）
+    fn emit_status_change(&self, id: &str, from: AccountStatus, to: AccountStatus) {
+        if from != to {
+            let _ = self.events.send(PoolEvent::StatusChanged {
+                account_id: id.to_string(),
+                from,
+                to,
+            });
+        }
+    }
+
+    /// 选择一个可用账号并获取其 TokenManager
+    pub async fn select_account(&self) -> Option<SelectedAccount> {
+        self.select_account_for_region(None, &[]).await
+    }
+
+    /// 选择一个可用账号，可指定期望区域（仅 GeoAware 策略使用，缺省取配置中的 region）；
+    /// `tags` 非空时只在带有全部标签的账号中选择（格式见 [`Account::has_tags`]）
+    pub async fn select_account_for_region(
+        &self,
+        preferred_region: Option<&str>,
+        tags: &[&str],
+    ) -> Option<SelectedAccount> {
+        self.select_account_filtered(preferred_region, &SelectionFilter { tags, exclude: &[] })
+            .await
+    }
+
+    /// 按过滤条件选择账号（换号重试时排除已尝试过的账号）
+    pub async fn select_account_filtered(
+        &self,
+        preferred_region: Option<&str>,
+        filter: &SelectionFilter<'_>,
+    ) -> Option<SelectedAccount> {
+        let strategy = *self.strategy.read().await;
+        if strategy == SelectionStrategy::SequentialExhaust {
+            return self.select_account_sequential_exhaust(filter).await;
+        }
+
+        // 先用读锁快速收集可用账号（避免长时间持有写锁）
+        let available: Vec<(String, u64)> = {
+            let accounts = self.accounts.read().await;
+            accounts
+                .iter()
+                .filter(|(_, a)| filter.accepts(a))
+                .map(|(id, a)| (id.clone(), a.request_count))
+                .collect()
+        };
+
+        if available.is_empty() {
+            return None;
+        }
+
+        // 根据策略选出候选 id（不持有 accounts 锁）
+        let candidate_id = match strategy {
+            SelectionStrategy::HighestCreditScore => {
+                let accounts = self.accounts.read().await;
+                let strategy_config = self.strategy_config.read().await;
+                let usage_cache = self.usage_cache.read().await;
+                available
+                    .iter()
+                    .filter_map(|(id, _)| {
+                        accounts
+                            .get(id)
+                            .map(|a| (id, a.credit_score(usage_cache.get(id), &strategy_config)))
+                    })
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(id, _)| id.clone())
+                    .unwrap_or_else(|| available[0].0.clone())
+            }
+            SelectionStrategy::RoundRobin => {
+                let mut index = self.round_robin_index.write().await;
+                let id = available[*index % available.len()].0.clone();
+                *index = (*index + 1) % available.len();
+                id
+            }
+            SelectionStrategy::GeoAware => {
+                let default_region = self.strategy_config.read().await.preferred_region.clone();
+                let geo = GeoAwareStrategy::new(
+                    preferred_region
+                        .or(default_region.as_deref())
+                        .unwrap_or(self.config.region.as_str()),
+                );
+                let candidates: Vec<(String, Option<String>)> = {
+                    let accounts = self.accounts.read().await;
+                    available
+                        .iter()
+                        .map(|(id, _)| {
+                            let region = accounts.get(id).and_then(|a| a.region.clone());
+                            (id.clone(), region)
+                        })
+                        .collect()
+                };
+                let tier = geo.best_tier(&candidates);
+                let mut index = self.round_robin_index.write().await;
+                let id = tier[*index % tier.len()].clone();
+                *index = (*index + 1) % tier.len();
+                id
+            }
+            SelectionStrategy::Random => {
+                let idx = fastrand::usize(..available.len());
+                available[idx].0.clone()
+            }
+            SelectionStrategy::LeastUsed => available
+                .iter()
+                .min_by_key(|(_, count)| *count)
+                .map(|(id, _)| id.clone())
+                .unwrap_or_else(|| available[0].0.clone()),
+            SelectionStrategy::CapacityAware => {
+                let weighted: Vec<(String, f64)> = {
+                    let usage_cache = self.usage_cache.read().await;
+                    available
+                        .iter()
+                        .map(|(id, _)| (id.clone(), capacity_weight(usage_cache.get(id))))
+                        .collect()
+                };
+                // 剩余配额均为 0 时不选择任何账号
+                weighted_pick(&weighted, fastrand::f64())?.to_string()
+            }
+            SelectionStrategy::LeastQuota => {
+                let quotas: Vec<(String, Option<f64>)> = {
+                    let usage_cache = self.usage_cache.read().await;
+                    available
+                        .iter()
+                        .map(|(id, _)| (id.clone(), usage_cache.get(id).map(|u| u.available)))
+                        .collect()
+                };
+                least_quota_pick(&quotas)?.to_string()
+            }
+            SelectionStrategy::WeightedRoundRobin => {
+                // 按 id 排序保证展开序列稳定，round_robin_index 作为加权计数器
+                let mut weighted: Vec<(String, u32)> = {
+                    let accounts = self.accounts.read().await;
+                    available
+                        .iter()
+                        .map(|(id, _)| (id.clone(), accounts.get(id).map_or(0, |a| a.weight)))
+                        .collect()
+                };
+                weighted.sort_by(|(a, _), (b, _)| a.cmp(b));
+                let total: usize = weighted.iter().map(|(_, w)| *w as usize).sum();
+                let mut index = self.round_robin_index.write().await;
+                let id = weighted_round_robin_pick(&weighted, *index)?.to_string();
+                *index = (*index + 1) % total;
+                id
+            }
+            SelectionStrategy::LowestLatency => {
+                let medians: Vec<(String, Option<u64>)> = {
+                    let accounts = self.accounts.read().await;
+                    available
+                        .iter()
+                        .map(|(id, _)| {
+                            (
+                                id.clone(),
+                                accounts.get(id).and_then(|a| a.median_latency_ms()),
+                            )
+                        })
+                        .collect()
+                };
+                match lowest_latency_pick(&medians) {
+                    Some(id) => id.to_string(),
+                    // 尚无延迟数据时退化为轮询
+                    None => {
+                        let mut index = self.round_robin_index.write().await;
+                        let id = available[*index % available.len()].0.clone();
+                        *index = (*index + 1) % available.len();
+                        id
+                    }
+                }
+            }
+            SelectionStrategy::SequentialExhaust => unreachable!(),
+        };
+
+        // 用写锁记录使用，并最终确认选中的账号
+        let (selected_id, selected_name) = {
+            let mut accounts = self.accounts.write().await;
+
+            if let Some(account) = accounts.get_mut(&candidate_id) {
+                if filter.accepts(account) {
+                    account.record_use(None);
+                    (candidate_id.clone(), account.name.clone())
+                } else {
+                    // 候选账号在并发下变为不可用，退化为找一个可用账号
+                    let mut picked: Option<(String, String)> = None;
+                    for (id, a) in accounts.iter_mut() {
+                        if filter.accepts(a) {
+                            a.record_use(None);
+                            picked = Some((id.clone(), a.name.clone()));
+                            break;
+                        }
+                    }
+                    picked?
+                }
+            } else {
+                // 候选账号已被删除，退化为找一个可用账号
+                let mut picked: Option<(String, String)> = None;
+                for (id, a) in accounts.iter_mut() {
+                    if filter.accepts(a) {
+                        a.record_use(None);
+                        picked = Some((id.clone(), a.name.clone()));
+                        break;
+                    }
+                }
+                picked?
+            }
+        };
+
+        self.build_selected(selected_id, selected_name).await
+    }
+
+    /// 顺序耗尽策略选账号：当前可用则持续使用，不可用才切下一个
+    async fn select_account_sequential_exhaust(
+        &self,
+        filter: &SelectionFilter<'_>,
+    ) -> Option<SelectedAccount> {
+        let current_id = self.sequential_current_id.read().await.clone();
+
+        // 快照：稳定顺序 + 是否可选（包含 cached quota 可用性）
+        let (ordered_ids, selectable_map, cached_exhausted_ids) = {
+            let accounts = self.accounts.read().await;
+            let usage_cache = self.usage_cache.read().await;
+
+            let mut ordered_accounts: Vec<&Account> = accounts.values().collect();
+            ordered_accounts.sort_by(|a, b| {
+                a.created_at
+                    .cmp(&b.created_at)
+                    .then_with(|| a.id.cmp(&b.id))
+            });
+
+            let cached_exhausted_ids: HashSet<String> = usage_cache
+                .iter()
+                .filter(|(_, usage)| usage.is_exhausted())
+                .map(|(id, _)| id.clone())
+                .collect();
+
+            let ordered_ids: Vec<String> = ordered_accounts.iter().map(|a| a.id.clone()).collect();
             let selectable_map: HashMap<String, bool> = ordered_accounts
                 .iter()
                 .map(|a| {
                     (
                         a.id.clone(),
-                        a.is_available() && !cached_exhausted_ids.contains(&a.id),
+                        filter.accepts(a) && !cached_exhausted_ids.contains(&a.id),
                     )
                 })
                 .collect();
@@ -412,8 +1468,8 @@ impl AccountPool {
                     continue;
                 }
                 if let Some(account) = accounts.get_mut(&id) {
-                    if account.is_available() {
-                        account.record_use();
+                    if filter.accepts(account) {
+                        account.record_use(None);
                         picked = Some((id, account.name.clone()));
                         break;
                     }
@@ -425,28 +1481,28 @@ impl AccountPool {
 
         let Some((selected_id, selected_name)) = selected else {
             *self.sequential_current_id.write().await = None;
+            if current_id.is_some() {
+                self.persist_strategy().await;
+            }
             return None;
         };
 
         *self.sequential_current_id.write().await = Some(selected_id.clone());
+        // 仅在切换账号时落盘，持续使用同一账号不产生写入
+        if current_id.as_deref() != Some(selected_id.as_str()) {
+            self.persist_strategy().await;
+        }
 
-        let provider = {
-            let providers = self.providers.read().await;
-            providers.get(&selected_id).cloned()?
-        };
-
-        Some(SelectedAccount {
-            id: selected_id,
-            name: selected_name,
-            provider,
-        })
+        self.build_selected(selected_id, selected_name).await
     }
 
     /// 启用账号
     pub async fn enable_account(&self, id: &str) -> bool {
         let mut accounts = self.accounts.write().await;
         if let Some(account) = accounts.get_mut(id) {
+            let from = account.status;
             account.enable();
+            self.emit_status_change(id, from, account.status);
             drop(accounts);
             let _ = self.save_to_file().await;
             true
@@ -455,11 +1511,49 @@ impl AccountPool {
         }
     }
 
+    /// 重置账号的请求 / 错误计数与最近使用时间，返回更新后的账号（不存在时返回 None）
+    pub async fn reset_account_stats(&self, id: &str) -> anyhow::Result<Option<Account>> {
+        let mut accounts = self.accounts.write().await;
+        let Some(account) = accounts.get_mut(id) else {
+            return Ok(None);
+        };
+        let from = account.status;
+        account.reset_stats();
+        self.emit_status_change(id, from, account.status);
+        let updated = account.clone();
+        drop(accounts);
+        self.save_to_file().await?;
+        Ok(Some(updated))
+    }
+
+    /// 固定账号：不再参与自动选择，仅供手动指定使用
+    pub async fn pin_account(&self, id: &str) -> bool {
+        self.set_pinned(id, true).await
+    }
+
+    /// 取消固定账号，恢复参与自动选择
+    pub async fn unpin_account(&self, id: &str) -> bool {
+        self.set_pinned(id, false).await
+    }
+
+    async fn set_pinned(&self, id: &str, pinned: bool) -> bool {
+        let mut accounts = self.accounts.write().await;
+        let Some(account) = accounts.get_mut(id) else {
+            return false;
+        };
+        account.pinned = pinned;
+        drop(accounts);
+        let _ = self.save_to_file().await;
+        true
+    }
+
     /// 禁用账号
     pub async fn disable_account(&self, id: &str) -> bool {
         let mut accounts = self.accounts.write().await;
         if let Some(account) = accounts.get_mut(id) {
+            let from = account.status;
             account.disable();
+            self.emit_status_change(id, from, account.status);
             drop(accounts);
             let _ = self.save_to_file().await;
             true
@@ -468,11 +1562,31 @@ impl AccountPool {
         }
     }
 
+    /// 记录账号一次请求的首 token 延迟（供 LowestLatency 策略使用）
+    pub async fn record_first_token_latency(&self, id: &str, ms: u64) {
+        if let Some(account) = self.accounts.write().await.get_mut(id) {
+            account.record_first_token_latency(ms);
+        }
+    }
+
     /// 记录账号错误
     pub async fn record_error(&self, id: &str, is_rate_limit: bool) {
         let mut accounts = self.accounts.write().await;
         if let Some(account) = accounts.get_mut(id) {
-            account.record_error(is_rate_limit);
+            let from = account.status;
+            account.record_error(is_rate_limit, self.config.max_cooldown_minutes);
+            self.metrics.observe_error(id, is_rate_limit);
+            if account
+                .circuit_state
+                .on_failure(chrono::Utc::now(), &self.config.circuit_breaker)
+            {
+                tracing::warn!(
+                    "账号 {} 熔断已打开，{} 秒后放行探测请求",
+                    id,
+                    self.config.circuit_breaker.half_open_timeout_secs
+                );
+            }
+            self.emit_status_change(id, from, account.status);
             tracing::info!(
                 "账号 {} 记录错误，限流: {}，当前错误数: {}，状态: {:?}",
                 id,
@@ -485,11 +1599,33 @@ impl AccountPool {
         }
     }
 
+    /// 记录请求成功：清零连续限流次数；半开状态下的探测成功达到阈值时关闭熔断
+    async fn record_success(&self, id: &str) {
+        let mut accounts = self.accounts.write().await;
+        let Some(account) = accounts.get_mut(id) else {
+            return;
+        };
+        account.record_success();
+        if account.circuit_state.status == CircuitStatus::Closed {
+            return;
+        }
+        if account
+            .circuit_state
+            .on_success(chrono::Utc::now(), &self.config.circuit_breaker)
+        {
+            tracing::info!("账号 {} 探测请求成功，熔断已关闭", id);
+            drop(accounts);
+            let _ = self.save_to_file().await;
+        }
+    }
+
     /// 标记账号为失效（自动禁用）
     pub async fn mark_invalid(&self, id: &str) {
         let mut accounts = self.accounts.write().await;
         if let Some(account) = accounts.get_mut(id) {
+            let from = account.status;
             account.mark_invalid();
+            self.emit_status_change(id, from, account.status);
             tracing::warn!("账号 {} 已检测为失效，已自动禁用", id);
             drop(accounts);
             let _ = self.save_to_file().await;
@@ -504,13 +1640,304 @@ impl AccountPool {
     ) {
         let mut accounts = self.accounts.write().await;
         if let Some(account) = accounts.get_mut(id) {
+            let from = account.status;
             account.mark_exhausted(next_reset);
+            self.emit_status_change(id, from, account.status);
             tracing::warn!("账号 {} 已标记为配额耗尽", id);
             drop(accounts);
             let _ = self.save_to_file().await;
         }
     }
 
+    /// 手动覆盖配额耗尽恢复时间
+    ///
+    /// `until` 为 None 时清除恢复时间，并将耗尽状态的账号恢复为可用；账号不存在时返回 `Ok(false)`，保存失败时返回错误
+    pub async fn set_exhausted_until(
+        &self,
+        id: &str,
+        until: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> anyhow::Result<bool> {
+        let mut accounts = self.accounts.write().await;
+        let Some(account) = accounts.get_mut(id) else {
+            return Ok(false);
+        };
+
+        account.exhausted_until = until;
+        if until.is_none() && account.status == AccountStatus::Exhausted {
+            account.set_status(AccountStatus::Active, "手动清除耗尽时间");
+            self.emit_status_change(id, AccountStatus::Exhausted, AccountStatus::Active);
+        }
+        tracing::info!("账号 {} 的配额耗尽恢复时间已设置为 {:?}", id, until);
+        drop(accounts);
+        self.save_to_file().await?;
+        Ok(true)
+    }
+
+    /// 获取单个账号
+    pub async fn get_account(&self, id: &str) -> Option<Account> {
+        self.accounts.read().await.get(id).cloned()
+    }
+
+    /// 设置账号备注（`None` 或空字符串表示清除）
+    ///
+    /// 超过 `MAX_NOTES_CHARS` 字符时返回错误，账号不存在时返回 `Ok(false)`
+    pub async fn set_account_notes(&self, id: &str, notes: Option<String>) -> anyhow::Result<bool> {
+        let notes = notes.filter(|n| !n.is_empty());
+        check_notes_length(notes.as_deref())?;
+
+        let mut accounts = self.accounts.write().await;
+        let Some(account) = accounts.get_mut(id) else {
+            return Ok(false);
+        };
+        account.notes = notes;
+        drop(accounts);
+        self.save_to_file().await?;
+        Ok(true)
+    }
+
+    /// 替换账号的全部标签并更新索引，账号不存在时返回 `Ok(false)`
+    pub async fn set_account_tags(
+        &self,
+        id: &str,
+        tags: std::collections::BTreeMap<String, String>,
+    ) -> anyhow::Result<bool> {
+        let mut accounts = self.accounts.write().await;
+        let Some(account) = accounts.get_mut(id) else {
+            return Ok(false);
+        };
+        let mut tag_index = self.tag_index.write().await;
+        tag_index.remove(id, &account.tags);
+        tag_index.insert(id, &tags);
+        account.tags = tags;
+        drop(tag_index);
+        drop(accounts);
+        self.save_to_file().await?;
+        Ok(true)
+    }
+
+    /// 一次性更新账号的多个属性（`None` 字段保持不变）
+    ///
+    /// 先校验全部字段，任一字段不合法时不做任何修改；全部应用后只保存一次。
+    /// 账号不存在时返回 `Ok(false)`
+    pub async fn update_account(&self, id: &str, update: AccountUpdate) -> anyhow::Result<bool> {
+        let name = update.name.map(|n| n.trim().to_string());
+        if name.as_deref().is_some_and(str::is_empty) {
+            anyhow::bail!("账号名称不能为空");
+        }
+        let notes = update.notes.map(|n| Some(n).filter(|n| !n.is_empty()));
+        check_notes_length(notes.clone().flatten().as_deref())?;
+
+        let mut accounts = self.accounts.write().await;
+        let Some(account) = accounts.get_mut(id) else {
+            return Ok(false);
+        };
+        if let Some(name) = name {
+            account.name = name;
+        }
+        if let Some(notes) = notes {
+            account.notes = notes;
+        }
+        if let Some(display_name) = update.display_name_override {
+            account.display_name_override = Some(display_name).filter(|n| !n.is_empty());
+        }
+        if let Some(tags) = update.tags {
+            let mut tag_index = self.tag_index.write().await;
+            tag_index.remove(id, &account.tags);
+            tag_index.insert(id, &tags);
+            account.tags = tags;
+        }
+        if let Some(weight) = update.weight {
+            account.weight = weight;
+        }
+        drop(accounts);
+        self.save_to_file().await?;
+        Ok(true)
+    }
+
+    /// 带有指定标签的账号 id（通过索引查找，无需遍历账号）
+    pub async fn get_accounts_by_tag(&self, key: &str, value: &str) -> Vec<String> {
+        self.tag_index.read().await.get(key, value)
+    }
+
+    /// 分组内的账号（分组不存在时为空）
+    pub async fn get_accounts_for_group(&self, group_id: &str) -> Vec<Account> {
+        let accounts = self.accounts.read().await;
+        let groups = self.groups.read().await;
+        let Some(group) = groups.get(group_id) else {
+            return Vec::new();
+        };
+        let mut members: Vec<Account> = group
+            .account_ids
+            .iter()
+            .filter_map(|id| accounts.get(id).cloned())
+            .collect();
+        members.sort_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        members
+    }
+
+    /// 更新账号凭证
+    ///
+    /// 当前 Token 仍有效（距过期超过 5 分钟）时暂存新凭证，待其过期后自动启用；否则立即替换。
+    /// 账号不存在时返回 `Ok(None)`
+    pub async fn update_credentials(
+        &self,
+        id: &str,
+        credentials: crate::kiro::model::credentials::KiroCredentials,
+    ) -> anyhow::Result<Option<CredentialUpdate>> {
+        if credentials
+            .refresh_token
+            .as_deref()
+            .is_none_or(|t| t.is_empty())
+        {
+            anyhow::bail!("缺少 refreshToken");
+        }
+
+        // Provider 刷新 Token 期间会一直持有 TokenManager 锁，等待该锁时不能持有账号池的锁，
+        // 否则刷新完成前所有账号选择都会被阻塞
+        let Some(tm) = self.token_managers.read().await.get(id).cloned() else {
+            return Ok(None);
+        };
+        let outcome = {
+            let mut tm = tm.lock().await;
+            if tm.is_token_valid() {
+                tm.stage_credentials(credentials.clone());
+                CredentialUpdate::Staged
+            } else {
+                tm.replace_credentials(credentials.clone());
+                CredentialUpdate::Applied
+            }
+        };
+
+        let mut accounts = self.accounts.write().await;
+        let Some(account) = accounts.get_mut(id) else {
+            return Ok(None);
+        };
+        match outcome {
+            CredentialUpdate::Staged => account.pending_credentials = Some(credentials),
+            CredentialUpdate::Applied => {
+                let mut token_hashes = self.refresh_token_hashes.write().await;
+                forget_refresh_token(&mut token_hashes, account);
+                if let Some(token) = credentials.refresh_token.as_deref() {
+                    token_hashes.insert(refresh_token_hash(token), id.to_string());
+                }
+                account.credentials = credentials;
+                account.pending_credentials = None;
+            }
+        }
+        drop(accounts);
+
+        tracing::info!("账号 {} 凭证更新: {:?}", id, outcome);
+        self.save_to_file().await?;
+        Ok(Some(outcome))
+    }
+
+    /// 轮换账号凭证（零停机）
+    ///
+    /// 先用临时 TokenManager 验证新凭证，验证失败时返回错误且不改动现有凭证；
+    /// 成功后在同一组写锁下替换账号凭证、TokenManager 与 Provider。账号不存在时返回 `Ok(false)`
+    pub async fn rotate_credentials(
+        &self,
+        id: &str,
+        credentials: crate::kiro::model::credentials::KiroCredentials,
+    ) -> anyhow::Result<bool> {
+        let Some(proxy) = self
+            .accounts
+            .read()
+            .await
+            .get(id)
+            .map(|a| self.proxy_for(a))
+        else {
+            return Ok(false);
+        };
+        let mut token_manager = TokenManager::new(self.config.clone(), credentials, proxy);
+        token_manager
+            .ensure_valid_token()
+            .await
+            .map_err(|e| anyhow::anyhow!("新凭证验证失败: {}", e))?;
+        self.swap_credentials(id, token_manager).await
+    }
+
+    /// 以已验证的 TokenManager 原子替换账号凭证，账号不存在时返回 `Ok(false)`
+    async fn swap_credentials(
+        &self,
+        id: &str,
+        token_manager: TokenManager,
+    ) -> anyhow::Result<bool> {
+        let credentials = token_manager.credentials().clone();
+        let proxy = match self.accounts.read().await.get(id) {
+            Some(account) => self.proxy_for(account),
+            None => return Ok(false),
+        };
+        let tm = Arc::new(tokio::sync::Mutex::new(token_manager));
+        let provider = Arc::new(KiroProvider::with_shared_token_manager(tm.clone(), proxy));
+
+        let mut accounts = self.accounts.write().await;
+        let mut managers = self.token_managers.write().await;
+        let mut providers = self.providers.write().await;
+        let mut token_hashes = self.refresh_token_hashes.write().await;
+
+        let Some(account) = accounts.get_mut(id) else {
+            return Ok(false);
+        };
+        if let Some(token) = credentials.refresh_token.as_deref() {
+            let hash = refresh_token_hash(token);
+            if let Some(owner) = token_hashes.get(&hash).filter(|owner| *owner != id) {
+                anyhow::bail!("refreshToken 已被账号 {} 使用", owner);
+            }
+            forget_refresh_token(&mut token_hashes, account);
+            token_hashes.insert(hash, id.to_string());
+        }
+        account.credentials = credentials;
+        account.pending_credentials = None;
+        managers.insert(id.to_string(), tm);
+        providers.insert(id.to_string(), provider);
+        drop(token_hashes);
+        drop(providers);
+        drop(managers);
+        drop(accounts);
+
+        tracing::info!("账号 {} 凭证已轮换", id);
+        self.save_to_file().await?;
+        Ok(true)
+    }
+
+    /// 将已被 TokenManager 启用的暂存凭证同步回账号记录
+    ///
+    /// TokenManager 正被占用时跳过，留待下次同步
+    async fn sync_promoted_credentials(&self) {
+        let mut accounts = self.accounts.write().await;
+        let managers = self.token_managers.read().await;
+        let mut token_hashes = self.refresh_token_hashes.write().await;
+
+        for account in accounts.values_mut() {
+            if account.pending_credentials.is_none() {
+                continue;
+            }
+            let Some(tm) = managers.get(&account.id) else {
+                continue;
+            };
+            let Ok(tm) = tm.try_lock() else {
+                continue;
+            };
+            if tm.has_pending_credentials() {
+                continue;
+            }
+            let Some(pending) = account.pending_credentials.take() else {
+                continue;
+            };
+            forget_refresh_token(&mut token_hashes, account);
+            if let Some(token) = pending.refresh_token.as_deref() {
+                token_hashes.insert(refresh_token_hash(token), account.id.clone());
+            }
+            account.credentials = pending;
+            tracing::info!("账号 {} 暂存凭证已启用", account.id);
+        }
+    }
+
     /// 扫描并恢复到期冷却账号（15分钟任务）
     pub async fn recover_cooldown_accounts(&self) -> usize {
         let mut accounts = self.accounts.write().await;
@@ -518,6 +1945,7 @@ impl AccountPool {
 
         for account in accounts.values_mut() {
             if account.status == AccountStatus::Cooldown && account.recover_if_ready() {
+                self.emit_status_change(&account.id, AccountStatus::Cooldown, account.status);
                 recovered += 1;
             }
         }
@@ -546,11 +1974,13 @@ impl AccountPool {
         for id in &exhausted_ids {
             match self.refresh_account_usage(id).await {
                 Ok(usage) => {
-                    if usage.available > 0.0 {
+                    if !usage.is_exhausted() {
                         let mut accounts = self.accounts.write().await;
                         if let Some(account) = accounts.get_mut(id) {
-                            account.status = AccountStatus::Active;
+                            let from = account.status;
+                            account.set_status(AccountStatus::Active, "配额已恢复");
                             account.exhausted_until = None;
+                            self.emit_status_change(id, from, account.status);
                             recovered += 1;
                         }
                         drop(accounts);
@@ -568,6 +1998,24 @@ impl AccountPool {
         (recovered, exhausted_ids.len())
     }
 
+    /// 所有配额耗尽账号中最早的额度重置时间（优先取配额缓存，其次取账号的耗尽恢复时间）
+    pub async fn estimate_next_reset(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let accounts = self.accounts.read().await;
+        let usage_cache = self.usage_cache.read().await;
+        accounts
+            .values()
+            .filter_map(|a| {
+                let usage = usage_cache.get(&a.id);
+                let exhausted =
+                    a.status == AccountStatus::Exhausted || usage.is_some_and(|u| u.is_exhausted());
+                if !exhausted {
+                    return None;
+                }
+                usage.and_then(|u| u.next_reset).or(a.exhausted_until)
+            })
+            .min()
+    }
+
     /// 获取统计信息
     pub async fn get_stats(&self) -> PoolStats {
         let accounts = self.accounts.read().await;
@@ -608,21 +2056,118 @@ impl AccountPool {
         }
     }
 
+    /// 重新平衡请求计数
+    ///
+    /// 将所有活跃账号的 `request_count` 设为活跃账号的平均值，
+    /// 避免 LeastUsed 策略因历史突发流量长期回避某个账号
+    pub async fn rebalance(&self) -> RebalanceResult {
+        let mut accounts = self.accounts.write().await;
+        let active: Vec<&mut Account> = accounts
+            .values_mut()
+            .filter(|a| a.status == AccountStatus::Active)
+            .collect();
+
+        if active.is_empty() {
+            return RebalanceResult {
+                accounts_adjusted: 0,
+                old_max: 0,
+                new_max: 0,
+            };
+        }
+
+        let old_max = active.iter().map(|a| a.request_count).max().unwrap_or(0);
+        let total: u64 = active.iter().map(|a| a.request_count).sum();
+        let average = total / active.len() as u64;
+
+        let mut accounts_adjusted = 0usize;
+        for account in active {
+            if account.request_count != average {
+                account.request_count = average;
+                accounts_adjusted += 1;
+            }
+        }
+
+        drop(accounts);
+        if accounts_adjusted > 0 {
+            if let Err(e) = self.save_to_file().await {
+                tracing::warn!("保存账号文件失败: {}", e);
+            }
+        }
+
+        tracing::info!(
+            "请求计数已重新平衡，调整 {} 个账号，最大值 {} -> {}",
+            accounts_adjusted,
+            old_max,
+            average
+        );
+
+        RebalanceResult {
+            accounts_adjusted,
+            old_max,
+            new_max: average,
+        }
+    }
+
+    /// 以 Prometheus 文本格式输出指标（抓取时刷新账号数与剩余配额）
+    pub async fn render_metrics(&self) -> anyhow::Result<String> {
+        let mut status_counts: HashMap<&'static str, i64> = [
+            AccountStatus::Active,
+            AccountStatus::Cooldown,
+            AccountStatus::Exhausted,
+            AccountStatus::Invalid,
+            AccountStatus::Disabled,
+        ]
+        .into_iter()
+        .map(|s| (s.to_status_string(), 0))
+        .collect();
+        for account in self.accounts.read().await.values() {
+            *status_counts
+                .entry(account.status.to_status_string())
+                .or_default() += 1;
+        }
+        let usage_cache = self.usage_cache.read().await;
+        self.metrics.set_pool_state(
+            status_counts,
+            usage_cache
+                .iter()
+                .map(|(id, usage)| (id.as_str(), usage.available)),
+        );
+        drop(usage_cache);
+        self.metrics.render()
+    }
+
     /// 添加请求记录
     pub async fn add_request_log(&self, log: RequestLog) {
-        let mut logger = self.request_logger.write().await;
-        logger.add(log);
-
-        // 异步保存到文件（不阻塞）
-        if let Some(data_dir) = &self.data_dir {
-            let logs = logger.get_all();
-            let file_path = data_dir.join(LOGS_FILE);
+        // 推送到外部 Webhook（不影响主流程）
+        if let Some(webhook) = self.config.request_log_webhook.clone() {
+            let proxy = self.proxy.clone();
+            let log = log.clone();
             tokio::spawn(async move {
-                if let Ok(content) = serde_json::to_string(&logs) {
-                    let _ = tokio::fs::write(&file_path, content).await;
+                if let Err(e) = webhook::deliver_request_log(&webhook, proxy.as_ref(), &log).await {
+                    tracing::warn!("请求记录 Webhook 推送失败: {}", e);
                 }
             });
         }
+
+        self.metrics.observe_request(&log);
+        if log.success {
+            self.record_success(&log.account_id).await;
+        }
+        self.request_logger.write().await.add(log);
+        self.auto_throttle().await;
+
+        // 异步保存到文件（不阻塞）；已有尚未开始的保存任务时由它一并写入本条记录
+        if let Some(data_dir) = &self.data_dir {
+            if !self.log_save_pending.swap(true, Ordering::AcqRel) {
+                tokio::spawn(persist_request_logs(
+                    self.request_logger.clone(),
+                    self.log_writer.clone(),
+                    self.log_save_pending.clone(),
+                    data_dir.join(LOGS_FILE),
+                    self.log_file_max_bytes(),
+                ));
+            }
+        }
     }
 
     /// 获取最近的请求记录
@@ -631,12 +2176,298 @@ impl AccountPool {
         logger.get_recent(n)
     }
 
+    /// 获取指定会话的请求记录（最新的在前）
+    pub async fn get_session_logs(&self, session_id: &str) -> Vec<RequestLog> {
+        self.request_logger.read().await.get_by_session(session_id)
+    }
+
     /// 获取请求统计
     pub async fn get_request_stats(&self) -> RequestStats {
         let logger = self.request_logger.read().await;
         logger.get_stats()
     }
 
+    /// 尝试获取全局限速令牌（未配置限速时总是成功）
+    pub async fn try_acquire_rate_limit(&self) -> bool {
+        match self.rate_limiter.lock().await.bucket_mut() {
+            Some(bucket) => bucket.try_acquire(),
+            None => true,
+        }
+    }
+
+    /// 当前全局限速（每秒请求数）
+    pub async fn current_rps(&self) -> Option<f64> {
+        self.rate_limiter
+            .lock()
+            .await
+            .bucket_mut()
+            .map(|bucket| bucket.rate())
+    }
+
+    /// 应用热加载后的限速参数（rpsLimit / minRps / maxRps）
+    pub async fn apply_rate_limit(&self, settings: RateLimitSettings) {
+        if self.rate_limiter.lock().await.apply(settings) {
+            tracing::info!(
+                "全局限速已更新：rpsLimit {:?}，minRps {}，maxRps {:?}",
+                settings.rps_limit,
+                settings.min_rps,
+                settings.max_rps
+            );
+        }
+    }
+
+    /// 根据近 10 分钟错误率自动调整全局限速
+    ///
+    /// 错误率超过 20% 时降低 10%（不低于 min_rps），低于 5% 时提高 10%（不超过 max_rps）
+    pub async fn auto_throttle(&self) {
+        if !self.config.auto_throttle_enabled {
+            return;
+        }
+        if self.rate_limiter.lock().await.bucket_mut().is_none() {
+            return;
+        }
+
+        let since = chrono::Utc::now() - chrono::Duration::minutes(AUTO_THROTTLE_WINDOW_MINUTES);
+        let (samples, error_rate) = self.request_logger.read().await.error_rate_since(since);
+        if samples < AUTO_THROTTLE_MIN_SAMPLES {
+            return;
+        }
+
+        let mut last = self.last_throttle_at.lock().await;
+        if last.is_some_and(|t| t.elapsed().as_secs() < AUTO_THROTTLE_INTERVAL_SECS) {
+            return;
+        }
+        *last = Some(std::time::Instant::now());
+        drop(last);
+
+        let mut limiter = self.rate_limiter.lock().await;
+        let settings = limiter.settings();
+        let Some(bucket) = limiter.bucket_mut() else {
+            return;
+        };
+        if let Some(rate) = throttle_adjustment(
+            bucket.rate(),
+            error_rate,
+            settings.min_rps,
+            settings.throttle_max(),
+        ) {
+            tracing::info!(
+                "自动限速：近 {} 分钟错误率 {:.1}%，速率调整为 {:.2} rps",
+                AUTO_THROTTLE_WINDOW_MINUTES,
+                error_rate * 100.0,
+                rate
+            );
+            bucket.set_rate(rate);
+        }
+    }
+
+    /// 将早于 `max_age_days` 天的请求记录压缩归档到 `logs-<日期>.json.gz`，并从活动记录中移除
+    pub async fn archive_logs(&self, max_age_days: u32) -> anyhow::Result<ArchiveResult> {
+        let Some(data_dir) = &self.data_dir else {
+            anyhow::bail!("未配置数据目录，无法归档请求记录");
+        };
+
+        let cutoff = chrono::Utc::now() - chrono::Duration::days(max_age_days as i64);
+        // 持有文件写入锁与记录写锁完成归档与重写，避免与后台保存任务交错
+        let _writer = self.log_writer.lock().await;
+        let mut logger = self.request_logger.write().await;
+        let archived = logger.drain_older_than(cutoff);
+        if archived.is_empty() {
+            return Ok(ArchiveResult {
+                archived: 0,
+                archive_file: None,
+                active_remaining: logger.len(),
+            });
+        }
+
+        let result = async {
+            let compressed = compress_logs(&archived)?;
+            tokio::fs::create_dir_all(data_dir).await?;
+            let file_name = archive_file_name(data_dir).await;
+            tokio::fs::write(data_dir.join(&file_name), compressed).await?;
+            let (content, trimmed) =
+                logs_to_json_within(&logger.get_all(), self.log_file_max_bytes())?;
+            logger.remove_oldest(trimmed);
+            tokio::fs::write(data_dir.join(LOGS_FILE), content).await?;
+            anyhow::Ok(file_name)
+        }
+        .await;
+
+        match result {
+            Ok(file_name) => {
+                tracing::info!("已归档 {} 条请求记录到 {}", archived.len(), file_name);
+                Ok(ArchiveResult {
+                    archived: archived.len(),
+                    archive_file: Some(file_name),
+                    active_remaining: logger.len(),
+                })
+            }
+            Err(e) => {
+                // 归档失败时恢复记录
+                logger.restore_oldest(archived);
+                Err(e)
+            }
+        }
+    }
+
+    /// 基于配额缓存与最近 100 条成功请求的额度消耗估算剩余请求容量
+    ///
+    /// 已禁用或失效的账号不计入
+    pub async fn estimate_capacity(&self) -> PoolCapacity {
+        let accounts = self.accounts.read().await;
+        let usage_cache = self.usage_cache.read().await;
+        let now = chrono::Utc::now();
+
+        let mut total_available_credits = 0.0;
+        let mut accounts_with_quota = 0;
+        let mut accounts_without_quota_data = 0;
+        let mut oldest_fetch: Option<chrono::DateTime<chrono::Utc>> = None;
+        let request_counts: Vec<u64> = accounts.values().map(|a| a.request_count).collect();
+        for account in accounts
+            .values()
+            .filter(|a| !matches!(a.status, AccountStatus::Disabled | AccountStatus::Invalid))
+        {
+            let Some(usage) = usage_cache.get(&account.id) else {
+                accounts_without_quota_data += 1;
+                continue;
+            };
+            if !usage.is_exhausted() {
+                accounts_with_quota += 1;
+                total_available_credits += usage.available.max(0.0);
+            }
+            if let Some(fetched_at) = usage.fetched_at {
+                oldest_fetch = Some(oldest_fetch.map_or(fetched_at, |t| t.min(fetched_at)));
+            }
+        }
+        drop(usage_cache);
+        drop(accounts);
+
+        let (avg_credits_per_request, per_model_avg) = self
+            .request_logger
+            .read()
+            .await
+            .avg_credits_per_request(CAPACITY_SAMPLE_SIZE);
+        let estimate = |avg: f64| {
+            if avg > 0.0 {
+                (total_available_credits / avg).floor() as u64
+            } else {
+                0
+            }
+        };
+
+        PoolCapacity {
+            total_available_credits,
+            avg_credits_per_request,
+            estimated_requests_remaining: avg_credits_per_request.map(estimate),
+            accounts_with_quota,
+            accounts_without_quota_data,
+            data_freshness_seconds: oldest_fetch
+                .map(|t| (now - t).num_seconds().max(0) as u64)
+                .unwrap_or(0),
+            per_model: per_model_avg
+                .into_iter()
+                .map(|(model, avg)| {
+                    (
+                        model,
+                        ModelCapacity {
+                            avg_credits_per_request: avg,
+                            estimated_requests_remaining: estimate(avg),
+                        },
+                    )
+                })
+                .collect(),
+            distribution_fairness: FairnessReport::from_counts(&request_counts),
+        }
+    }
+
+    /// 各账号请求数分布的公平性
+    pub async fn get_selection_fairness(&self) -> FairnessReport {
+        let counts: Vec<u64> = self
+            .accounts
+            .read()
+            .await
+            .values()
+            .map(|a| a.request_count)
+            .collect();
+        FairnessReport::from_counts(&counts)
+    }
+
+    /// 最近 `hours` 小时的请求按 `interval_mins` 分钟聚合的时间线
+    pub async fn usage_timeline(&self, interval_mins: u32, hours: u32) -> Vec<UsageIntervalBucket> {
+        let since = chrono::Utc::now() - chrono::Duration::hours(hours as i64);
+        let logs: Vec<RequestLog> = {
+            let logger = self.request_logger.read().await;
+            logger
+                .get_all()
+                .into_iter()
+                .filter(|l| l.timestamp >= since)
+                .collect()
+        };
+        aggregate_usage_timeline(&logs, interval_mins)
+    }
+
+    /// 运行时调整内存中请求记录的最大条数（缩小时移除最早的记录）
+    pub async fn adjust_max_logs(&self, new_max: usize) -> anyhow::Result<MaxLogsAdjustment> {
+        if new_max == 0 {
+            anyhow::bail!("max_logs 必须大于 0");
+        }
+        let mut logger = self.request_logger.write().await;
+        let old_max_logs = logger.max_logs();
+        let removed = logger.set_max_logs(new_max);
+        if removed > 0 {
+            tracing::info!(
+                "请求记录上限调整为 {}，移除 {} 条最早的记录",
+                new_max,
+                removed
+            );
+        }
+        Ok(MaxLogsAdjustment {
+            old_max_logs,
+            new_max_logs: new_max,
+            current_count: logger.len(),
+        })
+    }
+
+    /// 按日统计指定账号最近 `days` 天的请求数
+    pub async fn daily_request_history(&self, account_id: &str, days: u32) -> Vec<DailyCount> {
+        let logger = self.request_logger.read().await;
+        logger.get_daily_request_history(account_id, days)
+    }
+
+    /// 获取最常见的 N 条错误信息
+    pub async fn top_error_messages(&self, n: usize) -> Vec<(String, usize)> {
+        let logger = self.request_logger.read().await;
+        logger.top_error_messages(n)
+    }
+
+    /// request_logs.json 的大小上限（字节，未配置时不限制）
+    fn log_file_max_bytes(&self) -> Option<u64> {
+        self.config
+            .max_log_disk_mb
+            .map(|mb| mb.saturating_mul(1_000_000))
+    }
+
+    /// 内存中的请求记录数与 request_logs.json 的大小
+    pub async fn request_log_count(&self) -> RequestLogCount {
+        let (count, max_logs) = {
+            let logger = self.request_logger.read().await;
+            (logger.len(), logger.max_logs())
+        };
+        let file_size_bytes = match &self.data_dir {
+            Some(data_dir) => tokio::fs::metadata(data_dir.join(LOGS_FILE))
+                .await
+                .ok()
+                .map(|meta| meta.len()),
+            None => None,
+        };
+        RequestLogCount {
+            count,
+            max_logs,
+            file_size_bytes,
+            max_log_disk_mb: self.config.max_log_disk_mb,
+        }
+    }
+
     /// 从文件加载请求记录
     pub async fn load_logs_from_file(&self) -> anyhow::Result<usize> {
         let Some(data_dir) = &self.data_dir else {
@@ -651,13 +2482,14 @@ impl AccountPool {
         let content = tokio::fs::read_to_string(&file_path).await?;
         let mut logs: Vec<RequestLog> = serde_json::from_str(&content)?;
 
-        // 只保留最新的 1000 条（如果超过的话）
-        if logs.len() > 1000 {
-            logs = logs.split_off(logs.len() - 1000);
+        // 只保留最新的 max_logs 条（如果超过的话）
+        let mut logger = self.request_logger.write().await;
+        let max_logs = logger.max_logs();
+        if logs.len() > max_logs {
+            logs = logs.split_off(logs.len() - max_logs);
         }
 
         let count = logs.len();
-        let mut logger = self.request_logger.write().await;
         for log in logs {
             logger.add(log);
         }
@@ -672,6 +2504,69 @@ impl AccountPool {
         cache.get(id).cloned()
     }
 
+    /// 获取账号 accessToken 的过期时间，凭证未记录过期时间时返回 `Ok(None)`
+    pub async fn get_token_expiry(
+        &self,
+        id: &str,
+    ) -> anyhow::Result<Option<chrono::DateTime<chrono::Utc>>> {
+        let tm = self
+            .token_managers
+            .read()
+            .await
+            .get(id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("账号不存在"))?;
+        let tm_guard = tm.lock().await;
+        tm_guard
+            .credentials()
+            .expires_at
+            .as_deref()
+            .map(|expires_at| {
+                chrono::DateTime::parse_from_rfc3339(expires_at)
+                    .map(|t| t.with_timezone(&chrono::Utc))
+                    .map_err(|e| anyhow::anyhow!("无法解析过期时间 {}: {}", expires_at, e))
+            })
+            .transpose()
+    }
+
+    /// Token 是否已进入 `refresh_buffer_secs` 刷新窗口（未知过期时间视为需要刷新）
+    pub fn token_refresh_needed(&self, expires_at: Option<chrono::DateTime<chrono::Utc>>) -> bool {
+        let buffer = chrono::Duration::seconds(self.config.refresh_buffer_secs as i64);
+        expires_at.is_none_or(|t| t <= chrono::Utc::now() + buffer)
+    }
+
+    /// 强制刷新账号 Token（忽略缓存的 accessToken），返回新的过期时间
+    pub async fn force_refresh_token(&self, id: &str) -> anyhow::Result<String> {
+        let managers = self.token_managers.read().await;
+        let tm = managers
+            .get(id)
+            .ok_or_else(|| anyhow::anyhow!("账号不存在"))?;
+
+        let mut tm_guard = tm.lock().await;
+        if let Err(e) = tm_guard.force_refresh().await {
+            let error_msg = e.to_string();
+            // 检测 403/suspended 错误，自动禁用账号
+            if error_msg.contains("403")
+                || error_msg.contains("suspended")
+                || error_msg.contains("SUSPENDED")
+            {
+                drop(tm_guard);
+                drop(managers);
+                self.mark_invalid(id).await;
+                tracing::warn!("账号 {} 强制刷新 token 失败，已自动禁用: {}", id, error_msg);
+            }
+            return Err(e);
+        }
+
+        let expires_at = tm_guard
+            .credentials()
+            .expires_at
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("刷新结果缺少过期时间"))?;
+        tracing::info!("账号 {} 已强制刷新 token，过期时间: {}", id, expires_at);
+        Ok(expires_at)
+    }
+
     /// 刷新账号配额
     pub async fn refresh_account_usage(&self, id: &str) -> anyhow::Result<UsageLimits> {
         // 获取 TokenManager
@@ -703,7 +2598,7 @@ impl AccountPool {
         drop(managers);
 
         // 调用 API 获取配额
-        let usage = match super::usage::check_usage_limits(&token).await {
+        let usage = match super::usage::check_usage_limits(&self.config, &token).await {
             Ok(u) => u,
             Err(e) => {
                 let error_msg = e.to_string();
@@ -732,12 +2627,13 @@ impl AccountPool {
         drop(cache);
 
         // 同步账号状态：有额度则恢复，额度耗尽则标记为 Exhausted
-        if usage.available > 0.0 {
+        if !usage.is_exhausted() {
             let mut accounts = self.accounts.write().await;
             if let Some(account) = accounts.get_mut(id) {
                 if account.status == AccountStatus::Exhausted {
-                    account.status = AccountStatus::Active;
+                    account.set_status(AccountStatus::Active, "配额已恢复");
                     account.exhausted_until = None;
+                    self.emit_status_change(id, AccountStatus::Exhausted, AccountStatus::Active);
                 }
             }
             drop(accounts);
@@ -774,41 +2670,420 @@ impl AccountPool {
             return Ok(0);
         }
 
-        let content = tokio::fs::read_to_string(&file_path).await?;
-        let loaded: HashMap<String, UsageLimits> = serde_json::from_str(&content)?;
+        let content = tokio::fs::read_to_string(&file_path).await?;
+        let loaded: HashMap<String, UsageLimits> = serde_json::from_str(&content)?;
+
+        let count = loaded.len();
+        let mut cache = self.usage_cache.write().await;
+        *cache = loaded;
+
+        tracing::info!("从文件加载了 {} 个配额缓存", count);
+        Ok(count)
+    }
+
+    /// 刷新所有账号配额
+    pub async fn refresh_all_usage(&self) -> Vec<(String, Result<UsageLimits, String>)> {
+        let accounts = self.accounts.read().await;
+        let ids: Vec<String> = accounts.keys().cloned().collect();
+        drop(accounts);
+
+        let mut results = Vec::new();
+        for id in ids {
+            let result = match self.refresh_account_usage(&id).await {
+                Ok(usage) => Ok(usage),
+                Err(e) => Err(e.to_string()),
+            };
+            results.push((id, result));
+        }
+        results
+    }
+
+    /// 获取所有账号配额缓存
+    pub async fn get_all_usage(&self) -> HashMap<String, UsageLimits> {
+        let cache = self.usage_cache.read().await;
+        cache.clone()
+    }
+
+    /// 并发验证所有账号凭证（仅报告结果，不修改账号状态）
+    pub async fn validate_all(&self, concurrency: usize) -> Vec<ValidationResult> {
+        self.validate_all_with(concurrency, |credentials, proxy| async move {
+            let mut token_manager = TokenManager::new(self.config.clone(), credentials, proxy);
+            token_manager.ensure_valid_token().await.map(|_| ())
+        })
+        .await
+    }
+
+    /// 使用指定的验证函数并发验证所有账号，并发数由信号量限制
+    async fn validate_all_with<F, Fut>(
+        &self,
+        concurrency: usize,
+        validate: F,
+    ) -> Vec<ValidationResult>
+    where
+        F: Fn(crate::kiro::model::credentials::KiroCredentials, Option<ProxyConfig>) -> Fut,
+        Fut: std::future::Future<Output = anyhow::Result<()>>,
+    {
+        let mut targets: Vec<Account> = self.accounts.read().await.values().cloned().collect();
+        targets.sort_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let semaphore = tokio::sync::Semaphore::new(concurrency.max(1));
+        let tasks = targets.into_iter().map(|account| {
+            let semaphore = &semaphore;
+            let validate = &validate;
+            async move {
+                let _permit = semaphore.acquire().await.ok();
+                let started = std::time::Instant::now();
+                let proxy = self.proxy_for(&account);
+                let outcome = validate(account.credentials, proxy).await;
+                ValidationResult {
+                    id: account.id,
+                    name: account.name,
+                    valid: outcome.is_ok(),
+                    error: outcome.err().map(|e| e.to_string()),
+                    latency_ms: started.elapsed().as_millis() as u64,
+                }
+            }
+        });
+        let results = futures::future::join_all(tasks).await;
+
+        let valid = results.iter().filter(|r| r.valid).count();
+        tracing::info!("批量验证凭证完成：{}/{} 个有效", valid, results.len());
+        results
+    }
+
+    /// 预热账号池：对所有可用账号刷新 Token 并请求一次配额接口，记录基线延迟
+    pub async fn warm_up(&self) -> Vec<WarmUpResult> {
+        let targets: Vec<(String, String)> = self
+            .accounts
+            .read()
+            .await
+            .values()
+            .filter(|a| a.is_available())
+            .map(|a| (a.id.clone(), a.name.clone()))
+            .collect();
+
+        let mut results = Vec::with_capacity(targets.len());
+        for (id, name) in targets {
+            let Some(tm) = self.token_managers.read().await.get(&id).cloned() else {
+                continue;
+            };
+
+            let started = std::time::Instant::now();
+            let outcome = async {
+                let token = tm.lock().await.ensure_valid_token().await?;
+                super::usage::check_usage_limits(&self.config, &token).await
+            }
+            .await;
+            let latency_ms = started.elapsed().as_millis() as u64;
+
+            let error = match outcome {
+                Ok(usage) => {
+                    self.usage_cache.write().await.insert(id.clone(), usage);
+                    if let Some(account) = self.accounts.write().await.get_mut(&id) {
+                        account.warmup_latency_ms = Some(latency_ms);
+                    }
+                    None
+                }
+                Err(e) => Some(e.to_string()),
+            };
+
+            results.push(WarmUpResult {
+                account_id: id,
+                account_name: name,
+                latency_ms,
+                success: error.is_none(),
+                error,
+            });
+        }
+
+        let succeeded = results.iter().filter(|r| r.success).count();
+        tracing::info!("账号池预热完成：{}/{} 个账号成功", succeeded, results.len());
+        results
+    }
+
+    /// 获取账号池的时间点一致快照
+    ///
+    /// 按 accounts → strategy → usage_cache 的顺序同时持有读锁后再复制，避免读取期间被写入穿插
+    pub async fn snapshot(&self) -> PoolSnapshot {
+        let accounts = self.accounts.read().await;
+        let strategy = self.strategy.read().await;
+        let usage_cache = self.usage_cache.read().await;
+
+        PoolSnapshot {
+            accounts: accounts.values().cloned().collect(),
+            strategy: *strategy,
+            usage_cache: usage_cache.clone(),
+            captured_at: chrono::Utc::now(),
+        }
+    }
+
+    /// 从快照恢复账号池（灾难恢复）
+    ///
+    /// 仅添加池中不存在的账号（按 id），已存在的保持不变；新账号沿用快照中的状态（含禁用状态）与配额缓存。
+    /// 策略同步为快照中的策略。快照早于 `snapshot_max_age_hours` 时拒绝导入
+    pub async fn import_snapshot(
+        &self,
+        snapshot: PoolSnapshot,
+    ) -> anyhow::Result<SnapshotImportResult> {
+        let max_age = chrono::Duration::hours(self.config.snapshot_max_age_hours as i64);
+        let age = chrono::Utc::now() - snapshot.captured_at;
+        if age > max_age {
+            anyhow::bail!(
+                "快照已过期：采集于 {}，超过 {} 小时",
+                snapshot.captured_at.to_rfc3339(),
+                self.config.snapshot_max_age_hours
+            );
+        }
+
+        let mut result = SnapshotImportResult::default();
+        let mut restored_usage = HashMap::new();
+        for account in snapshot.accounts {
+            let id = account.id.clone();
+            if self.accounts.read().await.contains_key(&id) {
+                result.skipped_existing += 1;
+                continue;
+            }
+            let Some(token) = account.credentials.refresh_token.as_deref() else {
+                result.failed += 1;
+                result
+                    .errors
+                    .push(format!("{}: 快照中缺少 refreshToken", id));
+                continue;
+            };
+            if let Some(existing) = self
+                .get_account_by_refresh_token_hash(refresh_token_hash(token))
+                .await
+            {
+                result.failed += 1;
+                result
+                    .errors
+                    .push(format!("{}: refreshToken 已被账号 {} 使用", id, existing));
+                continue;
+            }
+
+            match self.add_account_internal(account).await {
+                Ok(()) => {
+                    result.added += 1;
+                    if let Some(usage) = snapshot.usage_cache.get(&id) {
+                        restored_usage.insert(id, usage.clone());
+                    }
+                }
+                Err(e) => {
+                    result.failed += 1;
+                    result.errors.push(format!("{}: {}", id, e));
+                }
+            }
+        }
+
+        self.usage_cache.write().await.extend(restored_usage);
+        self.set_strategy(snapshot.strategy).await;
+        if result.added > 0 {
+            self.save_to_file().await?;
+        }
+        tracing::info!(
+            "已从快照恢复账号池：新增 {}，已存在 {}，失败 {}",
+            result.added,
+            result.skipped_existing,
+            result.failed
+        );
+        Ok(result)
+    }
+}
+
+/// 请求记录上限调整结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MaxLogsAdjustment {
+    pub old_max_logs: usize,
+    pub new_max_logs: usize,
+    pub current_count: usize,
+}
+
+/// 账号对账结果
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ReconcileReport {
+    pub added_from_disk: usize,
+    pub removed_not_on_disk: usize,
+    pub conflicts: Vec<ConflictDetail>,
+}
+
+/// 磁盘与内存中同一账号的字段差异（凭证字段只报告是否不同，不输出原值）
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ConflictDetail {
+    pub id: String,
+    pub field: String,
+    pub disk_value: serde_json::Value,
+    pub memory_value: serde_json::Value,
+}
+
+/// 快照导入结果
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct SnapshotImportResult {
+    pub added: usize,
+    pub skipped_existing: usize,
+    pub failed: usize,
+    pub errors: Vec<String>,
+}
+
+/// 凭证更新结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialUpdate {
+    /// 已立即替换
+    Applied,
+    /// 已暂存，当前 Token 过期后启用
+    Staged,
+}
+
+/// 凭证验证结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ValidationResult {
+    pub id: String,
+    pub name: String,
+    pub valid: bool,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+}
+
+/// 账号预热结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WarmUpResult {
+    pub account_id: String,
+    pub account_name: String,
+    pub latency_ms: u64,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// 账号池快照
+///
+/// 账号按 accounts.json 的存储格式序列化（含凭证），序列化结果可直接用于 `import_snapshot` 恢复。
+/// 账号池没有整体暂停状态，被封禁或禁用的账号体现在各账号的 `status` 中，随账号一起快照与恢复
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PoolSnapshot {
+    #[serde(with = "snapshot_accounts")]
+    pub accounts: Vec<Account>,
+    pub strategy: SelectionStrategy,
+    pub usage_cache: HashMap<String, UsageLimits>,
+    pub captured_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// 请求记录数量与文件大小
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RequestLogCount {
+    /// 内存中的记录数
+    pub count: usize,
+    pub max_logs: usize,
+    /// request_logs.json 的大小（字节），未持久化或文件不存在时为 null
+    pub file_size_bytes: Option<u64>,
+    /// request_logs.json 的大小上限（MB），未配置时为 null
+    pub max_log_disk_mb: Option<u64>,
+}
+
+/// 将请求记录写入 request_logs.json
+///
+/// 通过 `writer` 保证同一时间只有一个写入者；在读锁下复制快照，序列化与裁剪都在锁外完成。
+/// 超过 `max_bytes` 时按请求 ID 从内存中移除被裁掉的最早记录
+async fn persist_request_logs(
+    logger: Arc<RwLock<RequestLogger>>,
+    writer: Arc<Mutex<()>>,
+    pending: Arc<AtomicBool>,
+    file_path: PathBuf,
+    max_bytes: Option<u64>,
+) {
+    let _writer = writer.lock().await;
+    // 此后新增的记录会重新触发保存
+    pending.store(false, Ordering::Release);
+    let snapshot = logger.read().await.get_all();
+
+    let (content, trimmed) = match logs_to_json_within(&snapshot, max_bytes) {
+        Ok(result) => result,
+        Err(e) => {
+            tracing::warn!("序列化请求记录失败: {}", e);
+            return;
+        }
+    };
+    if trimmed > 0 {
+        let ids: HashSet<&str> = snapshot[..trimmed].iter().map(|l| l.id.as_str()).collect();
+        let removed = logger.write().await.remove_ids(&ids);
+        tracing::warn!(
+            "请求记录超过文件大小上限 {} 字节，已移除最早的 {} 条记录",
+            max_bytes.unwrap_or_default(),
+            removed
+        );
+    }
+    if let Err(e) = tokio::fs::write(&file_path, content).await {
+        tracing::warn!("保存请求记录失败: {}", e);
+    }
+}
 
-        let count = loaded.len();
-        let mut cache = self.usage_cache.write().await;
-        *cache = loaded;
+/// 账号属性的批量更新（`None` 表示保持不变，空字符串表示清除备注或显示名）
+#[derive(Debug, Clone, Default)]
+pub struct AccountUpdate {
+    pub name: Option<String>,
+    pub notes: Option<String>,
+    pub display_name_override: Option<String>,
+    pub tags: Option<std::collections::BTreeMap<String, String>>,
+    pub weight: Option<u32>,
+}
 
-        tracing::info!("从文件加载了 {} 个配额缓存", count);
-        Ok(count)
+/// 校验备注长度不超过 `MAX_NOTES_CHARS`
+fn check_notes_length(notes: Option<&str>) -> anyhow::Result<()> {
+    if notes.is_some_and(|n| n.chars().count() > MAX_NOTES_CHARS) {
+        anyhow::bail!("备注不能超过 {} 个字符", MAX_NOTES_CHARS);
     }
+    Ok(())
+}
 
-    /// 刷新所有账号配额
-    pub async fn refresh_all_usage(&self) -> Vec<(String, Result<UsageLimits, String>)> {
-        let accounts = self.accounts.read().await;
-        let ids: Vec<String> = accounts.keys().cloned().collect();
-        drop(accounts);
+/// 批量导入的单条结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BulkImportResult {
+    /// 在请求数组中的位置
+    pub index: usize,
+    pub name: String,
+    pub success: bool,
+    /// 是否因 refreshToken 已存在而跳过
+    pub duplicate: bool,
+    pub account_id: Option<String>,
+    pub error: Option<String>,
+}
 
-        let mut results = Vec::new();
-        for id in ids {
-            let result = match self.refresh_account_usage(&id).await {
-                Ok(usage) => Ok(usage),
-                Err(e) => Err(e.to_string()),
-            };
-            results.push((id, result));
-        }
-        results
+/// JSON 导入结果
+#[derive(Debug, Clone)]
+pub struct JsonImportOutcome {
+    /// 输入是否为单个凭证对象（而非数组）
+    pub single: bool,
+    /// 逐条结果，与输入顺序一致
+    pub results: Vec<BulkImportResult>,
+}
+
+impl JsonImportOutcome {
+    /// 成功导入的数量
+    pub fn imported(&self) -> usize {
+        self.results.iter().filter(|r| r.success).count()
     }
 
-    /// 获取所有账号配额缓存
-    pub async fn get_all_usage(&self) -> HashMap<String, UsageLimits> {
-        let cache = self.usage_cache.read().await;
-        cache.clone()
+    /// 第一条导入失败原因（不含重复跳过）
+    pub fn first_error(&self) -> Option<&str> {
+        self.results
+            .iter()
+            .filter(|r| !r.duplicate)
+            .find_map(|r| r.error.as_deref())
     }
 }
 
+/// 持久化的选择策略
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct StoredStrategy {
+    strategy: SelectionStrategy,
+    /// 顺序耗尽策略当前使用的账号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sequential_current_id: Option<String>,
+}
+
 /// 账号池统计
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct PoolStats {
@@ -822,9 +3097,224 @@ pub struct PoolStats {
     pub total_errors: u64,
 }
 
+/// 账号池剩余容量估算
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PoolCapacity {
+    pub total_available_credits: f64,
+    /// 无额度消耗样本时为 null
+    pub avg_credits_per_request: Option<f64>,
+    pub estimated_requests_remaining: Option<u64>,
+    pub accounts_with_quota: usize,
+    pub accounts_without_quota_data: usize,
+    /// 最旧的配额缓存距今秒数
+    pub data_freshness_seconds: u64,
+    pub per_model: HashMap<String, ModelCapacity>,
+    pub distribution_fairness: FairnessReport,
+}
+
+/// 账号负载分布公平性（基于各账号 request_count）
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FairnessReport {
+    /// 基尼系数：0 表示完全均匀，接近 1 表示负载集中在单个账号
+    pub gini_coefficient: f64,
+    /// 请求数最多的账号占比（百分比）
+    pub max_account_pct: f64,
+    /// 请求数最少的账号占比（百分比）
+    pub min_account_pct: f64,
+}
+
+impl FairnessReport {
+    /// 由各账号请求数计算；无账号或无请求时各项均为 0
+    pub fn from_counts(counts: &[u64]) -> Self {
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return Self {
+                gini_coefficient: 0.0,
+                max_account_pct: 0.0,
+                min_account_pct: 0.0,
+            };
+        }
+
+        let mut sorted = counts.to_vec();
+        sorted.sort_unstable();
+        let n = sorted.len() as f64;
+        // G = 2·Σ(i·x_i) / (n·Σx) − (n + 1) / n，x 升序、i 从 1 开始
+        let weighted: f64 = sorted
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| (i + 1) as f64 * x as f64)
+            .sum();
+        let gini = 2.0 * weighted / (n * total as f64) - (n + 1.0) / n;
+        let pct = |x: u64| x as f64 / total as f64 * 100.0;
+
+        Self {
+            gini_coefficient: gini.max(0.0),
+            max_account_pct: pct(sorted[sorted.len() - 1]),
+            min_account_pct: pct(sorted[0]),
+        }
+    }
+}
+
+/// 单个模型的剩余容量估算
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModelCapacity {
+    pub avg_credits_per_request: f64,
+    pub estimated_requests_remaining: u64,
+}
+
+/// 请求记录归档结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ArchiveResult {
+    pub archived: usize,
+    /// 无记录可归档时为 null
+    pub archive_file: Option<String>,
+    pub active_remaining: usize,
+}
+
+/// 请求计数重新平衡结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RebalanceResult {
+    pub accounts_adjusted: usize,
+    pub old_max: u64,
+    pub new_max: u64,
+}
+
+/// 以 gzip 压缩 JSON 格式的请求记录
+fn compress_logs(logs: &[RequestLog]) -> anyhow::Result<Vec<u8>> {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&serde_json::to_vec(logs)?)?;
+    Ok(encoder.finish()?)
+}
+
+/// 当天归档文件名，已存在时追加序号
+async fn archive_file_name(data_dir: &std::path::Path) -> String {
+    let date = chrono::Utc::now().format("%Y-%m-%d");
+    let mut name = format!("logs-{}.json.gz", date);
+    let mut seq = 1;
+    while tokio::fs::try_exists(data_dir.join(&name))
+        .await
+        .unwrap_or(false)
+    {
+        seq += 1;
+        name = format!("logs-{}-{}.json.gz", date, seq);
+    }
+    name
+}
+
+/// 计算 refreshToken 哈希（仅用于进程内去重）
+pub fn refresh_token_hash(token: &str) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    token.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 从哈希索引中移除账号的 refreshToken（仅当索引仍指向该账号时）
+fn forget_refresh_token(token_hashes: &mut HashMap<u64, String>, account: &Account) {
+    if let Some(token) = account.credentials.refresh_token.as_deref() {
+        let hash = refresh_token_hash(token);
+        if token_hashes.get(&hash) == Some(&account.id) {
+            token_hashes.remove(&hash);
+        }
+    }
+}
+
+/// 对账时不比较的字段
+const RECONCILE_IGNORED_FIELDS: &[&str] = &["schema_version", "history", "circuit_state"];
+/// 对账时隐去取值的凭证字段
+const RECONCILE_SECRET_FIELDS: &[&str] = &[
+    "refresh_token",
+    "client_secret",
+    "pending_credentials",
+    "proxy_override",
+];
+
+/// 逐字段比较磁盘与内存中的账号记录
+fn stored_account_conflicts(
+    disk: &StoredAccount,
+    memory: &StoredAccount,
+) -> anyhow::Result<Vec<ConflictDetail>> {
+    let serde_json::Value::Object(disk_fields) = serde_json::to_value(disk)? else {
+        anyhow::bail!("账号记录序列化结果不是对象");
+    };
+    let serde_json::Value::Object(mut memory_fields) = serde_json::to_value(memory)? else {
+        anyhow::bail!("账号记录序列化结果不是对象");
+    };
+
+    let mut fields: Vec<String> = disk_fields.keys().cloned().collect();
+    fields.extend(
+        memory_fields
+            .keys()
+            .filter(|k| !disk_fields.contains_key(*k))
+            .cloned(),
+    );
+    fields.sort();
+
+    let mut conflicts = Vec::new();
+    for field in fields {
+        if RECONCILE_IGNORED_FIELDS.contains(&field.as_str()) {
+            continue;
+        }
+        let disk_value = disk_fields.get(&field).cloned().unwrap_or_default();
+        let memory_value = memory_fields.remove(&field).unwrap_or_default();
+        if disk_value == memory_value {
+            continue;
+        }
+        let (disk_value, memory_value) = if RECONCILE_SECRET_FIELDS.contains(&field.as_str()) {
+            let mask = |v: serde_json::Value| {
+                if v.is_null() {
+                    v
+                } else {
+                    serde_json::Value::String("***".to_string())
+                }
+            };
+            (mask(disk_value), mask(memory_value))
+        } else {
+            (disk_value, memory_value)
+        };
+        conflicts.push(ConflictDetail {
+            id: memory.id.clone(),
+            field,
+            disk_value,
+            memory_value,
+        });
+    }
+    Ok(conflicts)
+}
+
+/// 快照中的账号沿用 `StoredAccount` 格式，保证凭证随快照一起导出与恢复
+mod snapshot_accounts {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{Account, StoredAccount};
+
+    pub fn serialize<S: Serializer>(
+        accounts: &[Account],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(accounts.iter().map(StoredAccount::from_account))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Account>, D::Error> {
+        let stored = Vec::<StoredAccount>::deserialize(deserializer)?;
+        Ok(stored
+            .into_iter()
+            .map(|s| s.migrate().0.into_account())
+            .collect())
+    }
+}
+
 /// 用于持久化存储的账号结构
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 struct StoredAccount {
+    /// 存储结构版本（缺失视为 0）
+    #[serde(default)]
+    schema_version: u32,
     id: String,
     name: String,
     status: super::account::AccountStatus,
@@ -832,7 +3322,36 @@ struct StoredAccount {
     error_count: u64,
     created_at: chrono::DateTime<chrono::Utc>,
     #[serde(default)]
+    cooldown_until: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
     exhausted_until: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    region: Option<String>,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_timeout_secs: Option<u64>,
+    #[serde(default)]
+    pinned: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    display_name_override: Option<String>,
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    tags: std::collections::BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    proxy_override: Option<ProxyConfig>,
+    #[serde(default = "super::account::default_weight")]
+    weight: u32,
+    /// 待生效凭证（仅保存刷新所需字段）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pending_credentials: Option<crate::kiro::model::credentials::KiroCredentials>,
+    #[serde(default, skip_serializing_if = "std::collections::VecDeque::is_empty")]
+    history: std::collections::VecDeque<super::account::StatusTransition>,
+    #[serde(default, skip_serializing_if = "CircuitState::is_default")]
+    circuit_state: CircuitState,
+    #[serde(default)]
+    consecutive_rate_limit_errors: u32,
     // 凭证信息
     refresh_token: Option<String>,
     auth_method: Option<String>,
@@ -842,15 +3361,53 @@ struct StoredAccount {
 }
 
 impl StoredAccount {
+    /// 已过期的 `cooldown_until` / `exhausted_until` 写为 null，对应状态同时恢复为 Active
     fn from_account(account: &Account) -> Self {
+        let now = chrono::Utc::now();
+        let mut status = account.status;
+        let mut cooldown_until = account.cooldown_until;
+        let mut exhausted_until = account.exhausted_until;
+        if cooldown_until.is_some_and(|t| now >= t) {
+            cooldown_until = None;
+            if status == AccountStatus::Cooldown {
+                status = AccountStatus::Active;
+            }
+        }
+        if exhausted_until.is_some_and(|t| now >= t) {
+            exhausted_until = None;
+            if status == AccountStatus::Exhausted {
+                status = AccountStatus::Active;
+            }
+        }
+
         Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
             id: account.id.clone(),
             name: account.name.clone(),
-            status: account.status,
+            status,
             request_count: account.request_count,
             error_count: account.error_count,
             created_at: account.created_at,
-            exhausted_until: account.exhausted_until,
+            cooldown_until,
+            exhausted_until,
+            last_used_at: account.last_used_at,
+            region: account.region.clone(),
+            notes: account.notes.clone(),
+            request_timeout_secs: account.request_timeout_secs,
+            pinned: account.pinned,
+            display_name_override: account.display_name_override.clone(),
+            tags: account.tags.clone(),
+            proxy_override: account.proxy_override.clone(),
+            weight: account.weight,
+            pending_credentials: account.pending_credentials.as_ref().map(|c| {
+                let mut c = c.clone();
+                c.access_token = None;
+                c.expires_at = None;
+                c
+            }),
+            history: account.history.clone(),
+            circuit_state: account.circuit_state.clone(),
+            consecutive_rate_limit_errors: account.consecutive_rate_limit_errors,
             refresh_token: account.credentials.refresh_token.clone(),
             auth_method: account.credentials.auth_method.clone(),
             client_id: account.credentials.client_id.clone(),
@@ -859,6 +3416,22 @@ impl StoredAccount {
         }
     }
 
+    /// 迁移至当前存储版本，返回迁移后的记录及是否发生迁移
+    fn migrate(mut self) -> (Self, bool) {
+        let from_version = self.schema_version;
+
+        // v0 -> v1：历史 invalid 状态迁移为 disabled
+        if self.schema_version < 1 {
+            if self.status == AccountStatus::Invalid {
+                self.status = AccountStatus::Disabled;
+            }
+            self.schema_version = 1;
+        }
+
+        let was_migrated = self.schema_version != from_version;
+        (self, was_migrated)
+    }
+
     fn into_account(self) -> Account {
         use crate::kiro::model::credentials::KiroCredentials;
 
@@ -872,23 +3445,31 @@ impl StoredAccount {
             client_secret: self.client_secret,
         };
 
-        let status = if self.status == AccountStatus::Invalid {
-            AccountStatus::Disabled
-        } else {
-            self.status
-        };
-
         Account {
             id: self.id,
             name: self.name,
             credentials,
-            status,
+            status: self.status,
             request_count: self.request_count,
             error_count: self.error_count,
-            last_used_at: None,
-            cooldown_until: None,
+            last_used_at: self.last_used_at,
+            cooldown_until: self.cooldown_until,
             exhausted_until: self.exhausted_until,
             created_at: self.created_at,
+            region: self.region,
+            warmup_latency_ms: None,
+            notes: self.notes,
+            request_timeout_secs: self.request_timeout_secs,
+            pinned: self.pinned,
+            display_name_override: self.display_name_override,
+            tags: self.tags,
+            proxy_override: self.proxy_override,
+            weight: self.weight,
+            pending_credentials: self.pending_credentials,
+            history: self.history,
+            latency_window: Default::default(),
+            circuit_state: self.circuit_state,
+            consecutive_rate_limit_errors: self.consecutive_rate_limit_errors,
         }
     }
 }
@@ -897,6 +3478,7 @@ impl StoredAccount {
 mod tests {
     use super::*;
     use crate::kiro::model::credentials::KiroCredentials;
+    use crate::pool::AccountBuilder;
     use chrono::{Duration, Utc};
 
     fn test_usage(available: f64) -> UsageLimits {
@@ -909,23 +3491,613 @@ mod tests {
             free_trial: None,
             user_email: None,
             subscription_type: None,
+            request_limits: None,
+            fetched_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_estimate_capacity() {
+        let pool = build_two_account_pool().await;
+        pool.add_account(Account::new("c", "C", KiroCredentials::default()))
+            .await
+            .unwrap();
+        {
+            let mut cache = pool.usage_cache.write().await;
+            let mut usage = test_usage(30.0);
+            usage.fetched_at = Some(Utc::now() - Duration::seconds(120));
+            cache.insert("a".to_string(), usage);
+            cache.insert("b".to_string(), test_usage(10.0));
+        }
+        for (model, credits) in [("m1", 1.0), ("m1", 1.0), ("m2", 4.0)] {
+            pool.add_request_log(RequestLog {
+                id: uuid::Uuid::new_v4().to_string(),
+                account_id: "a".to_string(),
+                account_name: "A".to_string(),
+                model: model.to_string(),
+                input_tokens: 0,
+                output_tokens: 0,
+                success: true,
+                error: None,
+                timestamp: Utc::now(),
+                duration_ms: 0,
+                was_filtered: false,
+                context_window_used_pct: None,
+                credits_used: Some(credits),
+                heartbeat_count: None,
+                session_id: None,
+            })
+            .await;
+        }
+
+        let capacity = pool.estimate_capacity().await;
+        assert_eq!(capacity.total_available_credits, 40.0);
+        assert_eq!(capacity.accounts_with_quota, 2);
+        assert_eq!(capacity.accounts_without_quota_data, 1);
+        assert_eq!(capacity.avg_credits_per_request, Some(2.0));
+        assert_eq!(capacity.estimated_requests_remaining, Some(20));
+        assert_eq!(capacity.per_model["m1"].estimated_requests_remaining, 40);
+        assert_eq!(capacity.per_model["m2"].estimated_requests_remaining, 10);
+        assert!(capacity.data_freshness_seconds >= 120);
+    }
+
+    #[test]
+    fn test_fairness_gini_bounds() {
+        let even = FairnessReport::from_counts(&[5, 5, 5, 5]);
+        assert!(even.gini_coefficient.abs() < 1e-9);
+        assert_eq!(even.max_account_pct, 25.0);
+        assert_eq!(even.min_account_pct, 25.0);
+
+        let mut concentrated = vec![0; 99];
+        concentrated.push(1000);
+        let report = FairnessReport::from_counts(&concentrated);
+        assert!((report.gini_coefficient - 0.99).abs() < 1e-9);
+        assert_eq!(report.max_account_pct, 100.0);
+        assert_eq!(report.min_account_pct, 0.0);
+
+        let empty = FairnessReport::from_counts(&[]);
+        assert_eq!(empty.gini_coefficient, 0.0);
+        assert_eq!(FairnessReport::from_counts(&[0, 0]), empty);
+    }
+
+    #[tokio::test]
+    async fn test_selection_fairness_reflects_request_counts() {
+        let pool = build_two_account_pool().await;
+        assert_eq!(pool.get_selection_fairness().await.gini_coefficient, 0.0);
+
+        pool.accounts
+            .write()
+            .await
+            .get_mut("a")
+            .unwrap()
+            .request_count = 9;
+        pool.accounts
+            .write()
+            .await
+            .get_mut("b")
+            .unwrap()
+            .request_count = 1;
+        let report = pool.get_selection_fairness().await;
+        assert!((report.gini_coefficient - 0.4).abs() < 1e-9);
+        assert_eq!(report.max_account_pct, 90.0);
+        assert_eq!(pool.estimate_capacity().await.distribution_fairness, report);
+    }
+
+    #[tokio::test]
+    async fn test_auto_throttle_lowers_rate_on_errors() {
+        let config = Config {
+            rps_limit: Some(10.0),
+            auto_throttle_enabled: true,
+            ..Config::default()
+        };
+        let pool = AccountPool::new(config, None);
+        assert!(pool.try_acquire_rate_limit().await);
+
+        for _ in 0..AUTO_THROTTLE_MIN_SAMPLES {
+            pool.add_request_log(RequestLog {
+                id: uuid::Uuid::new_v4().to_string(),
+                account_id: "a".to_string(),
+                account_name: "A".to_string(),
+                model: "m".to_string(),
+                input_tokens: 0,
+                output_tokens: 0,
+                success: false,
+                error: Some("boom".to_string()),
+                timestamp: Utc::now(),
+                duration_ms: 0,
+                was_filtered: false,
+                context_window_used_pct: None,
+                credits_used: None,
+                heartbeat_count: None,
+                session_id: None,
+            })
+            .await;
+        }
+        assert_eq!(pool.current_rps().await, Some(9.0));
+
+        // 调整间隔内不会再次调整
+        pool.auto_throttle().await;
+        assert_eq!(pool.current_rps().await, Some(9.0));
+    }
+
+    #[tokio::test]
+    async fn test_apply_rate_limit_updates_bucket() {
+        let pool = AccountPool::new(Config::default(), None);
+        assert_eq!(pool.current_rps().await, None);
+
+        let settings = RateLimitSettings {
+            rps_limit: Some(2.0),
+            min_rps: 1.0,
+            max_rps: None,
+        };
+        pool.apply_rate_limit(settings).await;
+        assert_eq!(pool.current_rps().await, Some(2.0));
+        assert!(pool.try_acquire_rate_limit().await);
+        assert!(pool.try_acquire_rate_limit().await);
+        assert!(!pool.try_acquire_rate_limit().await);
+
+        pool.apply_rate_limit(RateLimitSettings {
+            rps_limit: Some(5.0),
+            ..settings
+        })
+        .await;
+        assert_eq!(pool.current_rps().await, Some(5.0));
+
+        pool.apply_rate_limit(RateLimitSettings {
+            rps_limit: None,
+            ..settings
+        })
+        .await;
+        assert_eq!(pool.current_rps().await, None);
+        assert!(pool.try_acquire_rate_limit().await);
+    }
+
+    async fn build_two_account_pool() -> AccountPool {
+        let pool = AccountPool::new(Config::default(), None);
+
+        let acc1 = AccountBuilder::new("a", "A", KiroCredentials::default())
+            .created_at(Utc::now() - Duration::minutes(2))
+            .build();
+        let acc2 = AccountBuilder::new("b", "B", KiroCredentials::default())
+            .created_at(Utc::now() - Duration::minutes(1))
+            .build();
+
+        pool.add_account(acc1).await.unwrap();
+        pool.add_account(acc2).await.unwrap();
+        pool.set_strategy(SelectionStrategy::SequentialExhaust)
+            .await;
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_lowest_latency_without_data_round_robins() {
+        let pool = build_two_account_pool().await;
+        pool.set_strategy(SelectionStrategy::LowestLatency).await;
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for _ in 0..4 {
+            *counts
+                .entry(pool.select_account().await.unwrap().id)
+                .or_default() += 1;
+        }
+        assert_eq!(counts.get("a"), Some(&2));
+        assert_eq!(counts.get("b"), Some(&2));
+    }
+
+    #[tokio::test]
+    async fn test_lowest_latency_prefers_faster_account() {
+        let pool = build_two_account_pool().await;
+        pool.set_strategy(SelectionStrategy::LowestLatency).await;
+        for ms in [900, 1100, 1000] {
+            pool.record_first_token_latency("a", ms).await;
+        }
+        for ms in [300, 5000, 250] {
+            pool.record_first_token_latency("b", ms).await;
+        }
+
+        for _ in 0..3 {
+            assert_eq!(pool.select_account().await.unwrap().id, "b");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_weighted_round_robin_follows_weights() {
+        let pool = build_two_account_pool().await;
+        let off = AccountBuilder::new("c", "C", KiroCredentials::default())
+            .weight(0)
+            .build();
+        pool.add_account(off).await.unwrap();
+        assert!(pool
+            .update_account(
+                "a",
+                AccountUpdate {
+                    weight: Some(3),
+                    ..Default::default()
+                }
+            )
+            .await
+            .unwrap());
+        assert!(!pool
+            .update_account(
+                "missing",
+                AccountUpdate {
+                    weight: Some(2),
+                    ..Default::default()
+                }
+            )
+            .await
+            .unwrap());
+        pool.set_strategy(SelectionStrategy::WeightedRoundRobin)
+            .await;
+
+        let mut picks = Vec::new();
+        for _ in 0..8 {
+            picks.push(pool.select_account().await.unwrap().id);
+        }
+        assert_eq!(picks, ["a", "a", "a", "b", "a", "a", "a", "b"]);
+
+        // 权重为 0 的账号在任何策略下都不参与选择
+        pool.set_strategy(SelectionStrategy::RoundRobin).await;
+        for _ in 0..4 {
+            assert_ne!(pool.select_account().await.unwrap().id, "c");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_least_quota_selects_highest_available() {
+        let pool = build_two_account_pool().await;
+        pool.set_strategy(SelectionStrategy::LeastQuota).await;
+        let usage = |available: f64| UsageLimits {
+            resource_type: "CREDIT".to_string(),
+            usage_limit: 100.0,
+            current_usage: 100.0 - available,
+            available,
+            next_reset: None,
+            free_trial: None,
+            user_email: None,
+            subscription_type: None,
+            request_limits: None,
+            fetched_at: None,
+        };
+        {
+            let mut cache = pool.usage_cache.write().await;
+            cache.insert("a".to_string(), usage(20.0));
+            cache.insert("b".to_string(), usage(80.0));
+        }
+        for _ in 0..3 {
+            assert_eq!(pool.select_account().await.unwrap().id, "b");
+        }
+
+        // 没有配额缓存的账号优先
+        let fresh = AccountBuilder::new("c", "C", KiroCredentials::default()).build();
+        pool.add_account(fresh).await.unwrap();
+        assert_eq!(pool.select_account().await.unwrap().id, "c");
+    }
+
+    #[tokio::test]
+    async fn test_stored_account_round_trips_weight() {
+        let account = AccountBuilder::new("w", "W", KiroCredentials::default())
+            .weight(5)
+            .build();
+        let json = serde_json::to_string(&StoredAccount::from_account(&account)).unwrap();
+        let stored: StoredAccount = serde_json::from_str(&json).unwrap();
+        assert_eq!(stored.into_account().weight, 5);
+
+        // 旧数据缺少 weight 字段时默认为 1
+        let mut legacy: serde_json::Value = serde_json::from_str(&json).unwrap();
+        legacy.as_object_mut().unwrap().remove("weight");
+        let stored: StoredAccount = serde_json::from_value(legacy).unwrap();
+        assert_eq!(stored.into_account().weight, 1);
+    }
+
+    #[tokio::test]
+    async fn test_selection_filters_by_tags() {
+        let pool = build_two_account_pool().await;
+        pool.set_account_tags(
+            "a",
+            std::collections::BTreeMap::from([
+                ("tier".to_string(), "high".to_string()),
+                ("opus".to_string(), String::new()),
+            ]),
+        )
+        .await
+        .unwrap();
+        pool.set_account_tags(
+            "b",
+            std::collections::BTreeMap::from([("tier".to_string(), "low".to_string())]),
+        )
+        .await
+        .unwrap();
+
+        for strategy in [
+            SelectionStrategy::RoundRobin,
+            SelectionStrategy::SequentialExhaust,
+        ] {
+            pool.set_strategy(strategy).await;
+            for _ in 0..3 {
+                let selected = pool
+                    .select_account_for_region(None, &["tier=high", "opus"])
+                    .await
+                    .unwrap();
+                assert_eq!(selected.id, "a");
+                let selected = pool
+                    .select_account_for_region(None, &["tier=low"])
+                    .await
+                    .unwrap();
+                assert_eq!(selected.id, "b");
+            }
+            assert!(pool
+                .select_account_for_region(None, &["tier=low", "opus"])
+                .await
+                .is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_blocks_failing_account() {
+        let config = Config {
+            circuit_breaker: crate::model::config::CircuitBreakerConfig {
+                failure_threshold: 1,
+                success_threshold: 1,
+                half_open_timeout_secs: 0,
+            },
+            ..Config::default()
+        };
+        let pool = AccountPool::new(config, None);
+        pool.add_account(AccountBuilder::new("a", "A", KiroCredentials::default()).build())
+            .await
+            .unwrap();
+
+        pool.record_error("a", false).await;
+        pool.record_error("a", false).await;
+        let circuit = pool.get_account("a").await.unwrap().circuit_state;
+        assert_eq!(circuit.status, CircuitStatus::Open);
+
+        // 等待时间为 0：立即放行探测请求，账号进入半开状态
+        assert_eq!(pool.select_account().await.unwrap().id, "a");
+        let circuit = pool.get_account("a").await.unwrap().circuit_state;
+        assert_eq!(circuit.status, CircuitStatus::HalfOpen);
+
+        pool.add_request_log(RequestLog {
+            id: uuid::Uuid::new_v4().to_string(),
+            account_id: "a".to_string(),
+            account_name: "A".to_string(),
+            model: "m".to_string(),
+            input_tokens: 0,
+            output_tokens: 0,
+            success: true,
+            error: None,
+            timestamp: Utc::now(),
+            duration_ms: 0,
+            was_filtered: false,
+            context_window_used_pct: None,
+            credits_used: None,
+            heartbeat_count: None,
+            session_id: None,
+        })
+        .await;
+        let circuit = pool.get_account("a").await.unwrap().circuit_state;
+        assert_eq!(circuit.status, CircuitStatus::Closed);
+
+        // 熔断状态随账号持久化
+        pool.record_error("a", false).await;
+        pool.record_error("a", false).await;
+        let account = pool.get_account("a").await.unwrap();
+        let json = serde_json::to_string(&StoredAccount::from_account(&account)).unwrap();
+        let stored: StoredAccount = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            stored.into_account().circuit_state.status,
+            CircuitStatus::Open
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pinned_account_is_skipped_by_selection() {
+        let pool = build_two_account_pool().await;
+        assert!(pool.pin_account("a").await);
+        assert_eq!(pool.select_account().await.unwrap().id, "b");
+
+        pool.set_strategy(SelectionStrategy::RoundRobin).await;
+        for _ in 0..3 {
+            assert_eq!(pool.select_account().await.unwrap().id, "b");
         }
+
+        assert!(pool.pin_account("b").await);
+        assert!(pool.select_account().await.is_none());
+
+        assert!(pool.unpin_account("a").await);
+        assert_eq!(pool.select_account().await.unwrap().id, "a");
+        assert!(!pool.pin_account("missing").await);
+    }
+
+    #[tokio::test]
+    async fn test_display_name_override_used_in_logs() {
+        let pool = build_two_account_pool().await;
+        assert!(pool
+            .update_account(
+                "a",
+                AccountUpdate {
+                    display_name_override: Some("account-1".to_string()),
+                    ..Default::default()
+                }
+            )
+            .await
+            .unwrap());
+
+        let selected = pool.select_account().await.unwrap();
+        assert_eq!(selected.id, "a");
+        pool.add_request_log(RequestLog {
+            id: uuid::Uuid::new_v4().to_string(),
+            account_id: selected.id.clone(),
+            account_name: selected.log_name().to_string(),
+            model: "m".to_string(),
+            input_tokens: 0,
+            output_tokens: 0,
+            success: true,
+            error: None,
+            timestamp: Utc::now(),
+            duration_ms: 0,
+            was_filtered: false,
+            context_window_used_pct: None,
+            credits_used: None,
+            heartbeat_count: None,
+            session_id: None,
+        })
+        .await;
+        assert_eq!(pool.get_recent_logs(1).await[0].account_name, "account-1");
+
+        // 空字符串清除覆盖，恢复使用账号名称
+        pool.update_account(
+            "a",
+            AccountUpdate {
+                display_name_override: Some(String::new()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(pool.select_account().await.unwrap().log_name(), "A");
+    }
+
+    #[tokio::test]
+    async fn test_render_metrics() {
+        let pool = build_two_account_pool().await;
+        pool.add_request_log(RequestLog {
+            id: uuid::Uuid::new_v4().to_string(),
+            account_id: "a".to_string(),
+            account_name: "A".to_string(),
+            model: "claude-sonnet-4".to_string(),
+            input_tokens: 120,
+            output_tokens: 30,
+            success: true,
+            error: None,
+            timestamp: Utc::now(),
+            duration_ms: 1500,
+            was_filtered: false,
+            context_window_used_pct: None,
+            credits_used: None,
+            heartbeat_count: None,
+            session_id: None,
+        })
+        .await;
+        pool.record_error("b", true).await;
+        pool.disable_account("b").await;
+
+        let text = pool.render_metrics().await.unwrap();
+        assert!(text.contains(
+            r#"kiro_requests_total{account_id="a",model="claude-sonnet-4",success="true"} 1"#
+        ));
+        assert!(text.contains(r#"kiro_tokens_total{account_id="a",direction="input"} 120"#));
+        assert!(text.contains(r#"kiro_tokens_total{account_id="a",direction="output"} 30"#));
+        assert!(text.contains(
+            r#"kiro_request_duration_seconds_sum{account_id="a",model="claude-sonnet-4"} 1.5"#
+        ));
+        assert!(text.contains(r#"kiro_account_errors_total{account_id="b",rate_limited="true"} 1"#));
+        assert!(text.contains(r#"kiro_accounts_total{status="active"} 1"#));
+        assert!(text.contains(r#"kiro_accounts_total{status="disabled"} 1"#));
+        assert!(text.contains(r#"kiro_accounts_total{status="exhausted"} 0"#));
+    }
+
+    #[tokio::test]
+    async fn test_swap_credentials_replaces_manager_and_provider() {
+        let pool = build_two_account_pool().await;
+        let old_provider = pool.providers.read().await["a"].clone();
+        let credentials = KiroCredentials {
+            access_token: Some("at-new".to_string()),
+            refresh_token: Some("rt-new".to_string()),
+            ..Default::default()
+        };
+        let tm = TokenManager::new(Config::default(), credentials.clone(), None);
+        assert!(pool.swap_credentials("a", tm).await.unwrap());
+
+        let account = pool.get_account("a").await.unwrap();
+        assert_eq!(account.credentials.access_token.as_deref(), Some("at-new"));
+        assert!(!Arc::ptr_eq(
+            &old_provider,
+            &pool.providers.read().await["a"]
+        ));
+        assert_eq!(
+            pool.get_account_by_refresh_token_hash(refresh_token_hash("rt-new"))
+                .await
+                .as_deref(),
+            Some("a")
+        );
+
+        // 新 refreshToken 已属于其他账号时拒绝，且不改动现有凭证
+        let tm = TokenManager::new(Config::default(), credentials, None);
+        assert!(pool.swap_credentials("b", tm).await.is_err());
+        assert!(pool
+            .get_account("b")
+            .await
+            .unwrap()
+            .credentials
+            .refresh_token
+            .is_none());
+
+        let tm = TokenManager::new(Config::default(), KiroCredentials::default(), None);
+        assert!(!pool.swap_credentials("missing", tm).await.unwrap());
+        assert!(!pool
+            .rotate_credentials("missing", KiroCredentials::default())
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_export_for_migration() {
+        let pool = AccountPool::new(Config::default(), None);
+        let credentials = KiroCredentials {
+            refresh_token: Some("rt-a".to_string()),
+            auth_method: Some("idc".to_string()),
+            client_id: Some("cid".to_string()),
+            client_secret: Some("secret".to_string()),
+            ..Default::default()
+        };
+        pool.add_account(Account::new("a", "Alice", credentials))
+            .await
+            .unwrap();
+        pool.add_account(Account::new("b", "NoToken", KiroCredentials::default()))
+            .await
+            .unwrap();
+
+        let json = pool.export_for_migration("kiro-json").await.unwrap();
+        let raw: Vec<KiroRawCredentials> = serde_json::from_str(&json).unwrap();
+        assert_eq!(raw.len(), 1);
+        assert_eq!(raw[0].refresh_token, "rt-a");
+        assert_eq!(raw[0].client_secret.as_deref(), Some("secret"));
+        assert_eq!(raw[0].clone().into_account().name, "Alice");
+
+        let csv = pool.export_for_migration("csv").await.unwrap();
+        assert_eq!(
+            csv,
+            "name,refresh_token,auth_method,client_id\nAlice,rt-a,idc,cid\n"
+        );
+
+        assert!(pool.export_for_migration("yaml").await.is_err());
     }
 
-    async fn build_two_account_pool() -> AccountPool {
+    #[tokio::test]
+    async fn test_get_token_expiry() {
         let pool = AccountPool::new(Config::default(), None);
-
-        let mut acc1 = Account::new("a", "A", KiroCredentials::default());
-        acc1.created_at = Utc::now() - Duration::minutes(2);
-
-        let mut acc2 = Account::new("b", "B", KiroCredentials::default());
-        acc2.created_at = Utc::now() - Duration::minutes(1);
-
-        pool.add_account(acc1).await.unwrap();
-        pool.add_account(acc2).await.unwrap();
-        pool.set_strategy(SelectionStrategy::SequentialExhaust)
-            .await;
-        pool
+        let expires_at = Utc::now() + Duration::hours(1);
+        let credentials = KiroCredentials {
+            expires_at: Some(expires_at.to_rfc3339()),
+            ..Default::default()
+        };
+        pool.add_account(Account::new("a", "A", credentials))
+            .await
+            .unwrap();
+        pool.add_account(Account::new("b", "B", KiroCredentials::default()))
+            .await
+            .unwrap();
+
+        let expiry = pool.get_token_expiry("a").await.unwrap().unwrap();
+        assert_eq!(expiry.timestamp(), expires_at.timestamp());
+        assert!(!pool.token_refresh_needed(Some(expiry)));
+        assert!(pool.token_refresh_needed(Some(Utc::now())));
+
+        assert_eq!(pool.get_token_expiry("b").await.unwrap(), None);
+        assert!(pool.token_refresh_needed(None));
+        assert!(pool.get_token_expiry("missing").await.is_err());
     }
 
     #[tokio::test]
@@ -982,16 +4154,1028 @@ mod tests {
         assert_eq!(selected.id, "b");
     }
 
+    #[tokio::test]
+    async fn test_prune_exhausted_accounts() {
+        let pool = build_two_account_pool().await;
+        pool.mark_exhausted("a", Some(Utc::now() - Duration::days(40)))
+            .await;
+        pool.mark_exhausted("b", Some(Utc::now() - Duration::days(1)))
+            .await;
+
+        pool.assign_to_group("g", None, &["a".to_string(), "b".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(pool.prune_exhausted_accounts(30).await, 1);
+
+        let ids: Vec<String> = pool
+            .list_accounts()
+            .await
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+        assert_eq!(ids, vec!["b".to_string()]);
+        assert!(!pool.providers.read().await.contains_key("a"));
+        // 被清理的账号同时移出分组
+        let groups = pool.list_groups().await;
+        assert_eq!(groups[0].account_ids, HashSet::from(["b".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn test_readding_account_keeps_refresh_token_hash() {
+        let pool = AccountPool::new(Config::default(), None);
+        let credentials = KiroCredentials {
+            refresh_token: Some("rt".to_string()),
+            ..Default::default()
+        };
+        pool.add_account(Account::new("a", "A", credentials.clone()))
+            .await
+            .unwrap();
+        // 同一 id、同一 refreshToken 再次写入（如更新账号信息）
+        pool.add_account(Account::new("a", "A2", credentials))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            pool.get_account_by_refresh_token_hash(refresh_token_hash("rt"))
+                .await
+                .as_deref(),
+            Some("a")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_from_json_string_single_object() {
+        let pool = AccountPool::new(Config::default(), None);
+        let json = r#"{"email": "a@example.com", "refreshToken": "rt", "clientId": "c", "clientSecret": "s", "region": "eu-west-1"}"#;
+
+        assert_eq!(pool.import_from_json_string(json, false).await.unwrap(), 1);
+
+        let accounts = pool.list_accounts().await;
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].name, "a@example.com");
+        assert_eq!(accounts[0].credentials.auth_method.as_deref(), Some("idc"));
+        assert_eq!(accounts[0].region.as_deref(), Some("eu-west-1"));
+    }
+
+    #[tokio::test]
+    async fn test_import_with_proxy_override() {
+        let global = ProxyConfig::new("http://global:8080");
+        let pool = AccountPool::new(Config::default(), Some(global.clone()));
+        let json = r#"[
+            {"name": "eu", "refreshToken": "rt1", "proxyUrl": "socks5://eu:1080", "proxyUsername": "u", "proxyPassword": "p"},
+            {"name": "plain", "refreshToken": "rt2"}
+        ]"#;
+        assert_eq!(pool.import_from_json_string(json, false).await.unwrap(), 2);
+
+        let accounts = pool.list_accounts().await;
+        let eu = accounts.iter().find(|a| a.name == "eu").unwrap();
+        let plain = accounts.iter().find(|a| a.name == "plain").unwrap();
+        assert_eq!(
+            eu.proxy_override,
+            Some(ProxyConfig::new("socks5://eu:1080").with_auth("u", "p"))
+        );
+        assert_eq!(pool.proxy_for(eu).unwrap().url, "socks5://eu:1080");
+        assert_eq!(pool.proxy_for(plain), Some(global));
+
+        // 持久化后代理配置保留，日志输出时密码脱敏
+        let json = serde_json::to_string(&StoredAccount::from_account(eu)).unwrap();
+        let stored: StoredAccount = serde_json::from_str(&json).unwrap();
+        assert_eq!(stored.into_account().proxy_override, eu.proxy_override);
+        assert_eq!(eu.to_redacted_json()["proxy_override"]["password"], "***");
+    }
+
+    #[tokio::test]
+    async fn test_selected_account_carries_request_timeout() {
+        let pool = AccountPool::new(Config::default(), None);
+        let json = r#"{"name": "slow", "refreshToken": "rt", "requestTimeoutSecs": 30}"#;
+        pool.import_from_json_string(json, false).await.unwrap();
+
+        let selected = pool.select_account().await.unwrap();
+        assert_eq!(selected.request_timeout_secs, Some(30));
+    }
+
+    #[tokio::test]
+    async fn test_import_from_json_string_array() {
+        let pool = AccountPool::new(Config::default(), None);
+        let json = r#"[
+            {"label": "one", "refreshToken": "rt1"},
+            {"name": "two", "refresh_token": "rt2", "auth_method": "social"}
+        ]"#;
+
+        assert_eq!(pool.import_from_json_string(json, false).await.unwrap(), 2);
+
+        let mut names: Vec<String> = pool
+            .list_accounts()
+            .await
+            .into_iter()
+            .map(|a| a.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["one", "two"]);
+
+        // 重复的 refreshToken 不会再次导入
+        let dup = r#"[{"refreshToken": "rt1"}, {"refreshToken": "rt3"}]"#;
+        assert_eq!(pool.import_from_json_string(dup, false).await.unwrap(), 1);
+
+        // 删除账号后其 refreshToken 可重新导入
+        let rt1_id = pool
+            .get_account_by_refresh_token_hash(refresh_token_hash("rt1"))
+            .await
+            .unwrap();
+        pool.remove_account(&rt1_id).await;
+        assert!(pool
+            .get_account_by_refresh_token_hash(refresh_token_hash("rt1"))
+            .await
+            .is_none());
+        assert_eq!(pool.import_from_json_string(dup, false).await.unwrap(), 1);
+
+        assert!(pool.import_from_json_string("{", false).await.is_err());
+        assert!(pool
+            .import_from_json_string(r#"{"label": "x"}"#, false)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_merge_pools_dedups_by_refresh_token() {
+        let pool = AccountPool::new(Config::default(), None);
+        pool.import_from_json_string(r#"[{"refreshToken": "rt1"}]"#, false)
+            .await
+            .unwrap();
+        let existing_id = pool.list_accounts().await[0].id.clone();
+
+        let other = AccountPool::new(Config::default(), None);
+        let mut dup = Account::new("dup", "dup", KiroCredentials::default());
+        dup.credentials.refresh_token = Some("rt1".to_string());
+        let mut clash = Account::new(existing_id.clone(), "clash", KiroCredentials::default());
+        clash.credentials.refresh_token = Some("rt2".to_string());
+        let no_token = Account::new("c", "C", KiroCredentials::default());
+        for account in [dup, clash, no_token] {
+            other.add_account_internal(account).await.unwrap();
+        }
+
+        assert_eq!(pool.merge_pools(other).await.unwrap(), (2, 1));
+        assert_eq!(
+            pool.get_account_by_refresh_token_hash(refresh_token_hash("rt2"))
+                .await
+                .as_deref(),
+            pool.list_accounts()
+                .await
+                .iter()
+                .find(|a| a.name == "clash")
+                .map(|a| a.id.as_str())
+        );
+
+        let accounts = pool.list_accounts().await;
+        assert_eq!(accounts.len(), 3);
+        let clash = accounts.iter().find(|a| a.name == "clash").unwrap();
+        assert_ne!(clash.id, existing_id);
+        assert!(pool.providers.read().await.contains_key(&clash.id));
+    }
+
+    #[tokio::test]
+    async fn test_request_log_limits_from_config() {
+        let dir = std::env::temp_dir().join(format!("kiro-log-count-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = Config {
+            max_request_logs: 3,
+            ..Config::default()
+        };
+        let pool = AccountPool::with_data_dir(config.clone(), None, dir.clone());
+        let logs: Vec<RequestLog> = (0..5)
+            .map(|i| RequestLog {
+                id: format!("log-{}", i),
+                account_id: "a".to_string(),
+                account_name: "A".to_string(),
+                model: "claude-sonnet-4".to_string(),
+                input_tokens: i,
+                output_tokens: 0,
+                success: true,
+                error: None,
+                timestamp: Utc::now(),
+                duration_ms: 0,
+                was_filtered: false,
+                context_window_used_pct: None,
+                credits_used: None,
+                heartbeat_count: None,
+                session_id: None,
+            })
+            .collect();
+        std::fs::write(dir.join(LOGS_FILE), serde_json::to_string(&logs).unwrap()).unwrap();
+
+        assert_eq!(pool.load_logs_from_file().await.unwrap(), 3);
+        let count = pool.request_log_count().await;
+        assert_eq!(count.count, 3);
+        assert_eq!(count.max_logs, 3);
+        assert_eq!(count.max_log_disk_mb, None);
+        assert!(count.file_size_bytes.unwrap() > 0);
+
+        let detached = AccountPool::new(config, None);
+        assert!(detached.request_log_count().await.file_size_bytes.is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_request_logs_file_respects_max_log_disk_mb() {
+        let dir = std::env::temp_dir().join(format!("kiro-logs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = Config {
+            max_log_disk_mb: Some(1),
+            ..Config::default()
+        };
+        let pool = AccountPool::with_data_dir(config, None, dir.clone());
+
+        // 每条约 100KB，共约 1.5MB
+        for _ in 0..15 {
+            pool.add_request_log(RequestLog {
+                id: uuid::Uuid::new_v4().to_string(),
+                account_id: "a".to_string(),
+                account_name: "A".to_string(),
+                model: "claude-sonnet-4".to_string(),
+                input_tokens: 0,
+                output_tokens: 0,
+                success: false,
+                error: Some("x".repeat(100_000)),
+                timestamp: Utc::now(),
+                duration_ms: 0,
+                was_filtered: false,
+                context_window_used_pct: None,
+                credits_used: None,
+                heartbeat_count: None,
+                session_id: None,
+            })
+            .await;
+        }
+
+        // 等待后台保存任务全部完成：待执行标记清除后，正在写入的任务仍持有写入锁
+        while pool.log_save_pending.load(Ordering::Acquire) {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        drop(pool.log_writer.lock().await);
+
+        let size = std::fs::metadata(dir.join(LOGS_FILE)).unwrap().len();
+        assert!(size <= 1_000_000, "日志文件大小 {} 超过上限", size);
+        // 循环裁剪直到文件能够容纳，内存与文件中的记录一致
+        let in_memory = pool.get_recent_logs(100).await.len();
+        assert!(in_memory < 15);
+        let on_disk: Vec<RequestLog> =
+            serde_json::from_str(&std::fs::read_to_string(dir.join(LOGS_FILE)).unwrap()).unwrap();
+        assert_eq!(on_disk.len(), in_memory);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_archive_logs_moves_old_entries_to_gzip() {
+        use std::io::Read;
+
+        let dir = std::env::temp_dir().join(format!("kiro-archive-{}", uuid::Uuid::new_v4()));
+        let pool = AccountPool::with_data_dir(Config::default(), None, dir.clone());
+        {
+            let mut logger = pool.request_logger.write().await;
+            for days_ago in [30, 10, 1, 0] {
+                logger.add(RequestLog {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    account_id: "a".to_string(),
+                    account_name: "A".to_string(),
+                    model: "claude-sonnet-4".to_string(),
+                    input_tokens: 0,
+                    output_tokens: 0,
+                    success: true,
+                    error: None,
+                    timestamp: Utc::now() - chrono::Duration::days(days_ago),
+                    duration_ms: 0,
+                    was_filtered: false,
+                    context_window_used_pct: None,
+                    credits_used: None,
+                    heartbeat_count: None,
+                    session_id: None,
+                });
+            }
+        }
+
+        let result = pool.archive_logs(7).await.unwrap();
+        assert_eq!(result.archived, 2);
+        assert_eq!(result.active_remaining, 2);
+        let file_name = result.archive_file.unwrap();
+
+        let mut json = String::new();
+        flate2::read::GzDecoder::new(std::fs::File::open(dir.join(&file_name)).unwrap())
+            .read_to_string(&mut json)
+            .unwrap();
+        let archived: Vec<RequestLog> = serde_json::from_str(&json).unwrap();
+        assert_eq!(archived.len(), 2);
+        assert_eq!(pool.get_recent_logs(100).await.len(), 2);
+
+        // 再次归档无记录可移除，不生成新文件
+        let again = pool.archive_logs(7).await.unwrap();
+        assert_eq!(again.archived, 0);
+        assert!(again.archive_file.is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_validate_all_reports_mixed_results_without_state_change() {
+        let pool = build_two_account_pool().await;
+        {
+            let mut accounts = pool.accounts.write().await;
+            accounts.get_mut("a").unwrap().credentials.refresh_token = Some("good".to_string());
+            accounts.get_mut("b").unwrap().credentials.refresh_token = Some("bad".to_string());
+        }
+
+        let results = pool
+            .validate_all_with(1, |credentials, _| async move {
+                match credentials.refresh_token.as_deref() {
+                    Some("good") => Ok(()),
+                    _ => Err(anyhow::anyhow!("refreshToken 已失效")),
+                }
+            })
+            .await;
+
+        assert_eq!(results.len(), 2);
+        assert!(results[0].valid && results[0].error.is_none());
+        assert!(!results[1].valid);
+        assert_eq!(results[1].error.as_deref(), Some("refreshToken 已失效"));
+        assert!(pool
+            .list_accounts()
+            .await
+            .iter()
+            .all(|a| a.status == AccountStatus::Active));
+    }
+
+    #[tokio::test]
+    async fn test_set_account_notes() {
+        let pool = build_two_account_pool().await;
+
+        assert!(pool
+            .set_account_notes("a", Some("2025-09-01 到期".to_string()))
+            .await
+            .unwrap());
+        assert_eq!(
+            pool.get_account("a").await.unwrap().notes.as_deref(),
+            Some("2025-09-01 到期")
+        );
+
+        assert!(pool
+            .set_account_notes("a", Some("x".repeat(MAX_NOTES_CHARS + 1)))
+            .await
+            .is_err());
+        assert!(!pool.set_account_notes("missing", None).await.unwrap());
+
+        assert!(pool.set_account_notes("a", None).await.unwrap());
+        assert!(pool.get_account("a").await.unwrap().notes.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_account_validates_before_applying() {
+        let pool = build_two_account_pool().await;
+
+        let invalid = AccountUpdate {
+            name: Some("新名称".to_string()),
+            notes: Some("x".repeat(MAX_NOTES_CHARS + 1)),
+            weight: Some(5),
+            ..Default::default()
+        };
+        assert!(pool.update_account("a", invalid).await.is_err());
+        let account = pool.get_account("a").await.unwrap();
+        assert_ne!(account.name, "新名称");
+        assert_ne!(account.weight, 5);
+
+        let valid = AccountUpdate {
+            name: Some(" 新名称 ".to_string()),
+            notes: Some("备用".to_string()),
+            weight: Some(5),
+            ..Default::default()
+        };
+        assert!(pool.update_account("a", valid).await.unwrap());
+        let account = pool.get_account("a").await.unwrap();
+        assert_eq!(account.name, "新名称");
+        assert_eq!(account.notes.as_deref(), Some("备用"));
+        assert_eq!(account.weight, 5);
+
+        let clear = AccountUpdate {
+            notes: Some(String::new()),
+            ..Default::default()
+        };
+        assert!(pool.update_account("a", clear).await.unwrap());
+        assert!(pool.get_account("a").await.unwrap().notes.is_none());
+        assert!(!pool
+            .update_account("missing", AccountUpdate::default())
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_rename_and_import_notes() {
+        let pool = build_two_account_pool().await;
+        assert!(pool
+            .update_account(
+                "a",
+                AccountUpdate {
+                    name: Some(" 主账号 ".to_string()),
+                    ..Default::default()
+                }
+            )
+            .await
+            .unwrap());
+        assert_eq!(pool.get_account("a").await.unwrap().name, "主账号");
+        assert!(pool
+            .update_account(
+                "a",
+                AccountUpdate {
+                    name: Some("  ".to_string()),
+                    ..Default::default()
+                }
+            )
+            .await
+            .is_err());
+
+        let json = r#"{"refreshToken": "r-notes", "name": "N", "description": "勿用于批处理"}"#;
+        assert_eq!(pool.import_from_json_string(json, false).await.unwrap(), 1);
+        let imported = pool
+            .list_accounts()
+            .await
+            .into_iter()
+            .find(|a| a.name == "N")
+            .unwrap();
+        assert_eq!(imported.notes.as_deref(), Some("勿用于批处理"));
+        let exported = KiroRawCredentials::from_account(&imported).unwrap();
+        assert_eq!(exported.notes.as_deref(), Some("勿用于批处理"));
+
+        let too_long = serde_json::json!({
+            "refreshToken": "r-long",
+            "notes": "x".repeat(MAX_NOTES_CHARS + 1),
+        });
+        assert!(pool
+            .import_from_json_string(&too_long.to_string(), false)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_simulate_selection_does_not_touch_real_accounts() {
+        let pool = build_two_account_pool().await;
+
+        let counts = pool
+            .simulate_selection(SelectionStrategy::RoundRobin, 10)
+            .await;
+        assert_eq!(counts.get("a"), Some(&5));
+        assert_eq!(counts.get("b"), Some(&5));
+
+        let counts = pool
+            .simulate_selection(SelectionStrategy::SequentialExhaust, 4)
+            .await;
+        assert_eq!(counts.get("a"), Some(&4));
+
+        for account in pool.list_accounts().await {
+            assert_eq!(account.request_count, 0);
+            assert!(account.last_used_at.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_exhausted_until() {
+        let pool = build_two_account_pool().await;
+        pool.mark_exhausted("a", None).await;
+
+        let until = Utc::now() + Duration::days(3);
+        assert!(pool.set_exhausted_until("a", Some(until)).await.unwrap());
+        let account = pool
+            .snapshot()
+            .await
+            .accounts
+            .into_iter()
+            .find(|a| a.id == "a")
+            .unwrap();
+        assert_eq!(account.exhausted_until, Some(until));
+        assert_eq!(account.status, AccountStatus::Exhausted);
+
+        assert!(pool.set_exhausted_until("a", None).await.unwrap());
+        let account = pool
+            .snapshot()
+            .await
+            .accounts
+            .into_iter()
+            .find(|a| a.id == "a")
+            .unwrap();
+        assert_eq!(account.exhausted_until, None);
+        assert_eq!(account.status, AccountStatus::Active);
+
+        assert!(!pool.set_exhausted_until("missing", None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_reports_failures_for_available_accounts() {
+        let pool = build_two_account_pool().await;
+        pool.disable_account("b").await;
+
+        // 测试账号没有 refreshToken，刷新会在发起网络请求前失败
+        let results = pool.warm_up().await;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].account_id, "a");
+        assert!(!results[0].success);
+        assert!(results[0].error.is_some());
+
+        let account = pool
+            .snapshot()
+            .await
+            .accounts
+            .into_iter()
+            .find(|a| a.id == "a")
+            .unwrap();
+        assert_eq!(account.warmup_latency_ms, None);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_captures_pool_state() {
+        let pool = build_two_account_pool().await;
+        {
+            let mut cache = pool.usage_cache.write().await;
+            cache.insert("a".to_string(), test_usage(5.0));
+        }
+
+        let snapshot = pool.snapshot().await;
+        assert_eq!(snapshot.accounts.len(), 2);
+        assert_eq!(snapshot.strategy, SelectionStrategy::SequentialExhaust);
+        assert_eq!(snapshot.usage_cache.len(), 1);
+        assert!(snapshot.captured_at <= Utc::now());
+    }
+
+    #[tokio::test]
+    async fn test_import_snapshot_restores_missing_accounts() {
+        let pool = build_two_account_pool().await;
+        let mut snapshot = pool.snapshot().await;
+        snapshot
+            .usage_cache
+            .insert("c".to_string(), test_usage(7.0));
+
+        let acc3 = AccountBuilder::new(
+            "c",
+            "C",
+            KiroCredentials {
+                refresh_token: Some("rt-c".to_string()),
+                ..Default::default()
+            },
+        )
+        .request_count(12)
+        .status(AccountStatus::Disabled)
+        .build();
+        snapshot.accounts.push(acc3);
+
+        let target = AccountPool::new(Config::default(), None);
+        target
+            .add_account(Account::new("a", "A", KiroCredentials::default()))
+            .await
+            .unwrap();
+        // 经 JSON 往返后 c 的凭证仍在；b 在原账号池中本就没有凭证，无法恢复
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let mut snapshot: PoolSnapshot = serde_json::from_str(&json).unwrap();
+        let result = target.import_snapshot(snapshot.clone()).await.unwrap();
+        assert_eq!(result.added, 1);
+        assert_eq!(result.skipped_existing, 1);
+        assert_eq!(result.failed, 1);
+        assert!(result.errors[0].starts_with("b:"));
+        assert_eq!(
+            target.get_strategy().await,
+            SelectionStrategy::SequentialExhaust
+        );
+
+        let restored = target.get_account("c").await.unwrap();
+        assert_eq!(restored.request_count, 12);
+        assert_eq!(restored.status, AccountStatus::Disabled);
+        assert_eq!(restored.credentials.refresh_token.as_deref(), Some("rt-c"));
+        assert!(target.usage_cache.read().await.contains_key("c"));
+
+        // 过期快照整体拒绝
+        snapshot.captured_at = Utc::now() - Duration::hours(25);
+        assert!(target.import_snapshot(snapshot).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_force_refresh_token_errors_without_refresh_token() {
+        let pool = build_two_account_pool().await;
+
+        assert!(pool.force_refresh_token("missing").await.is_err());
+
+        // 缺少 refreshToken 时刷新失败，但不应误判为封禁
+        assert!(pool.force_refresh_token("a").await.is_err());
+        let accounts = pool.list_accounts().await;
+        let account = accounts.iter().find(|a| a.id == "a").unwrap();
+        assert_eq!(account.status, AccountStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn test_rebalance_resets_active_counts_to_average() {
+        let pool = build_two_account_pool().await;
+        let mut acc3 = Account::new("c", "C", KiroCredentials::default());
+        acc3.disable();
+        pool.add_account(acc3).await.unwrap();
+        {
+            let mut accounts = pool.accounts.write().await;
+            accounts.get_mut("a").unwrap().request_count = 90;
+            accounts.get_mut("b").unwrap().request_count = 10;
+            accounts.get_mut("c").unwrap().request_count = 500;
+        }
+
+        let result = pool.rebalance().await;
+        assert_eq!(result.accounts_adjusted, 2);
+        assert_eq!(result.old_max, 90);
+        assert_eq!(result.new_max, 50);
+
+        let accounts = pool.accounts.read().await;
+        assert_eq!(accounts["a"].request_count, 50);
+        assert_eq!(accounts["b"].request_count, 50);
+        assert_eq!(accounts["c"].request_count, 500);
+    }
+
+    #[tokio::test]
+    async fn test_highest_credit_score_prefers_healthier_account() {
+        let pool = build_two_account_pool().await;
+        pool.set_strategy(SelectionStrategy::HighestCreditScore)
+            .await;
+        {
+            let mut cache = pool.usage_cache.write().await;
+            cache.insert("a".to_string(), test_usage(10.0));
+            cache.insert("b".to_string(), test_usage(90.0));
+        }
+
+        let selected = pool.select_account().await.unwrap();
+        assert_eq!(selected.id, "b");
+    }
+
+    #[tokio::test]
+    async fn test_remove_all_accounts_resets_pool() {
+        let dir = std::env::temp_dir().join(format!("kiro-wipe-{}", uuid::Uuid::new_v4()));
+        let pool = AccountPool::with_data_dir(Config::default(), None, dir.clone());
+        for id in ["a", "b"] {
+            let creds = KiroCredentials {
+                refresh_token: Some(format!("token-{}", id)),
+                ..Default::default()
+            };
+            pool.add_account(Account::new(id, id, creds)).await.unwrap();
+        }
+        pool.usage_cache
+            .write()
+            .await
+            .insert("a".to_string(), test_usage(10.0));
+        pool.select_account().await.unwrap();
+
+        assert_eq!(pool.remove_all_accounts().await, 2);
+        assert!(pool.list_accounts().await.is_empty());
+        assert!(pool.get_all_usage().await.is_empty());
+        assert!(pool.get_recent_logs(10).await.is_empty());
+        assert_eq!(*pool.round_robin_index.read().await, 0);
+        assert_eq!(std::fs::read_to_string(dir.join(LOGS_FILE)).unwrap(), "[]");
+
+        // refreshToken 索引已清空，可重新导入同一账号
+        let creds = KiroCredentials {
+            refresh_token: Some("token-a".to_string()),
+            ..Default::default()
+        };
+        pool.add_account(Account::new("a", "a", creds))
+            .await
+            .unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_reports_and_applies_drift() {
+        let dir = std::env::temp_dir().join(format!("kiro-reconcile-{}", uuid::Uuid::new_v4()));
+        let on_disk = AccountPool::with_data_dir(Config::default(), None, dir.clone());
+        for (id, token) in [("a", "rt-a"), ("b", "rt-b")] {
+            let creds = KiroCredentials {
+                refresh_token: Some(token.to_string()),
+                ..KiroCredentials::default()
+            };
+            on_disk
+                .add_account(Account::new(id, id.to_uppercase(), creds))
+                .await
+                .unwrap();
+        }
+
+        // 内存中：a 改名且换了凭证，缺少 b，多出未保存的 c
+        let pool = AccountPool::with_data_dir(Config::default(), None, dir.clone());
+        for (id, name, token) in [("a", "A-renamed", "rt-a2"), ("c", "C", "rt-c")] {
+            let creds = KiroCredentials {
+                refresh_token: Some(token.to_string()),
+                ..KiroCredentials::default()
+            };
+            pool.add_account_internal(Account::new(id, name, creds))
+                .await
+                .unwrap();
+        }
+
+        let report = pool.reconcile(false).await.unwrap();
+        assert_eq!(report.added_from_disk, 1);
+        assert_eq!(report.removed_not_on_disk, 1);
+        let fields: Vec<&str> = report.conflicts.iter().map(|c| c.field.as_str()).collect();
+        assert!(fields.contains(&"name"));
+        assert!(fields.contains(&"refresh_token"));
+        let name = report.conflicts.iter().find(|c| c.field == "name").unwrap();
+        assert_eq!(name.disk_value, "A");
+        assert_eq!(name.memory_value, "A-renamed");
+        let token = report
+            .conflicts
+            .iter()
+            .find(|c| c.field == "refresh_token")
+            .unwrap();
+        assert_eq!(token.disk_value, "***");
+        // dry-run 不修改内存
+        assert!(pool.get_account("b").await.is_none());
+        assert!(pool.get_account("c").await.is_some());
+
+        pool.reconcile(true).await.unwrap();
+        assert!(pool.get_account("b").await.is_some());
+        assert!(pool.get_account("c").await.is_none());
+        assert_eq!(pool.get_account("a").await.unwrap().name, "A-renamed");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_groups_persist_and_drop_removed_accounts() {
+        let dir = std::env::temp_dir().join(format!("kiro-groups-{}", uuid::Uuid::new_v4()));
+        let pool = AccountPool::with_data_dir(Config::default(), None, dir.clone());
+        pool.add_account(Account::new("a", "A", KiroCredentials::default()))
+            .await
+            .unwrap();
+        pool.add_account(Account::new("b", "B", KiroCredentials::default()))
+            .await
+            .unwrap();
+
+        let ids = vec!["a".to_string(), "b".to_string()];
+        let group = pool
+            .assign_to_group("prod", Some("Production".to_string()), &ids)
+            .await
+            .unwrap();
+        assert_eq!(group.name, "Production");
+        assert_eq!(group.account_ids.len(), 2);
+        assert!(pool
+            .assign_to_group("prod", None, &["missing".to_string()])
+            .await
+            .is_err());
+
+        pool.remove_account("a").await;
+        let reloaded = AccountPool::with_data_dir(Config::default(), None, dir.clone());
+        assert_eq!(reloaded.load_groups().await.unwrap(), 1);
+        let groups = reloaded.list_groups().await;
+        assert_eq!(groups[0].name, "Production");
+        assert_eq!(groups[0].account_ids.len(), 1);
+        assert!(groups[0].account_ids.contains("b"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_strategy_schedule_persists_and_overrides_config() {
+        use crate::model::config::{StrategyRule, StrategySchedule};
+
+        let dir = std::env::temp_dir().join(format!("kiro-schedule-{}", uuid::Uuid::new_v4()));
+        let config = Config {
+            strategy_schedule: StrategySchedule {
+                rules: vec![StrategyRule {
+                    cron: "0 9 * * *".to_string(),
+                    strategy: "round-robin".to_string(),
+                }],
+            },
+            ..Config::default()
+        };
+        let pool = AccountPool::with_data_dir(config.clone(), None, dir.clone());
+        assert_eq!(pool.get_strategy_schedule().await.rules.len(), 1);
+
+        let invalid = StrategySchedule {
+            rules: vec![StrategyRule {
+                cron: "0 9 * * *".to_string(),
+                strategy: "unknown".to_string(),
+            }],
+        };
+        assert!(pool.set_strategy_schedule(invalid).await.is_err());
+
+        let schedule = StrategySchedule {
+            rules: vec![
+                StrategyRule {
+                    cron: "0 9 * * MON-FRI".to_string(),
+                    strategy: "capacity-aware".to_string(),
+                },
+                StrategyRule {
+                    cron: "0 22 * * *".to_string(),
+                    strategy: "sequential-exhaust".to_string(),
+                },
+            ],
+        };
+        pool.set_strategy_schedule(schedule.clone()).await.unwrap();
+
+        let reloaded = AccountPool::with_data_dir(config, None, dir.clone());
+        assert_eq!(reloaded.load_strategy_schedule().await.unwrap(), 2);
+        assert_eq!(reloaded.get_strategy_schedule().await, schedule);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_import_accounts_bulk_skips_duplicates() {
+        let pool = build_two_account_pool().await;
+        let raw = |token: &str, name: &str| -> KiroRawCredentials {
+            serde_json::from_value(serde_json::json!({
+                "refreshToken": token,
+                "name": name,
+            }))
+            .unwrap()
+        };
+        let raws = vec![
+            raw("bulk-1", "one"),
+            raw("bulk-1", "again"),
+            raw("bulk-2", "two"),
+        ];
+
+        let results = pool.import_accounts_bulk(raws, false, 5).await.unwrap();
+        let flags: Vec<bool> = results.iter().map(|r| r.success).collect();
+        assert_eq!(flags, [true, false, true]);
+        assert!(results[1].error.as_deref().unwrap().contains("已存在"));
+        assert_eq!(pool.get_stats().await.total, 4);
+        let id = results[2].account_id.as_deref().unwrap();
+        assert_eq!(pool.get_account(id).await.unwrap().name, "two");
+
+        // 已在池中的 refreshToken 同样跳过
+        let results = pool
+            .import_accounts_bulk(vec![raw("bulk-2", "dup")], false, 5)
+            .await
+            .unwrap();
+        assert!(!results[0].success);
+        assert!(results[0].account_id.is_none());
+        assert_eq!(pool.get_stats().await.total, 4);
+    }
+
+    #[tokio::test]
+    async fn test_strategy_persists_across_restart() {
+        let dir = std::env::temp_dir().join(format!("kiro-strategy-{}", uuid::Uuid::new_v4()));
+        let pool = AccountPool::with_data_dir(Config::default(), None, dir.clone());
+        for (id, name) in [("a", "A"), ("b", "B")] {
+            pool.add_account(AccountBuilder::new(id, name, KiroCredentials::default()).build())
+                .await
+                .unwrap();
+        }
+        pool.set_strategy(SelectionStrategy::SequentialExhaust)
+            .await;
+        let sticky = pool.select_account().await.unwrap().id;
+
+        let reloaded = AccountPool::with_data_dir(Config::default(), None, dir.clone());
+        assert_eq!(
+            reloaded.load_strategy().await.unwrap(),
+            Some(SelectionStrategy::SequentialExhaust)
+        );
+        assert_eq!(
+            reloaded.sequential_current_id.read().await.as_deref(),
+            Some(sticky.as_str())
+        );
+
+        pool.set_strategy(SelectionStrategy::LeastQuota).await;
+        let reloaded = AccountPool::with_data_dir(Config::default(), None, dir.clone());
+        reloaded.load_strategy().await.unwrap();
+        assert_eq!(reloaded.get_strategy().await, SelectionStrategy::LeastQuota);
+        assert!(reloaded.sequential_current_id.read().await.is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_tag_index_follows_account_changes() {
+        let pool = build_two_account_pool().await;
+        let infra = std::collections::BTreeMap::from([("team".to_string(), "infra".to_string())]);
+        pool.add_account(
+            AccountBuilder::new("c", "C", KiroCredentials::default())
+                .tags(infra.clone())
+                .build(),
+        )
+        .await
+        .unwrap();
+        assert!(pool.set_account_tags("a", infra).await.unwrap());
+        assert_eq!(
+            pool.get_accounts_by_tag("team", "infra").await,
+            vec!["a", "c"]
+        );
+
+        assert!(pool
+            .set_account_tags(
+                "a",
+                std::collections::BTreeMap::from([("team".to_string(), "web".to_string())]),
+            )
+            .await
+            .unwrap());
+        pool.remove_account("c").await;
+        assert!(pool.get_accounts_by_tag("team", "infra").await.is_empty());
+        assert_eq!(pool.get_accounts_by_tag("team", "web").await, vec!["a"]);
+        assert!(!pool
+            .set_account_tags("missing", Default::default())
+            .await
+            .unwrap());
+
+        pool.assign_to_group("prod", None, &["b".to_string(), "a".to_string()])
+            .await
+            .unwrap();
+        let members: Vec<String> = pool
+            .get_accounts_for_group("prod")
+            .await
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+        assert_eq!(members, vec!["a", "b"]);
+        assert!(pool.get_accounts_for_group("missing").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_estimate_next_reset_picks_earliest_exhausted() {
+        let pool = build_two_account_pool().await;
+        pool.add_account(Account::new("c", "C", KiroCredentials::default()))
+            .await
+            .unwrap();
+        assert_eq!(pool.estimate_next_reset().await, None);
+
+        let soon = Utc::now() + Duration::days(1);
+        let later = Utc::now() + Duration::days(3);
+        pool.mark_exhausted("a", Some(later)).await;
+        {
+            let mut cache = pool.usage_cache.write().await;
+            cache.insert(
+                "b".to_string(),
+                UsageLimits {
+                    next_reset: Some(soon),
+                    ..test_usage(0.0)
+                },
+            );
+            // 仍有额度的账号不参与估算
+            cache.insert(
+                "c".to_string(),
+                UsageLimits {
+                    next_reset: Some(Utc::now()),
+                    ..test_usage(50.0)
+                },
+            );
+        }
+
+        assert_eq!(pool.estimate_next_reset().await, Some(soon));
+    }
+
+    #[tokio::test]
+    async fn test_capacity_aware_never_selects_empty_account() {
+        let pool = build_two_account_pool().await;
+        pool.set_strategy(SelectionStrategy::CapacityAware).await;
+        {
+            let mut cache = pool.usage_cache.write().await;
+            cache.insert("a".to_string(), test_usage(0.0));
+            cache.insert("b".to_string(), test_usage(20.0));
+        }
+
+        for _ in 0..50 {
+            assert_eq!(pool.select_account().await.unwrap().id, "b");
+        }
+
+        pool.usage_cache
+            .write()
+            .await
+            .insert("b".to_string(), test_usage(0.0));
+        assert!(pool.select_account().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_geo_aware_prefers_matching_region() {
+        let pool = build_two_account_pool().await;
+        pool.set_strategy(SelectionStrategy::GeoAware).await;
+        {
+            let mut accounts = pool.accounts.write().await;
+            accounts.get_mut("a").unwrap().region = Some("us-east-1".to_string());
+            accounts.get_mut("b").unwrap().region = Some("eu-west-1".to_string());
+        }
+
+        for _ in 0..3 {
+            let selected = pool.select_account_for_region(Some("eu-west-1"), &[]).await;
+            assert_eq!(selected.unwrap().id, "b");
+        }
+        let selected = pool.select_account_for_region(Some("us-east-2"), &[]).await;
+        assert_eq!(selected.unwrap().id, "a");
+    }
+
     #[test]
     fn test_stored_account_invalid_migrates_to_disabled() {
         let stored = StoredAccount {
+            schema_version: 0,
             id: "x".to_string(),
             name: "legacy".to_string(),
             status: AccountStatus::Invalid,
             request_count: 0,
             error_count: 0,
             created_at: Utc::now(),
+            cooldown_until: None,
             exhausted_until: None,
+            last_used_at: None,
+            region: None,
+            notes: None,
+            request_timeout_secs: None,
+            pinned: false,
+            display_name_override: None,
+            tags: Default::default(),
+            proxy_override: None,
+            weight: 1,
+            pending_credentials: None,
+            history: Default::default(),
+            circuit_state: Default::default(),
+            consecutive_rate_limit_errors: 0,
             refresh_token: Some("r".to_string()),
             auth_method: Some("social".to_string()),
             client_id: None,
@@ -999,7 +5183,216 @@ mod tests {
             profile_arn: None,
         };
 
-        let account = stored.into_account();
-        assert_eq!(account.status, AccountStatus::Disabled);
+        let (stored, was_migrated) = stored.migrate();
+        assert!(was_migrated);
+        assert_eq!(stored.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(stored.into_account().status, AccountStatus::Disabled);
+    }
+
+    #[tokio::test]
+    async fn test_load_from_file_migrates_and_saves_once() {
+        let dir = std::env::temp_dir().join(format!("kiro-schema-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let legacy = r#"[{"id": "x", "name": "legacy", "status": "invalid", "request_count": 0,
+            "error_count": 0, "created_at": "2025-01-01T00:00:00Z", "refresh_token": "r",
+            "auth_method": "social", "client_id": null, "client_secret": null, "profile_arn": null}]"#;
+        std::fs::write(dir.join(ACCOUNTS_FILE), legacy).unwrap();
+
+        let pool = AccountPool::with_data_dir(Config::default(), None, dir.clone());
+        assert_eq!(pool.load_from_file().await.unwrap(), 1);
+        assert_eq!(
+            pool.list_accounts().await[0].status,
+            AccountStatus::Disabled
+        );
+
+        let saved = std::fs::read_to_string(dir.join(ACCOUNTS_FILE)).unwrap();
+        let stored: Vec<StoredAccount> = serde_json::from_str(&saved).unwrap();
+        assert_eq!(stored[0].schema_version, CURRENT_SCHEMA_VERSION);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_update_credentials_stages_while_token_valid() {
+        let dir = std::env::temp_dir().join(format!("kiro-rotation-{}", uuid::Uuid::new_v4()));
+        let pool = AccountPool::with_data_dir(Config::default(), None, dir.clone());
+        let valid = KiroCredentials {
+            refresh_token: Some("old".to_string()),
+            access_token: Some("token".to_string()),
+            expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+            ..Default::default()
+        };
+        pool.add_account(Account::new("a", "A", valid))
+            .await
+            .unwrap();
+        pool.add_account(Account::new(
+            "b",
+            "B",
+            KiroCredentials {
+                refresh_token: Some("expired".to_string()),
+                ..Default::default()
+            },
+        ))
+        .await
+        .unwrap();
+
+        let new_creds = |token: &str| KiroCredentials {
+            refresh_token: Some(token.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            pool.update_credentials("a", new_creds("new-a"))
+                .await
+                .unwrap(),
+            Some(CredentialUpdate::Staged)
+        );
+        assert_eq!(
+            pool.update_credentials("b", new_creds("new-b"))
+                .await
+                .unwrap(),
+            Some(CredentialUpdate::Applied)
+        );
+        assert_eq!(
+            pool.update_credentials("missing", new_creds("x"))
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(pool.update_credentials("a", new_creds("")).await.is_err());
+
+        let a = pool.get_account("a").await.unwrap();
+        assert_eq!(a.credentials.refresh_token.as_deref(), Some("old"));
+        let b = pool.get_account("b").await.unwrap();
+        assert_eq!(b.credentials.refresh_token.as_deref(), Some("new-b"));
+        assert_eq!(
+            pool.get_account_by_refresh_token_hash(refresh_token_hash("new-b"))
+                .await,
+            Some("b".to_string())
+        );
+
+        // 暂存凭证随账号持久化
+        let reloaded = AccountPool::with_data_dir(Config::default(), None, dir.clone());
+        reloaded.load_from_file().await.unwrap();
+        let a = reloaded.get_account("a").await.unwrap();
+        assert_eq!(
+            a.pending_credentials.unwrap().refresh_token.as_deref(),
+            Some("new-a")
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_update_credentials_does_not_block_selection() {
+        let pool = Arc::new(AccountPool::new(Config::default(), None));
+        pool.add_account(Account::new(
+            "a",
+            "A",
+            KiroCredentials {
+                refresh_token: Some("old".to_string()),
+                ..Default::default()
+            },
+        ))
+        .await
+        .unwrap();
+
+        // 模拟 Provider 正在刷新 Token（持有 TokenManager 锁）
+        let tm = pool.token_managers.read().await["a"].clone();
+        let refreshing = tm.lock().await;
+        let update = tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                pool.update_credentials(
+                    "a",
+                    KiroCredentials {
+                        refresh_token: Some("new".to_string()),
+                        ..Default::default()
+                    },
+                )
+                .await
+            }
+        });
+        tokio::task::yield_now().await;
+
+        let selected =
+            tokio::time::timeout(std::time::Duration::from_secs(1), pool.select_account())
+                .await
+                .expect("刷新期间账号选择不应被凭证更新阻塞");
+        assert_eq!(selected.unwrap().id, "a");
+
+        drop(refreshing);
+        assert_eq!(
+            update.await.unwrap().unwrap(),
+            Some(CredentialUpdate::Applied)
+        );
+        assert_eq!(
+            pool.get_account("a")
+                .await
+                .unwrap()
+                .credentials
+                .refresh_token
+                .as_deref(),
+            Some("new")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_peek_available_account_does_not_record_use() {
+        let pool = build_two_account_pool().await;
+        pool.accounts
+            .write()
+            .await
+            .get_mut("b")
+            .unwrap()
+            .circuit_state
+            .status = CircuitStatus::Open;
+
+        for _ in 0..5 {
+            assert_eq!(pool.peek_available_account().await.as_deref(), Some("a"));
+        }
+        let a = pool.get_account("a").await.unwrap();
+        assert_eq!(a.request_count, 0);
+        assert!(a.last_used_at.is_none());
+
+        assert!(pool.disable_account("a").await);
+        assert!(pool.peek_available_account().await.is_none());
+    }
+
+    #[test]
+    fn test_stored_account_clears_expired_timestamps() {
+        let mut account = Account::new("x", "X", KiroCredentials::default());
+        account.record_error(true, 120);
+        assert_eq!(account.status, AccountStatus::Cooldown);
+        account.cooldown_until = Some(Utc::now() - Duration::minutes(1));
+        account.exhausted_until = Some(Utc::now() - Duration::minutes(1));
+
+        let json = serde_json::to_value(StoredAccount::from_account(&account)).unwrap();
+        assert!(json["cooldown_until"].is_null());
+        assert!(json["exhausted_until"].is_null());
+
+        let stored: StoredAccount = serde_json::from_value(json).unwrap();
+        let restored = stored.into_account();
+        assert_eq!(restored.status, AccountStatus::Active);
+        assert!(restored.cooldown_until.is_none());
+
+        // 未过期的冷却时间照常保存
+        let until = Utc::now() + Duration::minutes(5);
+        account.cooldown_until = Some(until);
+        let restored = StoredAccount::from_account(&account).into_account();
+        assert_eq!(restored.status, AccountStatus::Cooldown);
+        assert_eq!(restored.cooldown_until, Some(until));
+    }
+
+    #[test]
+    fn test_stored_account_roundtrip_last_used_at() {
+        let mut account = Account::new("x", "X", KiroCredentials::default());
+        account.record_use(None);
+        let last_used_at = account.last_used_at;
+        assert!(last_used_at.is_some());
+
+        let json = serde_json::to_string(&StoredAccount::from_account(&account)).unwrap();
+        let stored: StoredAccount = serde_json::from_str(&json).unwrap();
+        let restored = stored.into_account();
+
+        assert_eq!(restored.last_used_at, last_used_at);
+        assert_eq!(restored.request_count, 1);
     }
 }
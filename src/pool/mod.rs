@@ -3,11 +3,20 @@
 //! 提供多账号管理、负载均衡和状态追踪功能
 
 pub mod account;
+pub mod circuit;
+pub mod geo;
+pub mod groups;
 pub mod manager;
+pub mod monitor;
+pub mod schedule;
+pub mod seed;
 pub mod strategy;
+pub mod tags;
+pub mod throttle;
 pub mod usage;
+pub mod webhook;
 
-pub use account::Account;
-pub use manager::{AccountPool, PoolStats};
-pub use strategy::SelectionStrategy;
+pub use account::{Account, AccountBuilder};
+pub use manager::{AccountPool, PoolStats, SelectionFilter};
+pub use strategy::{SelectionStrategy, StrategyConfigPatch};
 pub use usage::RequestLog;
@@ -0,0 +1,160 @@
+//! 账号池后台监控
+//!
+//! 由单个监督循环统一驱动冷却恢复、配额耗尽扫描与健康检查，同时订阅账号池事件
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+
+use super::manager::{AccountPool, PoolEvent};
+
+/// 冷却账号扫描间隔（15 分钟）
+const COOLDOWN_SCAN_SECS: u64 = 15 * 60;
+/// 配额耗尽账号扫描间隔（1 小时）
+const EXHAUSTED_SCAN_SECS: u64 = 60 * 60;
+/// 健康检查间隔（5 分钟）
+const HEALTH_CHECK_SECS: u64 = 5 * 60;
+
+/// 各后台任务的执行间隔
+#[derive(Debug, Clone, Copy)]
+pub struct MonitorIntervals {
+    pub cooldown: Duration,
+    pub exhausted: Duration,
+    pub health: Duration,
+}
+
+impl Default for MonitorIntervals {
+    fn default() -> Self {
+        Self {
+            cooldown: Duration::from_secs(COOLDOWN_SCAN_SECS),
+            exhausted: Duration::from_secs(EXHAUSTED_SCAN_SECS),
+            health: Duration::from_secs(HEALTH_CHECK_SECS),
+        }
+    }
+}
+
+/// 账号池监控器
+pub struct PoolMonitor;
+
+impl PoolMonitor {
+    /// 以默认间隔启动监督循环
+    pub fn run(pool: Arc<AccountPool>) -> JoinHandle<()> {
+        Self::run_with_intervals(pool, MonitorIntervals::default())
+    }
+
+    /// 以指定间隔启动监督循环
+    pub fn run_with_intervals(
+        pool: Arc<AccountPool>,
+        intervals: MonitorIntervals,
+    ) -> JoinHandle<()> {
+        let mut events = pool.subscribe();
+        tokio::spawn(async move {
+            let mut cooldown_ticker = interval(intervals.cooldown);
+            let mut exhausted_ticker = interval(intervals.exhausted);
+            let mut health_ticker = interval(intervals.health);
+            let mut exhausted_scan: Option<JoinHandle<()>> = None;
+
+            loop {
+                tokio::select! {
+                    _ = cooldown_ticker.tick() => {
+                        let recovered = pool.recover_cooldown_accounts().await;
+                        if recovered > 0 {
+                            tracing::info!("冷却扫描完成，恢复 {} 个账号", recovered);
+                        }
+                    }
+                    _ = exhausted_ticker.tick() => {
+                        // 耗尽扫描需要逐个请求上游，放到子任务中执行，避免阻塞事件消费与其他扫描
+                        if exhausted_scan.as_ref().is_some_and(|h| !h.is_finished()) {
+                            tracing::debug!("上一轮配额耗尽扫描尚未完成，跳过本轮");
+                            continue;
+                        }
+                        let pool = pool.clone();
+                        exhausted_scan = Some(tokio::spawn(async move {
+                            let (recovered, scanned) = pool.refresh_exhausted_accounts().await;
+                            if scanned > 0 {
+                                tracing::info!(
+                                    "配额耗尽扫描完成，检查 {} 个账号，恢复 {} 个",
+                                    scanned,
+                                    recovered
+                                );
+                            }
+                        }));
+                    }
+                    _ = health_ticker.tick() => {
+                        let stats = pool.get_stats().await;
+                        if stats.total > 0 && stats.active == 0 {
+                            tracing::warn!(
+                                "账号池没有活跃账号：冷却 {}，耗尽 {}，失效 {}，禁用 {}",
+                                stats.cooldown,
+                                stats.exhausted,
+                                stats.invalid,
+                                stats.disabled
+                            );
+                        }
+                    }
+                    event = events.recv() => match event {
+                        Ok(PoolEvent::StatusChanged { account_id, from, to }) => {
+                            tracing::info!("账号 {} 状态变更: {:?} -> {:?}", account_id, from, to);
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!("账号池事件处理滞后，跳过 {} 条", skipped);
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                }
+            }
+
+            if let Some(handle) = exhausted_scan {
+                handle.abort();
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiro::model::credentials::KiroCredentials;
+    use crate::model::config::Config;
+    use crate::pool::account::AccountStatus;
+    use crate::pool::AccountBuilder;
+
+    #[tokio::test]
+    async fn test_monitor_recovers_cooldown_and_broadcasts_event() {
+        let pool = Arc::new(AccountPool::new(Config::default(), None));
+        let account = AccountBuilder::new("a", "A", KiroCredentials::default())
+            .status(AccountStatus::Cooldown)
+            .cooldown_until(Some(chrono::Utc::now() - chrono::Duration::seconds(1)))
+            .build();
+        pool.add_account(account).await.unwrap();
+
+        let mut events = pool.subscribe();
+        let interval = Duration::from_millis(10);
+        let handle = PoolMonitor::run_with_intervals(
+            pool.clone(),
+            MonitorIntervals {
+                cooldown: interval,
+                exhausted: interval,
+                health: interval,
+            },
+        );
+
+        let event = tokio::time::timeout(Duration::from_secs(1), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            event,
+            PoolEvent::StatusChanged {
+                account_id: "a".to_string(),
+                from: AccountStatus::Cooldown,
+                to: AccountStatus::Active,
+            }
+        );
+        assert_eq!(pool.list_accounts().await[0].status, AccountStatus::Active);
+        handle.abort();
+    }
+}
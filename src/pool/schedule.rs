@@ -0,0 +1,172 @@
+//! 选择策略定时切换
+//!
+//! 每分钟按 cron 规则计算当前应生效的策略：取最近一次触发时间最晚的规则（同时触发时列表中靠后的优先），
+//! 仅在出现新的触发时切换，两次触发之间手动设置的策略不会被覆盖
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, TimeZone};
+use cron::Schedule;
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+
+use crate::model::config::StrategySchedule;
+
+use super::manager::AccountPool;
+use super::strategy::SelectionStrategy;
+
+/// 计划检查间隔
+const SCHEDULE_TICK_SECS: u64 = 60;
+
+/// 解析 cron 表达式，5 段格式自动补齐秒字段
+pub fn parse_cron(expr: &str) -> anyhow::Result<Schedule> {
+    let expr = expr.trim();
+    let normalized = if expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr)
+    } else {
+        expr.to_string()
+    };
+    Schedule::from_str(&normalized)
+        .map_err(|e| anyhow::anyhow!("无效的 cron 表达式 {}: {}", expr, e))
+}
+
+/// 校验计划中的全部规则
+pub fn validate_schedule(schedule: &StrategySchedule) -> anyhow::Result<()> {
+    for rule in &schedule.rules {
+        parse_cron(&rule.cron)?;
+        if SelectionStrategy::parse(&rule.strategy).is_none() {
+            anyhow::bail!("无效的策略: {}", rule.strategy);
+        }
+    }
+    Ok(())
+}
+
+/// 截至 `now` 最近一次触发的规则，返回触发时间与对应策略
+pub fn latest_rule<Tz: TimeZone>(
+    schedule: &StrategySchedule,
+    now: &DateTime<Tz>,
+) -> Option<(DateTime<Tz>, SelectionStrategy)> {
+    // prev_from 不含起点，向后偏移 1 秒使恰好在 now 触发的规则也能命中
+    let upper = now.clone() + chrono::Duration::seconds(1);
+    let mut latest: Option<(DateTime<Tz>, SelectionStrategy)> = None;
+    for rule in &schedule.rules {
+        let (Ok(cron), Some(strategy)) = (
+            parse_cron(&rule.cron),
+            SelectionStrategy::parse(&rule.strategy),
+        ) else {
+            continue;
+        };
+        let Some(fired_at) = cron.after(&upper).next_back() else {
+            continue;
+        };
+        if latest.as_ref().is_none_or(|(at, _)| fired_at >= *at) {
+            latest = Some((fired_at, strategy));
+        }
+    }
+    latest
+}
+
+/// 启动定时切换任务
+pub fn spawn_strategy_scheduler(pool: Arc<AccountPool>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(SCHEDULE_TICK_SECS));
+        let mut last_applied: Option<(DateTime<Local>, SelectionStrategy)> = None;
+
+        loop {
+            ticker.tick().await;
+            let schedule = pool.get_strategy_schedule().await;
+            let Some(current) = latest_rule(&schedule, &Local::now()) else {
+                continue;
+            };
+            if last_applied.as_ref() == Some(&current) {
+                continue;
+            }
+
+            let (fired_at, strategy) = current;
+            if pool.get_strategy().await != strategy {
+                pool.set_strategy(strategy).await;
+                tracing::info!(
+                    "定时计划切换选择策略为 {}（规则触发于 {}）",
+                    strategy.as_str(),
+                    fired_at.format("%Y-%m-%d %H:%M")
+                );
+            }
+            last_applied = Some((fired_at, strategy));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::config::StrategyRule;
+    use chrono::Utc;
+
+    fn rule(cron: &str, strategy: &str) -> StrategyRule {
+        StrategyRule {
+            cron: cron.to_string(),
+            strategy: strategy.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validate_schedule() {
+        let valid = StrategySchedule {
+            rules: vec![rule("0 9 * * MON-FRI", "capacity-aware")],
+        };
+        assert!(validate_schedule(&valid).is_ok());
+
+        let bad_cron = StrategySchedule {
+            rules: vec![rule("not a cron", "round-robin")],
+        };
+        assert!(validate_schedule(&bad_cron).is_err());
+
+        let bad_strategy = StrategySchedule {
+            rules: vec![rule("0 9 * * *", "fastest")],
+        };
+        assert!(validate_schedule(&bad_strategy).is_err());
+    }
+
+    #[test]
+    fn test_latest_rule_picks_most_recent_trigger() {
+        let schedule = StrategySchedule {
+            rules: vec![
+                rule("0 9 * * MON-FRI", "capacity-aware"),
+                rule("0 22 * * *", "sequential-exhaust"),
+            ],
+        };
+
+        // 2026-10-14 是周三
+        let midday = Utc.with_ymd_and_hms(2026, 10, 14, 15, 0, 0).unwrap();
+        let (fired_at, strategy) = latest_rule(&schedule, &midday).unwrap();
+        assert_eq!(strategy, SelectionStrategy::CapacityAware);
+        assert_eq!(
+            fired_at,
+            Utc.with_ymd_and_hms(2026, 10, 14, 9, 0, 0).unwrap()
+        );
+
+        let night = Utc.with_ymd_and_hms(2026, 10, 14, 23, 30, 0).unwrap();
+        assert_eq!(
+            latest_rule(&schedule, &night).unwrap().1,
+            SelectionStrategy::SequentialExhaust
+        );
+
+        // 恰好在触发时刻也生效
+        let nine = Utc.with_ymd_and_hms(2026, 10, 15, 9, 0, 0).unwrap();
+        assert_eq!(
+            latest_rule(&schedule, &nine).unwrap().1,
+            SelectionStrategy::CapacityAware
+        );
+
+        // 周六早上沿用周五晚上的规则
+        let saturday = Utc.with_ymd_and_hms(2026, 10, 17, 10, 0, 0).unwrap();
+        assert_eq!(
+            latest_rule(&schedule, &saturday).unwrap().1,
+            SelectionStrategy::SequentialExhaust
+        );
+
+        assert!(latest_rule(&StrategySchedule::default(), &midday).is_none());
+    }
+}
@@ -0,0 +1,226 @@
+//! 账号池远程种子
+//!
+//! 启动时从远程 HTTP 端点（如密钥管理服务）拉取凭证数组并导入账号池
+
+use std::sync::Arc;
+
+use tokio::time::{interval, sleep, Duration};
+
+use crate::http_client::{build_client, ProxyConfig};
+
+use super::AccountPool;
+
+/// 请求超时（秒）
+const SEED_TIMEOUT_SECS: u64 = 10;
+/// 首次拉取最大尝试次数
+const SEED_MAX_ATTEMPTS: u32 = 3;
+/// 重试间隔（秒）
+const SEED_RETRY_DELAY_SECS: u64 = 2;
+
+/// 远程种子配置
+#[derive(Debug, Clone)]
+pub struct PoolSeed {
+    /// 凭证地址（POOL_SEED_URL）
+    url: String,
+    /// 可选的 Authorization 头（POOL_SEED_AUTH_HEADER）
+    auth_header: Option<String>,
+    /// 定期重新拉取间隔（POOL_SEED_REFRESH_HOURS，未设置或为 0 时不刷新）
+    refresh_hours: Option<u64>,
+    proxy: Option<ProxyConfig>,
+}
+
+impl PoolSeed {
+    /// 从环境变量读取配置，未设置 POOL_SEED_URL 时返回 None
+    pub fn from_env(proxy: Option<ProxyConfig>) -> Option<Self> {
+        Self::from_lookup(|name| std::env::var(name).ok(), proxy)
+    }
+
+    /// 通过 `lookup` 按名称读取变量构建配置
+    fn from_lookup(
+        lookup: impl Fn(&str) -> Option<String>,
+        proxy: Option<ProxyConfig>,
+    ) -> Option<Self> {
+        let url = lookup("POOL_SEED_URL").filter(|u| !u.is_empty())?;
+        Some(Self {
+            url,
+            auth_header: lookup("POOL_SEED_AUTH_HEADER"),
+            refresh_hours: lookup("POOL_SEED_REFRESH_HOURS")
+                .and_then(|h| h.parse().ok())
+                .filter(|h| *h > 0),
+            proxy,
+        })
+    }
+
+    /// 拉取凭证 JSON
+    async fn fetch(&self) -> anyhow::Result<String> {
+        let client = build_client(self.proxy.as_ref(), SEED_TIMEOUT_SECS)?;
+        let mut request = client.get(&self.url);
+        if let Some(auth) = &self.auth_header {
+            request = request.header(reqwest::header::AUTHORIZATION, auth);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            anyhow::bail!("拉取种子凭证失败: HTTP {}", status);
+        }
+        Ok(response.text().await?)
+    }
+
+    /// 拉取并导入账号（已存在的凭证会被跳过）
+    async fn import(&self, pool: &AccountPool) -> anyhow::Result<usize> {
+        let body = self.fetch().await?;
+        pool.import_from_json_string(&body, false).await
+    }
+
+    /// 首次导入（失败时重试）
+    pub async fn load_initial(&self, pool: &AccountPool) {
+        for attempt in 1..=SEED_MAX_ATTEMPTS {
+            match self.import(pool).await {
+                Ok(imported) => {
+                    tracing::info!("已从 POOL_SEED_URL 导入 {} 个账号", imported);
+                    return;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "从 POOL_SEED_URL 导入账号失败（第 {}/{} 次）: {}",
+                        attempt,
+                        SEED_MAX_ATTEMPTS,
+                        e
+                    );
+                    if attempt < SEED_MAX_ATTEMPTS {
+                        sleep(Duration::from_secs(SEED_RETRY_DELAY_SECS)).await;
+                    }
+                }
+            }
+        }
+    }
+
+    /// 启动定期重新拉取任务
+    pub fn spawn_refresh(self, pool: Arc<AccountPool>) {
+        let Some(hours) = self.refresh_hours else {
+            return;
+        };
+
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_secs(hours * 60 * 60));
+            // 跳过立即触发的第一次 tick
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match self.import(&pool).await {
+                    Ok(imported) => {
+                        if imported > 0 {
+                            tracing::info!("种子凭证刷新完成，新增 {} 个账号", imported);
+                        }
+                    }
+                    Err(e) => tracing::warn!("种子凭证刷新失败: {}", e),
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::config::Config;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn seed_from(vars: &[(&str, &str)]) -> Option<PoolSeed> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        PoolSeed::from_lookup(|name| vars.get(name).cloned(), None)
+    }
+
+    /// 启动本地种子服务：前 `failures` 次返回 500，之后返回凭证数组
+    async fn serve_seed(failures: u32) -> (String, Arc<AtomicU32>) {
+        let hits = Arc::new(AtomicU32::new(0));
+        let counter = hits.clone();
+        let app = axum::Router::new().route(
+            "/seed",
+            axum::routing::get(move |headers: axum::http::HeaderMap| {
+                let counter = counter.clone();
+                async move {
+                    assert_eq!(headers["authorization"], "Bearer seed-token");
+                    if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                        (axum::http::StatusCode::INTERNAL_SERVER_ERROR, String::new())
+                    } else {
+                        (
+                            axum::http::StatusCode::OK,
+                            r#"[{"refreshToken": "r-seed-1"}, {"refreshToken": "r-seed-2"}]"#
+                                .to_string(),
+                        )
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/seed", addr), hits)
+    }
+
+    fn seed_for(url: String) -> PoolSeed {
+        PoolSeed {
+            url,
+            auth_header: Some("Bearer seed-token".to_string()),
+            refresh_hours: None,
+            proxy: None,
+        }
+    }
+
+    #[test]
+    fn test_from_lookup_parses_env() {
+        assert!(seed_from(&[]).is_none());
+        assert!(seed_from(&[("POOL_SEED_URL", "")]).is_none());
+
+        let seed = seed_from(&[
+            ("POOL_SEED_URL", "https://vault.example/creds"),
+            ("POOL_SEED_AUTH_HEADER", "Bearer x"),
+            ("POOL_SEED_REFRESH_HOURS", "6"),
+        ])
+        .unwrap();
+        assert_eq!(seed.url, "https://vault.example/creds");
+        assert_eq!(seed.auth_header.as_deref(), Some("Bearer x"));
+        assert_eq!(seed.refresh_hours, Some(6));
+
+        // 0 或无法解析的刷新间隔视为不刷新
+        for hours in ["0", "abc"] {
+            let seed = seed_from(&[
+                ("POOL_SEED_URL", "https://vault.example/creds"),
+                ("POOL_SEED_REFRESH_HOURS", hours),
+            ])
+            .unwrap();
+            assert_eq!(seed.refresh_hours, None);
+            assert!(seed.auth_header.is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_initial_retries_then_imports() {
+        let (url, hits) = serve_seed(1).await;
+        let pool = AccountPool::new(Config::default(), None);
+
+        seed_for(url.clone()).load_initial(&pool).await;
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.list_accounts().await.len(), 2);
+
+        // 再次导入时已存在的凭证被跳过
+        assert_eq!(seed_for(url).import(&pool).await.unwrap(), 0);
+        assert_eq!(pool.list_accounts().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_load_initial_gives_up_after_max_attempts() {
+        let (url, hits) = serve_seed(u32::MAX).await;
+        let pool = AccountPool::new(Config::default(), None);
+
+        seed_for(url).load_initial(&pool).await;
+        assert_eq!(hits.load(Ordering::SeqCst), SEED_MAX_ATTEMPTS);
+        assert!(pool.list_accounts().await.is_empty());
+    }
+}
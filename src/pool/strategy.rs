@@ -2,6 +2,11 @@
 
 use serde::{Deserialize, Serialize};
 
+use super::usage::UsageLimits;
+
+/// 无配额缓存时假定的剩余比例
+const UNKNOWN_CAPACITY_WEIGHT: f64 = 0.5;
+
 /// 选择策略
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "kebab-case")]
@@ -15,22 +20,271 @@ pub enum SelectionStrategy {
     LeastUsed,
     /// 依次使用，当前账号耗尽后再切到下一个
     SequentialExhaust,
+    /// 健康分最高优先
+    HighestCreditScore,
+    /// 地域感知，优先同区域账号，层内轮询
+    GeoAware,
+    /// 按实时剩余配额比例加权随机
+    CapacityAware,
+    /// 最近 10 次首 token 延迟中位数最低优先，无延迟数据时轮询
+    LowestLatency,
+    /// 按账号权重轮询，权重 3 的账号连续被选 3 次后才轮到下一个
+    WeightedRoundRobin,
+    /// 剩余配额最多优先，无配额缓存的账号排在最前
+    LeastQuota,
 }
 
 impl SelectionStrategy {
+    /// 从 kebab-case 名称解析策略
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "round-robin" => Some(Self::RoundRobin),
+            "random" => Some(Self::Random),
+            "least-used" => Some(Self::LeastUsed),
+            "sequential-exhaust" => Some(Self::SequentialExhaust),
+            "highest-credit-score" => Some(Self::HighestCreditScore),
+            "geo-aware" => Some(Self::GeoAware),
+            "capacity-aware" => Some(Self::CapacityAware),
+            "lowest-latency" => Some(Self::LowestLatency),
+            "weighted-round-robin" => Some(Self::WeightedRoundRobin),
+            "least-quota" => Some(Self::LeastQuota),
+            _ => None,
+        }
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::RoundRobin => "round-robin",
             Self::Random => "random",
             Self::LeastUsed => "least-used",
             Self::SequentialExhaust => "sequential-exhaust",
+            Self::HighestCreditScore => "highest-credit-score",
+            Self::GeoAware => "geo-aware",
+            Self::CapacityAware => "capacity-aware",
+            Self::LowestLatency => "lowest-latency",
+            Self::WeightedRoundRobin => "weighted-round-robin",
+            Self::LeastQuota => "least-quota",
+        }
+    }
+}
+
+/// CapacityAware 的选择权重：剩余配额占限额的比例（0.0 ~ 1.0），无缓存时取 0.5
+pub fn capacity_weight(usage: Option<&UsageLimits>) -> f64 {
+    match usage {
+        None => UNKNOWN_CAPACITY_WEIGHT,
+        Some(u) if u.is_exhausted() => 0.0,
+        Some(u) if u.usage_limit > 0.0 => (u.available / u.usage_limit).clamp(0.0, 1.0),
+        Some(_) => UNKNOWN_CAPACITY_WEIGHT,
+    }
+}
+
+/// 按权重抽取候选，`roll` 为 [0, 1) 的随机数；权重全为 0 时返回 None
+pub fn weighted_pick(candidates: &[(String, f64)], roll: f64) -> Option<&str> {
+    let total: f64 = candidates.iter().map(|(_, w)| w.max(0.0)).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut target = roll * total;
+    let mut last = None;
+    for (id, weight) in candidates.iter().filter(|(_, w)| *w > 0.0) {
+        if target < *weight {
+            return Some(id);
+        }
+        target -= weight;
+        last = Some(id.as_str());
+    }
+    // 浮点误差兜底
+    last
+}
+
+/// WeightedRoundRobin 按计数器在权重展开的序列中取候选（每个候选连续占 weight 个位置），
+/// 候选需按固定顺序传入；权重全为 0 时返回 None
+pub fn weighted_round_robin_pick(candidates: &[(String, u32)], counter: usize) -> Option<&str> {
+    let total: usize = candidates.iter().map(|(_, w)| *w as usize).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut slot = counter % total;
+    for (id, weight) in candidates {
+        let weight = *weight as usize;
+        if slot < weight {
+            return Some(id);
+        }
+        slot -= weight;
+    }
+    None
+}
+
+/// LeastQuota 选出剩余配额最多的候选：无配额缓存（`None`）的优先，其次按剩余额度降序，
+/// 相同时取 id 较小者
+pub fn least_quota_pick(candidates: &[(String, Option<f64>)]) -> Option<&str> {
+    candidates
+        .iter()
+        .min_by(|(id_a, a), (id_b, b)| match (a, b) {
+            (None, None) => id_a.cmp(id_b),
+            (None, Some(_)) => std::cmp::Ordering::Less,
+            (Some(_), None) => std::cmp::Ordering::Greater,
+            (Some(a), Some(b)) => b.total_cmp(a).then_with(|| id_a.cmp(id_b)),
+        })
+        .map(|(id, _)| id.as_str())
+}
+
+/// LowestLatency 选出延迟中位数最低的候选，所有候选均无延迟数据时返回 None
+pub fn lowest_latency_pick(candidates: &[(String, Option<u64>)]) -> Option<&str> {
+    candidates
+        .iter()
+        .filter_map(|(id, median)| median.map(|m| (id, m)))
+        .min_by_key(|(_, median)| *median)
+        .map(|(id, _)| id.as_str())
+}
+
+/// 策略参数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StrategyConfig {
+    /// 健康分中错误率的扣分权重
+    pub error_rate_weight: f64,
+    /// 健康分中已用配额比例的扣分权重
+    pub quota_weight: f64,
+    /// GeoAware 默认期望区域（为空时使用全局配置的 region）
+    pub preferred_region: Option<String>,
+}
+
+impl Default for StrategyConfig {
+    fn default() -> Self {
+        Self {
+            error_rate_weight: 0.1,
+            quota_weight: 0.2,
+            preferred_region: None,
+        }
+    }
+}
+
+/// 策略参数的部分更新，未提供的字段保持不变
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyConfigPatch {
+    pub error_rate_weight: Option<f64>,
+    pub quota_weight: Option<f64>,
+    pub preferred_region: Option<String>,
+}
+
+impl StrategyConfig {
+    /// 应用部分更新，权重需在 0.0 ~ 1.0 之间；空字符串区域表示清除
+    pub fn apply(&self, patch: StrategyConfigPatch) -> anyhow::Result<Self> {
+        let mut next = self.clone();
+        if let Some(w) = patch.error_rate_weight {
+            next.error_rate_weight = w;
+        }
+        if let Some(w) = patch.quota_weight {
+            next.quota_weight = w;
         }
+        if let Some(region) = patch.preferred_region {
+            next.preferred_region = (!region.is_empty()).then_some(region);
+        }
+
+        for (name, w) in [
+            ("errorRateWeight", next.error_rate_weight),
+            ("quotaWeight", next.quota_weight),
+        ] {
+            if !(0.0..=1.0).contains(&w) {
+                anyhow::bail!("{} 必须在 0.0 ~ 1.0 之间", name);
+            }
+        }
+        Ok(next)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::SelectionStrategy;
+    use super::*;
+
+    #[test]
+    fn test_strategy_config_partial_apply() {
+        let config = StrategyConfig::default();
+        let patch = StrategyConfigPatch {
+            quota_weight: Some(0.5),
+            preferred_region: Some("eu-west-1".to_string()),
+            ..Default::default()
+        };
+        let next = config.apply(patch).unwrap();
+        assert_eq!(next.error_rate_weight, 0.1);
+        assert_eq!(next.quota_weight, 0.5);
+        assert_eq!(next.preferred_region.as_deref(), Some("eu-west-1"));
+
+        let invalid = StrategyConfigPatch {
+            error_rate_weight: Some(1.5),
+            ..Default::default()
+        };
+        assert!(next.apply(invalid).is_err());
+    }
+
+    fn usage(limit: f64, available: f64) -> UsageLimits {
+        UsageLimits {
+            resource_type: "CREDIT".to_string(),
+            usage_limit: limit,
+            current_usage: limit - available,
+            available,
+            next_reset: None,
+            free_trial: None,
+            user_email: None,
+            subscription_type: None,
+            request_limits: None,
+            fetched_at: None,
+        }
+    }
+
+    #[test]
+    fn test_capacity_weight() {
+        assert_eq!(capacity_weight(Some(&usage(100.0, 80.0))), 0.8);
+        assert_eq!(capacity_weight(Some(&usage(100.0, 20.0))), 0.2);
+        assert_eq!(capacity_weight(Some(&usage(100.0, 0.0))), 0.0);
+        assert_eq!(capacity_weight(None), 0.5);
+        assert_eq!(
+            SelectionStrategy::parse("capacity-aware"),
+            Some(SelectionStrategy::CapacityAware)
+        );
+    }
+
+    #[test]
+    fn test_weighted_pick_skips_zero_weight() {
+        let candidates = vec![
+            ("empty".to_string(), 0.0),
+            ("a".to_string(), 0.8),
+            ("b".to_string(), 0.2),
+        ];
+        for i in 0..100 {
+            let picked = weighted_pick(&candidates, i as f64 / 100.0).unwrap();
+            assert_ne!(picked, "empty");
+        }
+        assert_eq!(weighted_pick(&candidates, 0.0), Some("a"));
+        assert_eq!(weighted_pick(&candidates, 0.79), Some("a"));
+        assert_eq!(weighted_pick(&candidates, 0.81), Some("b"));
+
+        let exhausted = vec![("empty".to_string(), 0.0)];
+        assert_eq!(weighted_pick(&exhausted, 0.5), None);
+    }
+
+    #[test]
+    fn test_weighted_round_robin_pick() {
+        let candidates = vec![
+            ("a".to_string(), 3),
+            ("b".to_string(), 1),
+            ("off".to_string(), 0),
+        ];
+        let picks: Vec<&str> = (0..8)
+            .map(|i| weighted_round_robin_pick(&candidates, i).unwrap())
+            .collect();
+        assert_eq!(picks, ["a", "a", "a", "b", "a", "a", "a", "b"]);
+        assert_eq!(
+            SelectionStrategy::parse("weighted-round-robin"),
+            Some(SelectionStrategy::WeightedRoundRobin)
+        );
+        assert_eq!(
+            weighted_round_robin_pick(&[("off".to_string(), 0)], 0),
+            None
+        );
+    }
 
     #[test]
     fn test_sequential_exhaust_as_str() {
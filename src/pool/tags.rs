@@ -0,0 +1,107 @@
+//! 账号标签索引
+//!
+//! 以 `"<key>=<value>"` 为键索引账号 id，按标签查找账号时无需遍历账号池
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// 标签索引键
+pub fn tag_key(key: &str, value: &str) -> String {
+    format!("{}={}", key, value)
+}
+
+/// 解析标签列表：`key=value` 为键值标签，仅 `key` 时值为空字符串；键不能为空
+pub fn parse_tag_list(tags: &[String]) -> anyhow::Result<BTreeMap<String, String>> {
+    tags.iter()
+        .map(|tag| {
+            let (key, value) = tag.split_once('=').unwrap_or((tag.as_str(), ""));
+            let key = key.trim();
+            if key.is_empty() {
+                anyhow::bail!("无效的标签: {:?}", tag);
+            }
+            Ok((key.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// 标签 -> 账号 id 集合
+#[derive(Debug, Default)]
+pub struct TagIndex {
+    entries: HashMap<String, HashSet<String>>,
+}
+
+impl TagIndex {
+    /// 加入账号的全部标签
+    pub fn insert(&mut self, account_id: &str, tags: &BTreeMap<String, String>) {
+        for (key, value) in tags {
+            self.entries
+                .entry(tag_key(key, value))
+                .or_default()
+                .insert(account_id.to_string());
+        }
+    }
+
+    /// 移除账号的全部标签，空集合一并删除
+    pub fn remove(&mut self, account_id: &str, tags: &BTreeMap<String, String>) {
+        for (key, value) in tags {
+            let index_key = tag_key(key, value);
+            if let Some(ids) = self.entries.get_mut(&index_key) {
+                ids.remove(account_id);
+                if ids.is_empty() {
+                    self.entries.remove(&index_key);
+                }
+            }
+        }
+    }
+
+    /// 带有指定标签的账号 id（已排序）
+    pub fn get(&self, key: &str, value: &str) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .entries
+            .get(&tag_key(key, value))
+            .map(|ids| ids.iter().cloned().collect())
+            .unwrap_or_default();
+        ids.sort();
+        ids
+    }
+
+    /// 清空索引
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_remove() {
+        let tags = BTreeMap::from([
+            ("team".to_string(), "infra".to_string()),
+            ("tier".to_string(), "pro".to_string()),
+        ]);
+        let mut index = TagIndex::default();
+        index.insert("a", &tags);
+        index.insert(
+            "b",
+            &BTreeMap::from([("team".to_string(), "infra".to_string())]),
+        );
+
+        assert_eq!(index.get("team", "infra"), vec!["a", "b"]);
+        assert_eq!(index.get("tier", "pro"), vec!["a"]);
+        assert!(index.get("team", "web").is_empty());
+
+        index.remove("a", &tags);
+        assert_eq!(index.get("team", "infra"), vec!["b"]);
+        assert!(index.get("tier", "pro").is_empty());
+        assert!(!index.entries.contains_key("tier=pro"));
+    }
+
+    #[test]
+    fn test_parse_tag_list() {
+        let tags = parse_tag_list(&["tier=high".to_string(), "opus".to_string()]).unwrap();
+        assert_eq!(tags["tier"], "high");
+        assert_eq!(tags["opus"], "");
+        assert!(parse_tag_list(&["=x".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! 全局请求限速
+//!
+//! 令牌桶限制账号池整体每秒请求数，并可根据近期错误率自动调整填充速率
+
+use std::time::Instant;
+
+use crate::model::config::Config;
+
+/// 错误率高于该值时降低速率
+pub const THROTTLE_DOWN_ERROR_RATE: f64 = 0.2;
+/// 错误率低于该值时提高速率
+pub const THROTTLE_UP_ERROR_RATE: f64 = 0.05;
+/// 每次调整的幅度
+const THROTTLE_STEP: f64 = 0.1;
+
+/// 令牌桶
+///
+/// 突发容量等于每秒速率（至少 1 个令牌）
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// 以每秒 `rate` 个令牌创建满桶
+    pub fn new(rate: f64) -> Self {
+        let rate = rate.max(f64::MIN_POSITIVE);
+        Self {
+            rate,
+            tokens: Self::capacity_for(rate),
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn capacity_for(rate: f64) -> f64 {
+        rate.max(1.0)
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(Self::capacity_for(self.rate));
+        self.last_refill = now;
+    }
+
+    /// 尝试取出一个令牌
+    pub fn try_acquire(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 当前填充速率（每秒）
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// 调整填充速率，已累积的令牌按新容量截断
+    pub fn set_rate(&mut self, rate: f64) {
+        self.refill();
+        self.rate = rate.max(f64::MIN_POSITIVE);
+        self.tokens = self.tokens.min(Self::capacity_for(self.rate));
+    }
+}
+
+/// 全局限速参数（对应配置中的 rpsLimit / minRps / maxRps）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitSettings {
+    pub rps_limit: Option<f64>,
+    pub min_rps: f64,
+    pub max_rps: Option<f64>,
+}
+
+impl RateLimitSettings {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            rps_limit: config.rps_limit,
+            min_rps: config.min_rps,
+            max_rps: config.max_rps,
+        }
+    }
+
+    /// 自动限速允许提高到的最高速率（默认取 rpsLimit）
+    pub fn throttle_max(&self) -> f64 {
+        self.max_rps.or(self.rps_limit).unwrap_or(self.min_rps)
+    }
+}
+
+/// 全局限速器：当前参数与令牌桶（未设置 rpsLimit 时不限速）
+#[derive(Debug)]
+pub struct RateLimiter {
+    settings: RateLimitSettings,
+    bucket: Option<TokenBucket>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: RateLimitSettings) -> Self {
+        Self {
+            settings,
+            bucket: settings.rps_limit.map(TokenBucket::new),
+        }
+    }
+
+    pub fn settings(&self) -> RateLimitSettings {
+        self.settings
+    }
+
+    pub fn bucket_mut(&mut self) -> Option<&mut TokenBucket> {
+        self.bucket.as_mut()
+    }
+
+    /// 应用新的限速参数，返回是否发生变化
+    ///
+    /// rpsLimit 变化时重置速率（覆盖自动限速的调整），已有令牌桶保留累积的令牌
+    pub fn apply(&mut self, settings: RateLimitSettings) -> bool {
+        if settings == self.settings {
+            return false;
+        }
+        if settings.rps_limit != self.settings.rps_limit {
+            self.bucket = match (settings.rps_limit, self.bucket.take()) {
+                (Some(rps), Some(mut bucket)) => {
+                    bucket.set_rate(rps);
+                    Some(bucket)
+                }
+                (Some(rps), None) => Some(TokenBucket::new(rps)),
+                (None, _) => None,
+            };
+        }
+        self.settings = settings;
+        true
+    }
+}
+
+/// 根据错误率计算新的速率，无需调整时返回 None
+pub fn throttle_adjustment(
+    current: f64,
+    error_rate: f64,
+    min_rps: f64,
+    max_rps: f64,
+) -> Option<f64> {
+    let next = if error_rate > THROTTLE_DOWN_ERROR_RATE {
+        (current * (1.0 - THROTTLE_STEP)).max(min_rps)
+    } else if error_rate < THROTTLE_UP_ERROR_RATE {
+        (current * (1.0 + THROTTLE_STEP)).min(max_rps)
+    } else {
+        return None;
+    };
+    (next != current).then_some(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_limits_burst() {
+        let mut bucket = TokenBucket::new(2.0);
+        assert!(bucket.try_acquire());
+        assert!(bucket.try_acquire());
+        assert!(!bucket.try_acquire());
+
+        bucket.set_rate(0.5);
+        assert_eq!(bucket.rate(), 0.5);
+        assert!(!bucket.try_acquire());
+    }
+
+    #[test]
+    fn test_throttle_adjustment_follows_error_rate_feed() {
+        let (min, max) = (1.0, 10.0);
+        let mut rate = 10.0;
+        let feed = [0.5, 0.3, 0.1, 0.01, 0.0, 0.0];
+        let mut rates = Vec::new();
+        for error_rate in feed {
+            if let Some(next) = throttle_adjustment(rate, error_rate, min, max) {
+                rate = next;
+            }
+            rates.push((rate * 100.0).round() / 100.0);
+        }
+        assert_eq!(rates, vec![9.0, 8.1, 8.1, 8.91, 9.8, 10.0]);
+
+        // 已在边界时不再调整
+        assert_eq!(throttle_adjustment(10.0, 0.0, min, max), None);
+        assert_eq!(throttle_adjustment(1.0, 0.9, min, max), None);
+    }
+}
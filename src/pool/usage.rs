@@ -1,8 +1,10 @@
 //! 使用量和配额管理模块
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::OnceLock;
 
 /// 请求记录
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +29,43 @@ pub struct RequestLog {
     pub timestamp: DateTime<Utc>,
     /// 耗时（毫秒）
     pub duration_ms: u64,
+    /// 响应内容是否被过滤
+    #[serde(default)]
+    pub was_filtered: bool,
+    /// 上下文窗口使用百分比（来自 contextUsageEvent）
+    #[serde(default)]
+    pub context_window_used_pct: Option<f64>,
+    /// 本次请求消耗的额度（来自 meteringEvent）
+    #[serde(default)]
+    pub credits_used: Option<f64>,
+    /// 流式响应期间收到的上游心跳次数
+    #[serde(default)]
+    pub heartbeat_count: Option<u32>,
+    /// 会话 ID（来自请求头 `X-Session-ID`），用于关联同一多轮对话的记录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+/// 查找重复记录时向前检查的最近记录条数
+const DEDUP_LOOKBACK: usize = 32;
+
+impl RequestLog {
+    /// 合并较晚提交的重复记录：保留较晚的时间，任一记录失败即视为失败并保留错误信息
+    fn merge_duplicate(&mut self, later: RequestLog) {
+        self.timestamp = self.timestamp.max(later.timestamp);
+        self.success &= later.success;
+        if later.error.is_some() {
+            self.error = later.error;
+        }
+        self.output_tokens = self.output_tokens.max(later.output_tokens);
+        self.duration_ms = self.duration_ms.max(later.duration_ms);
+        self.was_filtered |= later.was_filtered;
+        self.context_window_used_pct = later
+            .context_window_used_pct
+            .or(self.context_window_used_pct);
+        self.credits_used = later.credits_used.or(self.credits_used);
+        self.heartbeat_count = later.heartbeat_count.or(self.heartbeat_count);
+    }
 }
 
 /// 使用限制信息（来自 AWS API）
@@ -48,6 +87,36 @@ pub struct UsageLimits {
     pub user_email: Option<String>,
     /// 订阅类型
     pub subscription_type: Option<String>,
+    /// 请求次数限制（AGENTIC_REQUEST 类型）
+    #[serde(default)]
+    pub request_limits: Option<RequestLimits>,
+    /// 配额查询时间
+    #[serde(default)]
+    pub fetched_at: Option<DateTime<Utc>>,
+}
+
+impl UsageLimits {
+    /// 额度或请求次数任一耗尽即视为耗尽
+    pub fn is_exhausted(&self) -> bool {
+        self.available <= 0.0
+            || self
+                .request_limits
+                .as_ref()
+                .is_some_and(|r| r.available == 0)
+    }
+}
+
+/// 请求次数限制信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestLimits {
+    /// 请求次数限额
+    pub limit: u32,
+    /// 已使用次数
+    pub used: u32,
+    /// 剩余次数
+    pub available: u32,
+    /// 重置日期
+    pub next_reset: Option<DateTime<Utc>>,
 }
 
 /// 免费试用信息
@@ -65,6 +134,8 @@ pub struct RequestLogger {
     logs: VecDeque<RequestLog>,
     /// 最大记录数
     max_logs: usize,
+    /// 被合并的重复记录数
+    dedup_count: u64,
 }
 
 impl RequestLogger {
@@ -73,17 +144,85 @@ impl RequestLogger {
         Self {
             logs: VecDeque::with_capacity(max_logs),
             max_logs,
+            dedup_count: 0,
         }
     }
 
     /// 添加请求记录
+    ///
+    /// 最近记录中已有相同请求 ID 时视为重复记录（同一次上游调用在错误路径和完成路径各记录一次），
+    /// 合并到已有记录而不追加
     pub fn add(&mut self, log: RequestLog) {
+        if let Some(existing) = self
+            .logs
+            .iter_mut()
+            .rev()
+            .take(DEDUP_LOOKBACK)
+            .find(|existing| existing.id == log.id)
+        {
+            existing.merge_duplicate(log);
+            self.dedup_count += 1;
+            return;
+        }
         if self.logs.len() >= self.max_logs {
             self.logs.pop_front();
         }
         self.logs.push_back(log);
     }
 
+    /// 移除最早的 `count` 条记录
+    pub fn remove_oldest(&mut self, count: usize) {
+        self.logs.drain(..count.min(self.logs.len()));
+    }
+
+    /// 按请求 ID 移除记录，返回移除数量
+    pub fn remove_ids(&mut self, ids: &HashSet<&str>) -> usize {
+        let before = self.logs.len();
+        self.logs.retain(|log| !ids.contains(log.id.as_str()));
+        before - self.logs.len()
+    }
+
+    /// 移除并返回早于指定时间的记录
+    pub fn drain_older_than(&mut self, cutoff: DateTime<Utc>) -> Vec<RequestLog> {
+        let (old, recent): (VecDeque<RequestLog>, VecDeque<RequestLog>) =
+            self.logs.drain(..).partition(|l| l.timestamp < cutoff);
+        self.logs = recent;
+        old.into()
+    }
+
+    /// 将记录放回队首（用于撤销 `drain_older_than`）
+    pub fn restore_oldest(&mut self, logs: Vec<RequestLog>) {
+        for log in logs.into_iter().rev() {
+            self.logs.push_front(log);
+        }
+    }
+
+    /// 最大记录数
+    pub fn max_logs(&self) -> usize {
+        self.max_logs
+    }
+
+    /// 调整最大记录数，缩小时移除最早的超出记录，返回移除数量
+    pub fn set_max_logs(&mut self, new_max: usize) -> usize {
+        self.max_logs = new_max;
+        let excess = self.logs.len().saturating_sub(new_max);
+        self.logs.drain(..excess);
+        if new_max > self.logs.capacity() {
+            self.logs.reserve(new_max - self.logs.len());
+        }
+        excess
+    }
+
+    /// 当前记录数
+    pub fn len(&self) -> usize {
+        self.logs.len()
+    }
+
+    /// 清空所有记录
+    pub fn clear(&mut self) {
+        self.logs.clear();
+    }
+
     /// 获取所有记录
     pub fn get_all(&self) -> Vec<RequestLog> {
         self.logs.iter().cloned().collect()
@@ -94,7 +233,34 @@ impl RequestLogger {
         self.logs.iter().rev().take(n).cloned().collect()
     }
 
+    /// 获取指定会话的全部记录（最新的在前）
+    pub fn get_by_session(&self, session_id: &str) -> Vec<RequestLog> {
+        self.logs
+            .iter()
+            .rev()
+            .filter(|l| l.session_id.as_deref() == Some(session_id))
+            .cloned()
+            .collect()
+    }
+
     /// 获取统计信息
+    /// 统计指定时间之后的请求数与错误率
+    pub fn error_rate_since(&self, since: DateTime<Utc>) -> (usize, f64) {
+        let (total, failed) = self
+            .logs
+            .iter()
+            .filter(|l| l.timestamp >= since)
+            .fold((0usize, 0usize), |(total, failed), l| {
+                (total + 1, failed + usize::from(!l.success))
+            });
+        let rate = if total > 0 {
+            failed as f64 / total as f64
+        } else {
+            0.0
+        };
+        (total, rate)
+    }
+
     pub fn get_stats(&self) -> RequestStats {
         let total = self.logs.len();
         let success = self.logs.iter().filter(|l| l.success).count();
@@ -120,10 +286,106 @@ impl RequestLogger {
             total_input_tokens,
             total_output_tokens,
             avg_duration_ms: avg_duration,
+            dedup_count: self.dedup_count,
         }
     }
 }
 
+impl RequestLogger {
+    /// 按最近 `sample` 条带额度数据的成功请求计算平均额度消耗，返回 (总体, 按模型)
+    pub fn avg_credits_per_request(&self, sample: usize) -> (Option<f64>, HashMap<String, f64>) {
+        let recent: Vec<(&str, f64)> = self
+            .logs
+            .iter()
+            .rev()
+            .filter(|l| l.success)
+            .filter_map(|l| l.credits_used.map(|c| (l.model.as_str(), c)))
+            .take(sample)
+            .collect();
+        if recent.is_empty() {
+            return (None, HashMap::new());
+        }
+
+        let overall = recent.iter().map(|(_, c)| c).sum::<f64>() / recent.len() as f64;
+        let mut per_model: HashMap<&str, (f64, usize)> = HashMap::new();
+        for (model, credits) in &recent {
+            let entry = per_model.entry(model).or_insert((0.0, 0));
+            entry.0 += credits;
+            entry.1 += 1;
+        }
+        let per_model = per_model
+            .into_iter()
+            .map(|(model, (sum, count))| (model.to_string(), sum / count as f64))
+            .collect();
+        (Some(overall), per_model)
+    }
+
+    /// 按 UTC 日期统计指定账号最近 `days` 天（含今天）的请求数，仅返回有记录的日期（由旧到新）
+    pub fn get_daily_request_history(&self, account_id: &str, days: u32) -> Vec<DailyCount> {
+        let today = Utc::now().date_naive();
+        let first_day = today - chrono::Duration::days(i64::from(days.max(1)) - 1);
+        let mut daily: BTreeMap<NaiveDate, DailyCount> = BTreeMap::new();
+        for log in self.logs.iter().filter(|l| l.account_id == account_id) {
+            let date = log.timestamp.date_naive();
+            if date < first_day {
+                continue;
+            }
+            let count = daily.entry(date).or_insert_with(|| DailyCount {
+                date,
+                total_requests: 0,
+                success: 0,
+                input_tokens: 0,
+                output_tokens: 0,
+            });
+            count.total_requests += 1;
+            count.success += usize::from(log.success);
+            count.input_tokens += log.input_tokens as i64;
+            // 忽略 -1（流式请求无法统计）
+            count.output_tokens += log.output_tokens.max(0) as i64;
+        }
+        daily.into_values().collect()
+    }
+
+    /// 获取出现次数最多的 N 条错误信息（归一化后统计）
+    pub fn top_error_messages(&self, n: usize) -> Vec<(String, usize)> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for error in self.logs.iter().filter_map(|l| l.error.as_deref()) {
+            *counts.entry(normalize_error_message(error)).or_insert(0) += 1;
+        }
+
+        let mut top: Vec<(String, usize)> = counts.into_iter().collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(n);
+        top
+    }
+}
+
+/// 归一化错误信息：替换时间戳、UUID 和纯数字等可变内容
+fn normalize_error_message(message: &str) -> String {
+    static PATTERNS: OnceLock<[(Regex, &'static str); 3]> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| {
+        [
+            (
+                Regex::new(r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2})?")
+                    .unwrap(),
+                "<ts>",
+            ),
+            (
+                Regex::new(r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b")
+                    .unwrap(),
+                "<uuid>",
+            ),
+            (Regex::new(r"\b\d+\b").unwrap(), "<N>"),
+        ]
+    });
+
+    let mut normalized = message.to_string();
+    for (regex, replacement) in patterns {
+        normalized = regex.replace_all(&normalized, *replacement).into_owned();
+    }
+    normalized
+}
+
 /// 请求统计
 #[derive(Debug, Clone, Serialize)]
 pub struct RequestStats {
@@ -133,6 +395,78 @@ pub struct RequestStats {
     pub total_input_tokens: i64,
     pub total_output_tokens: i64,
     pub avg_duration_ms: u64,
+    /// 被合并的重复记录数
+    pub dedup_count: u64,
+}
+
+/// 单日请求统计
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DailyCount {
+    pub date: NaiveDate,
+    pub total_requests: usize,
+    pub success: usize,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+}
+
+/// 时间线中的一个统计区间
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageIntervalBucket {
+    pub interval_start: DateTime<Utc>,
+    pub interval_end: DateTime<Utc>,
+    pub total_requests: usize,
+    pub success: usize,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub accounts_used: HashSet<String>,
+}
+
+/// 按 `interval_mins` 分钟切分请求记录（区间按 Unix 纪元对齐）
+///
+/// 返回从最早到最晚记录之间的连续区间，中间没有请求的区间也会保留
+pub fn aggregate_usage_timeline(
+    logs: &[RequestLog],
+    interval_mins: u32,
+) -> Vec<UsageIntervalBucket> {
+    let interval_secs = i64::from(interval_mins.max(1)) * 60;
+    let mut buckets: BTreeMap<i64, UsageIntervalBucket> = BTreeMap::new();
+    for log in logs {
+        let start = log.timestamp.timestamp().div_euclid(interval_secs) * interval_secs;
+        let bucket = buckets
+            .entry(start)
+            .or_insert_with(|| empty_bucket(start, interval_secs));
+        bucket.total_requests += 1;
+        if log.success {
+            bucket.success += 1;
+        }
+        bucket.input_tokens += log.input_tokens as i64;
+        bucket.output_tokens += log.output_tokens as i64;
+        bucket.accounts_used.insert(log.account_id.clone());
+    }
+
+    let (Some(&first), Some(&last)) = (buckets.keys().next(), buckets.keys().next_back()) else {
+        return Vec::new();
+    };
+    (0..=(last - first) / interval_secs)
+        .map(|i| first + i * interval_secs)
+        .map(|start| {
+            buckets
+                .remove(&start)
+                .unwrap_or_else(|| empty_bucket(start, interval_secs))
+        })
+        .collect()
+}
+
+fn empty_bucket(start: i64, interval_secs: i64) -> UsageIntervalBucket {
+    UsageIntervalBucket {
+        interval_start: DateTime::from_timestamp(start, 0).unwrap_or_default(),
+        interval_end: DateTime::from_timestamp(start + interval_secs, 0).unwrap_or_default(),
+        total_requests: 0,
+        success: 0,
+        input_tokens: 0,
+        output_tokens: 0,
+        accounts_used: HashSet::new(),
+    }
 }
 
 impl Default for RequestLogger {
@@ -141,6 +475,67 @@ impl Default for RequestLogger {
     }
 }
 
+/// 将请求记录序列化为 JSON 数组
+///
+/// 超过 `max_bytes` 时循环去掉最早的 20%（至少 1 条）直到能够容纳；返回 JSON 与去掉的最早记录数。
+/// 每条记录只序列化一次
+pub fn logs_to_json_within(
+    logs: &[RequestLog],
+    max_bytes: Option<u64>,
+) -> serde_json::Result<(String, usize)> {
+    let entries = logs
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<serde_json::Result<Vec<_>>>()?;
+    // 方括号占 2 字节，每条记录之间另有 1 字节逗号
+    let size_of = |entries: &[String]| {
+        2 + entries.iter().map(|e| e.len() as u64).sum::<u64>()
+            + entries.len().saturating_sub(1) as u64
+    };
+
+    let mut start = 0;
+    if let Some(max_bytes) = max_bytes {
+        while start < entries.len() && size_of(&entries[start..]) > max_bytes {
+            start += ((entries.len() - start) / 5).max(1);
+        }
+    }
+    Ok((format!("[{}]", entries[start..].join(",")), start))
+}
+
+/// 将请求记录导出为 CSV（含表头）
+pub fn logs_to_csv(logs: &[RequestLog]) -> anyhow::Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "id",
+        "account_id",
+        "account_name",
+        "model",
+        "input_tokens",
+        "output_tokens",
+        "success",
+        "error",
+        "timestamp",
+        "duration_ms",
+        "session_id",
+    ])?;
+    for log in logs {
+        writer.write_record([
+            log.id.clone(),
+            log.account_id.clone(),
+            log.account_name.clone(),
+            log.model.clone(),
+            log.input_tokens.to_string(),
+            log.output_tokens.to_string(),
+            log.success.to_string(),
+            log.error.clone().unwrap_or_default(),
+            log.timestamp.to_rfc3339(),
+            log.duration_ms.to_string(),
+            log.session_id.clone().unwrap_or_default(),
+        ])?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
 /// AWS 使用限制 API 响应结构
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -191,13 +586,19 @@ pub struct AwsSubscriptionInfo {
 }
 
 /// 检查账号使用限制
-pub async fn check_usage_limits(access_token: &str) -> anyhow::Result<UsageLimits> {
+pub async fn check_usage_limits(
+    config: &crate::model::config::Config,
+    access_token: &str,
+) -> anyhow::Result<UsageLimits> {
     let client = reqwest::Client::new();
 
-    let url = "https://codewhisperer.us-east-1.amazonaws.com/getUsageLimits?isEmailRequired=true&origin=AI_EDITOR&resourceType=AGENTIC_REQUEST";
+    let url = format!(
+        "{}/getUsageLimits?isEmailRequired=true&origin=AI_EDITOR&resourceType=AGENTIC_REQUEST",
+        config.kiro_api_url()
+    );
 
     let response = client
-        .get(url)
+        .get(&url)
         .header("Authorization", format!("Bearer {}", access_token))
         .header("x-amz-user-agent", "aws-sdk-js/1.0.0 KiroIDE")
         .header("user-agent", "aws-sdk-js/1.0.0 KiroIDE")
@@ -211,6 +612,29 @@ pub async fn check_usage_limits(access_token: &str) -> anyhow::Result<UsageLimit
     }
 
     let aws_response: AwsUsageLimitsResponse = response.json().await?;
+    parse_usage_limits(&aws_response)
+}
+
+/// 将 AWS 响应解析为使用限制（CREDIT 必需，AGENTIC_REQUEST 可选）
+pub fn parse_usage_limits(aws_response: &AwsUsageLimitsResponse) -> anyhow::Result<UsageLimits> {
+    let next_reset = aws_response
+        .next_date_reset
+        .map(|ts| DateTime::from_timestamp_millis(ts as i64).unwrap_or_default());
+
+    let request_limits = aws_response
+        .usage_breakdown_list
+        .iter()
+        .find(|b| b.resource_type == "AGENTIC_REQUEST")
+        .map(|b| {
+            let limit = b.usage_limit.unwrap_or(0).max(0) as u32;
+            let used = b.current_usage.unwrap_or(0).max(0) as u32;
+            RequestLimits {
+                limit,
+                used,
+                available: limit.saturating_sub(used),
+                next_reset,
+            }
+        });
 
     // 解析 CREDIT 类型的使用限制
     for breakdown in &aws_response.usage_breakdown_list {
@@ -240,10 +664,6 @@ pub async fn check_usage_limits(access_token: &str) -> anyhow::Result<UsageLimit
                 None
             };
 
-            let next_reset = aws_response
-                .next_date_reset
-                .map(|ts| DateTime::from_timestamp_millis(ts as i64).unwrap_or_default());
-
             return Ok(UsageLimits {
                 resource_type: "CREDIT".to_string(),
                 usage_limit: total_limit,
@@ -259,9 +679,303 @@ pub async fn check_usage_limits(access_token: &str) -> anyhow::Result<UsageLimit
                     .subscription_info
                     .as_ref()
                     .and_then(|s| s.subscription_type.clone()),
+                request_limits,
+                fetched_at: Some(Utc::now()),
             });
         }
     }
 
     anyhow::bail!("未找到 CREDIT 类型的使用限制")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_log(error: String) -> RequestLog {
+        RequestLog {
+            id: uuid::Uuid::new_v4().to_string(),
+            account_id: "a".to_string(),
+            account_name: "A".to_string(),
+            model: "claude-sonnet-4".to_string(),
+            input_tokens: 0,
+            output_tokens: 0,
+            success: false,
+            error: Some(error),
+            timestamp: Utc::now(),
+            duration_ms: 0,
+            was_filtered: false,
+            context_window_used_pct: None,
+            credits_used: None,
+            heartbeat_count: None,
+            session_id: None,
+        }
+    }
+
+    #[test]
+    fn test_logs_to_json_within_keeps_newest() {
+        let logs: Vec<RequestLog> = (0..5)
+            .map(|i| RequestLog {
+                input_tokens: i,
+                ..error_log("x".repeat(100))
+            })
+            .collect();
+        let (full, trimmed) = logs_to_json_within(&logs, None).unwrap();
+        assert_eq!(full, serde_json::to_string(&logs).unwrap());
+        assert_eq!(trimmed, 0);
+        assert_eq!(
+            logs_to_json_within(&logs, Some(full.len() as u64)).unwrap(),
+            (full.clone(), 0)
+        );
+
+        let limit = full.len() as u64 / 2;
+        let (truncated, trimmed) = logs_to_json_within(&logs, Some(limit)).unwrap();
+        assert!(truncated.len() as u64 <= limit);
+        let kept: Vec<RequestLog> = serde_json::from_str(&truncated).unwrap();
+        assert_eq!(kept.len(), 2);
+        assert_eq!(trimmed, 3);
+        assert_eq!(kept.last().unwrap().input_tokens, 4);
+
+        assert_eq!(
+            logs_to_json_within(&logs, Some(1)).unwrap(),
+            ("[]".to_string(), 5)
+        );
+    }
+
+    #[test]
+    fn test_get_by_session_filters_and_orders_newest_first() {
+        let mut logger = RequestLogger::new(10);
+        for (i, session) in [Some("chat-1"), None, Some("chat-2"), Some("chat-1")]
+            .into_iter()
+            .enumerate()
+        {
+            logger.add(RequestLog {
+                input_tokens: i as i32,
+                session_id: session.map(str::to_string),
+                ..error_log("x".to_string())
+            });
+        }
+
+        let logs = logger.get_by_session("chat-1");
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].input_tokens, 3);
+        assert_eq!(logs[1].input_tokens, 0);
+        assert_eq!(logger.get_by_session("chat-2").len(), 1);
+        assert!(logger.get_by_session("chat-3").is_empty());
+    }
+
+    #[test]
+    fn test_set_max_logs_grows_and_shrinks() {
+        let mut logger = RequestLogger::new(3);
+        for i in 0..3 {
+            let mut log = error_log("x".to_string());
+            log.input_tokens = i;
+            logger.add(log);
+        }
+
+        assert_eq!(logger.set_max_logs(5), 0);
+        for i in 3..5 {
+            let mut log = error_log("x".to_string());
+            log.input_tokens = i;
+            logger.add(log);
+        }
+        assert_eq!(logger.len(), 5);
+
+        // 缩小时保留最新的记录
+        assert_eq!(logger.set_max_logs(2), 3);
+        assert_eq!(logger.max_logs(), 2);
+        let tokens: Vec<i32> = logger.get_all().iter().map(|l| l.input_tokens).collect();
+        assert_eq!(tokens, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_daily_request_history_buckets_by_date() {
+        let mut logger = RequestLogger::new(100);
+        let now = Utc::now();
+        for (i, (days_ago, success)) in [(0, true), (0, false), (1, true), (3, true), (40, true)]
+            .into_iter()
+            .enumerate()
+        {
+            let mut log = error_log("x".to_string());
+            log.success = success;
+            log.input_tokens = 10 + i as i32;
+            log.output_tokens = 5;
+            log.timestamp = now - chrono::Duration::days(days_ago);
+            logger.add(log);
+        }
+        let mut other = error_log("x".to_string());
+        other.account_id = "b".to_string();
+        logger.add(other);
+
+        let history = logger.get_daily_request_history("a", 30);
+        let dates: Vec<NaiveDate> = history.iter().map(|d| d.date).collect();
+        assert_eq!(
+            dates,
+            [3, 1, 0].map(|d| (now - chrono::Duration::days(d)).date_naive())
+        );
+        assert_eq!(history[2].total_requests, 2);
+        assert_eq!(history[2].success, 1);
+        assert_eq!(history[2].input_tokens, 21);
+        assert_eq!(history[2].output_tokens, 10);
+
+        assert!(logger.get_daily_request_history("missing", 30).is_empty());
+    }
+
+    #[test]
+    fn test_add_merges_duplicate_request_id() {
+        let mut logger = RequestLogger::new(100);
+        let first = error_log("上游 API 调用失败".to_string());
+        // 同一请求的完成记录晚于错误记录提交，中间夹着其他请求
+        let mut second = first.clone();
+        second.timestamp = first.timestamp + chrono::Duration::seconds(5);
+        second.error = None;
+        second.output_tokens = 10;
+        logger.add(first.clone());
+        logger.add(error_log("x".to_string()));
+        logger.add(second.clone());
+
+        let logs = logger.get_all();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].id, first.id);
+        assert_eq!(logs[0].timestamp, second.timestamp);
+        assert!(!logs[0].success);
+        assert_eq!(logs[0].error, first.error);
+        assert_eq!(logs[0].output_tokens, 10);
+        assert_eq!(logger.get_stats().dedup_count, 1);
+
+        // 账号、模型、输入 tokens 相同但请求 ID 不同的记录不合并
+        let mut other = first.clone();
+        other.id = uuid::Uuid::new_v4().to_string();
+        logger.add(other);
+        assert_eq!(logger.len(), 3);
+        assert_eq!(logger.get_stats().dedup_count, 1);
+    }
+
+    #[test]
+    fn test_top_error_messages_collapses_variable_data() {
+        let mut logger = RequestLogger::new(100);
+        for i in 0..5 {
+            logger.add(error_log(format!(
+                "上游 API 调用失败: request {} at 2025-01-0{}T10:00:00Z",
+                uuid::Uuid::new_v4(),
+                i + 1
+            )));
+        }
+        logger.add(error_log("流式请求失败: 429 Too Many Requests".to_string()));
+
+        let top = logger.top_error_messages(10);
+        assert_eq!(top.len(), 2);
+        assert_eq!(
+            top[0],
+            ("上游 API 调用失败: request <uuid> at <ts>".to_string(), 5)
+        );
+        assert_eq!(
+            top[1],
+            ("流式请求失败: <N> Too Many Requests".to_string(), 1)
+        );
+
+        assert_eq!(logger.top_error_messages(1).len(), 1);
+    }
+
+    #[test]
+    fn test_aggregate_usage_timeline_15_minute_buckets() {
+        let base = DateTime::parse_from_rfc3339("2025-08-01T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let log_at = |mins: i64, account: &str, success: bool| RequestLog {
+            account_id: account.to_string(),
+            input_tokens: 10,
+            output_tokens: 5,
+            success,
+            error: None,
+            timestamp: base + chrono::Duration::minutes(mins),
+            ..error_log(String::new())
+        };
+        // 10:00 ~ 10:30 之间的请求，10:15 区间为空
+        let logs = vec![
+            log_at(0, "a", true),
+            log_at(14, "b", false),
+            log_at(30, "a", true),
+        ];
+
+        let buckets = aggregate_usage_timeline(&logs, 15);
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[0].interval_start, base);
+        assert_eq!(
+            buckets[0].interval_end,
+            base + chrono::Duration::minutes(15)
+        );
+        assert_eq!(buckets[0].total_requests, 2);
+        assert_eq!(buckets[0].success, 1);
+        assert_eq!(buckets[0].input_tokens, 20);
+        assert_eq!(buckets[0].output_tokens, 10);
+        assert_eq!(buckets[0].accounts_used.len(), 2);
+        assert_eq!(buckets[1].total_requests, 0);
+        assert_eq!(
+            buckets[2].interval_start,
+            base + chrono::Duration::minutes(30)
+        );
+        assert_eq!(buckets[2].total_requests, 1);
+
+        assert!(aggregate_usage_timeline(&[], 15).is_empty());
+    }
+
+    #[test]
+    fn test_parse_usage_limits_with_agentic_request() {
+        let json = serde_json::json!({
+            "usageBreakdownList": [
+                {
+                    "resourceType": "CREDIT",
+                    "usageLimitWithPrecision": 50.0,
+                    "currentUsageWithPrecision": 10.0
+                },
+                {
+                    "resourceType": "AGENTIC_REQUEST",
+                    "usageLimit": 100,
+                    "currentUsage": 100
+                }
+            ],
+            "nextDateReset": 1767225600000.0
+        });
+        let response: AwsUsageLimitsResponse = serde_json::from_value(json).unwrap();
+        let usage = parse_usage_limits(&response).unwrap();
+
+        assert_eq!(usage.available, 40.0);
+        let requests = usage.request_limits.as_ref().unwrap();
+        assert_eq!(
+            (requests.limit, requests.used, requests.available),
+            (100, 100, 0)
+        );
+        assert!(requests.next_reset.is_some());
+        assert!(usage.is_exhausted());
+    }
+
+    #[test]
+    fn test_parse_usage_limits_without_agentic_request() {
+        let json = serde_json::json!({
+            "usageBreakdownList": [{
+                "resourceType": "CREDIT",
+                "usageLimitWithPrecision": 50.0,
+                "currentUsageWithPrecision": 10.0
+            }]
+        });
+        let response: AwsUsageLimitsResponse = serde_json::from_value(json).unwrap();
+        let usage = parse_usage_limits(&response).unwrap();
+
+        assert!(usage.request_limits.is_none());
+        assert!(!usage.is_exhausted());
+    }
+
+    #[test]
+    fn test_logs_to_csv_header_and_escaping() {
+        let csv = logs_to_csv(&[error_log("上游错误, 请重试".to_string())]).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("id,account_id,account_name,model,input_tokens,output_tokens,success,error,timestamp,duration_ms,session_id")
+        );
+        let row = lines.next().unwrap();
+        assert!(row.contains(",false,\"上游错误, 请重试\","));
+        assert!(lines.next().is_none());
+    }
+}
@@ -0,0 +1,95 @@
+//! 请求记录 Webhook 推送
+//!
+//! 将每条请求记录以 JSON 形式实时 POST 到外部地址，配置密钥时附带 HMAC-SHA256 签名
+
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::http_client::{build_client, ProxyConfig};
+use crate::model::config::RequestLogWebhook;
+
+use super::usage::RequestLog;
+
+/// 签名请求头
+pub const SIGNATURE_HEADER: &str = "X-Kiro-Signature-256";
+/// 推送超时（秒）
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+/// 首次重试前的等待时间（毫秒），之后每次翻倍
+const WEBHOOK_BASE_BACKOFF_MS: u64 = 500;
+
+/// 计算请求体签名，格式为 `sha256=<hex>`
+pub fn sign_payload(secret: &str, payload: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC 接受任意长度的密钥");
+    mac.update(payload);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// 第 `attempt` 次重试前的退避时间
+fn backoff_delay(attempt: u32) -> Duration {
+    Duration::from_millis(WEBHOOK_BASE_BACKOFF_MS.saturating_mul(1 << attempt.min(16)))
+}
+
+/// 推送一条请求记录，失败时按指数退避重试 `max_retries` 次
+pub async fn deliver_request_log(
+    webhook: &RequestLogWebhook,
+    proxy: Option<&ProxyConfig>,
+    log: &RequestLog,
+) -> anyhow::Result<()> {
+    let body = serde_json::to_vec(log)?;
+    let signature = webhook
+        .secret
+        .as_deref()
+        .map(|secret| sign_payload(secret, &body));
+    let client = build_client(proxy, WEBHOOK_TIMEOUT_SECS)?;
+
+    let mut attempt = 0;
+    loop {
+        let mut request = client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .body(body.clone());
+        if let Some(signature) = &signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => anyhow::anyhow!("Webhook 返回错误状态: {}", response.status()),
+            Err(e) => e.into(),
+        };
+        if attempt >= webhook.max_retries {
+            return Err(error);
+        }
+        tracing::debug!(
+            "请求记录 Webhook 推送失败，第 {} 次重试: {}",
+            attempt + 1,
+            error
+        );
+        tokio::time::sleep(backoff_delay(attempt)).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_payload() {
+        // RFC 4231 测试用例 2
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles() {
+        assert_eq!(backoff_delay(0), Duration::from_millis(500));
+        assert_eq!(backoff_delay(1), Duration::from_millis(1000));
+        assert_eq!(backoff_delay(3), Duration::from_millis(4000));
+    }
+}
@@ -11,7 +11,18 @@ use crate::anthropic::types::{
     CountTokensRequest, CountTokensResponse, Message, SystemMessage, Tool,
 };
 use crate::http_client::{build_client, ProxyConfig};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::sync::OnceLock;
+use std::time::Duration;
+
+pub mod cache;
+
+pub use cache::{CacheStats, TokenCountCache};
+
+/// token 计数缓存有效期（5 分钟）
+const COUNT_TOKENS_CACHE_TTL_SECS: u64 = 5 * 60;
 
 /// Count Tokens API 配置
 #[derive(Clone, Default)]
@@ -41,6 +52,19 @@ fn get_config() -> Option<&'static CountTokensConfig> {
     COUNT_TOKENS_CONFIG.get()
 }
 
+/// 全局 token 计数缓存
+static COUNT_TOKENS_CACHE: Lazy<TokenCountCache> = Lazy::new(|| {
+    TokenCountCache::new(
+        cache::DEFAULT_CAPACITY,
+        Duration::from_secs(COUNT_TOKENS_CACHE_TTL_SECS),
+    )
+});
+
+/// 全局 token 计数缓存的命中统计
+pub fn with_cache_stats() -> CacheStats {
+    COUNT_TOKENS_CACHE.stats()
+}
+
 /// 判断字符是否为非西文字符
 ///
 /// 西文字符包括：
@@ -101,12 +125,31 @@ pub fn count_tokens(text: &str) -> u64 {
 
 /// 估算请求的输入 tokens
 ///
-/// 优先调用远程 API，失败时回退到本地计算
+/// 相同输入在 5 分钟内直接返回缓存结果（最多缓存 256 条，按 LRU 淘汰）；
+/// 否则优先调用远程 API，失败时回退到本地计算
 pub(crate) fn count_all_tokens(
     model: String,
     system: Option<Vec<SystemMessage>>,
     messages: Vec<Message>,
     tools: Option<Vec<Tool>>,
+) -> u64 {
+    let key = TokenCountCache::key(&model, &system, &messages, &tools);
+    if let Some(tokens) = COUNT_TOKENS_CACHE.get(&key) {
+        tracing::debug!("count_tokens 命中缓存: {}", tokens);
+        return tokens;
+    }
+
+    let tokens = count_all_tokens_uncached(model, system, messages, tools);
+    COUNT_TOKENS_CACHE.insert(key, tokens);
+    tokens
+}
+
+/// 不经缓存估算请求的输入 tokens
+fn count_all_tokens_uncached(
+    model: String,
+    system: Option<Vec<SystemMessage>>,
+    messages: Vec<Message>,
+    tools: Option<Vec<Tool>>,
 ) -> u64 {
     // 检查是否配置了远程 API
     if let Some(config) = get_config() {
@@ -240,3 +283,183 @@ pub(crate) fn estimate_output_tokens(content: &[serde_json::Value]) -> i32 {
 
     total.max(1)
 }
+
+/// 单条消息估算 tokens 超过该值时提示截断风险
+const LONG_MESSAGE_TOKENS: u64 = 50_000;
+
+/// 消息校验问题级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationSeverity {
+    Warning,
+    Error,
+}
+
+/// 消息列表的结构性问题
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationWarning {
+    pub severity: ValidationSeverity,
+    pub message: String,
+    pub message_index: usize,
+}
+
+/// 检查消息列表中的常见问题：超长消息、空内容，以及找不到对应 tool_use 的 tool_result
+pub fn validate_messages(messages: &[Message]) -> Vec<ValidationWarning> {
+    let mut warnings = Vec::new();
+    let mut tool_use_ids: HashSet<&str> = HashSet::new();
+    let mut push = |severity, message: String, message_index| {
+        warnings.push(ValidationWarning {
+            severity,
+            message,
+            message_index,
+        })
+    };
+
+    for (index, msg) in messages.iter().enumerate() {
+        let blocks: &[serde_json::Value] = match &msg.content {
+            serde_json::Value::String(s) => {
+                if s.trim().is_empty() {
+                    push(
+                        ValidationSeverity::Warning,
+                        "消息内容为空".to_string(),
+                        index,
+                    );
+                }
+                &[]
+            }
+            serde_json::Value::Array(arr) => {
+                if arr.is_empty() {
+                    push(
+                        ValidationSeverity::Warning,
+                        "消息内容为空".to_string(),
+                        index,
+                    );
+                }
+                arr
+            }
+            _ => &[],
+        };
+
+        for block in blocks {
+            match block.get("type").and_then(|v| v.as_str()) {
+                Some("text") => {
+                    let text = block.get("text").and_then(|v| v.as_str()).unwrap_or("");
+                    if text.trim().is_empty() {
+                        push(
+                            ValidationSeverity::Warning,
+                            "存在空的文本块".to_string(),
+                            index,
+                        );
+                    }
+                }
+                Some("tool_use") if msg.role == "assistant" => {
+                    if let Some(id) = block.get("id").and_then(|v| v.as_str()) {
+                        tool_use_ids.insert(id);
+                    }
+                }
+                Some("tool_result") => {
+                    let id = block
+                        .get("tool_use_id")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    if !tool_use_ids.contains(id) {
+                        push(
+                            ValidationSeverity::Error,
+                            format!("tool_result {} 之前没有对应的 assistant tool_use", id),
+                            index,
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let tokens = message_tokens(msg);
+        if tokens > LONG_MESSAGE_TOKENS {
+            push(
+                ValidationSeverity::Warning,
+                format!(
+                    "消息约 {} tokens，超过 {}，可能被截断",
+                    tokens, LONG_MESSAGE_TOKENS
+                ),
+                index,
+            );
+        }
+    }
+
+    warnings
+}
+
+/// 估算单条消息的 tokens（文本块与 tool_result 内容）
+fn message_tokens(msg: &Message) -> u64 {
+    match &msg.content {
+        serde_json::Value::String(s) => count_tokens(s),
+        serde_json::Value::Array(arr) => arr
+            .iter()
+            .map(|block| match block.get("content") {
+                Some(serde_json::Value::String(s)) => count_tokens(s),
+                Some(serde_json::Value::Array(items)) => items
+                    .iter()
+                    .filter_map(|item| item.get("text").and_then(|v| v.as_str()))
+                    .map(count_tokens)
+                    .sum(),
+                _ => block
+                    .get("text")
+                    .and_then(|v| v.as_str())
+                    .map(count_tokens)
+                    .unwrap_or(0),
+            })
+            .sum(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(role: &str, content: serde_json::Value) -> Message {
+        Message {
+            role: role.to_string(),
+            content,
+        }
+    }
+
+    #[test]
+    fn test_validate_messages_flags_empty_and_long_content() {
+        let warnings = validate_messages(&[
+            message("user", json!("  ")),
+            message("user", json!([{"type": "text", "text": ""}])),
+            message("user", json!("a".repeat(400_000))),
+            message("user", json!("hello")),
+        ]);
+        let indices: Vec<usize> = warnings.iter().map(|w| w.message_index).collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+        assert!(warnings
+            .iter()
+            .all(|w| w.severity == ValidationSeverity::Warning));
+    }
+
+    #[test]
+    fn test_validate_messages_requires_preceding_tool_use() {
+        let tool_result =
+            |id: &str| json!([{"type": "tool_result", "tool_use_id": id, "content": "ok"}]);
+        let warnings = validate_messages(&[
+            message("user", json!("run it")),
+            message(
+                "assistant",
+                json!([{"type": "tool_use", "id": "t1", "name": "Bash", "input": {}}]),
+            ),
+            message("user", tool_result("t1")),
+            message("user", tool_result("t2")),
+        ]);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, ValidationSeverity::Error);
+        assert_eq!(warnings[0].message_index, 3);
+        assert_eq!(
+            serde_json::to_value(&warnings[0]).unwrap()["severity"],
+            "error"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! token 计数缓存
+//!
+//! 以 `(模型, 输入哈希)` 为键缓存 `count_all_tokens` 的结果，
+//! 条目超过有效期即失效，容量满时淘汰最久未使用的条目
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::anthropic::types::{Message, SystemMessage, Tool};
+
+/// 默认最大条目数
+pub const DEFAULT_CAPACITY: usize = 256;
+
+/// 缓存键：(模型, system + messages + tools 序列化后的哈希)
+pub type CacheKey = (String, u64);
+
+/// 缓存命中统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+struct Entry {
+    tokens: u64,
+    cached_at: Instant,
+    /// 最近一次访问的序号，越小越久未使用
+    last_used: u64,
+}
+
+struct Inner {
+    entries: HashMap<CacheKey, Entry>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl Inner {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+/// 线程安全的 LRU token 计数缓存
+pub struct TokenCountCache {
+    inner: Mutex<Inner>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl TokenCountCache {
+    /// 创建指定容量与有效期的缓存
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                entries: HashMap::with_capacity(capacity),
+                tick: 0,
+                stats: CacheStats::default(),
+            }),
+            capacity: capacity.max(1),
+            ttl,
+        }
+    }
+
+    /// 计算缓存键（对序列化后的输入做哈希）
+    pub fn key(
+        model: &str,
+        system: &Option<Vec<SystemMessage>>,
+        messages: &[Message],
+        tools: &Option<Vec<Tool>>,
+    ) -> CacheKey {
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_string(system)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        serde_json::to_string(messages)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        serde_json::to_string(tools)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        (model.to_string(), hasher.finish())
+    }
+
+    /// 获取未过期的缓存值，过期条目会被移除
+    pub fn get(&self, key: &CacheKey) -> Option<u64> {
+        let mut inner = self.inner.lock().unwrap();
+        let tick = inner.next_tick();
+        let hit = match inner.entries.get_mut(key) {
+            Some(entry) if entry.cached_at.elapsed() < self.ttl => {
+                entry.last_used = tick;
+                Some(entry.tokens)
+            }
+            Some(_) => {
+                inner.entries.remove(key);
+                None
+            }
+            None => None,
+        };
+        match hit {
+            Some(_) => inner.stats.hits += 1,
+            None => inner.stats.misses += 1,
+        }
+        hit
+    }
+
+    /// 写入缓存，容量已满时先淘汰最久未使用的条目
+    pub fn insert(&self, key: CacheKey, tokens: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.entries.contains_key(&key) && inner.entries.len() >= self.capacity {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+                inner.stats.evictions += 1;
+            }
+        }
+        let last_used = inner.next_tick();
+        inner.entries.insert(
+            key,
+            Entry {
+                tokens,
+                cached_at: Instant::now(),
+                last_used,
+            },
+        );
+    }
+
+    /// 命中统计
+    pub fn stats(&self) -> CacheStats {
+        self.inner.lock().unwrap().stats
+    }
+
+    /// 当前条目数
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_message(text: &str) -> Message {
+        serde_json::from_value(serde_json::json!({"role": "user", "content": text})).unwrap()
+    }
+
+    fn key(model: &str, text: &str) -> CacheKey {
+        TokenCountCache::key(model, &None, &[user_message(text)], &None)
+    }
+
+    #[test]
+    fn test_cache_key_and_expiry() {
+        let k = key("m", "hello");
+        assert_eq!(k, key("m", "hello"));
+        assert_ne!(k, key("other", "hello"));
+        assert_ne!(k, key("m", "hi"));
+
+        let cache = TokenCountCache::new(DEFAULT_CAPACITY, Duration::from_secs(60));
+        assert_eq!(cache.get(&k), None);
+        cache.insert(k.clone(), 42);
+        assert_eq!(cache.get(&k), Some(42));
+
+        let expired = TokenCountCache::new(DEFAULT_CAPACITY, Duration::ZERO);
+        expired.insert(k.clone(), 42);
+        assert_eq!(expired.get(&k), None);
+        assert!(expired.is_empty());
+    }
+
+    #[test]
+    fn test_lru_eviction_and_stats() {
+        let cache = TokenCountCache::new(2, Duration::from_secs(60));
+        let (a, b, c) = (key("m", "a"), key("m", "b"), key("m", "c"));
+        cache.insert(a.clone(), 1);
+        cache.insert(b.clone(), 2);
+
+        // 访问 a 后，b 成为最久未使用的条目
+        assert_eq!(cache.get(&a), Some(1));
+        cache.insert(c.clone(), 3);
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&b), None);
+        assert_eq!(cache.get(&a), Some(1));
+        assert_eq!(cache.get(&c), Some(3));
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 3,
+                misses: 1,
+                evictions: 1,
+            }
+        );
+    }
+}
@@ -5,15 +5,25 @@ use axum::{
     http::{header, Request, StatusCode},
     middleware::{self, Next},
     response::{Html, IntoResponse, Json, Response},
-    routing::{delete, get, post},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::Instant;
+use tokio::sync::RwLock;
 
+use crate::anthropic::{benchmark, constant_time_eq};
 use crate::kiro::model::credentials::KiroCredentials;
-use crate::pool::{Account, AccountPool, SelectionStrategy};
+use crate::kiro::telemetry::TelemetryStore;
+use crate::model::config::{Config, StrategySchedule};
+use crate::pool::account::{KiroRawCredentials, StatusTransition};
+use crate::pool::circuit::CircuitStatus;
+use crate::pool::manager::{AccountUpdate, JsonImportOutcome};
+use crate::pool::strategy::StrategyConfig;
+use crate::pool::usage::UsageLimits;
+use crate::pool::{Account, AccountPool, SelectionStrategy, StrategyConfigPatch};
 
 const FUSION_PIXEL_FONT_WOFF2: &[u8] =
     include_bytes!("../../assets/fonts/fusion-pixel-12px-monospaced-zh_hans.woff2");
@@ -23,9 +33,32 @@ const PROJECT_ICON_SVG: &[u8] = include_bytes!("../../assets/icon.svg");
 #[derive(Clone)]
 pub struct UiState {
     pub pool: Arc<AccountPool>,
+    pub telemetry: Arc<TelemetryStore>,
     pub start_time: Instant,
     pub version: String,
+    /// 启动时的 API Key（未设置共享配置或配置中没有 apiKey 时使用）
     pub api_key: String,
+    /// 可热更新的共享配置，认证时从中读取当前的 apiKey
+    pub config: Option<Arc<RwLock<Config>>>,
+    /// 管理员密钥（导出未脱敏凭证时需要，未配置则禁用这些接口）
+    pub admin_key: Option<String>,
+    /// 批量导入时并发验证凭证的数量
+    pub import_concurrency: usize,
+}
+
+impl UiState {
+    /// 当前生效的 API Key
+    async fn current_api_key(&self) -> String {
+        match &self.config {
+            Some(config) => config
+                .read()
+                .await
+                .api_key
+                .clone()
+                .unwrap_or_else(|| self.api_key.clone()),
+            None => self.api_key.clone(),
+        }
+    }
 }
 
 /// 认证中间件
@@ -48,9 +81,10 @@ async fn auth_middleware(
     });
 
     let provided_key = auth_header.or(query_key);
+    let api_key = state.current_api_key().await;
 
     match provided_key {
-        Some(key) if key == state.api_key => next.run(request).await,
+        Some(key) if constant_time_eq(&key, &api_key) => next.run(request).await,
         _ => (
             StatusCode::UNAUTHORIZED,
             Json(serde_json::json!({"error": "需要认证，请提供 API 密钥"})),
@@ -59,26 +93,123 @@ async fn auth_middleware(
     }
 }
 
+/// 管理员认证中间件
+///
+/// 只接受 `Authorization: Bearer <adminKey>`，不接受 `?key=` 查询参数；
+/// 未配置 adminKey 时一律拒绝
+async fn admin_auth_middleware(
+    State(state): State<UiState>,
+    request: Request<axum::body::Body>,
+    next: Next,
+) -> Response {
+    let Some(admin_key) = state.admin_key.as_deref() else {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": "未配置 adminKey，该接口已禁用"})),
+        )
+            .into_response();
+    };
+
+    let provided_key = request
+        .headers()
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.trim_start_matches("Bearer "));
+
+    match provided_key {
+        Some(key) if constant_time_eq(key, admin_key) => next.run(request).await,
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({"error": "需要管理员密钥，请通过 Authorization 头提供 adminKey"})),
+        )
+            .into_response(),
+    }
+}
+
 /// 创建 UI 路由
 pub fn create_ui_router(state: UiState) -> Router {
     // 需要认证的 API 路由
     let protected_api = Router::new()
         .route("/api/status", get(get_status))
+        .route("/metrics", get(get_metrics))
         .route("/api/accounts", get(list_accounts))
         .route("/api/accounts", post(add_account))
+        .route("/api/accounts", delete(remove_all_accounts))
         .route("/api/accounts/import", post(import_account))
+        .route("/api/accounts/import/bulk", post(import_accounts_bulk))
+        .route(
+            "/api/accounts/prune-exhausted",
+            post(prune_exhausted_accounts),
+        )
+        .route("/api/accounts/{id}", get(get_account))
         .route("/api/accounts/{id}", delete(remove_account))
+        .route("/api/accounts/{id}", patch(update_account))
+        .route("/api/accounts/{id}/token-expiry", get(get_token_expiry))
+        .route("/api/accounts/{id}/tags", post(set_account_tags))
+        .route("/api/accounts/{id}/notes", get(get_account_notes))
+        .route("/api/accounts/{id}/notes", post(set_account_notes))
+        .route("/api/accounts/{id}/notes", delete(clear_account_notes))
+        .route(
+            "/api/accounts/{id}/credentials",
+            put(update_account_credentials),
+        )
+        .route(
+            "/api/accounts/{id}/rotate-credentials",
+            post(rotate_account_credentials),
+        )
         .route("/api/accounts/{id}/enable", post(enable_account))
         .route("/api/accounts/{id}/disable", post(disable_account))
+        .route("/api/accounts/{id}/reset-stats", post(reset_account_stats))
+        .route("/api/accounts/{id}/pin", post(pin_account))
+        .route("/api/accounts/{id}/unpin", post(unpin_account))
+        .route("/api/accounts/validate-all", post(validate_all_accounts))
+        .route("/api/accounts/assign-to-group", post(assign_to_group))
+        .route("/api/groups", get(list_groups))
+        .route("/api/groups/{id}/accounts", get(list_group_accounts))
+        .route("/api/accounts/by-tag", get(list_accounts_by_tag))
         .route("/api/accounts/{id}/usage", get(get_account_usage))
+        .route("/api/accounts/{id}/benchmark", post(benchmark_account))
+        .route(
+            "/api/accounts/{id}/request-count-history",
+            get(get_request_count_history),
+        )
         .route(
             "/api/accounts/{id}/usage/refresh",
             post(refresh_account_usage),
         )
+        .route(
+            "/api/accounts/{id}/force-refresh-token",
+            post(force_refresh_token),
+        )
+        .route(
+            "/api/accounts/{id}/set-exhausted-until",
+            post(set_exhausted_until),
+        )
+        .route("/api/pool/config", patch(patch_pool_config))
+        .route("/api/pool/rebalance", post(rebalance_pool))
+        .route("/api/pool/warm-up", post(warm_up_pool))
+        .route("/api/pool/merge", post(merge_pool))
+        .route("/api/pool/import-snapshot", post(import_pool_snapshot))
+        .route("/api/pool/reconcile", post(reconcile_pool))
+        .route("/api/pool/simulate-selection", post(simulate_selection))
+        .route("/api/pool/topology", get(get_pool_topology))
+        .route("/api/pool/capacity", get(get_pool_capacity))
+        .route("/api/telemetry/latency", get(get_telemetry_latency))
         .route("/api/strategy", get(get_strategy))
         .route("/api/strategy", post(set_strategy))
+        .route("/api/pool/strategy", patch(set_strategy_with_config))
+        .route(
+            "/api/pool/strategy-schedule",
+            get(get_strategy_schedule).put(set_strategy_schedule),
+        )
         .route("/api/logs", get(get_request_logs))
         .route("/api/logs/stats", get(get_request_stats))
+        .route("/api/logs/count", get(get_request_log_count))
+        .route("/api/logs/session/{session_id}", get(get_session_logs))
+        .route("/api/logs/archive", post(archive_request_logs))
+        .route("/api/stats/errors", get(get_top_errors))
+        .route("/api/stats/timeline", get(get_usage_timeline))
+        .route("/api/stats/fairness", get(get_selection_fairness))
         .route("/api/usage/refresh", post(refresh_all_usage))
         .route("/api/usage", get(get_all_usage))
         .layer(middleware::from_fn_with_state(
@@ -87,15 +218,28 @@ pub fn create_ui_router(state: UiState) -> Router {
         ))
         .with_state(state.clone());
 
-    // 公开路由（登录页面）
+    // 需要管理员密钥的路由（仅接受 Authorization 头，避免密钥出现在 URL 和访问日志中）
+    let admin_api = Router::new()
+        .route("/api/accounts/export", get(export_accounts))
+        .route("/api/pool/snapshot", get(get_pool_snapshot))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin_auth_middleware,
+        ))
+        .with_state(state.clone());
+
+    // 公开路由（登录页面与 API 文档）
     Router::new()
         .route("/", get(index_page))
+        .route("/api/docs", get(api_docs))
+        .route("/api/docs/ui", get(api_docs_ui))
         .route("/assets/icon.svg", get(project_icon))
         .route(
             "/assets/fonts/fusion-pixel-12px-monospaced-zh_hans.woff2",
             get(font_fusion_pixel),
         )
         .merge(protected_api)
+        .merge(admin_api)
 }
 
 /// 首页
@@ -103,6 +247,402 @@ async fn index_page() -> impl IntoResponse {
     Html(include_str!("index.html"))
 }
 
+/// 管理 API 的 OpenAPI 文档
+async fn api_docs() -> impl IntoResponse {
+    Json(generate_openapi_spec())
+}
+
+/// Swagger UI 页面（加载 `/api/docs`）
+async fn api_docs_ui() -> impl IntoResponse {
+    Html(include_str!("docs.html"))
+}
+
+/// 管理 API 文档条目：(方法, 路径, 摘要, 请求体 schema)
+///
+/// 新增 `/api/*` 路由时同步补充此表
+const API_DOC_ROUTES: &[(&str, &str, &str, Option<&str>)] = &[
+    ("get", "/api/status", "获取状态", None),
+    ("get", "/metrics", "Prometheus 指标（文本格式）", None),
+    ("get", "/api/accounts", "获取账号列表", None),
+    (
+        "post",
+        "/api/accounts",
+        "添加账号（验证凭证）",
+        Some("AddAccountRequest"),
+    ),
+    (
+        "delete",
+        "/api/accounts",
+        "删除全部账号（需 `?confirm=true`）",
+        None,
+    ),
+    (
+        "post",
+        "/api/accounts/import",
+        "导入账号（支持 Kiro 原始 JSON 格式）",
+        None,
+    ),
+    (
+        "post",
+        "/api/accounts/import/bulk",
+        "批量导入账号（并发验证，逐条返回结果）",
+        None,
+    ),
+    (
+        "get",
+        "/api/accounts/export",
+        "导出全部账号凭证（未脱敏）",
+        None,
+    ),
+    (
+        "post",
+        "/api/accounts/prune-exhausted",
+        "清理长期配额耗尽的账号",
+        None,
+    ),
+    ("get", "/api/accounts/{id}", "获取单个账号", None),
+    ("delete", "/api/accounts/{id}", "移除账号", None),
+    ("patch", "/api/accounts/{id}", "更新账号属性", None),
+    (
+        "get",
+        "/api/accounts/{id}/token-expiry",
+        "获取账号 accessToken 过期时间",
+        None,
+    ),
+    (
+        "post",
+        "/api/accounts/{id}/tags",
+        "替换账号标签（`{\"tags\": [\"tier=high\", \"opus\"]}`）",
+        None,
+    ),
+    ("get", "/api/accounts/{id}/notes", "获取账号备注", None),
+    ("post", "/api/accounts/{id}/notes", "设置账号备注", None),
+    ("delete", "/api/accounts/{id}/notes", "清除账号备注", None),
+    (
+        "put",
+        "/api/accounts/{id}/credentials",
+        "更新账号凭证",
+        Some("AddAccountRequest"),
+    ),
+    (
+        "post",
+        "/api/accounts/{id}/rotate-credentials",
+        "轮换账号凭证",
+        Some("AddAccountRequest"),
+    ),
+    ("post", "/api/accounts/{id}/enable", "启用账号", None),
+    ("post", "/api/accounts/{id}/disable", "禁用账号", None),
+    (
+        "post",
+        "/api/accounts/{id}/reset-stats",
+        "清零请求 / 错误计数与最近使用时间（冷却中的账号恢复为活跃）",
+        None,
+    ),
+    (
+        "post",
+        "/api/accounts/{id}/pin",
+        "固定账号（不参与自动选择）",
+        None,
+    ),
+    ("post", "/api/accounts/{id}/unpin", "取消固定账号", None),
+    (
+        "post",
+        "/api/accounts/validate-all",
+        "批量验证所有账号凭证",
+        None,
+    ),
+    (
+        "post",
+        "/api/accounts/assign-to-group",
+        "将账号加入分组",
+        None,
+    ),
+    ("get", "/api/groups", "获取分组列表", None),
+    ("get", "/api/groups/{id}/accounts", "获取分组内的账号", None),
+    (
+        "get",
+        "/api/accounts/by-tag",
+        "获取带有指定标签的账号 id",
+        None,
+    ),
+    ("get", "/api/accounts/{id}/usage", "获取账号配额", None),
+    (
+        "post",
+        "/api/accounts/{id}/benchmark",
+        "对账号进行基准测试",
+        None,
+    ),
+    (
+        "get",
+        "/api/accounts/{id}/request-count-history",
+        "获取账号最近每日请求量",
+        None,
+    ),
+    (
+        "post",
+        "/api/accounts/{id}/usage/refresh",
+        "刷新账号配额",
+        None,
+    ),
+    (
+        "post",
+        "/api/accounts/{id}/force-refresh-token",
+        "强制刷新账号 Token",
+        None,
+    ),
+    (
+        "post",
+        "/api/accounts/{id}/set-exhausted-until",
+        "手动设置配额耗尽恢复时间",
+        None,
+    ),
+    ("patch", "/api/pool/config", "运行时调整账号池配置", None),
+    ("post", "/api/pool/rebalance", "重新平衡账号请求计数", None),
+    ("post", "/api/pool/warm-up", "预热账号池", None),
+    ("post", "/api/pool/merge", "合并账号池", None),
+    (
+        "get",
+        "/api/pool/snapshot",
+        "导出账号池快照（含凭证）",
+        None,
+    ),
+    (
+        "post",
+        "/api/pool/import-snapshot",
+        "从快照恢复账号池",
+        None,
+    ),
+    (
+        "post",
+        "/api/pool/reconcile",
+        "比较内存与 accounts.json 的差异",
+        None,
+    ),
+    (
+        "post",
+        "/api/pool/simulate-selection",
+        "模拟策略分配结果",
+        None,
+    ),
+    ("get", "/api/pool/topology", "获取账号池拓扑", None),
+    ("get", "/api/pool/capacity", "估算账号池剩余请求容量", None),
+    (
+        "get",
+        "/api/telemetry/latency",
+        "获取各阶段调用耗时分位数",
+        None,
+    ),
+    ("get", "/api/strategy", "获取策略", None),
+    (
+        "post",
+        "/api/strategy",
+        "设置策略（已弃用）",
+        Some("SetStrategyRequest"),
+    ),
+    (
+        "patch",
+        "/api/pool/strategy",
+        "同时设置策略与策略参数",
+        Some("SetStrategyRequest"),
+    ),
+    (
+        "get",
+        "/api/pool/strategy-schedule",
+        "获取策略定时切换计划",
+        None,
+    ),
+    (
+        "put",
+        "/api/pool/strategy-schedule",
+        "替换策略定时切换计划",
+        Some("StrategySchedule"),
+    ),
+    ("get", "/api/logs", "获取请求记录", None),
+    ("get", "/api/logs/stats", "获取请求统计", None),
+    (
+        "get",
+        "/api/logs/count",
+        "内存中的请求记录数与 request_logs.json 文件大小",
+        None,
+    ),
+    (
+        "get",
+        "/api/logs/session/{session_id}",
+        "获取指定会话的请求记录",
+        None,
+    ),
+    ("post", "/api/logs/archive", "归档旧请求记录", None),
+    ("get", "/api/stats/errors", "获取最常见的错误信息", None),
+    (
+        "get",
+        "/api/stats/timeline",
+        "按自定义间隔获取请求时间线",
+        None,
+    ),
+    ("get", "/api/stats/fairness", "账号负载分布公平性", None),
+    ("post", "/api/usage/refresh", "刷新所有账号配额", None),
+    ("get", "/api/usage", "获取所有配额缓存", None),
+];
+
+/// 生成管理 API 的 OpenAPI 3.1 文档
+fn generate_openapi_spec() -> serde_json::Value {
+    let mut paths = serde_json::Map::new();
+    for (method, path, summary, body_schema) in API_DOC_ROUTES {
+        let parameters: Vec<serde_json::Value> = path
+            .split('/')
+            .filter_map(|seg| seg.strip_prefix('{')?.strip_suffix('}'))
+            .map(|name| {
+                serde_json::json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": {"type": "string"}
+                })
+            })
+            .collect();
+        let success_schema = match (*method, *path) {
+            ("get", "/api/accounts") => serde_json::json!({
+                "type": "array",
+                "items": {"$ref": "#/components/schemas/Account"}
+            }),
+            ("get", "/api/accounts/{id}") => {
+                serde_json::json!({"$ref": "#/components/schemas/Account"})
+            }
+            _ => serde_json::json!({}),
+        };
+
+        let mut operation = serde_json::json!({
+            "summary": summary,
+            "security": [{"bearerAuth": []}, {"apiKey": []}],
+            "responses": {
+                "200": {
+                    "description": "成功",
+                    "content": {"application/json": {"schema": success_schema}}
+                },
+                "400": {"$ref": "#/components/responses/Error"},
+                "401": {"$ref": "#/components/responses/Error"}
+            }
+        });
+        if !parameters.is_empty() {
+            operation["parameters"] = serde_json::Value::Array(parameters);
+        }
+        if let Some(schema) = body_schema {
+            operation["requestBody"] = serde_json::json!({
+                "required": true,
+                "content": {"application/json": {"schema": {"$ref": format!("#/components/schemas/{}", schema)}}}
+            });
+        }
+
+        paths
+            .entry(path.to_string())
+            .or_insert_with(|| serde_json::json!({}))[*method] = operation;
+    }
+
+    let optional_string = serde_json::json!({"type": ["string", "null"]});
+    serde_json::json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "kiro2api Management API",
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "bearerAuth": {"type": "http", "scheme": "bearer"},
+                "apiKey": {"type": "apiKey", "in": "query", "name": "key"}
+            },
+            "responses": {
+                "Error": {
+                    "description": "错误",
+                    "content": {"application/json": {"schema": {"$ref": "#/components/schemas/Error"}}}
+                }
+            },
+            "schemas": {
+                "Error": {
+                    "type": "object",
+                    "properties": {"error": {"type": "string"}}
+                },
+                "AddAccountRequest": {
+                    "type": "object",
+                    "required": ["refreshToken"],
+                    "properties": {
+                        "name": optional_string,
+                        "refreshToken": {"type": "string"},
+                        "authMethod": {"type": ["string", "null"], "enum": ["social", "idc", null]},
+                        "clientId": optional_string,
+                        "clientSecret": optional_string,
+                        "profileArn": optional_string,
+                        "region": optional_string,
+                        "requestTimeoutSecs": {"type": ["integer", "null"], "minimum": 0},
+                        "pinned": {"type": "boolean"},
+                        "displayNameOverride": optional_string,
+                        "proxyUrl": optional_string,
+                        "proxyUsername": optional_string,
+                        "proxyPassword": optional_string,
+                        "notes": optional_string
+                    }
+                },
+                "Account": {
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "string"},
+                        "name": {"type": "string"},
+                        "status": {"type": "string"},
+                        "request_count": {"type": "integer"},
+                        "error_count": {"type": "integer"},
+                        "last_used_at": optional_string,
+                        "created_at": {"type": "string", "format": "date-time"},
+                        "credit_score": {"type": "number"},
+                        "warmup_latency_ms": {"type": ["integer", "null"]},
+                        "notes": optional_string,
+                        "free_trial_active": {"type": "boolean"},
+                        "free_trial_expiry": optional_string,
+                        "free_trial_remaining": {"type": ["number", "null"]},
+                        "pinned": {"type": "boolean"},
+                        "display_name_override": optional_string,
+                        "tags": {"type": "object", "additionalProperties": {"type": "string"}},
+                        "weight": {"type": "integer", "minimum": 0},
+                        "proxy_url": optional_string,
+                        "circuit_state": {"type": "string", "enum": ["closed", "open", "half-open"]},
+                        "history": {"type": "array", "items": {"type": "object"}}
+                    }
+                },
+                "SetStrategyRequest": {
+                    "type": "object",
+                    "required": ["strategy"],
+                    "properties": {
+                        "strategy": {
+                            "type": "string",
+                            "enum": [
+                                "round-robin", "random", "least-used", "sequential-exhaust",
+                                "highest-credit-score", "geo-aware", "capacity-aware", "lowest-latency",
+                                "weighted-round-robin", "least-quota"
+                            ]
+                        },
+                        "config": {"type": ["object", "null"]}
+                    }
+                },
+                "StrategySchedule": {
+                    "type": "object",
+                    "properties": {
+                        "rules": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["cron", "strategy"],
+                                "properties": {
+                                    "cron": {"type": "string", "examples": ["0 9 * * MON-FRI"]},
+                                    "strategy": {"type": "string"}
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
 /// 像素字体静态资源
 async fn font_fusion_pixel() -> impl IntoResponse {
     (
@@ -132,6 +672,12 @@ struct StatusResponse {
     version: String,
     uptime_secs: u64,
     pool: crate::pool::PoolStats,
+    /// 当前全局限速（每秒请求数），未启用时为 null
+    rps_limit: Option<f64>,
+    /// 配额耗尽账号中最早的额度重置时间
+    next_reset_at: Option<String>,
+    /// token 计数缓存命中统计
+    token_cache: crate::token::CacheStats,
 }
 
 /// 获取状态
@@ -142,9 +688,35 @@ async fn get_status(State(state): State<UiState>) -> impl IntoResponse {
         version: state.version.clone(),
         uptime_secs: state.start_time.elapsed().as_secs(),
         pool: stats,
+        rps_limit: state.pool.current_rps().await,
+        next_reset_at: state
+            .pool
+            .estimate_next_reset()
+            .await
+            .map(|t| t.to_rfc3339()),
+        token_cache: crate::token::with_cache_stats(),
     })
 }
 
+/// Prometheus 指标（文本格式）
+async fn get_metrics(State(state): State<UiState>) -> impl IntoResponse {
+    match state.pool.render_metrics().await {
+        Ok(body) => (
+            [(
+                header::CONTENT_TYPE,
+                "text/plain; version=0.0.4; charset=utf-8",
+            )],
+            body,
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+            .into_response(),
+    }
+}
+
 /// 账号列表响应
 #[derive(Serialize)]
 struct AccountResponse {
@@ -155,229 +727,1322 @@ struct AccountResponse {
     error_count: u64,
     last_used_at: Option<String>,
     created_at: String,
+    credit_score: f64,
+    warmup_latency_ms: Option<u64>,
+    notes: Option<String>,
+    free_trial_active: bool,
+    free_trial_expiry: Option<String>,
+    free_trial_remaining: Option<f64>,
+    pinned: bool,
+    display_name_override: Option<String>,
+    tags: BTreeMap<String, String>,
+    weight: u32,
+    /// 账号专用代理地址（不含认证信息）
+    proxy_url: Option<String>,
+    /// 熔断器状态（closed / open / half-open）
+    circuit_state: CircuitStatus,
+    history: VecDeque<StatusTransition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token_expiry: Option<TokenExpiryResponse>,
 }
 
-/// 获取账号列表
-async fn list_accounts(State(state): State<UiState>) -> impl IntoResponse {
-    let accounts = state.pool.list_accounts().await;
-    let response: Vec<AccountResponse> = accounts
-        .into_iter()
-        .map(|a| AccountResponse {
+/// accessToken 过期信息
+#[derive(Serialize)]
+struct TokenExpiryResponse {
+    expires_at: Option<String>,
+    expires_in_secs: Option<i64>,
+    refresh_needed: bool,
+}
+
+impl TokenExpiryResponse {
+    /// 读取账号的 Token 过期信息
+    async fn load(pool: &AccountPool, id: &str) -> anyhow::Result<Self> {
+        let expires_at = pool.get_token_expiry(id).await?;
+        Ok(Self {
+            expires_at: expires_at.map(|t| t.to_rfc3339()),
+            expires_in_secs: expires_at.map(|t| (t - chrono::Utc::now()).num_seconds()),
+            refresh_needed: pool.token_refresh_needed(expires_at),
+        })
+    }
+}
+
+impl AccountResponse {
+    fn new(
+        a: Account,
+        usage_cache: &HashMap<String, UsageLimits>,
+        strategy_config: &StrategyConfig,
+    ) -> Self {
+        let usage = usage_cache.get(&a.id);
+        let free_trial_active = a.is_in_free_trial(usage_cache);
+        let free_trial = usage
+            .and_then(|u| u.free_trial.as_ref())
+            .filter(|_| free_trial_active);
+        Self {
+            credit_score: a.credit_score(usage, strategy_config),
+            free_trial_active,
+            free_trial_expiry: free_trial.and_then(|ft| ft.expiry.map(|t| t.to_rfc3339())),
+            free_trial_remaining: free_trial.map(|ft| (ft.usage_limit - ft.current_usage).max(0.0)),
             id: a.id,
             name: a.name,
-            status: format!("{:?}", a.status).to_lowercase(),
+            status: a.status.to_status_string().to_string(),
             request_count: a.request_count,
             error_count: a.error_count,
             last_used_at: a.last_used_at.map(|t| t.to_rfc3339()),
             created_at: a.created_at.to_rfc3339(),
-        })
+            warmup_latency_ms: a.warmup_latency_ms,
+            notes: a.notes,
+            pinned: a.pinned,
+            display_name_override: a.display_name_override,
+            tags: a.tags,
+            weight: a.weight,
+            proxy_url: a.proxy_override.map(|p| p.url),
+            circuit_state: a.circuit_state.status,
+            history: a.history,
+            token_expiry: None,
+        }
+    }
+}
+
+/// 获取账号列表
+async fn list_accounts(State(state): State<UiState>) -> impl IntoResponse {
+    let accounts = state.pool.list_accounts().await;
+    let usage = state.pool.get_all_usage().await;
+    let strategy_config = state.pool.get_strategy_config().await;
+    let response: Vec<AccountResponse> = accounts
+        .into_iter()
+        .map(|a| AccountResponse::new(a, &usage, &strategy_config))
         .collect();
     Json(response)
 }
 
-/// 添加账号请求
-#[derive(Deserialize)]
-struct AddAccountRequest {
-    name: String,
-    refresh_token: String,
-    auth_method: String,
-    #[serde(default)]
-    client_id: Option<String>,
-    #[serde(default)]
-    client_secret: Option<String>,
-    #[serde(default)]
-    profile_arn: Option<String>,
+/// 获取单个账号
+async fn get_account(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    let Some(account) = state.pool.get_account(&id).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "账号不存在"})),
+        )
+            .into_response();
+    };
+    let usage = state.pool.get_all_usage().await;
+    let strategy_config = state.pool.get_strategy_config().await;
+    let mut response = AccountResponse::new(account, &usage, &strategy_config);
+    response.token_expiry = TokenExpiryResponse::load(&state.pool, &id)
+        .await
+        .inspect_err(|e| tracing::warn!("读取账号 {} Token 过期时间失败: {}", id, e))
+        .ok();
+    Json(response).into_response()
 }
 
-/// Kiro 原始凭证格式（直接导入）
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct KiroRawCredentials {
-    email: Option<String>,
-    label: Option<String>,
-    provider: Option<String>,
-    #[serde(default)]
-    access_token: Option<String>,
-    refresh_token: String,
-    client_id: Option<String>,
-    client_secret: Option<String>,
-    #[serde(default)]
-    region: Option<String>,
+/// 获取账号 accessToken 过期时间
+async fn get_token_expiry(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    if state.pool.get_account(&id).await.is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "账号不存在"})),
+        )
+            .into_response();
+    }
+    match TokenExpiryResponse::load(&state.pool, &id).await {
+        Ok(expiry) => Json(expiry).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+            .into_response(),
+    }
 }
 
-/// 导入账号请求（支持原始 JSON）
+/// 批量删除确认参数
 #[derive(Deserialize)]
-struct ImportAccountRequest {
-    /// 原始 JSON 字符串
-    raw_json: String,
-    /// 可选的自定义名称
+struct ConfirmQuery {
     #[serde(default)]
-    name: Option<String>,
+    confirm: bool,
 }
 
-/// 添加账号
-async fn add_account(
+/// 删除全部账号（需 `?confirm=true`）
+async fn remove_all_accounts(
     State(state): State<UiState>,
-    Json(req): Json<AddAccountRequest>,
+    axum::extract::Query(query): axum::extract::Query<ConfirmQuery>,
 ) -> impl IntoResponse {
-    let id = uuid::Uuid::new_v4().to_string();
-
-    let credentials = KiroCredentials {
-        access_token: None,
-        refresh_token: Some(req.refresh_token),
-        profile_arn: req.profile_arn,
-        expires_at: Some("2000-01-01T00:00:00Z".to_string()), // 强制刷新
-        auth_method: Some(req.auth_method),
-        client_id: req.client_id,
-        client_secret: req.client_secret,
-    };
-
-    let account = Account::new(&id, req.name, credentials);
-
-    // 使用带验证的添加方法，凭证无效则拒绝添加
-    match state.pool.add_account_with_validation(account).await {
-        Ok(_) => (StatusCode::CREATED, Json(serde_json::json!({"id": id}))),
-        Err(e) => (
+    if !query.confirm {
+        return (
             StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({"error": format!("凭证验证失败: {}", e)})),
-        ),
+            Json(serde_json::json!({"error": "Must pass ?confirm=true"})),
+        );
     }
+    let removed = state.pool.remove_all_accounts().await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({"removed": removed})),
+    )
 }
 
-/// 导入账号（支持 Kiro 原始 JSON 格式）
-async fn import_account(
+/// 设置账号备注请求
+#[derive(Deserialize)]
+struct SetNotesRequest {
+    notes: Option<String>,
+}
+
+/// 获取账号备注
+async fn get_account_notes(
     State(state): State<UiState>,
-    Json(req): Json<ImportAccountRequest>,
+    axum::extract::Path(id): axum::extract::Path<String>,
 ) -> impl IntoResponse {
-    // 解析原始 JSON
-    let raw: KiroRawCredentials = match serde_json::from_str(&req.raw_json) {
-        Ok(r) => r,
-        Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({"error": format!("JSON 解析失败: {}", e)})),
-            );
-        }
-    };
-
-    let id = uuid::Uuid::new_v4().to_string();
-
-    // 自动检测认证方式
-    let auth_method = if raw.client_id.is_some() && raw.client_secret.is_some() {
-        "idc".to_string()
-    } else {
-        "social".to_string()
-    };
-
-    // 生成名称：优先使用自定义名称，其次 label，再次 email
-    let name = req
-        .name
-        .or(raw.label.clone())
-        .or(raw.email.clone())
-        .unwrap_or_else(|| "导入的账号".to_string());
-
-    let credentials = KiroCredentials {
-        access_token: raw.access_token,
-        refresh_token: Some(raw.refresh_token),
-        profile_arn: None,
-        expires_at: Some("2000-01-01T00:00:00Z".to_string()),
-        auth_method: Some(auth_method),
-        client_id: raw.client_id,
-        client_secret: raw.client_secret,
-    };
-
-    let account = Account::new(&id, name, credentials);
-
-    // 使用带验证的添加方法，凭证无效则拒绝添加
-    match state.pool.add_account_with_validation(account).await {
-        Ok(_) => (StatusCode::CREATED, Json(serde_json::json!({"id": id}))),
-        Err(e) => (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({"error": format!("凭证验证失败: {}", e)})),
+    match state.pool.get_account(&id).await {
+        Some(account) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"id": account.id, "notes": account.notes})),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "账号不存在"})),
         ),
     }
 }
 
-/// 移除账号
-async fn remove_account(
+/// 替换账号标签请求
+#[derive(Deserialize)]
+struct SetTagsRequest {
+    /// 标签列表（`key=value` 或仅 `key`）
+    tags: Vec<String>,
+}
+
+/// 替换账号的全部标签
+async fn set_account_tags(
     State(state): State<UiState>,
     axum::extract::Path(id): axum::extract::Path<String>,
+    Json(req): Json<SetTagsRequest>,
 ) -> impl IntoResponse {
-    match state.pool.remove_account(&id).await {
-        Some(_) => StatusCode::NO_CONTENT,
-        None => StatusCode::NOT_FOUND,
+    let result = async {
+        let tags = crate::pool::tags::parse_tag_list(&req.tags)?;
+        state.pool.set_account_tags(&id, tags).await
     }
+    .await;
+    notes_response(&id, result)
 }
 
-/// 启用账号
-async fn enable_account(
+/// 设置账号备注（覆盖原有备注）
+async fn set_account_notes(
     State(state): State<UiState>,
     axum::extract::Path(id): axum::extract::Path<String>,
+    Json(req): Json<SetNotesRequest>,
 ) -> impl IntoResponse {
-    if state.pool.enable_account(&id).await {
-        Json(serde_json::json!({"success": true}))
-    } else {
-        Json(serde_json::json!({"success": false, "error": "账号不存在"}))
-    }
+    notes_response(&id, state.pool.set_account_notes(&id, req.notes).await)
 }
 
-/// 禁用账号
-async fn disable_account(
+/// 清除账号备注
+async fn clear_account_notes(
     State(state): State<UiState>,
     axum::extract::Path(id): axum::extract::Path<String>,
 ) -> impl IntoResponse {
-    if state.pool.disable_account(&id).await {
-        Json(serde_json::json!({"success": true}))
-    } else {
-        Json(serde_json::json!({"success": false, "error": "账号不存在"}))
-    }
+    notes_response(&id, state.pool.set_account_notes(&id, None).await)
 }
 
-/// 获取策略
-async fn get_strategy(State(state): State<UiState>) -> impl IntoResponse {
-    let strategy = state.pool.get_strategy().await;
-    Json(serde_json::json!({"strategy": strategy.as_str()}))
+/// 备注更新结果响应
+fn notes_response(id: &str, result: anyhow::Result<bool>) -> (StatusCode, Json<serde_json::Value>) {
+    match result {
+        Ok(true) => (StatusCode::OK, Json(serde_json::json!({"success": true}))),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"success": false, "error": format!("账号 {} 不存在", id)})),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"success": false, "error": e.to_string()})),
+        ),
+    }
 }
 
-/// 设置策略请求
+/// 账号更新请求（未提供的字段保持不变）
 #[derive(Deserialize)]
-struct SetStrategyRequest {
-    strategy: String,
+struct UpdateAccountRequest {
+    /// 账号名称
+    #[serde(default)]
+    name: Option<String>,
+    /// 运维备注（空字符串表示清除）
+    #[serde(default, alias = "description")]
+    notes: Option<String>,
+    /// 显示名覆盖（空字符串表示清除）
+    #[serde(default)]
+    display_name_override: Option<String>,
+    /// 替换全部标签
+    #[serde(default)]
+    tags: Option<BTreeMap<String, String>>,
+    /// 加权轮询权重（0 表示不参与自动选择）
+    #[serde(default)]
+    weight: Option<u32>,
 }
 
-/// 设置策略
-async fn set_strategy(
+/// 更新账号属性
+async fn update_account(
     State(state): State<UiState>,
-    Json(req): Json<SetStrategyRequest>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Json(req): Json<UpdateAccountRequest>,
 ) -> impl IntoResponse {
-    let strategy = match req.strategy.as_str() {
-        "round-robin" => SelectionStrategy::RoundRobin,
-        "random" => SelectionStrategy::Random,
-        "least-used" => SelectionStrategy::LeastUsed,
-        "sequential-exhaust" => SelectionStrategy::SequentialExhaust,
-        _ => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({"error": "无效的策略"})),
-            )
-        }
+    let update = AccountUpdate {
+        name: req.name,
+        notes: req.notes,
+        display_name_override: req.display_name_override,
+        tags: req.tags,
+        weight: req.weight,
     };
-    state.pool.set_strategy(strategy).await;
-    (StatusCode::OK, Json(serde_json::json!({"success": true})))
+    match state.pool.update_account(&id, update).await {
+        Ok(true) => (StatusCode::OK, Json(serde_json::json!({"success": true}))),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"success": false, "error": format!("账号 {} 不存在", id)})),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"success": false, "error": e.to_string()})),
+        ),
+    }
 }
 
-/// 获取请求记录
-async fn get_request_logs(State(state): State<UiState>) -> impl IntoResponse {
-    let logs = state.pool.get_recent_logs(100).await;
-    Json(logs)
+/// 导入账号请求（支持原始 JSON）
+#[derive(Deserialize)]
+struct ImportAccountRequest {
+    /// 原始 JSON 字符串（单个凭证对象或数组）
+    raw_json: String,
+    /// 可选的自定义名称（仅对单个凭证对象生效）
+    #[serde(default)]
+    name: Option<String>,
+    /// 可选的运维备注（仅对单个凭证对象生效）
+    #[serde(default, alias = "description")]
+    notes: Option<String>,
+    /// 可选的账号专用代理地址（对全部导入的凭证生效）
+    #[serde(default)]
+    proxy_url: Option<String>,
+    /// 账号专用代理认证用户名
+    #[serde(default)]
+    proxy_username: Option<String>,
+    /// 账号专用代理认证密码
+    #[serde(default)]
+    proxy_password: Option<String>,
 }
 
-/// 获取请求统计
+impl ImportAccountRequest {
+    /// 将代理字段写入凭证对象（单个对象或数组中的每个对象）
+    fn apply_proxy(&self, value: &mut serde_json::Value) {
+        let fields = [
+            ("proxyUrl", &self.proxy_url),
+            ("proxyUsername", &self.proxy_username),
+            ("proxyPassword", &self.proxy_password),
+        ];
+        let objects: Vec<&mut serde_json::Map<String, serde_json::Value>> = match value {
+            serde_json::Value::Object(obj) => vec![obj],
+            serde_json::Value::Array(items) => {
+                items.iter_mut().filter_map(|v| v.as_object_mut()).collect()
+            }
+            _ => Vec::new(),
+        };
+        for obj in objects {
+            for (key, field) in &fields {
+                if let Some(v) = field {
+                    obj.insert(key.to_string(), serde_json::Value::String(v.clone()));
+                }
+            }
+        }
+    }
+}
+
+/// 添加账号
+async fn add_account(
+    State(state): State<UiState>,
+    Json(req): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    // 使用带验证的导入，凭证无效则拒绝添加
+    import_response(
+        state
+            .pool
+            .import_json(&req.to_string(), true, state.import_concurrency)
+            .await,
+    )
+}
+
+/// 导入账号（支持 Kiro 原始 JSON 格式）
+async fn import_account(
+    State(state): State<UiState>,
+    Json(req): Json<ImportAccountRequest>,
+) -> impl IntoResponse {
+    let raw_json = match serde_json::from_str::<serde_json::Value>(&req.raw_json) {
+        Ok(mut value) => {
+            if let Some(obj) = value.as_object_mut() {
+                if let Some(name) = &req.name {
+                    obj.insert("name".to_string(), serde_json::Value::String(name.clone()));
+                }
+                if let Some(notes) = &req.notes {
+                    obj.insert(
+                        "notes".to_string(),
+                        serde_json::Value::String(notes.clone()),
+                    );
+                }
+            }
+            req.apply_proxy(&mut value);
+            value.to_string()
+        }
+        Err(_) => req.raw_json,
+    };
+
+    // 使用带验证的导入，凭证无效则拒绝添加
+    import_response(
+        state
+            .pool
+            .import_json(&raw_json, true, state.import_concurrency)
+            .await,
+    )
+}
+
+/// 批量导入请求
+#[derive(Deserialize)]
+struct BulkImportRequest {
+    accounts: Vec<KiroRawCredentials>,
+    /// 是否先验证凭证（默认 true）
+    #[serde(default = "default_bulk_validate")]
+    validate: bool,
+}
+
+fn default_bulk_validate() -> bool {
+    true
+}
+
+/// 批量导入账号：通过验证的账号统一写入，返回逐条结果
+async fn import_accounts_bulk(
+    State(state): State<UiState>,
+    Json(req): Json<BulkImportRequest>,
+) -> impl IntoResponse {
+    match state
+        .pool
+        .import_accounts_bulk(req.accounts, req.validate, state.import_concurrency)
+        .await
+    {
+        Ok(results) => {
+            let imported = results.iter().filter(|r| r.success).count();
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({"imported": imported, "results": results})),
+            )
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+/// 迁移导出查询参数
+#[derive(Deserialize)]
+struct ExportQuery {
+    /// 导出格式（`kiro-json` 或 `csv`）
+    format: String,
+}
+
+/// 导出全部账号凭证（未脱敏），用于迁移到其他工具
+async fn export_accounts(
+    State(state): State<UiState>,
+    axum::extract::Query(query): axum::extract::Query<ExportQuery>,
+) -> Response {
+    let exported = match state.pool.export_for_migration(&query.format).await {
+        Ok(exported) => exported,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response()
+        }
+    };
+
+    if query.format == "csv" {
+        (
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"accounts.csv\"",
+                ),
+            ],
+            exported,
+        )
+            .into_response()
+    } else {
+        ([(header::CONTENT_TYPE, "application/json")], exported).into_response()
+    }
+}
+
+/// 更新账号凭证（当前 Token 仍有效时暂存，过期后自动启用）
+async fn update_account_credentials(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Json(credentials): Json<KiroCredentials>,
+) -> impl IntoResponse {
+    match state.pool.update_credentials(&id, credentials).await {
+        Ok(Some(outcome)) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"success": true, "result": outcome})),
+        ),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"success": false, "error": format!("账号 {} 不存在", id)})),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"success": false, "error": e.to_string()})),
+        ),
+    }
+}
+
+/// 轮换账号凭证（请求体与添加账号相同，忽略 name）
+async fn rotate_account_credentials(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Json(raw): Json<KiroRawCredentials>,
+) -> impl IntoResponse {
+    let credentials = raw.into_account().credentials;
+    match state.pool.rotate_credentials(&id, credentials).await {
+        Ok(true) => (StatusCode::OK, Json(serde_json::json!({"success": true}))),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"success": false, "error": format!("账号 {} 不存在", id)})),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"success": false, "error": e.to_string()})),
+        ),
+    }
+}
+
+/// 导入结果响应
+///
+/// 单个凭证对象沿用 `{"id": ...}` 响应（重复返回 409）；数组返回导入数量与逐条结果，
+/// 全部失败（不含重复跳过）时返回 400
+fn import_response(
+    result: anyhow::Result<JsonImportOutcome>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let outcome = match result {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        }
+    };
+
+    if outcome.single {
+        let result = &outcome.results[0];
+        return match (&result.account_id, &result.error) {
+            (Some(id), _) => (StatusCode::CREATED, Json(serde_json::json!({"id": id}))),
+            (None, error) => (
+                if result.duplicate {
+                    StatusCode::CONFLICT
+                } else {
+                    StatusCode::BAD_REQUEST
+                },
+                Json(serde_json::json!({"error": error})),
+            ),
+        };
+    }
+
+    let imported = outcome.imported();
+    match (imported, outcome.first_error()) {
+        (0, Some(error)) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": error,
+                "imported": 0,
+                "results": outcome.results,
+            })),
+        ),
+        _ => (
+            StatusCode::CREATED,
+            Json(serde_json::json!({"imported": imported, "results": outcome.results})),
+        ),
+    }
+}
+
+/// 移除账号
+async fn remove_account(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    match state.pool.remove_account(&id).await {
+        Some(_) => StatusCode::NO_CONTENT,
+        None => StatusCode::NOT_FOUND,
+    }
+}
+
+/// 清理耗尽账号请求
+#[derive(Deserialize)]
+struct PruneExhaustedRequest {
+    older_than_days: u32,
+}
+
+/// 清理长期配额耗尽的账号
+async fn prune_exhausted_accounts(
+    State(state): State<UiState>,
+    Json(req): Json<PruneExhaustedRequest>,
+) -> impl IntoResponse {
+    let pruned = state
+        .pool
+        .prune_exhausted_accounts(req.older_than_days)
+        .await;
+    Json(serde_json::json!({"pruned": pruned}))
+}
+
+/// 启用账号
+async fn enable_account(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    if state.pool.enable_account(&id).await {
+        Json(serde_json::json!({"success": true}))
+    } else {
+        Json(serde_json::json!({"success": false, "error": "账号不存在"}))
+    }
+}
+
+/// 重置账号统计，返回更新后的计数
+async fn reset_account_stats(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    match state.pool.reset_account_stats(&id).await {
+        Ok(Some(account)) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "id": account.id,
+                "status": account.status,
+                "request_count": account.request_count,
+                "error_count": account.error_count,
+                "last_used_at": account.last_used_at,
+            })),
+        ),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"success": false, "error": "账号不存在"})),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"success": false, "error": e.to_string()})),
+        ),
+    }
+}
+
+/// 固定账号（不参与自动选择）
+async fn pin_account(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    if state.pool.pin_account(&id).await {
+        Json(serde_json::json!({"success": true}))
+    } else {
+        Json(serde_json::json!({"success": false, "error": "账号不存在"}))
+    }
+}
+
+/// 取消固定账号
+async fn unpin_account(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    if state.pool.unpin_account(&id).await {
+        Json(serde_json::json!({"success": true}))
+    } else {
+        Json(serde_json::json!({"success": false, "error": "账号不存在"}))
+    }
+}
+
+/// 禁用账号
+async fn disable_account(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    if state.pool.disable_account(&id).await {
+        Json(serde_json::json!({"success": true}))
+    } else {
+        Json(serde_json::json!({"success": false, "error": "账号不存在"}))
+    }
+}
+
+/// 设置配额耗尽恢复时间请求
+#[derive(Deserialize)]
+struct SetExhaustedUntilRequest {
+    #[serde(default)]
+    until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// 手动设置配额耗尽恢复时间
+async fn set_exhausted_until(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Json(req): Json<SetExhaustedUntilRequest>,
+) -> impl IntoResponse {
+    match state.pool.set_exhausted_until(&id, req.until).await {
+        Ok(true) => (StatusCode::OK, Json(serde_json::json!({"success": true}))),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"success": false, "error": "账号不存在"})),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"success": false, "error": e.to_string()})),
+        ),
+    }
+}
+
+/// 重新平衡账号请求计数
+async fn rebalance_pool(State(state): State<UiState>) -> impl IntoResponse {
+    let result = state.pool.rebalance().await;
+    Json(result)
+}
+
+/// 合并账号池（请求体为 base64 编码的 accounts.json）
+async fn merge_pool(State(state): State<UiState>, body: String) -> impl IntoResponse {
+    use base64::Engine;
+
+    let json = match base64::engine::general_purpose::STANDARD
+        .decode(body.trim())
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| String::from_utf8(bytes).map_err(|e| e.to_string()))
+    {
+        Ok(json) => json,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": format!("base64 解码失败: {}", e)})),
+            )
+        }
+    };
+
+    let result = match state.pool.detached_from_json(&json).await {
+        Ok(other) => state.pool.merge_pools(other).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok((added, skipped)) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"added": added, "skipped": skipped})),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+/// 归档请求记录请求
+#[derive(Deserialize)]
+struct ArchiveLogsRequest {
+    #[serde(default = "default_archive_max_age_days")]
+    max_age_days: u32,
+}
+
+fn default_archive_max_age_days() -> u32 {
+    7
+}
+
+/// 归档旧请求记录（默认 7 天前）
+async fn archive_request_logs(
+    State(state): State<UiState>,
+    body: Option<Json<ArchiveLogsRequest>>,
+) -> impl IntoResponse {
+    let max_age_days = body
+        .map(|Json(req)| req.max_age_days)
+        .unwrap_or_else(default_archive_max_age_days);
+    match state.pool.archive_logs(max_age_days).await {
+        Ok(result) => (StatusCode::OK, Json(serde_json::json!(result))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+/// 分组分配请求
+#[derive(Deserialize)]
+struct AssignToGroupRequest {
+    group_id: String,
+    #[serde(default)]
+    name: Option<String>,
+    account_ids: Vec<String>,
+}
+
+/// 将账号加入分组（分组不存在时自动创建）
+async fn assign_to_group(
+    State(state): State<UiState>,
+    Json(req): Json<AssignToGroupRequest>,
+) -> impl IntoResponse {
+    match state
+        .pool
+        .assign_to_group(&req.group_id, req.name, &req.account_ids)
+        .await
+    {
+        Ok(group) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "id": group.id,
+                "name": group.name,
+                "account_ids": group.account_ids,
+                "member_count": group.account_ids.len(),
+            })),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+/// 获取分组内的账号
+async fn list_group_accounts(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    let usage = state.pool.get_all_usage().await;
+    let strategy_config = state.pool.get_strategy_config().await;
+    let response: Vec<AccountResponse> = state
+        .pool
+        .get_accounts_for_group(&id)
+        .await
+        .into_iter()
+        .map(|a| AccountResponse::new(a, &usage, &strategy_config))
+        .collect();
+    Json(response)
+}
+
+/// 按标签查询参数
+#[derive(Deserialize)]
+struct TagQuery {
+    key: String,
+    value: String,
+}
+
+/// 获取带有指定标签的账号 id
+async fn list_accounts_by_tag(
+    State(state): State<UiState>,
+    axum::extract::Query(query): axum::extract::Query<TagQuery>,
+) -> impl IntoResponse {
+    Json(
+        state
+            .pool
+            .get_accounts_by_tag(&query.key, &query.value)
+            .await,
+    )
+}
+
+/// 获取分组列表（含成员数）
+async fn list_groups(State(state): State<UiState>) -> impl IntoResponse {
+    let groups: Vec<serde_json::Value> = state
+        .pool
+        .list_groups()
+        .await
+        .into_iter()
+        .map(|g| {
+            serde_json::json!({
+                "id": g.id,
+                "name": g.name,
+                "member_count": g.account_ids.len(),
+                "account_ids": g.account_ids,
+            })
+        })
+        .collect();
+    Json(groups)
+}
+
+/// 批量验证凭证请求
+#[derive(Deserialize)]
+struct ValidateAllRequest {
+    #[serde(default = "default_validate_concurrency")]
+    concurrency: usize,
+}
+
+fn default_validate_concurrency() -> usize {
+    5
+}
+
+/// 批量验证所有账号凭证（不修改账号状态）
+async fn validate_all_accounts(
+    State(state): State<UiState>,
+    body: Option<Json<ValidateAllRequest>>,
+) -> impl IntoResponse {
+    let concurrency = body
+        .map(|Json(req)| req.concurrency)
+        .unwrap_or_else(default_validate_concurrency);
+    Json(state.pool.validate_all(concurrency).await)
+}
+
+/// 模拟选择请求
+#[derive(Deserialize)]
+struct SimulateSelectionRequest {
+    strategy: String,
+    n: usize,
+}
+
+/// 单次模拟的最大请求数
+const MAX_SIMULATED_SELECTIONS: usize = 100_000;
+
+/// 模拟策略分配结果（不修改账号状态）
+async fn simulate_selection(
+    State(state): State<UiState>,
+    Json(req): Json<SimulateSelectionRequest>,
+) -> impl IntoResponse {
+    let Some(strategy) = SelectionStrategy::parse(&req.strategy) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "无效的策略"})),
+        );
+    };
+    if req.n > MAX_SIMULATED_SELECTIONS {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("n 不能超过 {}", MAX_SIMULATED_SELECTIONS)
+            })),
+        );
+    }
+    let counts = state.pool.simulate_selection(strategy, req.n).await;
+    (StatusCode::OK, Json(serde_json::json!(counts)))
+}
+
+/// 预热账号池
+async fn warm_up_pool(State(state): State<UiState>) -> impl IntoResponse {
+    Json(state.pool.warm_up().await)
+}
+
+/// 拓扑节点
+#[derive(Serialize)]
+struct TopologyNode {
+    id: String,
+    name: String,
+    status: String,
+    region: Option<String>,
+    credit_score: f64,
+    /// 冷却剩余秒数
+    cooldown_remaining_secs: Option<i64>,
+    /// 配额恢复剩余秒数
+    exhausted_remaining_secs: Option<i64>,
+}
+
+/// 拓扑元信息
+#[derive(Serialize)]
+struct TopologyMeta {
+    strategy: String,
+    total_active: usize,
+}
+
+/// 账号池拓扑响应
+#[derive(Serialize)]
+struct TopologyResponse {
+    nodes: Vec<TopologyNode>,
+    meta: TopologyMeta,
+}
+
+/// 估算账号池剩余请求容量
+async fn get_pool_capacity(State(state): State<UiState>) -> impl IntoResponse {
+    Json(state.pool.estimate_capacity().await)
+}
+
+/// 账号负载分布公平性（基尼系数）
+async fn get_selection_fairness(State(state): State<UiState>) -> impl IntoResponse {
+    Json(state.pool.get_selection_fairness().await)
+}
+
+/// 账号池运行时配置更新请求
+#[derive(Deserialize)]
+struct PoolConfigPatch {
+    max_logs: usize,
+}
+
+/// 运行时调整账号池配置（当前支持请求记录上限）
+async fn patch_pool_config(
+    State(state): State<UiState>,
+    Json(req): Json<PoolConfigPatch>,
+) -> impl IntoResponse {
+    match state.pool.adjust_max_logs(req.max_logs).await {
+        Ok(result) => (StatusCode::OK, Json(serde_json::json!(result))),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+/// 从快照恢复账号池
+async fn import_pool_snapshot(
+    State(state): State<UiState>,
+    Json(snapshot): Json<crate::pool::manager::PoolSnapshot>,
+) -> impl IntoResponse {
+    match state.pool.import_snapshot(snapshot).await {
+        Ok(result) => (StatusCode::OK, Json(serde_json::json!(result))),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+/// 导出账号池快照（含未脱敏凭证），可直接提交给 `/api/pool/import-snapshot` 恢复
+async fn get_pool_snapshot(State(state): State<UiState>) -> impl IntoResponse {
+    Json(state.pool.snapshot().await)
+}
+
+/// 对账查询参数
+#[derive(Deserialize)]
+struct ReconcileQuery {
+    /// 为 true 时按磁盘内容修正内存（默认仅报告）
+    #[serde(default)]
+    apply: bool,
+}
+
+/// 比较内存与 accounts.json 的差异，可选按磁盘修正
+async fn reconcile_pool(
+    State(state): State<UiState>,
+    axum::extract::Query(query): axum::extract::Query<ReconcileQuery>,
+) -> impl IntoResponse {
+    match state.pool.reconcile(query.apply).await {
+        Ok(report) => (StatusCode::OK, Json(serde_json::json!(report))),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+/// 获取账号池拓扑（用于可视化）
+async fn get_pool_topology(State(state): State<UiState>) -> impl IntoResponse {
+    let snapshot = state.pool.snapshot().await;
+    let accounts = snapshot.accounts;
+    let usage = snapshot.usage_cache;
+    let strategy_config = state.pool.get_strategy_config().await;
+    let strategy = snapshot.strategy;
+    let now = snapshot.captured_at;
+
+    let remaining = |until: Option<chrono::DateTime<chrono::Utc>>| {
+        until.map(|t| (t - now).num_seconds().max(0))
+    };
+
+    let total_active = accounts
+        .iter()
+        .filter(|a| a.status == crate::pool::account::AccountStatus::Active)
+        .count();
+    let nodes = accounts
+        .into_iter()
+        .map(|a| TopologyNode {
+            status: a.status.to_status_string().to_string(),
+            credit_score: a.credit_score(usage.get(&a.id), &strategy_config),
+            cooldown_remaining_secs: remaining(a.cooldown_until),
+            exhausted_remaining_secs: remaining(a.exhausted_until),
+            region: a.region,
+            id: a.id,
+            name: a.name,
+        })
+        .collect();
+
+    Json(TopologyResponse {
+        nodes,
+        meta: TopologyMeta {
+            strategy: strategy.as_str().to_string(),
+            total_active,
+        },
+    })
+}
+
+/// 获取各阶段调用耗时分位数
+async fn get_telemetry_latency(State(state): State<UiState>) -> impl IntoResponse {
+    Json(state.telemetry.latency_breakdown().await)
+}
+
+/// 获取策略
+async fn get_strategy(State(state): State<UiState>) -> impl IntoResponse {
+    let strategy = state.pool.get_strategy().await;
+    Json(serde_json::json!({"strategy": strategy.as_str()}))
+}
+
+/// 设置策略请求
+#[derive(Deserialize)]
+struct SetStrategyRequest {
+    strategy: String,
+}
+
+/// 设置策略（已弃用，请使用 `PATCH /api/pool/strategy`）
+async fn set_strategy(
+    State(state): State<UiState>,
+    Json(req): Json<SetStrategyRequest>,
+) -> impl IntoResponse {
+    tracing::warn!("POST /api/strategy 已弃用，请改用 PATCH /api/pool/strategy");
+    let Some(strategy) = SelectionStrategy::parse(&req.strategy) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "无效的策略"})),
+        );
+    };
+    state.pool.set_strategy(strategy).await;
+    (StatusCode::OK, Json(serde_json::json!({"success": true})))
+}
+
+/// 设置策略及参数请求
+#[derive(Deserialize)]
+struct SetStrategyWithConfigRequest {
+    strategy: String,
+    /// 部分策略参数，未提供的字段保持不变
+    #[serde(default)]
+    config: Option<StrategyConfigPatch>,
+}
+
+/// 同时设置策略与策略参数
+async fn set_strategy_with_config(
+    State(state): State<UiState>,
+    Json(req): Json<SetStrategyWithConfigRequest>,
+) -> impl IntoResponse {
+    let Some(strategy) = SelectionStrategy::parse(&req.strategy) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "无效的策略"})),
+        );
+    };
+    match state
+        .pool
+        .set_strategy_with_config(strategy, req.config)
+        .await
+    {
+        Ok((strategy, config)) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"strategy": strategy.as_str(), "config": config})),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+/// 获取策略定时切换计划
+async fn get_strategy_schedule(State(state): State<UiState>) -> impl IntoResponse {
+    Json(state.pool.get_strategy_schedule().await)
+}
+
+/// 替换策略定时切换计划
+async fn set_strategy_schedule(
+    State(state): State<UiState>,
+    Json(schedule): Json<StrategySchedule>,
+) -> impl IntoResponse {
+    match state.pool.set_strategy_schedule(schedule).await {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(serde_json::json!(state.pool.get_strategy_schedule().await)),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+/// 请求记录查询参数
+#[derive(Deserialize)]
+struct LogsQuery {
+    /// 导出格式（`csv`，缺省为 JSON）
+    #[serde(default)]
+    format: Option<String>,
+    /// 仅返回上下文窗口使用百分比高于该值的记录
+    #[serde(default)]
+    context_pct_above: Option<f64>,
+    /// 仅返回指定会话的记录（不限于最近 100 条）
+    #[serde(default)]
+    session_id: Option<String>,
+}
+
+/// 获取请求记录
+async fn get_request_logs(
+    State(state): State<UiState>,
+    axum::extract::Query(query): axum::extract::Query<LogsQuery>,
+) -> Response {
+    let mut logs = match &query.session_id {
+        Some(session_id) => state.pool.get_session_logs(session_id).await,
+        None => state.pool.get_recent_logs(100).await,
+    };
+    if let Some(threshold) = query.context_pct_above {
+        logs.retain(|l| l.context_window_used_pct.is_some_and(|pct| pct > threshold));
+    }
+    if query.format.as_deref() != Some("csv") {
+        return Json(logs).into_response();
+    }
+
+    match crate::pool::usage::logs_to_csv(&logs) {
+        Ok(csv) => (
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"request-logs.csv\"",
+                ),
+            ],
+            csv,
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+            .into_response(),
+    }
+}
+
+/// 获取指定会话的请求记录
+async fn get_session_logs(
+    State(state): State<UiState>,
+    axum::extract::Path(session_id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    Json(state.pool.get_session_logs(&session_id).await)
+}
+
+/// 获取请求统计
 async fn get_request_stats(State(state): State<UiState>) -> impl IntoResponse {
     let stats = state.pool.get_request_stats().await;
     Json(stats)
 }
 
+/// 获取请求记录数量与文件大小
+async fn get_request_log_count(State(state): State<UiState>) -> impl IntoResponse {
+    Json(state.pool.request_log_count().await)
+}
+
+/// 错误统计查询参数
+#[derive(Deserialize)]
+struct TopErrorsQuery {
+    #[serde(default = "default_top_errors")]
+    top: usize,
+}
+
+fn default_top_errors() -> usize {
+    10
+}
+
+/// 获取最常见的错误信息
+async fn get_top_errors(
+    State(state): State<UiState>,
+    axum::extract::Query(query): axum::extract::Query<TopErrorsQuery>,
+) -> impl IntoResponse {
+    let errors: Vec<serde_json::Value> = state
+        .pool
+        .top_error_messages(query.top)
+        .await
+        .into_iter()
+        .map(|(message, count)| serde_json::json!({"message": message, "count": count}))
+        .collect();
+    Json(errors)
+}
+
+/// 基准测试请求
+#[derive(Deserialize)]
+struct BenchmarkRequest {
+    #[serde(default = "default_benchmark_samples")]
+    samples: usize,
+    #[serde(default)]
+    prompt: Option<String>,
+}
+
+fn default_benchmark_samples() -> usize {
+    5
+}
+
+/// 对账号进行基准测试（直接使用账号的 Provider，不更新账号统计）
+async fn benchmark_account(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    Json(req): Json<BenchmarkRequest>,
+) -> impl IntoResponse {
+    if !(1..=benchmark::MAX_BENCHMARK_SAMPLES).contains(&req.samples) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("samples 必须在 1 ~ {} 之间", benchmark::MAX_BENCHMARK_SAMPLES)
+            })),
+        );
+    }
+    let Some(provider) = state.pool.get_provider(&id).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": format!("账号 {} 不存在", id)})),
+        );
+    };
+    let prompt = req
+        .prompt
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| benchmark::DEFAULT_BENCHMARK_PROMPT.to_string());
+    match benchmark::run_benchmark(&provider, &id, req.samples, &prompt).await {
+        Ok(report) => (StatusCode::OK, Json(serde_json::json!(report))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+/// 请求量历史查询参数
+#[derive(Deserialize)]
+struct RequestHistoryQuery {
+    #[serde(default = "default_history_days")]
+    days: u32,
+}
+
+fn default_history_days() -> u32 {
+    30
+}
+
+/// 获取账号最近每日请求量
+async fn get_request_count_history(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    axum::extract::Query(query): axum::extract::Query<RequestHistoryQuery>,
+) -> impl IntoResponse {
+    Json(state.pool.daily_request_history(&id, query.days).await)
+}
+
+/// 时间线查询参数
+#[derive(Deserialize)]
+struct TimelineQuery {
+    #[serde(default = "default_timeline_interval_mins")]
+    interval_mins: u32,
+    #[serde(default = "default_timeline_hours")]
+    hours: u32,
+}
+
+fn default_timeline_interval_mins() -> u32 {
+    15
+}
+
+fn default_timeline_hours() -> u32 {
+    6
+}
+
+/// 按自定义间隔获取请求时间线
+async fn get_usage_timeline(
+    State(state): State<UiState>,
+    axum::extract::Query(query): axum::extract::Query<TimelineQuery>,
+) -> impl IntoResponse {
+    if !(1..=1440).contains(&query.interval_mins) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "interval_mins 必须在 1 ~ 1440 之间"})),
+        );
+    }
+    let timeline = state
+        .pool
+        .usage_timeline(query.interval_mins, query.hours)
+        .await;
+    (StatusCode::OK, Json(serde_json::json!(timeline)))
+}
+
 /// 获取账号配额
 async fn get_account_usage(
     State(state): State<UiState>,
@@ -406,6 +2071,23 @@ async fn refresh_account_usage(
     }
 }
 
+/// 强制刷新账号 Token
+async fn force_refresh_token(
+    State(state): State<UiState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    match state.pool.force_refresh_token(&id).await {
+        Ok(expires_at) => (
+            StatusCode::OK,
+            Json(serde_json::json!({"expires_at": expires_at})),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
 /// 刷新所有账号配额
 async fn refresh_all_usage(State(state): State<UiState>) -> impl IntoResponse {
     let results = state.pool.refresh_all_usage().await;
@@ -432,3 +2114,388 @@ async fn get_all_usage(State(state): State<UiState>) -> impl IntoResponse {
     let usage = state.pool.get_all_usage().await;
     Json(usage)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiro::model::credentials::KiroCredentials;
+    use crate::pool::usage::FreeTrialInfo;
+
+    /// 收集文档中所有 `$ref` 引用
+    fn collect_refs(value: &serde_json::Value, refs: &mut Vec<String>) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, v) in map {
+                    match (key.as_str(), v) {
+                        ("$ref", serde_json::Value::String(r)) => refs.push(r.clone()),
+                        _ => collect_refs(v, refs),
+                    }
+                }
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|v| collect_refs(v, refs)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_openapi_spec_is_valid_3_1() {
+        let spec = generate_openapi_spec();
+        assert_eq!(spec["openapi"], "3.1.0");
+        assert_eq!(spec["info"]["title"], "kiro2api Management API");
+        assert_eq!(spec["info"]["version"], env!("CARGO_PKG_VERSION"));
+
+        let methods = ["get", "post", "put", "patch", "delete"];
+        let paths = spec["paths"].as_object().unwrap();
+        assert!(paths.contains_key("/api/accounts/{id}"));
+        for (path, item) in paths {
+            assert!(path.starts_with('/'));
+            for (method, operation) in item.as_object().unwrap() {
+                assert!(methods.contains(&method.as_str()), "{} {}", method, path);
+                assert!(operation["responses"]["200"].is_object());
+                // 路径模板中的每个参数都必须声明
+                for name in path
+                    .split('/')
+                    .filter_map(|seg| seg.strip_prefix('{')?.strip_suffix('}'))
+                {
+                    let declared =
+                        operation["parameters"].as_array().unwrap().iter().any(|p| {
+                            p["name"] == name && p["in"] == "path" && p["required"] == true
+                        });
+                    assert!(declared, "{} {} 缺少路径参数 {}", method, path, name);
+                }
+            }
+        }
+
+        let mut refs = Vec::new();
+        collect_refs(&spec, &mut refs);
+        assert!(!refs.is_empty());
+        for r in refs {
+            let pointer = r.strip_prefix('#').unwrap();
+            assert!(spec.pointer(pointer).is_some(), "无法解析引用 {}", r);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_docs_is_public() {
+        use tower::ServiceExt;
+
+        let state = UiState {
+            pool: Arc::new(AccountPool::new(
+                crate::model::config::Config::default(),
+                None,
+            )),
+            telemetry: Arc::new(TelemetryStore::default()),
+            start_time: Instant::now(),
+            version: "test".to_string(),
+            api_key: "secret".to_string(),
+            config: None,
+            admin_key: None,
+            import_concurrency: 5,
+        };
+        let router = create_ui_router(state);
+        let docs = router
+            .clone()
+            .oneshot(
+                Request::get("/api/docs")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(docs.status(), StatusCode::OK);
+        let status = router
+            .oneshot(
+                Request::get("/api/status")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(status.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_auth_follows_reloaded_api_key() {
+        use tower::ServiceExt;
+
+        let shared = Arc::new(RwLock::new(Config {
+            api_key: Some("old".to_string()),
+            ..Config::default()
+        }));
+        let router = create_ui_router(UiState {
+            pool: Arc::new(AccountPool::new(Config::default(), None)),
+            telemetry: Arc::new(TelemetryStore::default()),
+            start_time: Instant::now(),
+            version: "test".to_string(),
+            api_key: "old".to_string(),
+            config: Some(shared.clone()),
+            admin_key: None,
+            import_concurrency: 5,
+        });
+        let status = |key: &str| {
+            Request::get("/api/status")
+                .header("Authorization", format!("Bearer {}", key))
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        shared.write().await.api_key = Some("new".to_string());
+        let resp = router.clone().oneshot(status("old")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = router.oneshot(status("new")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_import_response_shapes() {
+        let pool = AccountPool::new(crate::model::config::Config::default(), None);
+
+        // 单个对象沿用 {"id": ...}
+        let (status, Json(body)) = import_response(
+            pool.import_json(r#"{"refreshToken": "rt1"}"#, false, 1)
+                .await,
+        );
+        assert_eq!(status, StatusCode::CREATED);
+        let id = body["id"].as_str().unwrap();
+        assert!(pool.get_account(id).await.is_some());
+
+        let (status, Json(body)) = import_response(
+            pool.import_json(r#"{"refreshToken": "rt1"}"#, false, 1)
+                .await,
+        );
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"], "refreshToken 已存在");
+
+        // 数组逐条返回跳过原因
+        let (status, Json(body)) = import_response(
+            pool.import_json(
+                r#"[{"refreshToken": "rt1"}, {"refreshToken": "rt2"}]"#,
+                false,
+                1,
+            )
+            .await,
+        );
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["imported"], 1);
+        assert_eq!(body["results"][0]["duplicate"], true);
+        assert_eq!(body["results"][0]["error"], "refreshToken 已存在");
+        assert_eq!(body["results"][1]["success"], true);
+    }
+
+    #[tokio::test]
+    async fn test_export_requires_admin_key_header() {
+        use tower::ServiceExt;
+
+        let build = |admin_key: Option<&str>| {
+            create_ui_router(UiState {
+                pool: Arc::new(AccountPool::new(
+                    crate::model::config::Config::default(),
+                    None,
+                )),
+                telemetry: Arc::new(TelemetryStore::default()),
+                start_time: Instant::now(),
+                version: "test".to_string(),
+                api_key: "secret".to_string(),
+                config: None,
+                admin_key: admin_key.map(str::to_string),
+                import_concurrency: 5,
+            })
+        };
+        let export = |uri: &str, auth: Option<&str>| {
+            let mut builder = Request::get(uri);
+            if let Some(auth) = auth {
+                builder = builder.header("Authorization", format!("Bearer {}", auth));
+            }
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+        let uri = "/api/accounts/export?format=kiro-json";
+
+        // 未配置 adminKey 时禁用
+        let resp = build(None)
+            .oneshot(export(uri, Some("secret")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let router = build(Some("admin"));
+        // 普通 API Key 不足以导出
+        let resp = router
+            .clone()
+            .oneshot(export(uri, Some("secret")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        // 查询参数中的管理员密钥不被接受
+        let resp = router
+            .clone()
+            .oneshot(export(
+                "/api/accounts/export?format=kiro-json&key=admin",
+                None,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = router.oneshot(export(uri, Some("admin"))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_pool_snapshot_round_trip_restores_accounts() {
+        use tower::ServiceExt;
+
+        let build = |pool: Arc<AccountPool>| {
+            create_ui_router(UiState {
+                pool,
+                telemetry: Arc::new(TelemetryStore::default()),
+                start_time: Instant::now(),
+                version: "test".to_string(),
+                api_key: "secret".to_string(),
+                config: None,
+                admin_key: Some("admin".to_string()),
+                import_concurrency: 5,
+            })
+        };
+        let source = Arc::new(AccountPool::new(
+            crate::model::config::Config::default(),
+            None,
+        ));
+        let credentials = KiroCredentials {
+            refresh_token: Some("rt-snapshot".to_string()),
+            ..Default::default()
+        };
+        source
+            .add_account(Account::new("a", "A", credentials))
+            .await
+            .unwrap();
+
+        let resp = build(source)
+            .oneshot(
+                Request::get("/api/pool/snapshot")
+                    .header("Authorization", "Bearer admin")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let snapshot = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        let target = Arc::new(AccountPool::new(
+            crate::model::config::Config::default(),
+            None,
+        ));
+        let resp = build(target.clone())
+            .oneshot(
+                Request::post("/api/pool/import-snapshot")
+                    .header("Authorization", "Bearer secret")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(axum::body::Body::from(snapshot))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["added"], 1);
+        assert_eq!(body["failed"], 0);
+
+        let restored = target.get_account("a").await.unwrap();
+        assert_eq!(restored.name, "A");
+        assert_eq!(
+            restored.credentials.refresh_token.as_deref(),
+            Some("rt-snapshot")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pool_topology_nodes_and_meta() {
+        use crate::pool::account::AccountStatus;
+        use crate::pool::AccountBuilder;
+
+        let pool = Arc::new(AccountPool::new(
+            crate::model::config::Config::default(),
+            None,
+        ));
+        let active = AccountBuilder::new("a", "A", KiroCredentials::default())
+            .region(Some("us-east-1".to_string()))
+            .build();
+        let cooling = AccountBuilder::new("b", "B", KiroCredentials::default())
+            .status(AccountStatus::Cooldown)
+            .cooldown_until(Some(chrono::Utc::now() + chrono::Duration::minutes(10)))
+            .build();
+        pool.add_account(active).await.unwrap();
+        pool.add_account(cooling).await.unwrap();
+        let strategy = pool.get_strategy().await;
+
+        let state = UiState {
+            pool,
+            telemetry: Arc::new(TelemetryStore::default()),
+            start_time: Instant::now(),
+            version: "test".to_string(),
+            api_key: "secret".to_string(),
+            config: None,
+            admin_key: None,
+            import_concurrency: 5,
+        };
+        let resp = get_pool_topology(State(state)).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+        let nodes = body["nodes"].as_array().unwrap();
+        assert_eq!(nodes.len(), 2);
+        let node = |id: &str| nodes.iter().find(|n| n["id"] == id).unwrap();
+
+        let a = node("a");
+        assert_eq!(a["name"], "A");
+        assert_eq!(a["status"], "active");
+        assert_eq!(a["region"], "us-east-1");
+        assert_eq!(a["credit_score"], 1.0);
+        assert!(a["cooldown_remaining_secs"].is_null());
+
+        let b = node("b");
+        assert_eq!(b["status"], "cooldown");
+        assert!(b["region"].is_null());
+        assert_eq!(b["credit_score"], 0.5);
+        let remaining = b["cooldown_remaining_secs"].as_i64().unwrap();
+        assert!(remaining > 0 && remaining <= 600);
+
+        assert_eq!(body["meta"]["strategy"], strategy.as_str());
+        assert_eq!(body["meta"]["total_active"], 1);
+    }
+
+    #[test]
+    fn test_account_response_free_trial_fields() {
+        let account = Account::new("a", "A", KiroCredentials::default());
+        let usage = UsageLimits {
+            resource_type: "CREDIT".to_string(),
+            usage_limit: 550.0,
+            current_usage: 20.0,
+            available: 530.0,
+            next_reset: None,
+            free_trial: Some(FreeTrialInfo {
+                status: "ACTIVE".to_string(),
+                usage_limit: 500.0,
+                current_usage: 20.0,
+                expiry: None,
+            }),
+            user_email: None,
+            subscription_type: None,
+            request_limits: None,
+            fetched_at: None,
+        };
+        let cache = HashMap::from([("a".to_string(), usage)]);
+
+        let response = AccountResponse::new(account, &cache, &StrategyConfig::default());
+        assert!(response.free_trial_active);
+        assert_eq!(response.free_trial_remaining, Some(480.0));
+        assert_eq!(response.free_trial_expiry, None);
+    }
+}